@@ -165,6 +165,7 @@ fn main() {
     } = create_genesis_config(mint_total);
 
     let (verified_sender, verified_receiver) = unbounded();
+    let (tpu_vote_sender, tpu_vote_receiver) = unbounded();
     let (vote_sender, vote_receiver) = unbounded();
     let (replay_vote_sender, _replay_vote_receiver) = unbounded();
     let bank0 = Bank::new(&genesis_config);
@@ -222,6 +223,7 @@ fn main() {
             &cluster_info,
             &poh_recorder,
             verified_receiver,
+            tpu_vote_receiver,
             vote_receiver,
             None,
             replay_vote_sender,
@@ -378,6 +380,7 @@ fn main() {
 
         drop(verified_sender);
         drop(vote_sender);
+        drop(tpu_vote_sender);
         exit.store(true, Ordering::Relaxed);
         banking_stage.join().unwrap();
         debug!("waited for banking_stage");
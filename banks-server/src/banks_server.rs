@@ -65,7 +65,7 @@ impl BanksServer {
         }
     }
 
-    fn run(bank: &Bank, transaction_receiver: Receiver<TransactionInfo>) {
+    fn run(bank_forks: &RwLock<BankForks>, transaction_receiver: Receiver<TransactionInfo>) {
         while let Ok(info) = transaction_receiver.recv() {
             let mut transaction_infos = vec![info];
             while let Ok(info) = transaction_receiver.try_recv() {
@@ -75,23 +75,23 @@ impl BanksServer {
                 .into_iter()
                 .map(|info| deserialize(&info.wire_transaction).unwrap())
                 .collect();
+            // Always process into the current working bank, so that the bank can be
+            // swapped out (e.g. warped forward) while the server is running
+            let bank = bank_forks.read().unwrap().working_bank();
             let _ = bank.process_transactions(&transactions);
         }
     }
 
     /// Useful for unit-testing
-    fn new_loopback(bank_forks: Arc<RwLock<BankForks>>) -> Self {
+    fn new_loopback(
+        bank_forks: Arc<RwLock<BankForks>>,
+        block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
+    ) -> Self {
         let (transaction_sender, transaction_receiver) = channel();
-        let bank = bank_forks.read().unwrap().working_bank();
-        let slot = bank.slot();
-        let block_commitment_cache = Arc::new(RwLock::new(BlockCommitmentCache::new(
-            HashMap::default(),
-            0,
-            CommitmentSlots::new_from_slot(slot),
-        )));
+        let server_bank_forks = bank_forks.clone();
         Builder::new()
             .name("solana-bank-forks-client".to_string())
-            .spawn(move || Self::run(&bank, transaction_receiver))
+            .spawn(move || Self::run(&server_bank_forks, transaction_receiver))
             .unwrap();
         Self::new(bank_forks, block_commitment_cache, transaction_sender)
     }
@@ -217,7 +217,23 @@ impl Banks for BanksServer {
 pub async fn start_local_server(
     bank_forks: &Arc<RwLock<BankForks>>,
 ) -> UnboundedChannel<Response<BanksResponse>, ClientMessage<BanksRequest>> {
-    let banks_server = BanksServer::new_loopback(bank_forks.clone());
+    let slot = bank_forks.read().unwrap().working_bank().slot();
+    let block_commitment_cache = Arc::new(RwLock::new(BlockCommitmentCache::new(
+        HashMap::default(),
+        0,
+        CommitmentSlots::new_from_slot(slot),
+    )));
+    start_local_server_with_commitment(bank_forks, &block_commitment_cache).await
+}
+
+/// Like `start_local_server`, but reads commitment from the given cache so the
+/// caller can advance the slots served as it moves the `BankForks` along
+pub async fn start_local_server_with_commitment(
+    bank_forks: &Arc<RwLock<BankForks>>,
+    block_commitment_cache: &Arc<RwLock<BlockCommitmentCache>>,
+) -> UnboundedChannel<Response<BanksResponse>, ClientMessage<BanksRequest>> {
+    let banks_server =
+        BanksServer::new_loopback(bank_forks.clone(), block_commitment_cache.clone());
     let (client_transport, server_transport) = transport::channel::unbounded();
     let server = server::new(server::Config::default())
         .incoming(stream::once(future::ready(server_transport)))
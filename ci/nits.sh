@@ -27,6 +27,7 @@ declare print_free_tree=(
   ':sdk/bpf/rust/rust-utils/**.rs'
   ':sdk/**.rs'
   ':^sdk/cargo-build-bpf/**.rs'
+  ':^sdk/cargo-test-bpf/**.rs'
   ':^sdk/program/src/program_option.rs'
   ':^sdk/program/src/program_stubs.rs'
   ':programs/**.rs'
@@ -4,9 +4,9 @@ use crate::{
     mock_sender::{MockSender, Mocks},
     rpc_config::RpcAccountInfoConfig,
     rpc_config::{
-        RpcGetConfirmedSignaturesForAddress2Config, RpcLargestAccountsConfig,
-        RpcProgramAccountsConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig,
-        RpcTokenAccountsFilter,
+        RpcAccountProofConfig, RpcGetConfirmedSignaturesForAddress2Config,
+        RpcLargestAccountsConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+        RpcSimulateTransactionConfig, RpcTokenAccountsFilter,
     },
     rpc_request::{RpcError, RpcRequest, TokenAccountsFilter},
     rpc_response::*,
@@ -603,6 +603,18 @@ impl RpcClient {
             })?
     }
 
+    /// Returns the account together with a proof of its inclusion in the latest accounts hash
+    /// computed by the node, or None if the account is not included in it
+    pub fn get_account_proof(&self, pubkey: &Pubkey) -> ClientResult<Option<RpcAccountProof>> {
+        let config = RpcAccountProofConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+        };
+        self.send(
+            RpcRequest::GetAccountProof,
+            json!([pubkey.to_string(), config]),
+        )
+    }
+
     pub fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
         Ok(self
             .get_multiple_accounts_with_commitment(pubkeys, CommitmentConfig::default())?
@@ -8,6 +8,7 @@ pub enum RpcRequest {
     DeregisterNode,
     ValidatorExit,
     GetAccountInfo,
+    GetAccountProof,
     GetBalance,
     GetBlockTime,
     GetClusterNodes,
@@ -63,6 +64,7 @@ impl fmt::Display for RpcRequest {
             RpcRequest::DeregisterNode => "deregisterNode",
             RpcRequest::ValidatorExit => "validatorExit",
             RpcRequest::GetAccountInfo => "getAccountInfo",
+            RpcRequest::GetAccountProof => "getAccountProof",
             RpcRequest::GetBalance => "getBalance",
             RpcRequest::GetBlockTime => "getBlockTime",
             RpcRequest::GetClusterNodes => "getClusterNodes",
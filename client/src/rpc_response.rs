@@ -1,9 +1,13 @@
 use crate::client_error;
 use solana_account_decoder::{parse_token::UiTokenAmount, UiAccount};
 use solana_sdk::{
+    accounts_merkle_proof::{verify_account, AccountsMerkleProof, MerkleProofLevel},
     clock::{Epoch, Slot},
     fee_calculator::{FeeCalculator, FeeRateGovernor},
+    genesis_config::ClusterType,
+    hash::{Hash, ParseHashError},
     inflation::Inflation,
+    pubkey::Pubkey,
     transaction::{Result, TransactionError},
 };
 use solana_transaction_status::ConfirmedTransactionStatusWithSignature;
@@ -94,6 +98,77 @@ pub struct RpcKeyedAccount {
     pub account: UiAccount,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcMerkleProofLevel {
+    pub position: usize,
+    pub siblings: Vec<String>,
+}
+
+impl From<&MerkleProofLevel> for RpcMerkleProofLevel {
+    fn from(level: &MerkleProofLevel) -> Self {
+        Self {
+            position: level.position,
+            siblings: level.siblings.iter().map(|hash| hash.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAccountProof {
+    /// Slot of the accounts hash the account is proven to be included in
+    pub slot: Slot,
+    pub accounts_hash: String,
+    pub account_hash: String,
+    /// Slot the account was last written in, which is hashed into the account hash
+    pub account_slot: Slot,
+    pub account: UiAccount,
+    pub proof: Vec<RpcMerkleProofLevel>,
+}
+
+impl RpcAccountProof {
+    pub fn merkle_proof(&self) -> std::result::Result<AccountsMerkleProof, ParseHashError> {
+        let levels = self
+            .proof
+            .iter()
+            .map(|level| {
+                let siblings = level
+                    .siblings
+                    .iter()
+                    .map(|hash| hash.parse())
+                    .collect::<std::result::Result<Vec<Hash>, _>>()?;
+                Ok(MerkleProofLevel {
+                    position: level.position,
+                    siblings,
+                })
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(AccountsMerkleProof { levels })
+    }
+
+    /// Checks that the account of `pubkey` is included in `accounts_hash`, which should come
+    /// from a trusted source rather than from this response
+    pub fn verify(
+        &self,
+        pubkey: &Pubkey,
+        cluster_type: &ClusterType,
+        accounts_hash: &Hash,
+    ) -> bool {
+        match (self.account.decode(), self.merkle_proof()) {
+            (Some(account), Ok(proof)) => verify_account(
+                pubkey,
+                &account,
+                self.account_slot,
+                cluster_type,
+                &proof,
+                accounts_hash,
+            ),
+            _ => false,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct SlotInfo {
     pub slot: Slot,
@@ -17,6 +17,7 @@ use solana_ledger::{blockstore::Blockstore, get_tmp_ledger_path};
 use solana_perf::packet::to_packets_chunked;
 use solana_perf::test_tx::test_tx;
 use solana_runtime::bank::Bank;
+use solana_runtime::cost_tracker::CostTracker;
 use solana_sdk::genesis_config::GenesisConfig;
 use solana_sdk::hash::Hash;
 use solana_sdk::message::Message;
@@ -30,7 +31,7 @@ use solana_sdk::timing::{duration_as_us, timestamp};
 use solana_sdk::transaction::Transaction;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::Receiver;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use test::Bencher;
 
@@ -74,6 +75,7 @@ fn bench_consume_buffered(bencher: &mut Bencher) {
             packets.push((batch, vec![0usize; batch_len]));
         }
         let (s, _r) = unbounded();
+        let cost_tracker = RwLock::new(CostTracker::default());
         // This tests the performance of buffering packets.
         // If the packet buffers are copied, performance will be poor.
         bencher.iter(move || {
@@ -84,6 +86,7 @@ fn bench_consume_buffered(bencher: &mut Bencher) {
                 10_000,
                 None,
                 &s,
+                &cost_tracker,
             );
         });
 
@@ -150,6 +153,7 @@ fn bench_banking(bencher: &mut Bencher, tx_type: TransactionType) {
     genesis_config.ticks_per_slot = 10_000;
 
     let (verified_sender, verified_receiver) = unbounded();
+    let (tpu_vote_sender, tpu_vote_receiver) = unbounded();
     let (vote_sender, vote_receiver) = unbounded();
     let bank = Arc::new(Bank::new(&genesis_config));
 
@@ -198,6 +202,7 @@ fn bench_banking(bencher: &mut Bencher, tx_type: TransactionType) {
             &cluster_info,
             &poh_recorder,
             verified_receiver,
+            tpu_vote_receiver,
             vote_receiver,
             None,
             s,
@@ -245,6 +250,7 @@ fn bench_banking(bencher: &mut Bencher, tx_type: TransactionType) {
             start %= verified.len();
         });
         drop(vote_sender);
+        drop(tpu_vote_sender);
         exit.store(true, Ordering::Relaxed);
         poh_service.join().unwrap();
     }
@@ -241,6 +241,7 @@ mod tests {
                 storages: vec![],
                 compression: CompressionType::Bzip2,
                 snapshot_version: SnapshotVersion::default(),
+                include_storage_indexes: false,
             };
 
             AccountsHashVerifier::process_accounts_package(
@@ -0,0 +1,425 @@
+//! The `ancestor_hashes_service` resolves slots for which the cluster optimistically
+//! confirmed a different version than the one this node replayed. It asks a stake
+//! weighted sample of peers for the (slot, bank hash) chain leading up to such a slot,
+//! and once enough of the sample agrees, hands the earliest slot on that chain which
+//! this node got wrong to the repair service to be dumped and repaired again.
+use crate::{
+    cluster_info::ClusterInfo,
+    repair_response,
+    result::Result,
+    serve_repair::{AncestorHashesResponseVersion, ServeRepair},
+    weighted_shuffle::weighted_shuffle,
+};
+use crossbeam_channel::{Receiver as CrossbeamReceiver, Sender as CrossbeamSender};
+use rand::{thread_rng, Rng};
+use solana_ledger::{
+    blockstore::Blockstore,
+    shred::{Nonce, SIZE_OF_NONCE},
+};
+use solana_perf::{
+    packet::{limited_deserialize, Packets},
+    recycler::Recycler,
+};
+use solana_runtime::{bank::Bank, bank_forks::BankForks};
+use solana_sdk::{clock::Slot, hash::Hash, pubkey::Pubkey, timing::timestamp};
+use solana_streamer::streamer::{self, PacketReceiver};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, RwLock,
+    },
+    thread::{self, Builder, JoinHandle},
+    time::Duration,
+};
+
+pub type AncestorHashesReplayUpdateSender = CrossbeamSender<Slot>;
+pub type AncestorHashesReplayUpdateReceiver = CrossbeamReceiver<Slot>;
+pub type AncestorDuplicateSlotsSender = CrossbeamSender<(Slot, (Pubkey, SocketAddr))>;
+pub type AncestorDuplicateSlotsReceiver = CrossbeamReceiver<(Slot, (Pubkey, SocketAddr))>;
+
+/// Number of staked peers asked for the ancestors of a slot
+pub const ANCESTOR_HASH_REPAIR_SAMPLE_SIZE: usize = 21;
+/// Time to wait for the sample to agree before asking a new sample
+pub const ANCESTOR_HASH_RETRY_MS: u64 = 2_000;
+const RECV_TIMEOUT_MS: u64 = 100;
+
+struct AncestorRequestStatus {
+    nonce: Nonce,
+    start: u64,
+    // Sampled peers that have not responded yet, keyed by the address the
+    // response is expected from
+    pending_peers: HashMap<SocketAddr, Pubkey>,
+    // Peers grouped by the ancestor chain they responded with
+    responses: HashMap<Vec<(Slot, Hash)>, Vec<(Pubkey, SocketAddr)>>,
+    num_required: usize,
+}
+
+impl AncestorRequestStatus {
+    fn new(nonce: Nonce, sampled_peers: &[(Pubkey, SocketAddr)]) -> Self {
+        // More than half of the sample has to agree on the same chain
+        let num_required = sampled_peers.len() / 2 + 1;
+        Self {
+            nonce,
+            start: timestamp(),
+            pending_peers: sampled_peers
+                .iter()
+                .map(|(pubkey, addr)| (*addr, *pubkey))
+                .collect(),
+            responses: HashMap::new(),
+            num_required,
+        }
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.start) >= ANCESTOR_HASH_RETRY_MS
+    }
+
+    // Records a response, returning the agreed upon ancestors and the peers
+    // that sent them once enough of the sample agrees
+    fn add_response(
+        &mut self,
+        from_addr: &SocketAddr,
+        ancestor_hashes: Vec<(Slot, Hash)>,
+    ) -> Option<(Vec<(Slot, Hash)>, Vec<(Pubkey, SocketAddr)>)> {
+        let pubkey = self.pending_peers.remove(from_addr)?;
+        let agreeing_peers = self.responses.entry(ancestor_hashes.clone()).or_default();
+        agreeing_peers.push((pubkey, *from_addr));
+        if agreeing_peers.len() >= self.num_required {
+            Some((ancestor_hashes, agreeing_peers.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct AncestorHashesService {
+    thread_hdls: Vec<JoinHandle<()>>,
+}
+
+impl AncestorHashesService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        exit: Arc<AtomicBool>,
+        blockstore: Arc<Blockstore>,
+        ancestor_hashes_socket: Arc<UdpSocket>,
+        cluster_info: Arc<ClusterInfo>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        repair_validators: Option<HashSet<Pubkey>>,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
+        ancestor_duplicate_slots_sender: AncestorDuplicateSlotsSender,
+    ) -> Self {
+        let (response_sender, response_receiver) = channel();
+        let t_receiver = streamer::receiver(
+            ancestor_hashes_socket.clone(),
+            &exit,
+            response_sender,
+            Recycler::default(),
+            "ancestor_hashes_response_receiver",
+        );
+        let t_ancestor_hashes = Builder::new()
+            .name("solana-ancestor-hashes".to_string())
+            .spawn(move || {
+                Self::run(
+                    &exit,
+                    &blockstore,
+                    &ancestor_hashes_socket,
+                    cluster_info,
+                    &bank_forks,
+                    &repair_validators,
+                    &ancestor_hashes_replay_update_receiver,
+                    &response_receiver,
+                    &ancestor_duplicate_slots_sender,
+                )
+            })
+            .unwrap();
+
+        Self {
+            thread_hdls: vec![t_receiver, t_ancestor_hashes],
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        exit: &AtomicBool,
+        blockstore: &Blockstore,
+        ancestor_hashes_socket: &UdpSocket,
+        cluster_info: Arc<ClusterInfo>,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        repair_validators: &Option<HashSet<Pubkey>>,
+        ancestor_hashes_replay_update_receiver: &AncestorHashesReplayUpdateReceiver,
+        response_receiver: &PacketReceiver,
+        ancestor_duplicate_slots_sender: &AncestorDuplicateSlotsSender,
+    ) {
+        let serve_repair = ServeRepair::new(cluster_info.clone(), Some(bank_forks.clone()));
+        let mut request_statuses: HashMap<Slot, Option<AncestorRequestStatus>> = HashMap::new();
+        loop {
+            if exit.load(Ordering::Relaxed) {
+                break;
+            }
+            let root_bank = bank_forks.read().unwrap().root_bank().clone();
+            let root = root_bank.slot();
+            request_statuses.retain(|slot, _| *slot > root);
+            for slot in ancestor_hashes_replay_update_receiver.try_iter() {
+                if slot > root {
+                    request_statuses.entry(slot).or_insert(None);
+                }
+            }
+
+            if let Ok(packets) =
+                response_receiver.recv_timeout(Duration::from_millis(RECV_TIMEOUT_MS))
+            {
+                let packets = std::iter::once(packets).chain(response_receiver.try_iter());
+                for packets in packets {
+                    Self::process_responses(
+                        &packets,
+                        blockstore,
+                        &mut request_statuses,
+                        ancestor_duplicate_slots_sender,
+                    );
+                }
+            }
+
+            let now = timestamp();
+            for (slot, status) in request_statuses.iter_mut() {
+                if status
+                    .as_ref()
+                    .map(|status| !status.is_expired(now))
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                let sampled_peers =
+                    Self::sample_peers(&cluster_info, &root_bank, *slot, repair_validators);
+                if sampled_peers.is_empty() {
+                    continue;
+                }
+                let nonce = thread_rng().gen();
+                if let Err(e) = Self::send_requests(
+                    &serve_repair,
+                    ancestor_hashes_socket,
+                    *slot,
+                    nonce,
+                    &sampled_peers,
+                ) {
+                    info!("ancestor hashes request for slot {} error {:?}", slot, e);
+                }
+                *status = Some(AncestorRequestStatus::new(nonce, &sampled_peers));
+            }
+        }
+    }
+
+    fn send_requests(
+        serve_repair: &ServeRepair,
+        ancestor_hashes_socket: &UdpSocket,
+        slot: Slot,
+        nonce: Nonce,
+        sampled_peers: &[(Pubkey, SocketAddr)],
+    ) -> Result<()> {
+        // Responses come back to this socket, reachable on the advertised ip
+        let response_addr = SocketAddr::new(
+            serve_repair.my_info().repair.ip(),
+            ancestor_hashes_socket.local_addr()?.port(),
+        );
+        // Requests are addressed to each peer, so that signed ones cannot be
+        // replayed against other nodes
+        for (pubkey, addr) in sampled_peers {
+            let request =
+                serve_repair.ancestor_repair_request_bytes(slot, pubkey, response_addr, nonce)?;
+            ancestor_hashes_socket.send_to(&request, addr)?;
+        }
+        inc_new_counter_info!("ancestor_hashes_service-requests", sampled_peers.len());
+        Ok(())
+    }
+
+    fn process_responses(
+        packets: &Packets,
+        blockstore: &Blockstore,
+        request_statuses: &mut HashMap<Slot, Option<AncestorRequestStatus>>,
+        ancestor_duplicate_slots_sender: &AncestorDuplicateSlotsSender,
+    ) {
+        for packet in &packets.packets {
+            let data = &packet.data[..packet.meta.size];
+            let nonce = match repair_response::nonce(data) {
+                Some(nonce) => nonce,
+                None => continue,
+            };
+            let response: AncestorHashesResponseVersion =
+                match limited_deserialize(&data[..data.len() - SIZE_OF_NONCE]) {
+                    Ok(response) => response,
+                    Err(_) => continue,
+                };
+            let request_slot = match response.slot_hashes().first() {
+                Some((slot, _)) => *slot,
+                None => continue,
+            };
+            let status = match request_statuses.get_mut(&request_slot) {
+                Some(Some(status)) if status.nonce == nonce => status,
+                _ => continue,
+            };
+            let decision = status.add_response(&packet.meta.addr(), response.into_slot_hashes());
+            if let Some((ancestor_hashes, agreeing_peers)) = decision {
+                request_statuses.remove(&request_slot);
+                match Self::find_earliest_mismatch(blockstore, &ancestor_hashes) {
+                    Some(dump_slot) => {
+                        warn!(
+                            "cluster agrees on a different version of slot {}, repairing from {}",
+                            dump_slot, request_slot
+                        );
+                        datapoint_info!(
+                            "ancestor_hashes_service-mismatch",
+                            ("request_slot", request_slot, i64),
+                            ("dump_slot", dump_slot, i64),
+                        );
+                        let repair_peer =
+                            agreeing_peers[thread_rng().gen_range(0, agreeing_peers.len())];
+                        let _ = ancestor_duplicate_slots_sender.send((dump_slot, repair_peer));
+                    }
+                    None => info!(
+                        "ancestors of slot {} agree with the cluster, nothing to repair",
+                        request_slot
+                    ),
+                }
+            }
+        }
+    }
+
+    // Returns the earliest slot in `ancestor_hashes` whose version this node
+    // replayed differently from the cluster, or marked dead
+    fn find_earliest_mismatch(
+        blockstore: &Blockstore,
+        ancestor_hashes: &[(Slot, Hash)],
+    ) -> Option<Slot> {
+        ancestor_hashes
+            .iter()
+            .rev()
+            .find(|(slot, hash)| {
+                blockstore.is_dead(*slot)
+                    || blockstore
+                        .get_bank_hash(*slot)
+                        .map(|frozen_hash| frozen_hash != *hash)
+                        .unwrap_or(false)
+            })
+            .map(|(slot, _)| *slot)
+    }
+
+    fn sample_peers(
+        cluster_info: &ClusterInfo,
+        root_bank: &Bank,
+        slot: Slot,
+        repair_validators: &Option<HashSet<Pubkey>>,
+    ) -> Vec<(Pubkey, SocketAddr)> {
+        let epoch = root_bank.get_epoch_and_slot_index(slot).0;
+        let epoch_stakes = match root_bank.epoch_stakes(epoch) {
+            Some(epoch_stakes) => epoch_stakes,
+            None => return vec![],
+        };
+        let node_id_to_vote_accounts = epoch_stakes.node_id_to_vote_accounts();
+        let (weights, peers): (Vec<_>, Vec<_>) = cluster_info
+            .repair_peers(slot)
+            .into_iter()
+            .filter(|peer| {
+                repair_validators
+                    .as_ref()
+                    .map(|repair_validators| repair_validators.contains(&peer.id))
+                    .unwrap_or(true)
+            })
+            .filter_map(|peer| {
+                let stake = node_id_to_vote_accounts.get(&peer.id)?.total_stake;
+                if stake == 0 {
+                    None
+                } else {
+                    Some((stake, (peer.id, peer.serve_repair)))
+                }
+            })
+            .unzip();
+        if peers.is_empty() {
+            return vec![];
+        }
+        weighted_shuffle(weights, thread_rng().gen())
+            .into_iter()
+            .take(ANCESTOR_HASH_REPAIR_SAMPLE_SIZE)
+            .map(|i| peers[i])
+            .collect()
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        for thread_hdl in self.thread_hdls {
+            thread_hdl.join()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_ledger::{blockstore::make_many_slot_entries, get_tmp_ledger_path};
+
+    #[test]
+    fn test_ancestor_request_status() {
+        let peers: Vec<_> = (0..5)
+            .map(|i| {
+                (
+                    solana_sdk::pubkey::new_rand(),
+                    SocketAddr::from(([127, 0, 0, 1], 8000 + i)),
+                )
+            })
+            .collect();
+        let mut status = AncestorRequestStatus::new(7, &peers);
+        assert_eq!(status.num_required, 3);
+        let good = vec![(2, Hash::new_unique()), (1, Hash::new_unique())];
+        let bad = vec![(2, Hash::new_unique()), (1, Hash::new_unique())];
+
+        assert!(status.add_response(&peers[0].1, good.clone()).is_none());
+        assert!(status.add_response(&peers[1].1, bad.clone()).is_none());
+        // Responses from unknown or already responded peers are ignored
+        let unknown = SocketAddr::from(([127, 0, 0, 1], 9000));
+        assert!(status.add_response(&unknown, good.clone()).is_none());
+        assert!(status.add_response(&peers[0].1, good.clone()).is_none());
+        assert!(status.add_response(&peers[2].1, bad).is_none());
+        assert!(status.add_response(&peers[3].1, good.clone()).is_none());
+        let (ancestors, agreeing_peers) = status.add_response(&peers[4].1, good.clone()).unwrap();
+        assert_eq!(ancestors, good);
+        assert_eq!(agreeing_peers, vec![peers[0], peers[3], peers[4]]);
+    }
+
+    #[test]
+    fn test_find_earliest_mismatch() {
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&ledger_path).unwrap();
+            let (shreds, _) = make_many_slot_entries(0, 5, 1);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            let hashes: Vec<_> = (0..5).map(|_| Hash::new_unique()).collect();
+            for (slot, hash) in hashes.iter().enumerate().take(4) {
+                blockstore.insert_bank_hash(slot as Slot, *hash);
+            }
+            let mut ancestors: Vec<_> = (0..5).rev().map(|i| (i, hashes[i as usize])).collect();
+
+            // Slot 4 has not been replayed, and everything else matches
+            assert_eq!(
+                AncestorHashesService::find_earliest_mismatch(&blockstore, &ancestors),
+                None
+            );
+
+            // The earliest mismatched slot is reported
+            ancestors[1].1 = Hash::new_unique();
+            ancestors[3].1 = Hash::new_unique();
+            assert_eq!(
+                AncestorHashesService::find_earliest_mismatch(&blockstore, &ancestors),
+                Some(1)
+            );
+
+            // Dead slots always mismatch
+            blockstore.set_dead_slot(0).unwrap();
+            assert_eq!(
+                AncestorHashesService::find_earliest_mismatch(&blockstore, &ancestors),
+                Some(0)
+            );
+        }
+        Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
+    }
+}
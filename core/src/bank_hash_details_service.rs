@@ -0,0 +1,78 @@
+//! The `bank_hash_details_service` writes the hash details of frozen banks off of the
+//! replay thread, keeping only those of the most recent slots
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use solana_measure::measure::Measure;
+use solana_runtime::{
+    bank::Bank,
+    bank_hash_details::{remove_old_bank_hash_details, write_bank_hash_details},
+};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, Builder, JoinHandle},
+    time::Duration,
+};
+
+pub type BankHashDetailsReceiver = Receiver<Arc<Bank>>;
+pub type BankHashDetailsSender = Sender<Arc<Bank>>;
+
+/// The number of most recent slots whose bank hash details are kept on disk
+pub const DEFAULT_MAX_BANK_HASH_DETAILS_SLOTS: usize = 1_000;
+
+const BANK_HASH_DETAILS_WARNING_MS: u64 = 500;
+
+pub struct BankHashDetailsService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl BankHashDetailsService {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        bank_hash_details_receiver: BankHashDetailsReceiver,
+        bank_hash_details_dir: PathBuf,
+        max_slots: usize,
+        exit: &Arc<AtomicBool>,
+    ) -> Self {
+        let exit = exit.clone();
+        let thread_hdl = Builder::new()
+            .name("solana-bank-hash-details".to_string())
+            .spawn(move || loop {
+                if exit.load(Ordering::Relaxed) {
+                    break;
+                }
+                let recv_result = bank_hash_details_receiver.recv_timeout(Duration::from_secs(1));
+                match recv_result {
+                    Err(RecvTimeoutError::Disconnected) => {
+                        break;
+                    }
+                    Ok(bank) => {
+                        let mut write_timer = Measure::start("bank_hash_details_timer");
+                        write_bank_hash_details(&bank, &bank_hash_details_dir);
+                        if let Err(err) =
+                            remove_old_bank_hash_details(&bank_hash_details_dir, max_slots)
+                        {
+                            warn!("failed to remove old bank hash details: {}", err);
+                        }
+                        write_timer.stop();
+                        if write_timer.as_ms() > BANK_HASH_DETAILS_WARNING_MS {
+                            warn!(
+                                "bank hash details for slot {} took: {}ms",
+                                bank.slot(),
+                                write_timer.as_ms()
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            })
+            .unwrap();
+        Self { thread_hdl }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
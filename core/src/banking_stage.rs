@@ -25,6 +25,8 @@ use solana_runtime::{
     accounts_db::ErrorCounters,
     bank::{Bank, TransactionBalancesSet, TransactionProcessResult},
     bank_utils,
+    cost_model::{CostModel, TransactionCost},
+    cost_tracker::CostTracker,
     transaction_batch::TransactionBatch,
     vote_sender_types::ReplayVoteSender,
 };
@@ -38,12 +40,13 @@ use solana_sdk::{
     timing::{duration_as_ms, timestamp},
     transaction::{self, Transaction, TransactionError},
 };
+use solana_vote_program::vote_transaction::parse_vote_transaction;
 use std::{
     cmp, env,
     net::UdpSocket,
     sync::atomic::AtomicBool,
     sync::mpsc::Receiver,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, RwLock},
     thread::{self, Builder, JoinHandle},
     time::Duration,
     time::Instant,
@@ -58,6 +61,10 @@ pub const FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET: u64 = 1;
 // Fixed thread size seems to be fastest on GCP setup
 pub const NUM_THREADS: u32 = 4;
 
+// One thread for votes received through gossip and one for votes sent to the
+// TPU vote port
+const NUM_VOTE_PROCESSING_THREADS: u32 = 2;
+
 const TOTAL_BUFFERED_PACKETS: usize = 500_000;
 
 const MAX_NUM_TRANSACTIONS_PER_BATCH: usize = 128;
@@ -67,6 +74,15 @@ pub struct BankingStage {
     bank_thread_hdls: Vec<JoinHandle<()>>,
 }
 
+/// Where a banking thread sends the packets it buffered when another node is
+/// about to be the leader
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ForwardOption {
+    NotForward,
+    ForwardTpuVote,
+    ForwardTransaction,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum BufferedPacketsDecision {
     Consume,
@@ -81,6 +97,7 @@ impl BankingStage {
         cluster_info: &Arc<ClusterInfo>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         verified_receiver: CrossbeamReceiver<Vec<Packets>>,
+        tpu_verified_vote_receiver: CrossbeamReceiver<Vec<Packets>>,
         verified_vote_receiver: CrossbeamReceiver<Vec<Packets>>,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
@@ -89,6 +106,7 @@ impl BankingStage {
             cluster_info,
             poh_recorder,
             verified_receiver,
+            tpu_verified_vote_receiver,
             verified_vote_receiver,
             Self::num_threads(),
             transaction_status_sender,
@@ -100,24 +118,36 @@ impl BankingStage {
         cluster_info: &Arc<ClusterInfo>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         verified_receiver: CrossbeamReceiver<Vec<Packets>>,
+        tpu_verified_vote_receiver: CrossbeamReceiver<Vec<Packets>>,
         verified_vote_receiver: CrossbeamReceiver<Vec<Packets>>,
         num_threads: u32,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
     ) -> Self {
-        let batch_limit = TOTAL_BUFFERED_PACKETS / ((num_threads - 1) as usize * PACKETS_PER_BATCH);
+        assert!(num_threads >= NUM_VOTE_PROCESSING_THREADS + 1);
+        let batch_limit = TOTAL_BUFFERED_PACKETS
+            / ((num_threads - NUM_VOTE_PROCESSING_THREADS) as usize * PACKETS_PER_BATCH);
         // Single thread to generate entries from many banks.
         // This thread talks to poh_service and broadcasts the entries once they have been recorded.
         // Once an entry has been recorded, its blockhash is registered with the bank.
         let my_pubkey = cluster_info.id();
+        // All threads pack the same block, so they share one cost tracker
+        let cost_tracker = Arc::new(RwLock::new(CostTracker::new(
+            Arc::new(CostModel::default()),
+        )));
         // Many banks that process transactions in parallel.
         let bank_thread_hdls: Vec<JoinHandle<()>> = (0..num_threads)
             .map(|i| {
-                let (verified_receiver, enable_forwarding) = if i < num_threads - 1 {
-                    (verified_receiver.clone(), true)
-                } else {
-                    // Disable forwarding of vote transactions, as votes are gossiped
-                    (verified_vote_receiver.clone(), false)
+                // Votes get threads, and so buffers, of their own, so that they
+                // are not stuck behind user transactions
+                let (verified_receiver, forward_option) = match i {
+                    // Disable forwarding of gossip votes, as they are gossiped anyway
+                    0 => (verified_vote_receiver.clone(), ForwardOption::NotForward),
+                    1 => (
+                        tpu_verified_vote_receiver.clone(),
+                        ForwardOption::ForwardTpuVote,
+                    ),
+                    _ => (verified_receiver.clone(), ForwardOption::ForwardTransaction),
                 };
 
                 let poh_recorder = poh_recorder.clone();
@@ -125,6 +155,7 @@ impl BankingStage {
                 let mut recv_start = Instant::now();
                 let transaction_status_sender = transaction_status_sender.clone();
                 let gossip_vote_sender = gossip_vote_sender.clone();
+                let cost_tracker = cost_tracker.clone();
                 Builder::new()
                     .name("solana-banking-stage-tx".to_string())
                     .spawn(move || {
@@ -135,11 +166,12 @@ impl BankingStage {
                             &poh_recorder,
                             &cluster_info,
                             &mut recv_start,
-                            enable_forwarding,
+                            forward_option,
                             i,
                             batch_limit,
                             transaction_status_sender,
                             gossip_vote_sender,
+                            &cost_tracker,
                         );
                     })
                     .unwrap()
@@ -176,6 +208,7 @@ impl BankingStage {
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        cost_tracker: &RwLock<CostTracker>,
     ) -> UnprocessedPackets {
         let mut unprocessed_packets = vec![];
         let mut rebuffered_packets = 0;
@@ -208,6 +241,7 @@ impl BankingStage {
                     unprocessed_indexes.to_owned(),
                     transaction_status_sender.clone(),
                     gossip_vote_sender,
+                    cost_tracker,
                 );
 
             new_tx_count += processed;
@@ -299,10 +333,11 @@ impl BankingStage {
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         cluster_info: &ClusterInfo,
         buffered_packets: &mut Vec<PacketsAndOffsets>,
-        enable_forwarding: bool,
+        forward_option: ForwardOption,
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        cost_tracker: &RwLock<CostTracker>,
     ) -> BufferedPacketsDecision {
         let (leader_at_slot_offset, poh_has_bank, would_be_leader) = {
             let poh = poh_recorder.lock().unwrap();
@@ -331,19 +366,23 @@ impl BankingStage {
                     batch_limit,
                     transaction_status_sender,
                     gossip_vote_sender,
+                    cost_tracker,
                 );
                 buffered_packets.append(&mut unprocessed);
             }
             BufferedPacketsDecision::Forward => {
-                if enable_forwarding {
+                if forward_option != ForwardOption::NotForward {
                     let next_leader = poh_recorder
                         .lock()
                         .unwrap()
                         .leader_after_n_slots(FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET);
                     next_leader.map_or((), |leader_pubkey| {
-                        let leader_addr = {
-                            cluster_info
-                                .lookup_contact_info(&leader_pubkey, |leader| leader.tpu_forwards)
+                        let leader_addr = match forward_option {
+                            ForwardOption::ForwardTpuVote => {
+                                cluster_info.lookup_tpu_vote_address(&leader_pubkey)
+                            }
+                            _ => cluster_info
+                                .lookup_contact_info(&leader_pubkey, |leader| leader.tpu_forwards),
                         };
 
                         leader_addr.map_or((), |leader_addr| {
@@ -371,11 +410,12 @@ impl BankingStage {
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         cluster_info: &ClusterInfo,
         recv_start: &mut Instant,
-        enable_forwarding: bool,
+        forward_option: ForwardOption,
         id: u32,
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
+        cost_tracker: &RwLock<CostTracker>,
     ) {
         let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let mut buffered_packets = vec![];
@@ -387,10 +427,11 @@ impl BankingStage {
                     poh_recorder,
                     cluster_info,
                     &mut buffered_packets,
-                    enable_forwarding,
+                    forward_option,
                     batch_limit,
                     transaction_status_sender.clone(),
                     &gossip_vote_sender,
+                    cost_tracker,
                 );
                 if decision == BufferedPacketsDecision::Hold {
                     // If we are waiting on a new bank,
@@ -419,6 +460,7 @@ impl BankingStage {
                 batch_limit,
                 transaction_status_sender.clone(),
                 &gossip_vote_sender,
+                cost_tracker,
             ) {
                 Err(RecvTimeoutError::Timeout) => (),
                 Err(RecvTimeoutError::Disconnected) => break,
@@ -438,13 +480,12 @@ impl BankingStage {
     }
 
     pub fn num_threads() -> u32 {
-        const MIN_THREADS_VOTES: u32 = 1;
         const MIN_THREADS_BANKING: u32 = 1;
         cmp::max(
             env::var("SOLANA_BANKING_THREADS")
                 .map(|x| x.parse().unwrap_or(NUM_THREADS))
                 .unwrap_or(NUM_THREADS),
-            MIN_THREADS_VOTES + MIN_THREADS_BANKING,
+            NUM_VOTE_PROCESSING_THREADS + MIN_THREADS_BANKING,
         )
     }
 
@@ -518,7 +559,7 @@ impl BankingStage {
         batch: &TransactionBatch,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
-    ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
+    ) -> (Result<usize, PohRecorderError>, Vec<usize>, Vec<usize>) {
         let mut load_execute_time = Measure::start("load_execute_time");
         // Use a shorter maximum age when adding transactions into the pipeline.  This will reduce
         // the likelihood of any single thread getting starved and processing old ids.
@@ -535,6 +576,7 @@ impl BankingStage {
             results,
             inner_instructions,
             transaction_logs,
+            execution_traces,
             mut retryable_txs,
             tx_count,
             signature_count,
@@ -543,6 +585,7 @@ impl BankingStage {
             MAX_PROCESSING_AGE,
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
+            false,
         );
         load_execute_time.stop();
 
@@ -553,8 +596,14 @@ impl BankingStage {
             Self::record_transactions(bank.slot(), txs, &results, poh);
         retryable_txs.extend(retryable_record_txs);
         if num_to_commit.is_err() {
-            return (num_to_commit, retryable_txs);
+            return (num_to_commit, retryable_txs, (0..txs.len()).collect());
         }
+        let uncommitted_txs = results
+            .iter()
+            .enumerate()
+            .filter(|(_, (r, _h))| !Bank::can_commit(r))
+            .map(|(i, _)| i)
+            .collect();
         record_time.stop();
 
         let mut commit_time = Measure::start("commit_time");
@@ -582,6 +631,7 @@ impl BankingStage {
                     TransactionBalancesSet::new(pre_balances, post_balances),
                     inner_instructions,
                     transaction_logs,
+                    execution_traces,
                     sender,
                 );
             }
@@ -599,7 +649,7 @@ impl BankingStage {
             txs.len(),
         );
 
-        (Ok(num_to_commit), retryable_txs)
+        (Ok(num_to_commit), retryable_txs, uncommitted_txs)
     }
 
     pub fn process_and_record_transactions(
@@ -609,21 +659,23 @@ impl BankingStage {
         chunk_offset: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
-    ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
+    ) -> (Result<usize, PohRecorderError>, Vec<usize>, Vec<usize>) {
         let mut lock_time = Measure::start("lock_time");
         // Once accounts are locked, other threads cannot encode transactions that will modify the
         // same account state
         let batch = bank.prepare_batch(txs, None);
         lock_time.stop();
 
-        let (result, mut retryable_txs) = Self::process_and_record_transactions_locked(
-            bank,
-            poh,
-            &batch,
-            transaction_status_sender,
-            gossip_vote_sender,
-        );
+        let (result, mut retryable_txs, mut uncommitted_txs) =
+            Self::process_and_record_transactions_locked(
+                bank,
+                poh,
+                &batch,
+                transaction_status_sender,
+                gossip_vote_sender,
+            );
         retryable_txs.iter_mut().for_each(|x| *x += chunk_offset);
+        uncommitted_txs.iter_mut().for_each(|x| *x += chunk_offset);
 
         let mut unlock_time = Measure::start("unlock_time");
         // Once the accounts are new transactions can enter the pipeline to process them
@@ -638,41 +690,45 @@ impl BankingStage {
             txs.len(),
         );
 
-        (result, retryable_txs)
+        (result, retryable_txs, uncommitted_txs)
     }
 
     /// Sends transactions to the bank.
     ///
     /// Returns the number of transactions successfully processed by the bank, which may be less
-    /// than the total number if max PoH height was reached and the bank halted
+    /// than the total number if max PoH height was reached and the bank halted, the indexes of
+    /// the transactions to retry, and the indexes of all transactions which were not committed
     fn process_transactions(
         bank: &Arc<Bank>,
         transactions: &[Transaction],
         poh: &Arc<Mutex<PohRecorder>>,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
-    ) -> (usize, Vec<usize>) {
+    ) -> (usize, Vec<usize>, Vec<usize>) {
         let mut chunk_start = 0;
         let mut unprocessed_txs = vec![];
+        let mut uncommitted_txs = vec![];
         while chunk_start != transactions.len() {
             let chunk_end = std::cmp::min(
                 transactions.len(),
                 chunk_start + MAX_NUM_TRANSACTIONS_PER_BATCH,
             );
 
-            let (result, retryable_txs_in_chunk) = Self::process_and_record_transactions(
-                bank,
-                &transactions[chunk_start..chunk_end],
-                poh,
-                chunk_start,
-                transaction_status_sender.clone(),
-                gossip_vote_sender,
-            );
+            let (result, retryable_txs_in_chunk, uncommitted_txs_in_chunk) =
+                Self::process_and_record_transactions(
+                    bank,
+                    &transactions[chunk_start..chunk_end],
+                    poh,
+                    chunk_start,
+                    transaction_status_sender.clone(),
+                    gossip_vote_sender,
+                );
             trace!("process_transactions result: {:?}", result);
 
             // Add the retryable txs (transactions that errored in a way that warrants a retry)
             // to the list of unprocessed txs.
             unprocessed_txs.extend_from_slice(&retryable_txs_in_chunk);
+            uncommitted_txs.extend(uncommitted_txs_in_chunk);
             if let Err(PohRecorderError::MaxHeightReached) = result {
                 info!(
                     "process transactions: max height reached slot: {} height: {}",
@@ -683,13 +739,14 @@ impl BankingStage {
                 // transactions[chunk_start..chunk_end], so we just need to push the remaining
                 // transactions into the unprocessed queue.
                 unprocessed_txs.extend(chunk_end..transactions.len());
+                uncommitted_txs.extend(chunk_end..transactions.len());
                 break;
             }
             // Don't exit early on any other type of error, continue processing...
             chunk_start = chunk_end;
         }
 
-        (chunk_start, unprocessed_txs)
+        (chunk_start, unprocessed_txs, uncommitted_txs)
     }
 
     // This function returns a vector of transactions that are not None. It also returns a vector
@@ -770,6 +827,72 @@ impl BankingStage {
         Self::filter_transaction_indexes(maybe_secp_verified_transactions, &transaction_indexes)
     }
 
+    // Transactions which would push the block, or an account they write to,
+    // over its cost limit are held back for a later block. Votes are charged
+    // but never held back. The cost of the remaining transactions is reserved
+    // until they are processed, and refunded by `refund_uncommitted_costs` for
+    // those which are not committed. Returns the remaining transactions, their
+    // packet indexes and costs, and the packet indexes of the transactions
+    // held back
+    fn filter_transactions_by_cost(
+        bank_slot: Slot,
+        transactions: Vec<Transaction>,
+        transaction_to_packet_indexes: Vec<usize>,
+        cost_tracker: &RwLock<CostTracker>,
+    ) -> (
+        Vec<Transaction>,
+        Vec<usize>,
+        Vec<TransactionCost>,
+        Vec<usize>,
+    ) {
+        let mut cost_tracker = cost_tracker.write().unwrap();
+        cost_tracker.reset_if_new_bank(bank_slot);
+        let mut retryable_packet_indexes = vec![];
+        let mut transaction_costs = vec![];
+        let (transactions, transaction_to_packet_indexes) = transactions
+            .into_iter()
+            .zip(transaction_to_packet_indexes)
+            .filter(|(tx, packet_index)| {
+                let cost = cost_tracker.cost_model().calculate_cost(tx);
+                if parse_vote_transaction(tx).is_some() || cost_tracker.would_fit(&cost).is_ok() {
+                    cost_tracker.add_transaction_cost(&cost);
+                    transaction_costs.push(cost);
+                    true
+                } else {
+                    retryable_packet_indexes.push(*packet_index);
+                    false
+                }
+            })
+            .unzip();
+        inc_new_counter_info!(
+            "banking_stage-cost_model_throttled_txs",
+            retryable_packet_indexes.len()
+        );
+        (
+            transactions,
+            transaction_to_packet_indexes,
+            transaction_costs,
+            retryable_packet_indexes,
+        )
+    }
+
+    // Takes back the cost reserved for the transactions which were not
+    // committed, so that retrying them does not charge the block twice
+    fn refund_uncommitted_costs(
+        bank_slot: Slot,
+        transaction_costs: &[TransactionCost],
+        uncommitted_tx_indexes: &[usize],
+        cost_tracker: &RwLock<CostTracker>,
+    ) {
+        if uncommitted_tx_indexes.is_empty() {
+            return;
+        }
+        let mut cost_tracker = cost_tracker.write().unwrap();
+        for index in uncommitted_tx_indexes {
+            cost_tracker.remove_transaction_cost(bank_slot, &transaction_costs[*index]);
+        }
+    }
+
     /// This function filters pending packets that are still valid
     /// # Arguments
     /// * `transactions` - a batch of transactions deserialized from packets
@@ -815,12 +938,24 @@ impl BankingStage {
         packet_indexes: Vec<usize>,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        cost_tracker: &RwLock<CostTracker>,
     ) -> (usize, usize, Vec<usize>) {
         let (transactions, transaction_to_packet_indexes) = Self::transactions_from_packets(
             msgs,
             &packet_indexes,
             bank.secp256k1_program_enabled(),
         );
+        let (
+            transactions,
+            transaction_to_packet_indexes,
+            transaction_costs,
+            retryable_packet_indexes,
+        ) = Self::filter_transactions_by_cost(
+            bank.slot(),
+            transactions,
+            transaction_to_packet_indexes,
+            cost_tracker,
+        );
         debug!(
             "bank: {} filtered transactions {}",
             bank.slot(),
@@ -829,17 +964,24 @@ impl BankingStage {
 
         let tx_len = transactions.len();
 
-        let (processed, unprocessed_tx_indexes) = Self::process_transactions(
-            bank,
-            &transactions,
-            poh,
-            transaction_status_sender,
-            gossip_vote_sender,
+        let (processed, unprocessed_tx_indexes, uncommitted_tx_indexes) =
+            Self::process_transactions(
+                bank,
+                &transactions,
+                poh,
+                transaction_status_sender,
+                gossip_vote_sender,
+            );
+        Self::refund_uncommitted_costs(
+            bank.slot(),
+            &transaction_costs,
+            &uncommitted_tx_indexes,
+            cost_tracker,
         );
 
         let unprocessed_tx_count = unprocessed_tx_indexes.len();
 
-        let filtered_unprocessed_packet_indexes = Self::filter_pending_packets_from_pending_txs(
+        let mut filtered_unprocessed_packet_indexes = Self::filter_pending_packets_from_pending_txs(
             bank,
             &transactions,
             &transaction_to_packet_indexes,
@@ -849,6 +991,7 @@ impl BankingStage {
             "banking_stage-dropped_tx_before_forwarding",
             unprocessed_tx_count.saturating_sub(filtered_unprocessed_packet_indexes.len())
         );
+        filtered_unprocessed_packet_indexes.extend(retryable_packet_indexes);
 
         (processed, tx_len, filtered_unprocessed_packet_indexes)
     }
@@ -920,6 +1063,7 @@ impl BankingStage {
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        cost_tracker: &RwLock<CostTracker>,
     ) -> Result<UnprocessedPackets, RecvTimeoutError> {
         let mut recv_time = Measure::start("process_packets_recv");
         let mms = verified_receiver.recv_timeout(recv_timeout)?;
@@ -963,6 +1107,7 @@ impl BankingStage {
                 packet_indexes,
                 transaction_status_sender.clone(),
                 gossip_vote_sender,
+                cost_tracker,
             );
 
             new_tx_count += processed;
@@ -1095,6 +1240,7 @@ mod tests {
     use solana_perf::packet::to_packets;
     use solana_runtime::bank::HashAgeKind;
     use solana_sdk::{
+        hash::Hash,
         instruction::InstructionError,
         signature::{Keypair, Signer},
         system_instruction::SystemError,
@@ -1109,6 +1255,7 @@ mod tests {
         let genesis_config = create_genesis_config(2).genesis_config;
         let bank = Arc::new(Bank::new(&genesis_config));
         let (verified_sender, verified_receiver) = unbounded();
+        let (tpu_vote_sender, tpu_vote_receiver) = unbounded();
         let (vote_sender, vote_receiver) = unbounded();
         let (gossip_vote_sender, _gossip_vote_receiver) = unbounded();
         let ledger_path = get_tmp_ledger_path!();
@@ -1125,12 +1272,14 @@ mod tests {
                 &cluster_info,
                 &poh_recorder,
                 verified_receiver,
+                tpu_vote_receiver,
                 vote_receiver,
                 None,
                 gossip_vote_sender,
             );
             drop(verified_sender);
             drop(vote_sender);
+            drop(tpu_vote_sender);
             exit.store(true, Ordering::Relaxed);
             banking_stage.join().unwrap();
             poh_service.join().unwrap();
@@ -1149,6 +1298,7 @@ mod tests {
         let bank = Arc::new(Bank::new(&genesis_config));
         let start_hash = bank.last_blockhash();
         let (verified_sender, verified_receiver) = unbounded();
+        let (tpu_vote_sender, tpu_vote_receiver) = unbounded();
         let (vote_sender, vote_receiver) = unbounded();
         let ledger_path = get_tmp_ledger_path!();
         {
@@ -1168,6 +1318,7 @@ mod tests {
                 &cluster_info,
                 &poh_recorder,
                 verified_receiver,
+                tpu_vote_receiver,
                 vote_receiver,
                 None,
                 gossip_vote_sender,
@@ -1175,6 +1326,7 @@ mod tests {
             trace!("sending bank");
             drop(verified_sender);
             drop(vote_sender);
+            drop(tpu_vote_sender);
             exit.store(true, Ordering::Relaxed);
             poh_service.join().unwrap();
             drop(poh_recorder);
@@ -1214,6 +1366,7 @@ mod tests {
         let bank = Arc::new(Bank::new(&genesis_config));
         let start_hash = bank.last_blockhash();
         let (verified_sender, verified_receiver) = unbounded();
+        let (tpu_vote_sender, tpu_vote_receiver) = unbounded();
         let (vote_sender, vote_receiver) = unbounded();
         let ledger_path = get_tmp_ledger_path!();
         {
@@ -1234,6 +1387,7 @@ mod tests {
                 &cluster_info,
                 &poh_recorder,
                 verified_receiver,
+                tpu_vote_receiver,
                 vote_receiver,
                 None,
                 gossip_vote_sender,
@@ -1275,6 +1429,7 @@ mod tests {
 
             drop(verified_sender);
             drop(vote_sender);
+            drop(tpu_vote_sender);
             // wait until banking_stage to finish up all packets
             banking_stage.join().unwrap();
 
@@ -1354,6 +1509,8 @@ mod tests {
         let packets = convert_from_old_verified(packets);
         verified_sender.send(packets).unwrap();
 
+        let (tpu_vote_sender, tpu_vote_receiver) = unbounded();
+
         let (vote_sender, vote_receiver) = unbounded();
         let ledger_path = get_tmp_ledger_path!();
         {
@@ -1378,8 +1535,9 @@ mod tests {
                     &cluster_info,
                     &poh_recorder,
                     verified_receiver,
+                    tpu_vote_receiver,
                     vote_receiver,
-                    2,
+                    3,
                     None,
                     gossip_vote_sender,
                 );
@@ -1394,6 +1552,7 @@ mod tests {
             };
             drop(verified_sender);
             drop(vote_sender);
+            drop(tpu_vote_sender);
 
             // consume the entire entry_receiver, feed it into a new bank
             // check that the balance is what we expect.
@@ -1521,6 +1680,62 @@ mod tests {
         Blockstore::destroy(&ledger_path).unwrap();
     }
 
+    #[test]
+    fn test_filter_transactions_by_cost() {
+        let payer = Keypair::new();
+        let transactions: Vec<_> = (0..3)
+            .map(|_| {
+                system_transaction::transfer(
+                    &payer,
+                    &solana_sdk::pubkey::new_rand(),
+                    1,
+                    Hash::default(),
+                )
+            })
+            .collect();
+        let cost_model = CostModel::default();
+        let tx_cost = cost_model.calculate_cost(&transactions[0]).sum();
+        // The payer is written by every transfer, so only two of them fit
+        let cost_tracker = RwLock::new(CostTracker::new(Arc::new(CostModel::new(
+            2 * tx_cost,
+            10 * tx_cost,
+        ))));
+
+        let (filtered, packet_indexes, transaction_costs, retryable_packet_indexes) =
+            BankingStage::filter_transactions_by_cost(
+                0,
+                transactions.clone(),
+                vec![3, 5, 7],
+                &cost_tracker,
+            );
+        assert_eq!(filtered, transactions[..2].to_vec());
+        assert_eq!(packet_indexes, vec![3, 5]);
+        assert_eq!(transaction_costs.len(), 2);
+        assert_eq!(retryable_packet_indexes, vec![7]);
+
+        // Once the cost of an uncommitted transaction is refunded, the held
+        // back one fits in the same block
+        BankingStage::refund_uncommitted_costs(0, &transaction_costs, &[1], &cost_tracker);
+        let (filtered, _, _, retryable_packet_indexes) = BankingStage::filter_transactions_by_cost(
+            0,
+            transactions[2..].to_vec(),
+            vec![7],
+            &cost_tracker,
+        );
+        assert_eq!(filtered, transactions[2..].to_vec());
+        assert!(retryable_packet_indexes.is_empty());
+
+        // The next block starts with a clean slate
+        let (filtered, _, _, retryable_packet_indexes) = BankingStage::filter_transactions_by_cost(
+            1,
+            transactions[2..].to_vec(),
+            vec![7],
+            &cost_tracker,
+        );
+        assert_eq!(filtered, transactions[2..].to_vec());
+        assert!(retryable_packet_indexes.is_empty());
+    }
+
     #[test]
     fn test_bank_filter_transaction_indexes() {
         let GenesisConfigInfo {
@@ -1858,7 +2073,7 @@ mod tests {
 
             let (gossip_vote_sender, _gossip_vote_receiver) = unbounded();
 
-            let (result, unprocessed) = BankingStage::process_and_record_transactions(
+            let (result, unprocessed, _) = BankingStage::process_and_record_transactions(
                 &bank,
                 &transactions,
                 &poh_recorder,
@@ -1950,7 +2165,7 @@ mod tests {
 
             let (gossip_vote_sender, _gossip_vote_receiver) = unbounded();
 
-            let (processed_transactions_count, mut retryable_txs) =
+            let (processed_transactions_count, mut retryable_txs, _) =
                 BankingStage::process_transactions(
                     &bank,
                     &transactions,
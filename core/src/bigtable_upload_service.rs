@@ -1,5 +1,6 @@
 use solana_ledger::blockstore::Blockstore;
 use solana_runtime::commitment::BlockCommitmentCache;
+use solana_storage_bigtable::LedgerStorageAdapter;
 use std::{
     sync::atomic::{AtomicBool, Ordering},
     sync::{Arc, RwLock},
@@ -22,7 +23,7 @@ pub struct BigTableUploadService {
 impl BigTableUploadService {
     pub fn new(
         runtime_handle: runtime::Handle,
-        bigtable_ledger_storage: solana_storage_bigtable::LedgerStorage,
+        bigtable_ledger_storage: Arc<dyn LedgerStorageAdapter>,
         blockstore: Arc<Blockstore>,
         block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
         exit: Arc<AtomicBool>,
@@ -46,7 +47,7 @@ impl BigTableUploadService {
 
     fn run(
         runtime: runtime::Handle,
-        bigtable_ledger_storage: solana_storage_bigtable::LedgerStorage,
+        bigtable_ledger_storage: Arc<dyn LedgerStorageAdapter>,
         blockstore: Arc<Blockstore>,
         block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
         exit: Arc<AtomicBool>,
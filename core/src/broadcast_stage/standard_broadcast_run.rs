@@ -7,6 +7,7 @@ use solana_ledger::{
     entry::Entry,
     shred::{Shred, Shredder, RECOMMENDED_FEC_RATE, SHRED_TICK_REFERENCE_MASK},
 };
+use solana_runtime::feature_set;
 use solana_sdk::{pubkey::Pubkey, signature::Keypair, timing::duration_as_us};
 use std::collections::HashMap;
 use std::sync::RwLock;
@@ -128,6 +129,25 @@ impl StandardBroadcastRun {
         data_shreds
     }
 
+    fn entries_to_merkle_shreds(
+        &mut self,
+        shredder: &Shredder,
+        next_shred_index: u32,
+        entries: &[Entry],
+        is_slot_end: bool,
+    ) -> (Vec<Shred>, Vec<Shred>) {
+        let (data_shreds, coding_shreds, new_next_shred_index) =
+            shredder.entries_to_merkle_shreds(entries, is_slot_end, next_shred_index);
+
+        self.unfinished_slot = Some(UnfinishedSlotInfo {
+            next_shred_index: new_next_shred_index,
+            slot: shredder.slot,
+            parent: shredder.parent_slot,
+        });
+
+        (data_shreds, coding_shreds)
+    }
+
     #[cfg(test)]
     fn test_process_receive_results(
         &mut self,
@@ -188,12 +208,28 @@ impl StandardBroadcastRun {
             (bank.tick_height() % bank.ticks_per_slot()) as u8,
         );
         let is_last_in_slot = last_tick_height == bank.max_tick_height();
-        let data_shreds = self.entries_to_data_shreds(
-            &shredder,
-            next_shred_index,
-            &receive_results.entries,
-            is_last_in_slot,
-        );
+        // Merkle variant shreds are erasure coded as they are signed, so
+        // their coding shreds come back together with the data shreds
+        let merkle_shreds = bank
+            .feature_set
+            .is_active(&feature_set::merkle_shreds::id());
+        let (data_shreds, coding_shreds) = if merkle_shreds {
+            let (data_shreds, coding_shreds) = self.entries_to_merkle_shreds(
+                &shredder,
+                next_shred_index,
+                &receive_results.entries,
+                is_last_in_slot,
+            );
+            (data_shreds, Some(coding_shreds))
+        } else {
+            let data_shreds = self.entries_to_data_shreds(
+                &shredder,
+                next_shred_index,
+                &receive_results.entries,
+                is_last_in_slot,
+            );
+            (data_shreds, None)
+        };
         // Insert the first shred so blockstore stores that the leader started this block
         // This must be done before the blocks are sent out over the wire.
         if !data_shreds.is_empty() && data_shreds[0].index() == 0 {
@@ -245,7 +281,9 @@ impl StandardBroadcastRun {
         let data_shreds = Arc::new(data_shreds);
         socket_sender.send(((stakes.clone(), data_shreds.clone()), batch_info.clone()))?;
         blockstore_sender.send((data_shreds.clone(), batch_info.clone()))?;
-        let coding_shreds = shredder.data_shreds_to_coding_shreds(&data_shreds[0..last_data_shred]);
+        let coding_shreds = coding_shreds.unwrap_or_else(|| {
+            shredder.data_shreds_to_coding_shreds(&data_shreds[0..last_data_shred])
+        });
         let coding_shreds = Arc::new(coding_shreds);
         socket_sender.send(((stakes, coding_shreds.clone()), batch_info.clone()))?;
         blockstore_sender.send((coding_shreds, batch_info))?;
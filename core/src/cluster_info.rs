@@ -18,17 +18,18 @@ use crate::{
     crds_gossip_error::CrdsGossipError,
     crds_gossip_pull::{CrdsFilter, ProcessPullStats, CRDS_GOSSIP_PULL_CRDS_TIMEOUT_MS},
     crds_value::{
-        self, CrdsData, CrdsValue, CrdsValueLabel, EpochSlotsIndex, LowestSlot, SnapshotHash,
-        Version, Vote, MAX_WALLCLOCK,
+        self, CrdsData, CrdsValue, CrdsValueLabel, EpochSlotsIndex, LowestSlot, NodeInstance,
+        SnapshotHash, TpuVoteAddress, Version, Vote, MAX_WALLCLOCK,
     },
     data_budget::DataBudget,
+    duplicate_shred::{self, DuplicateShred},
     epoch_slots::EpochSlots,
     result::{Error, Result},
     weighted_shuffle::weighted_shuffle,
 };
 
 use rand::distributions::{Distribution, WeightedIndex};
-use rand::SeedableRng;
+use rand::{thread_rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use solana_sdk::sanitize::{Sanitize, SanitizeError};
 
@@ -38,7 +39,7 @@ use itertools::Itertools;
 use rayon::iter::IntoParallelIterator;
 use rayon::iter::ParallelIterator;
 use rayon::{ThreadPool, ThreadPoolBuilder};
-use solana_ledger::staking_utils;
+use solana_ledger::{shred::Shred, staking_utils};
 use solana_measure::measure::Measure;
 use solana_measure::thread_mem_usage;
 use solana_metrics::{datapoint_debug, inc_new_counter_debug, inc_new_counter_error};
@@ -67,9 +68,10 @@ use std::{
     borrow::Cow,
     cmp::min,
     collections::{HashMap, HashSet},
-    fmt,
+    fmt, fs,
     net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, UdpSocket},
     ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
     sync::atomic::{AtomicBool, AtomicU64, Ordering},
     sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
     thread::{sleep, Builder, JoinHandle},
@@ -97,6 +99,15 @@ const MAX_GOSSIP_TRAFFIC: usize = 128_000_000 / PACKET_DATA_SIZE;
 
 /// Keep the number of snapshot hashes a node publishes under MAX_PROTOCOL_PAYLOAD_SIZE
 pub const MAX_SNAPSHOT_HASHES: usize = 16;
+/// Maximum serialized size of a DuplicateShred chunk, leaving room for the
+/// CrdsValue signature and enum tags within MAX_PROTOCOL_PAYLOAD_SIZE
+const DUPLICATE_SHRED_MAX_PAYLOAD_SIZE: usize = MAX_PROTOCOL_PAYLOAD_SIZE as usize - 115;
+/// File in the ledger directory that gossip values are backed up to
+pub const CRDS_BACKUP_FILE: &str = "gossip-crds.bin";
+/// How often the gossip thread backs up the crds table
+const CRDS_BACKUP_SAVE_INTERVAL_MS: u64 = 60_000;
+/// Backed up values signed longer ago than this are not restored
+pub const CRDS_BACKUP_MAX_AGE_MS: u64 = 60 * 60 * 1000;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ClusterInfoError {
@@ -104,6 +115,7 @@ pub enum ClusterInfoError {
     NoLeader,
     BadContactInfo,
     BadGossipAddress,
+    DuplicateNodeInstance,
 }
 
 struct GossipWriteLock<'a> {
@@ -228,6 +240,7 @@ struct GossipStats {
     prune_message_len: Counter,
     purge: Counter,
     epoch_slots_lookup: Counter,
+    duplicate_shreds_lookup: Counter,
     epoch_slots_push: Counter,
     push_message: Counter,
     new_pull_requests: Counter,
@@ -251,10 +264,15 @@ pub struct ClusterInfo {
     entrypoint: RwLock<Option<ContactInfo>>,
     outbound_budget: DataBudget,
     my_contact_info: RwLock<ContactInfo>,
+    /// Address votes are sent to, advertised as a TpuVoteAddress value
+    my_tpu_vote_address: RwLock<Option<SocketAddr>>,
     id: Pubkey,
     stats: GossipStats,
     socket: UdpSocket,
     local_message_pending_push_queue: RwLock<Vec<(CrdsValue, u64)>>,
+    instance: NodeInstance,
+    /// Where the crds table is backed up to, if anywhere
+    crds_backup_path: Option<PathBuf>,
 }
 
 impl Default for ClusterInfo {
@@ -356,7 +374,7 @@ pub fn make_accounts_hashes_message(
 }
 
 // TODO These messages should go through the gpu pipeline for spam filtering
-#[frozen_abi(digest = "21UweZ4WXK9RHypF97D1rEJQ4C8Bh4pw52SBSNAKxJvW")]
+#[frozen_abi(digest = "ibcd9rzY5wX2EQqfNqUva5Cvm5XY5kKn9oMXWvcZj2t")]
 #[derive(Serialize, Deserialize, Debug, AbiEnumVisitor, AbiExample)]
 #[allow(clippy::large_enum_variant)]
 enum Protocol {
@@ -459,10 +477,13 @@ impl ClusterInfo {
             entrypoint: RwLock::new(None),
             outbound_budget: DataBudget::default(),
             my_contact_info: RwLock::new(contact_info),
+            my_tpu_vote_address: RwLock::new(None),
             id,
             stats: GossipStats::default(),
             socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
             local_message_pending_push_queue: RwLock::new(vec![]),
+            instance: NodeInstance::new(&mut thread_rng(), id, timestamp()),
+            crds_backup_path: None,
         };
         {
             let mut gossip = me.gossip.write().unwrap();
@@ -486,6 +507,7 @@ impl ClusterInfo {
             entrypoint: RwLock::new(self.entrypoint.read().unwrap().clone()),
             outbound_budget: self.outbound_budget.clone_non_atomic(),
             my_contact_info: RwLock::new(my_contact_info),
+            my_tpu_vote_address: RwLock::new(*self.my_tpu_vote_address.read().unwrap()),
             id: *new_id,
             stats: GossipStats::default(),
             socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
@@ -495,9 +517,141 @@ impl ClusterInfo {
                     .unwrap()
                     .clone(),
             ),
+            instance: NodeInstance::new(&mut thread_rng(), *new_id, timestamp()),
+            crds_backup_path: self.crds_backup_path.clone(),
         }
     }
 
+    /// Returns true for the kinds of values which help a restarted node find
+    /// its peers and their snapshots before gossip has converged again
+    fn is_crds_backup_value(value: &CrdsValue) -> bool {
+        matches!(
+            value.data,
+            CrdsData::ContactInfo(_)
+                | CrdsData::SnapshotHashes(_)
+                | CrdsData::EpochSlots(_, _)
+                | CrdsData::LegacyVersion(_)
+                | CrdsData::Version(_)
+        )
+    }
+
+    /// Keeps the values of other nodes which are recent enough and, if
+    /// `shred_version` is known, whose contact info matches it
+    fn prune_crds_backup(
+        values: Vec<CrdsValue>,
+        self_id: &Pubkey,
+        shred_version: u16,
+        now: u64,
+        max_age_ms: u64,
+    ) -> Vec<CrdsValue> {
+        let values: Vec<_> = values
+            .into_iter()
+            .filter(|value| {
+                value.pubkey() != *self_id
+                    && Self::is_crds_backup_value(value)
+                    && now.saturating_sub(value.wallclock()) <= max_age_ms
+            })
+            .collect();
+        let nodes: HashSet<_> = values
+            .iter()
+            .filter_map(CrdsValue::contact_info)
+            .filter(|node| shred_version == 0 || node.shred_version == shred_version)
+            .map(|node| node.id)
+            .collect();
+        values
+            .into_iter()
+            .filter(|value| nodes.contains(&value.pubkey()))
+            .collect()
+    }
+
+    fn write_crds_backup(path: &Path, values: &[CrdsValue]) -> Result<()> {
+        // Write to a temporary file first, so that a crash mid-write does not
+        // leave a truncated backup behind
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serialize(values)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    fn read_crds_backup(path: &Path) -> Result<Vec<CrdsValue>> {
+        let bytes = fs::read(path)?;
+        Ok(bincode::deserialize(&bytes)?)
+    }
+
+    /// Backs up the gossip values of other nodes, if a backup path was set
+    /// by `restore_crds_backup`. Only copying the values holds the gossip
+    /// lock; they are serialized and written out on the returned thread.
+    pub fn save_crds_backup(&self) -> Option<JoinHandle<()>> {
+        let path = self.crds_backup_path.clone()?;
+        let values: Vec<_> = self
+            .gossip
+            .read()
+            .unwrap()
+            .crds
+            .table
+            .values()
+            .filter(|versioned| Self::is_crds_backup_value(&versioned.value))
+            .map(|versioned| versioned.value.clone())
+            .collect();
+        let self_id = self.id();
+        let shred_version = self.my_shred_version();
+        Builder::new()
+            .name("solana-crds-backup".to_string())
+            .spawn(move || {
+                let values = Self::prune_crds_backup(
+                    values,
+                    &self_id,
+                    shred_version,
+                    timestamp(),
+                    CRDS_BACKUP_MAX_AGE_MS,
+                );
+                match Self::write_crds_backup(&path, &values) {
+                    Ok(()) => info!("Saved {} gossip values to {:?}", values.len(), path),
+                    Err(err) => warn!("Failed to save gossip values to {:?}: {:?}", path, err),
+                }
+            })
+            .map_err(|err| warn!("Failed to spawn gossip backup thread: {:?}", err))
+            .ok()
+    }
+
+    /// Restores the gossip values backed up in `ledger_path` by a previous
+    /// run, so that pull and push peers are known before the entrypoint
+    /// answers, and backs up to the same file from now on
+    pub fn restore_crds_backup(&mut self, ledger_path: &Path) {
+        let path = ledger_path.join(CRDS_BACKUP_FILE);
+        match Self::read_crds_backup(&path) {
+            Ok(values) => {
+                let now = timestamp();
+                let values: Vec<_> = values
+                    .into_iter()
+                    .filter(|value| value.sanitize().is_ok() && value.verify())
+                    .collect();
+                let values = Self::prune_crds_backup(
+                    values,
+                    &self.id,
+                    self.my_shred_version(),
+                    now,
+                    CRDS_BACKUP_MAX_AGE_MS,
+                );
+                let mut num_restored = 0;
+                let mut gossip = self.gossip.write().unwrap();
+                for value in values {
+                    // Keep the original wallclock as the local timestamp, so
+                    // that values nobody refreshes are purged on the usual
+                    // timeouts rather than outliving them by a restart
+                    let local_timestamp = value.wallclock().min(now);
+                    if gossip.crds.insert(value, local_timestamp).is_ok() {
+                        num_restored += 1;
+                    }
+                }
+                gossip.refresh_push_active_set(&HashMap::new(), None);
+                info!("Restored {} gossip values from {:?}", num_restored, path);
+            }
+            Err(err) => info!("No gossip values restored from {:?}: {:?}", path, err),
+        }
+        self.crds_backup_path = Some(path);
+    }
+
     pub fn update_contact_info<F>(&self, modify: F)
     where
         F: FnOnce(&mut ContactInfo),
@@ -515,8 +669,21 @@ impl ClusterInfo {
     ) {
         let now = timestamp();
         self.my_contact_info.write().unwrap().wallclock = now;
-        let entry =
-            CrdsValue::new_signed(CrdsData::ContactInfo(self.my_contact_info()), &self.keypair);
+        let mut entries = vec![
+            CrdsData::ContactInfo(self.my_contact_info()),
+            CrdsData::NodeInstance(self.instance.with_wallclock(now)),
+        ];
+        if let Some(address) = self.my_tpu_vote_address() {
+            entries.push(CrdsData::TpuVoteAddress(TpuVoteAddress::new(
+                self.id(),
+                address,
+                now,
+            )));
+        }
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|v| (CrdsValue::new_signed(v, &self.keypair), now))
+            .collect();
         self.gossip
             .write()
             .unwrap()
@@ -524,7 +691,7 @@ impl ClusterInfo {
         self.local_message_pending_push_queue
             .write()
             .unwrap()
-            .push((entry, now));
+            .extend(entries);
     }
 
     // TODO kill insert_info, only used by tests
@@ -574,6 +741,35 @@ impl ClusterInfo {
         self.my_contact_info.read().unwrap().clone()
     }
 
+    pub fn my_tpu_vote_address(&self) -> Option<SocketAddr> {
+        *self.my_tpu_vote_address.read().unwrap()
+    }
+
+    /// Advertise `address` as the port this node receives votes on
+    pub fn set_tpu_vote_address(&self, address: SocketAddr) {
+        *self.my_tpu_vote_address.write().unwrap() = Some(address);
+        let value = CrdsValue::new_signed(
+            CrdsData::TpuVoteAddress(TpuVoteAddress::new(self.id(), address, timestamp())),
+            &self.keypair,
+        );
+        let _ = self.gossip.write().unwrap().crds.insert(value, timestamp());
+    }
+
+    /// Returns the address `id` receives votes on. Nodes which do not
+    /// advertise a vote port take votes on their TPU
+    pub fn lookup_tpu_vote_address(&self, id: &Pubkey) -> Option<SocketAddr> {
+        let entry = CrdsValueLabel::TpuVoteAddress(*id);
+        let address = self
+            .gossip
+            .read()
+            .unwrap()
+            .crds
+            .lookup(&entry)
+            .and_then(CrdsValue::tpu_vote_address)
+            .map(|address| address.address);
+        address.or_else(|| self.lookup_contact_info(id, |info| info.tpu))
+    }
+
     pub fn my_shred_version(&self) -> u16 {
         self.my_contact_info.read().unwrap().shred_version
     }
@@ -872,6 +1068,47 @@ impl ClusterInfo {
         self.push_message(CrdsValue::new_signed(message, &self.keypair));
     }
 
+    /// Publish a proof that `shred` and `other_payload` are conflicting shreds
+    /// for the same slot and index, split into as many chunks as necessary
+    pub fn push_duplicate_shred(
+        &self,
+        shred: &Shred,
+        other_payload: &[u8],
+    ) -> std::result::Result<(), duplicate_shred::Error> {
+        let now = timestamp();
+        let chunks = duplicate_shred::from_duplicate_slot_proof(
+            shred,
+            other_payload.to_vec(),
+            self.id(),
+            now,
+            DUPLICATE_SHRED_MAX_PAYLOAD_SIZE,
+        )?;
+        // Overwrite this node's oldest duplicate shred chunks first
+        let mut indices: Vec<_> = {
+            let r_gossip = self.time_gossip_read_lock(
+                "lookup_duplicate_shreds",
+                &self.stats.duplicate_shreds_lookup,
+            );
+            (0..crds_value::MAX_DUPLICATE_SHREDS)
+                .map(|ix| {
+                    let wallclock = r_gossip
+                        .crds
+                        .lookup(&CrdsValueLabel::DuplicateShred(ix, self.id()))
+                        .map(CrdsValue::wallclock)
+                        .unwrap_or_default();
+                    (wallclock, ix)
+                })
+                .collect()
+        };
+        indices.sort_unstable();
+        let mut pending_push_queue = self.local_message_pending_push_queue.write().unwrap();
+        for (chunk, (_, ix)) in chunks.zip(indices) {
+            let entry = CrdsValue::new_signed(CrdsData::DuplicateShred(ix, chunk), &self.keypair);
+            pending_push_queue.push((entry, now));
+        }
+        Ok(())
+    }
+
     pub fn push_vote(&self, tower_index: usize, vote: Transaction) {
         let now = timestamp();
         let vote = Vote::new(&self.id(), vote, now);
@@ -891,9 +1128,11 @@ impl ClusterInfo {
     }
 
     pub fn send_vote(&self, vote: &Transaction) -> Result<()> {
-        let tpu = self.my_contact_info().tpu;
+        let tpu_vote = self
+            .my_tpu_vote_address()
+            .unwrap_or_else(|| self.my_contact_info().tpu);
         let buf = serialize(vote)?;
-        self.socket.send_to(&buf, &tpu)?;
+        self.socket.send_to(&buf, &tpu_vote)?;
         Ok(())
     }
 
@@ -1007,6 +1246,59 @@ impl ClusterInfo {
         (vec, max)
     }
 
+    /// Returns the duplicate slot proofs which gained a chunk in gossip since
+    /// `cursor`, once all of their chunks have arrived, along with the cursor
+    /// to pass in on the next call.  Each proof is reassembled and verified
+    /// against the leader given by `slot_leader`; proofs which fail
+    /// verification are dropped.
+    pub fn get_duplicate_shreds_since<F>(
+        &self,
+        cursor: usize,
+        slot_leader: F,
+    ) -> (Vec<(Shred, Shred)>, usize)
+    where
+        F: Fn(Slot) -> Option<Pubkey>,
+    {
+        let (chunks, cursor) = {
+            let r_gossip = self.time_gossip_read_lock(
+                "get_duplicate_shreds_since",
+                &self.stats.duplicate_shreds_lookup,
+            );
+            let (new_chunks, cursor) = r_gossip.crds.get_duplicate_shreds_since(cursor);
+            let updated: HashSet<(Pubkey, Slot)> = new_chunks
+                .into_iter()
+                .filter_map(CrdsValue::duplicate_shred)
+                .map(|chunk| (chunk.from, chunk.slot))
+                .collect();
+            // Proofs are reassembled from all of their chunks, including
+            // those which arrived before `cursor`
+            let origins: HashSet<Pubkey> = updated.iter().map(|(from, _)| *from).collect();
+            let chunks: Vec<DuplicateShred> = origins
+                .iter()
+                .flat_map(|from| r_gossip.crds.get_duplicate_shreds_from(from))
+                .filter_map(CrdsValue::duplicate_shred)
+                .filter(|chunk| updated.contains(&(chunk.from, chunk.slot)))
+                .cloned()
+                .collect();
+            (chunks, cursor)
+        };
+        let proofs = duplicate_shred::complete_proofs(chunks)
+            .into_iter()
+            .filter_map(|chunks| {
+                let from = chunks[0].from;
+                match duplicate_shred::into_shreds(chunks, &slot_leader) {
+                    Ok(shreds) => Some(shreds),
+                    Err(err) => {
+                        inc_new_counter_error!("cluster_info-invalid_duplicate_shred", 1);
+                        warn!("invalid duplicate shred proof from {}: {}", from, err);
+                        None
+                    }
+                }
+            })
+            .collect();
+        (proofs, cursor)
+    }
+
     pub fn get_node_version(&self, pubkey: &Pubkey) -> Option<solana_version::Version> {
         let version = self
             .gossip
@@ -1705,6 +1997,8 @@ impl ClusterInfo {
             .spawn(move || {
                 let mut last_push = timestamp();
                 let mut last_contact_info_trace = timestamp();
+                let mut last_crds_backup = timestamp();
+                let mut crds_backup: Option<JoinHandle<()>> = None;
                 let mut adopt_shred_version = self.my_shred_version() == 0;
                 let recycler = PacketsRecycler::default();
 
@@ -1739,7 +2033,19 @@ impl ClusterInfo {
                         &sender,
                         generate_pull_requests,
                     );
-                    if exit.load(Ordering::Relaxed) {
+                    let exiting = exit.load(Ordering::Relaxed);
+                    if exiting || start - last_crds_backup > CRDS_BACKUP_SAVE_INTERVAL_MS {
+                        // Never have two backups writing to the same file
+                        if let Some(handle) = crds_backup.take() {
+                            let _ = handle.join();
+                        }
+                        crds_backup = self.save_crds_backup();
+                        last_crds_backup = timestamp();
+                    }
+                    if exiting {
+                        if let Some(handle) = crds_backup.take() {
+                            let _ = handle.join();
+                        }
                         return;
                     }
 
@@ -1773,7 +2079,17 @@ impl ClusterInfo {
         response_sender: &PacketSender,
         feature_set: Option<&FeatureSet>,
         epoch_time_ms: u64,
-    ) {
+    ) -> Result<()> {
+        // Check if there is a duplicate instance of
+        // this node with more recent timestamp.
+        let check_duplicate_instance = |values: &[CrdsValue]| {
+            for value in values {
+                if self.instance.check_duplicate(value) {
+                    return Err(Error::from(ClusterInfoError::DuplicateNodeInstance));
+                }
+            }
+            Ok(())
+        };
         // iter over the packets, collect pulls separately and process everything else
         let allocated = thread_mem_usage::Allocatedp::default();
         let mut gossip_pull_data: Vec<PullData> = vec![];
@@ -1810,6 +2126,7 @@ impl ClusterInfo {
                     );
                 }
                 Protocol::PullResponse(from, data) => {
+                    check_duplicate_instance(&data)?;
                     let start = allocated.get();
                     let pull_entry = pull_responses.entry(from).or_insert_with(Vec::new);
                     pull_entry.extend(data);
@@ -1819,6 +2136,7 @@ impl ClusterInfo {
                     );
                 }
                 Protocol::PushMessage(from, data) => {
+                    check_duplicate_instance(&data)?;
                     let start = allocated.get();
                     let rsp = self.handle_push_message(recycler, &from, data, stakes);
                     if let Some(rsp) = rsp {
@@ -1874,6 +2192,7 @@ impl ClusterInfo {
                 let _ignore_disconnect = response_sender.send(rsp);
             }
         }
+        Ok(())
     }
 
     fn update_data_budget(&self, num_staked: usize) {
@@ -2210,7 +2529,7 @@ impl ClusterInfo {
         stakes: HashMap<Pubkey, u64>,
         feature_set: Option<&FeatureSet>,
         epoch_time_ms: u64,
-    ) {
+    ) -> Result<()> {
         let mut timer = Measure::start("process_gossip_packets_time");
         let packets: Vec<_> = thread_pool.install(|| {
             requests
@@ -2232,10 +2551,11 @@ impl ClusterInfo {
             response_sender,
             feature_set,
             epoch_time_ms,
-        );
+        )?;
         self.stats
             .process_gossip_packets_time
             .add_measure(&mut timer);
+        Ok(())
     }
 
     /// Process messages from the network
@@ -2287,7 +2607,7 @@ impl ClusterInfo {
             stakes,
             feature_set.as_deref(),
             epoch_time_ms,
-        );
+        )?;
 
         self.print_reset_stats(last_print);
 
@@ -2425,6 +2745,11 @@ impl ClusterInfo {
                     self.stats.epoch_slots_lookup.clear(),
                     i64
                 ),
+                (
+                    "duplicate_shreds_lookup",
+                    self.stats.duplicate_shreds_lookup.clear(),
+                    i64
+                ),
                 ("epoch_slots_push", self.stats.epoch_slots_push.clear(), i64),
                 ("push_message", self.stats.push_message.clear(), i64),
                 (
@@ -2524,13 +2849,24 @@ impl ClusterInfo {
                     if exit.load(Ordering::Relaxed) {
                         return;
                     }
-                    if e.is_err() {
-                        let r_gossip = self.gossip.read().unwrap();
-                        debug!(
-                            "{}: run_listen timeout, table size: {}",
-                            self.id(),
-                            r_gossip.crds.table.len()
-                        );
+                    match e {
+                        Err(Error::ClusterInfoError(ClusterInfoError::DuplicateNodeInstance)) => {
+                            error!(
+                                "duplicate running instances of the same validator node: {}",
+                                self.id()
+                            );
+                            exit.store(true, Ordering::Relaxed);
+                            return;
+                        }
+                        Err(_) => {
+                            let r_gossip = self.gossip.read().unwrap();
+                            debug!(
+                                "{}: run_listen timeout, table size: {}",
+                                self.id(),
+                                r_gossip.crds.table.len()
+                            );
+                        }
+                        Ok(()) => (),
                     }
                     thread_mem_usage::datapoint("solana-listen");
                 }
@@ -2621,15 +2957,20 @@ pub struct Sockets {
     pub tvu_forwards: Vec<UdpSocket>,
     pub tpu: Vec<UdpSocket>,
     pub tpu_forwards: Vec<UdpSocket>,
+    pub tpu_vote: Vec<UdpSocket>,
     pub broadcast: Vec<UdpSocket>,
     pub repair: UdpSocket,
     pub retransmit_sockets: Vec<UdpSocket>,
     pub serve_repair: UdpSocket,
+    pub ancestor_hashes_requests: UdpSocket,
 }
 
 #[derive(Debug)]
 pub struct Node {
     pub info: ContactInfo,
+    /// Advertised address of `sockets.tpu_vote`, which is not part of
+    /// ContactInfo
+    pub tpu_vote: SocketAddr,
     pub sockets: Sockets,
 }
 
@@ -2647,6 +2988,7 @@ impl Node {
         let tvu = UdpSocket::bind("127.0.0.1:0").unwrap();
         let tvu_forwards = UdpSocket::bind("127.0.0.1:0").unwrap();
         let tpu_forwards = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tpu_vote = UdpSocket::bind("127.0.0.1:0").unwrap();
         let repair = UdpSocket::bind("127.0.0.1:0").unwrap();
         let rpc_port = find_available_port_in_range(bind_ip_addr, (1024, 65535)).unwrap();
         let rpc_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), rpc_port);
@@ -2660,6 +3002,7 @@ impl Node {
         let broadcast = vec![UdpSocket::bind("0.0.0.0:0").unwrap()];
         let retransmit_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let serve_repair = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ancestor_hashes_requests = UdpSocket::bind("127.0.0.1:0").unwrap();
         let info = ContactInfo {
             id: *pubkey,
             gossip: gossip_addr,
@@ -2677,6 +3020,7 @@ impl Node {
         };
         Node {
             info,
+            tpu_vote: tpu_vote.local_addr().unwrap(),
             sockets: Sockets {
                 gossip,
                 ip_echo: Some(ip_echo),
@@ -2684,10 +3028,12 @@ impl Node {
                 tvu_forwards: vec![tvu_forwards],
                 tpu: vec![tpu],
                 tpu_forwards: vec![tpu_forwards],
+                tpu_vote: vec![tpu_vote],
                 broadcast,
                 repair,
                 retransmit_sockets: vec![retransmit_socket],
                 serve_repair,
+                ancestor_hashes_requests,
             },
         }
     }
@@ -2732,11 +3078,15 @@ impl Node {
         let (tpu_forwards_port, tpu_forwards_sockets) =
             multi_bind_in_range(bind_ip_addr, port_range, 8).expect("tpu_forwards multi_bind");
 
+        let (tpu_vote_port, tpu_vote_sockets) =
+            multi_bind_in_range(bind_ip_addr, port_range, 1).expect("tpu_vote multi_bind");
+
         let (_, retransmit_sockets) =
             multi_bind_in_range(bind_ip_addr, port_range, 8).expect("retransmit multi_bind");
 
         let (repair_port, repair) = Self::bind(bind_ip_addr, port_range);
         let (serve_repair_port, serve_repair) = Self::bind(bind_ip_addr, port_range);
+        let (_, ancestor_hashes_requests) = Self::bind(bind_ip_addr, port_range);
 
         let (_, broadcast) =
             multi_bind_in_range(bind_ip_addr, port_range, 4).expect("broadcast multi_bind");
@@ -2760,17 +3110,20 @@ impl Node {
 
         Node {
             info,
+            tpu_vote: SocketAddr::new(gossip_addr.ip(), tpu_vote_port),
             sockets: Sockets {
                 gossip,
                 tvu: tvu_sockets,
                 tvu_forwards: tvu_forwards_sockets,
                 tpu: tpu_sockets,
                 tpu_forwards: tpu_forwards_sockets,
+                tpu_vote: tpu_vote_sockets,
                 broadcast,
                 repair,
                 retransmit_sockets,
                 serve_repair,
                 ip_echo: Some(ip_echo),
+                ancestor_hashes_requests,
             },
         }
     }
@@ -2788,7 +3141,9 @@ pub fn stake_weight_peers<S: std::hash::BuildHasher>(
 mod tests {
     use super::*;
     use crate::crds_value::{CrdsValue, CrdsValueLabel, Vote as CrdsVote};
+    use crate::duplicate_shred::tests::new_rand_shred;
     use rayon::prelude::*;
+    use solana_ledger::shred::Shredder;
     use solana_perf::test_tx::test_tx;
     use solana_sdk::signature::{Keypair, Signer};
     use solana_vote_program::{vote_instruction, vote_state::Vote};
@@ -2924,6 +3279,80 @@ mod tests {
         assert_eq!(d.id, cluster_info.id());
     }
 
+    #[test]
+    fn test_crds_backup_save_and_restore() {
+        let ledger_dir = tempfile::tempdir().unwrap();
+        let new_node = |shred_version, wallclock| {
+            let keypair = Keypair::new();
+            let mut node = ContactInfo::new_localhost(&keypair.pubkey(), wallclock);
+            node.shred_version = shred_version;
+            let value = CrdsValue::new_signed(CrdsData::ContactInfo(node.clone()), &keypair);
+            (node, keypair, value)
+        };
+        let (me, keypair, _) = new_node(1, timestamp());
+        let mut cluster_info = ClusterInfo::new(me, Arc::new(keypair));
+        // Nothing to restore yet, but this sets where the backup goes
+        cluster_info.restore_crds_backup(ledger_dir.path());
+
+        let now = timestamp();
+        let (peer, peer_keypair, peer_value) = new_node(1, now - 1000);
+        let (stale_peer, _, stale_value) = new_node(1, now - 2 * CRDS_BACKUP_MAX_AGE_MS);
+        let (other_cluster_peer, _, other_cluster_value) = new_node(2, now);
+        let peer_snapshot_hashes = CrdsValue::new_signed(
+            CrdsData::SnapshotHashes(SnapshotHash::new(peer.id, vec![(5, Hash::default())])),
+            &peer_keypair,
+        );
+        let peer_accounts_hashes = CrdsValue::new_signed(
+            CrdsData::AccountsHashes(SnapshotHash::new(peer.id, vec![(5, Hash::default())])),
+            &peer_keypair,
+        );
+        {
+            let mut gossip = cluster_info.gossip.write().unwrap();
+            for value in vec![
+                peer_value,
+                stale_value,
+                other_cluster_value,
+                peer_snapshot_hashes,
+                peer_accounts_hashes,
+            ] {
+                gossip.crds.insert(value, now).unwrap();
+            }
+        }
+        cluster_info.save_crds_backup().unwrap().join().unwrap();
+        assert!(ledger_dir.path().join(CRDS_BACKUP_FILE).exists());
+
+        let (me, keypair, _) = new_node(1, timestamp());
+        let mut restored = ClusterInfo::new(me, Arc::new(keypair));
+        restored.restore_crds_backup(ledger_dir.path());
+        assert!(restored.lookup_contact_info(&peer.id, |_| ()).is_some());
+        // Restored values are not made to look any fresher than they are
+        assert_eq!(
+            restored
+                .gossip
+                .read()
+                .unwrap()
+                .crds
+                .table
+                .get(&CrdsValueLabel::ContactInfo(peer.id))
+                .unwrap()
+                .local_timestamp,
+            now - 1000
+        );
+        assert!(restored
+            .lookup_contact_info(&stale_peer.id, |_| ())
+            .is_none());
+        assert!(restored
+            .lookup_contact_info(&other_cluster_peer.id, |_| ())
+            .is_none());
+        assert_eq!(
+            restored.get_snapshot_hash_for_node(&peer.id, |hashes| hashes.clone()),
+            Some(vec![(5, Hash::default())])
+        );
+        assert!(restored
+            .get_accounts_hash_for_node(&peer.id, |hashes| hashes.clone())
+            .is_none());
+    }
+
     #[test]
     fn insert_info_test() {
         let d = ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp());
@@ -2940,6 +3369,23 @@ mod tests {
             .is_some());
     }
     #[test]
+    fn test_tpu_vote_address() {
+        let d = ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp());
+        let cluster_info = ClusterInfo::new_with_invalid_keypair(d.clone());
+        // Without an advertised vote port, votes go to the TPU
+        assert_eq!(cluster_info.my_tpu_vote_address(), None);
+        assert_eq!(cluster_info.lookup_tpu_vote_address(&d.id), Some(d.tpu));
+
+        let tpu_vote = socketaddr!("127.0.0.1:1250");
+        cluster_info.set_tpu_vote_address(tpu_vote);
+        assert_eq!(cluster_info.my_tpu_vote_address(), Some(tpu_vote));
+        assert_eq!(cluster_info.lookup_tpu_vote_address(&d.id), Some(tpu_vote));
+        assert_eq!(
+            cluster_info.lookup_tpu_vote_address(&solana_sdk::pubkey::new_rand()),
+            None
+        );
+    }
+    #[test]
     #[should_panic]
     fn test_update_contact_info() {
         let d = ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp());
@@ -2997,9 +3443,11 @@ mod tests {
     fn check_node_sockets(node: &Node, ip: IpAddr, range: (u16, u16)) {
         check_socket(&node.sockets.gossip, ip, range);
         check_socket(&node.sockets.repair, ip, range);
+        check_socket(&node.sockets.ancestor_hashes_requests, ip, range);
 
         check_sockets(&node.sockets.tvu, ip, range);
         check_sockets(&node.sockets.tpu, ip, range);
+        check_sockets(&node.sockets.tpu_vote, ip, range);
     }
 
     #[test]
@@ -3285,6 +3733,54 @@ mod tests {
         assert_eq!(since2, since);
     }
 
+    #[test]
+    fn test_push_duplicate_shred() {
+        let mut rng = rand::thread_rng();
+        let keys = Keypair::new();
+        let contact_info = ContactInfo::new_localhost(&keys.pubkey(), 0);
+        let cluster_info = ClusterInfo::new_with_invalid_keypair(contact_info);
+        let leader = Arc::new(Keypair::new());
+        let slot = 42;
+        let shredder = Shredder::new(slot, slot - 1, 0.0, leader.clone(), 0, 0).unwrap();
+        let shred1 = new_rand_shred(&mut rng, 7, &shredder);
+        let shred2 = new_rand_shred(&mut rng, 7, &shredder);
+        let slot_leader = |s| {
+            if s == slot {
+                Some(leader.pubkey())
+            } else {
+                None
+            }
+        };
+
+        let (proofs, cursor) = cluster_info.get_duplicate_shreds_since(0, slot_leader);
+        assert!(proofs.is_empty());
+        assert_eq!(cursor, 0);
+
+        cluster_info
+            .push_duplicate_shred(&shred1, &shred2.payload)
+            .unwrap();
+        cluster_info.flush_push_queue();
+        for value in cluster_info.gossip.read().unwrap().crds.table.values() {
+            if value.value.duplicate_shred().is_some() {
+                assert!(value.value.size() <= MAX_PROTOCOL_PAYLOAD_SIZE);
+            }
+        }
+
+        let (proofs, cursor) = cluster_info.get_duplicate_shreds_since(0, slot_leader);
+        assert_eq!(proofs, vec![(shred1, shred2)]);
+        assert!(cursor > 0);
+
+        // Nothing new since the last call
+        let (proofs, cursor2) = cluster_info.get_duplicate_shreds_since(cursor, slot_leader);
+        assert!(proofs.is_empty());
+        assert_eq!(cursor2, cursor);
+
+        // Proofs not signed by the slot leader are dropped
+        let impostor = Keypair::new().pubkey();
+        let (proofs, _) = cluster_info.get_duplicate_shreds_since(0, |_| Some(impostor));
+        assert!(proofs.is_empty());
+    }
+
     #[test]
     fn test_append_entrypoint_to_pulls() {
         let thread_pool = ThreadPoolBuilder::new().build().unwrap();
@@ -75,6 +75,16 @@ impl SlotVoteTracker {
     pub fn optimistic_votes_tracker(&self, hash: &Hash) -> Option<&VoteStakeTracker> {
         self.optimistic_votes_tracker.get(hash)
     }
+
+    // Returns the hashes of this slot that have been voted on by more
+    // than `supermajority_stake`
+    pub fn optimistically_confirmed_hashes(&self, supermajority_stake: u64) -> Vec<Hash> {
+        self.optimistic_votes_tracker
+            .iter()
+            .filter(|(_, tracker)| tracker.stake() > supermajority_stake)
+            .map(|(hash, _)| *hash)
+            .collect()
+    }
 }
 
 #[derive(Default)]
@@ -26,13 +26,14 @@
 
 use crate::crds_shards::CrdsShards;
 use crate::crds_value::{CrdsValue, CrdsValueLabel};
+use crate::duplicate_shred::DuplicateShredIndex;
 use bincode::serialize;
 use indexmap::map::{Entry, IndexMap};
 use rayon::{prelude::*, ThreadPool};
 use solana_sdk::hash::{hash, Hash};
 use solana_sdk::pubkey::Pubkey;
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Index;
 
 const CRDS_SHARDS_BITS: u32 = 8;
@@ -43,6 +44,12 @@ pub struct Crds {
     pub table: IndexMap<CrdsValueLabel, VersionedCrdsValue>,
     pub num_inserts: usize,
     pub shards: CrdsShards,
+    /// DuplicateShred labels keyed by the order they were inserted in, so that
+    /// new duplicate shred chunks can be found without scanning the table
+    duplicate_shreds: BTreeMap<usize, CrdsValueLabel>,
+    /// Insert order of each DuplicateShred value in the table, by origin and index
+    duplicate_shred_ordinals: HashMap<Pubkey, HashMap<DuplicateShredIndex, usize>>,
+    num_duplicate_shred_inserts: usize,
 }
 
 #[derive(PartialEq, Debug)]
@@ -93,6 +100,9 @@ impl Default for Crds {
             table: IndexMap::new(),
             num_inserts: 0,
             shards: CrdsShards::new(CRDS_SHARDS_BITS),
+            duplicate_shreds: BTreeMap::new(),
+            duplicate_shred_ordinals: HashMap::new(),
+            num_duplicate_shred_inserts: 0,
         }
     }
 }
@@ -120,11 +130,12 @@ impl Crds {
         new_value: VersionedCrdsValue,
     ) -> Result<Option<VersionedCrdsValue>, CrdsError> {
         let label = new_value.value.label();
-        match self.table.entry(label) {
+        match self.table.entry(label.clone()) {
             Entry::Vacant(entry) => {
                 assert!(self.shards.insert(entry.index(), &new_value));
                 entry.insert(new_value);
                 self.num_inserts += 1;
+                self.index_duplicate_shred(label);
                 Ok(None)
             }
             Entry::Occupied(mut entry) if *entry.get() < new_value => {
@@ -132,7 +143,10 @@ impl Crds {
                 assert!(self.shards.remove(index, entry.get()));
                 assert!(self.shards.insert(index, &new_value));
                 self.num_inserts += 1;
-                Ok(Some(entry.insert(new_value)))
+                let old_value = entry.insert(new_value);
+                self.unindex_duplicate_shred(&label);
+                self.index_duplicate_shred(label);
+                Ok(Some(old_value))
             }
             _ => {
                 trace!(
@@ -171,6 +185,64 @@ impl Crds {
         for label in &CrdsValue::record_labels(pubkey) {
             self.update_label_timestamp(label, now);
         }
+        // Duplicate shred chunks are looked up through the index rather than
+        // record_labels, which would otherwise list every possible index
+        if let Some(ordinals) = self.duplicate_shred_ordinals.get(pubkey) {
+            for ix in ordinals.keys() {
+                let label = CrdsValueLabel::DuplicateShred(*ix, *pubkey);
+                if let Some(e) = self.table.get_mut(&label) {
+                    e.local_timestamp = cmp::max(e.local_timestamp, now);
+                }
+            }
+        }
+    }
+
+    /// Returns the DuplicateShred values inserted since `cursor`, and the
+    /// cursor to pass in on the next call to only get newer values
+    pub fn get_duplicate_shreds_since(&self, cursor: usize) -> (Vec<&CrdsValue>, usize) {
+        let values = self
+            .duplicate_shreds
+            .range(cursor..)
+            .map(|(_, label)| &self.table[label].value)
+            .collect();
+        (values, self.num_duplicate_shred_inserts)
+    }
+
+    /// Returns all the DuplicateShred values from `pubkey`
+    pub fn get_duplicate_shreds_from<'a>(
+        &'a self,
+        pubkey: &'a Pubkey,
+    ) -> impl Iterator<Item = &'a CrdsValue> + 'a {
+        self.duplicate_shred_ordinals
+            .get(pubkey)
+            .into_iter()
+            .flat_map(HashMap::keys)
+            .filter_map(move |ix| self.lookup(&CrdsValueLabel::DuplicateShred(*ix, *pubkey)))
+    }
+
+    fn index_duplicate_shred(&mut self, label: CrdsValueLabel) {
+        if let CrdsValueLabel::DuplicateShred(ix, pubkey) = label {
+            let ordinal = self.num_duplicate_shred_inserts;
+            self.num_duplicate_shred_inserts += 1;
+            self.duplicate_shred_ordinals
+                .entry(pubkey)
+                .or_default()
+                .insert(ix, ordinal);
+            self.duplicate_shreds.insert(ordinal, label);
+        }
+    }
+
+    fn unindex_duplicate_shred(&mut self, label: &CrdsValueLabel) {
+        if let CrdsValueLabel::DuplicateShred(ix, pubkey) = label {
+            if let Some(ordinals) = self.duplicate_shred_ordinals.get_mut(pubkey) {
+                if let Some(ordinal) = ordinals.remove(ix) {
+                    self.duplicate_shreds.remove(&ordinal);
+                }
+                if ordinals.is_empty() {
+                    self.duplicate_shred_ordinals.remove(pubkey);
+                }
+            }
+        }
     }
 
     /// Find all the keys that are older or equal to the timeout.
@@ -203,6 +275,7 @@ impl Crds {
     pub fn remove(&mut self, key: &CrdsValueLabel) -> Option<VersionedCrdsValue> {
         let (index, _, value) = self.table.swap_remove_full(key)?;
         assert!(self.shards.remove(index, &value));
+        self.unindex_duplicate_shred(key);
         // The previously last element in the table is now moved to the
         // 'index' position. Shards need to be updated accordingly.
         if index < self.table.len() {
@@ -219,8 +292,12 @@ mod test {
     use super::*;
     use crate::contact_info::ContactInfo;
     use crate::crds_value::CrdsData;
+    use crate::duplicate_shred::{self, tests::new_rand_shred};
     use rand::{thread_rng, Rng};
     use rayon::ThreadPoolBuilder;
+    use solana_ledger::shred::Shredder;
+    use solana_sdk::signature::Keypair;
+    use std::sync::Arc;
 
     #[test]
     fn test_insert() {
@@ -291,6 +368,60 @@ mod test {
         assert_eq!(crds.table[&val2.label()].local_timestamp, 3);
         assert_eq!(crds.table[&val2.label()].insert_timestamp, 3);
     }
+    #[test]
+    fn test_duplicate_shred_index() {
+        let mut rng = thread_rng();
+        let mut crds = Crds::default();
+        let leader = Arc::new(Keypair::new());
+        let shredder = Shredder::new(42, 41, 0.0, leader, 0, 0).unwrap();
+        let shred1 = new_rand_shred(&mut rng, 7, &shredder);
+        let shred2 = new_rand_shred(&mut rng, 7, &shredder);
+        let from = solana_sdk::pubkey::new_rand();
+        let new_chunks = |wallclock| -> Vec<CrdsValue> {
+            duplicate_shred::from_duplicate_slot_proof(
+                &shred1,
+                shred2.payload.clone(),
+                from,
+                wallclock,
+                512,
+            )
+            .unwrap()
+            .enumerate()
+            .map(|(ix, chunk)| CrdsValue::new_unsigned(CrdsData::DuplicateShred(ix as u16, chunk)))
+            .collect()
+        };
+        let chunks = new_chunks(0);
+        assert!(chunks.len() > 1);
+
+        let (values, cursor) = crds.get_duplicate_shreds_since(0);
+        assert!(values.is_empty());
+        for chunk in &chunks {
+            assert_eq!(crds.insert(chunk.clone(), 0), Ok(None));
+        }
+        let (values, cursor) = crds.get_duplicate_shreds_since(cursor);
+        assert_eq!(values.len(), chunks.len());
+        assert_eq!(crds.get_duplicate_shreds_from(&from).count(), chunks.len());
+        assert!(crds.get_duplicate_shreds_since(cursor).0.is_empty());
+
+        // Overwritten values move to the end of the index
+        let newer = new_chunks(1);
+        assert_matches!(crds.insert(newer[0].clone(), 1), Ok(Some(_)));
+        let (values, cursor) = crds.get_duplicate_shreds_since(cursor);
+        assert_eq!(values, vec![&newer[0]]);
+        assert_eq!(crds.get_duplicate_shreds_from(&from).count(), chunks.len());
+
+        // Record timestamps reach the indexed values
+        crds.update_record_timestamp(&from, 2);
+        assert_eq!(crds.table[&chunks[1].label()].local_timestamp, 2);
+
+        for chunk in &chunks {
+            assert!(crds.remove(&chunk.label()).is_some());
+        }
+        assert_eq!(crds.get_duplicate_shreds_from(&from).count(), 0);
+        assert!(crds.get_duplicate_shreds_since(0).0.is_empty());
+        assert!(crds.get_duplicate_shreds_since(cursor).0.is_empty());
+    }
+
     #[test]
     fn test_find_old_records_default() {
         let thread_pool = ThreadPoolBuilder::new().build().unwrap();
@@ -1,7 +1,9 @@
 use crate::contact_info::ContactInfo;
 use crate::deprecated;
+use crate::duplicate_shred::{DuplicateShred, DuplicateShredIndex};
 use crate::epoch_slots::EpochSlots;
 use bincode::{serialize, serialized_size};
+use rand::Rng;
 use solana_sdk::sanitize::{Sanitize, SanitizeError};
 use solana_sdk::timing::timestamp;
 use solana_sdk::{
@@ -15,6 +17,7 @@ use std::{
     borrow::{Borrow, Cow},
     collections::{BTreeSet, HashSet},
     fmt,
+    net::SocketAddr,
 };
 
 pub const MAX_WALLCLOCK: u64 = 1_000_000_000_000_000;
@@ -26,6 +29,9 @@ pub const MAX_VOTES: VoteIndex = 32;
 pub type EpochSlotsIndex = u8;
 pub const MAX_EPOCH_SLOTS: EpochSlotsIndex = 255;
 
+/// Maximum number of duplicate shred chunks a node keeps in gossip at once
+pub const MAX_DUPLICATE_SHREDS: DuplicateShredIndex = 512;
+
 /// CrdsValue that is replicated across the cluster
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, AbiExample)]
 pub struct CrdsValue {
@@ -77,6 +83,9 @@ pub enum CrdsData {
     EpochSlots(EpochSlotsIndex, EpochSlots),
     LegacyVersion(LegacyVersion),
     Version(Version),
+    DuplicateShred(DuplicateShredIndex, DuplicateShred),
+    NodeInstance(NodeInstance),
+    TpuVoteAddress(TpuVoteAddress),
 }
 
 impl Sanitize for CrdsData {
@@ -105,6 +114,15 @@ impl Sanitize for CrdsData {
             }
             CrdsData::LegacyVersion(version) => version.sanitize(),
             CrdsData::Version(version) => version.sanitize(),
+            CrdsData::DuplicateShred(ix, shred) => {
+                if *ix >= MAX_DUPLICATE_SHREDS {
+                    Err(SanitizeError::ValueOutOfBounds)
+                } else {
+                    shred.sanitize()
+                }
+            }
+            CrdsData::NodeInstance(node) => node.sanitize(),
+            CrdsData::TpuVoteAddress(address) => address.sanitize(),
         }
     }
 }
@@ -254,6 +272,89 @@ impl Version {
     }
 }
 
+/// Identifies a running instance of a node, so that a second process started
+/// with the same identity keypair can be detected
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, AbiExample)]
+pub struct NodeInstance {
+    from: Pubkey,
+    wallclock: u64,
+    timestamp: u64, // Timestamp when the instance was created.
+    token: u64,     // Randomly generated value at node instantiation.
+}
+
+impl NodeInstance {
+    pub fn new<R>(rng: &mut R, from: Pubkey, now: u64) -> Self
+    where
+        R: Rng,
+    {
+        Self {
+            from,
+            wallclock: now,
+            timestamp: now,
+            token: rng.gen(),
+        }
+    }
+
+    // Clones the value with an updated wallclock.
+    pub fn with_wallclock(&self, now: u64) -> Self {
+        Self {
+            wallclock: now,
+            ..*self
+        }
+    }
+
+    // Returns true if the crds-value is a duplicate instance
+    // of this node, with a more recent timestamp.
+    pub fn check_duplicate(&self, other: &CrdsValue) -> bool {
+        match &other.data {
+            CrdsData::NodeInstance(other) => {
+                self.token != other.token
+                    && self.timestamp <= other.timestamp
+                    && self.from == other.from
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Sanitize for NodeInstance {
+    fn sanitize(&self) -> Result<(), SanitizeError> {
+        if self.wallclock >= MAX_WALLCLOCK {
+            return Err(SanitizeError::ValueOutOfBounds);
+        }
+        self.from.sanitize()
+    }
+}
+
+/// Address a node receives vote transactions on. It is gossiped separately
+/// from ContactInfo so that the ContactInfo layout stays compatible with
+/// nodes which do not know about the vote port
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, AbiExample)]
+pub struct TpuVoteAddress {
+    pub from: Pubkey,
+    pub wallclock: u64,
+    pub address: SocketAddr,
+}
+
+impl TpuVoteAddress {
+    pub fn new(from: Pubkey, address: SocketAddr, wallclock: u64) -> Self {
+        Self {
+            from,
+            wallclock,
+            address,
+        }
+    }
+}
+
+impl Sanitize for TpuVoteAddress {
+    fn sanitize(&self) -> Result<(), SanitizeError> {
+        if self.wallclock >= MAX_WALLCLOCK {
+            return Err(SanitizeError::ValueOutOfBounds);
+        }
+        self.from.sanitize()
+    }
+}
+
 /// Type of the replicated value
 /// These are labels for values in a record that is associated with `Pubkey`
 #[derive(PartialEq, Hash, Eq, Clone, Debug)]
@@ -266,6 +367,9 @@ pub enum CrdsValueLabel {
     AccountsHashes(Pubkey),
     LegacyVersion(Pubkey),
     Version(Pubkey),
+    DuplicateShred(DuplicateShredIndex, Pubkey),
+    NodeInstance(Pubkey),
+    TpuVoteAddress(Pubkey),
 }
 
 impl fmt::Display for CrdsValueLabel {
@@ -279,6 +383,9 @@ impl fmt::Display for CrdsValueLabel {
             CrdsValueLabel::AccountsHashes(_) => write!(f, "AccountsHashes({})", self.pubkey()),
             CrdsValueLabel::LegacyVersion(_) => write!(f, "LegacyVersion({})", self.pubkey()),
             CrdsValueLabel::Version(_) => write!(f, "Version({})", self.pubkey()),
+            CrdsValueLabel::DuplicateShred(ix, pk) => write!(f, "DuplicateShred({}, {})", ix, pk),
+            CrdsValueLabel::NodeInstance(pk) => write!(f, "NodeInstance({})", pk),
+            CrdsValueLabel::TpuVoteAddress(pk) => write!(f, "TpuVoteAddress({})", pk),
         }
     }
 }
@@ -294,6 +401,9 @@ impl CrdsValueLabel {
             CrdsValueLabel::AccountsHashes(p) => *p,
             CrdsValueLabel::LegacyVersion(p) => *p,
             CrdsValueLabel::Version(p) => *p,
+            CrdsValueLabel::DuplicateShred(_, p) => *p,
+            CrdsValueLabel::NodeInstance(p) => *p,
+            CrdsValueLabel::TpuVoteAddress(p) => *p,
         }
     }
 }
@@ -335,6 +445,9 @@ impl CrdsValue {
             CrdsData::EpochSlots(_, p) => p.wallclock,
             CrdsData::LegacyVersion(version) => version.wallclock,
             CrdsData::Version(version) => version.wallclock,
+            CrdsData::DuplicateShred(_, shred) => shred.wallclock,
+            CrdsData::NodeInstance(node) => node.wallclock,
+            CrdsData::TpuVoteAddress(address) => address.wallclock,
         }
     }
     pub fn pubkey(&self) -> Pubkey {
@@ -347,6 +460,9 @@ impl CrdsValue {
             CrdsData::EpochSlots(_, p) => p.from,
             CrdsData::LegacyVersion(version) => version.from,
             CrdsData::Version(version) => version.from,
+            CrdsData::DuplicateShred(_, shred) => shred.from,
+            CrdsData::NodeInstance(node) => node.from,
+            CrdsData::TpuVoteAddress(address) => address.from,
         }
     }
     pub fn label(&self) -> CrdsValueLabel {
@@ -359,6 +475,9 @@ impl CrdsValue {
             CrdsData::EpochSlots(ix, _) => CrdsValueLabel::EpochSlots(*ix, self.pubkey()),
             CrdsData::LegacyVersion(_) => CrdsValueLabel::LegacyVersion(self.pubkey()),
             CrdsData::Version(_) => CrdsValueLabel::Version(self.pubkey()),
+            CrdsData::DuplicateShred(ix, _) => CrdsValueLabel::DuplicateShred(*ix, self.pubkey()),
+            CrdsData::NodeInstance(_) => CrdsValueLabel::NodeInstance(self.pubkey()),
+            CrdsData::TpuVoteAddress(_) => CrdsValueLabel::TpuVoteAddress(self.pubkey()),
         }
     }
     pub fn contact_info(&self) -> Option<&ContactInfo> {
@@ -423,7 +542,23 @@ impl CrdsValue {
         }
     }
 
+    pub fn duplicate_shred(&self) -> Option<&DuplicateShred> {
+        match &self.data {
+            CrdsData::DuplicateShred(_, shred) => Some(shred),
+            _ => None,
+        }
+    }
+
+    pub fn tpu_vote_address(&self) -> Option<&TpuVoteAddress> {
+        match &self.data {
+            CrdsData::TpuVoteAddress(address) => Some(address),
+            _ => None,
+        }
+    }
+
     /// Return all the possible labels for a record identified by Pubkey.
+    /// DuplicateShred labels are left out, since Crds keeps an index of the
+    /// few which are actually present.
     pub fn record_labels(key: &Pubkey) -> Vec<CrdsValueLabel> {
         let mut labels = vec![
             CrdsValueLabel::ContactInfo(*key),
@@ -432,6 +567,8 @@ impl CrdsValue {
             CrdsValueLabel::AccountsHashes(*key),
             CrdsValueLabel::LegacyVersion(*key),
             CrdsValueLabel::Version(*key),
+            CrdsValueLabel::NodeInstance(*key),
+            CrdsValueLabel::TpuVoteAddress(*key),
         ];
         labels.extend((0..MAX_VOTES).map(|ix| CrdsValueLabel::Vote(ix, *key)));
         labels.extend((0..MAX_EPOCH_SLOTS).map(|ix| CrdsValueLabel::EpochSlots(ix, *key)));
@@ -482,7 +619,7 @@ mod test {
 
     #[test]
     fn test_labels() {
-        let mut hits = [false; 6 + MAX_VOTES as usize + MAX_EPOCH_SLOTS as usize];
+        let mut hits = [false; 8 + MAX_VOTES as usize + MAX_EPOCH_SLOTS as usize];
         // this method should cover all the possible labels
         for v in &CrdsValue::record_labels(&Pubkey::default()) {
             match v {
@@ -492,10 +629,13 @@ mod test {
                 CrdsValueLabel::AccountsHashes(_) => hits[3] = true,
                 CrdsValueLabel::LegacyVersion(_) => hits[4] = true,
                 CrdsValueLabel::Version(_) => hits[5] = true,
-                CrdsValueLabel::Vote(ix, _) => hits[*ix as usize + 6] = true,
+                CrdsValueLabel::NodeInstance(_) => hits[6] = true,
+                CrdsValueLabel::TpuVoteAddress(_) => hits[7] = true,
+                CrdsValueLabel::Vote(ix, _) => hits[*ix as usize + 8] = true,
                 CrdsValueLabel::EpochSlots(ix, _) => {
-                    hits[*ix as usize + MAX_VOTES as usize + 6] = true
+                    hits[*ix as usize + MAX_VOTES as usize + 8] = true
                 }
+                CrdsValueLabel::DuplicateShred(_, _) => panic!("indexed separately by Crds"),
             }
         }
         assert!(hits.iter().all(|x| *x));
@@ -640,6 +780,63 @@ mod test {
         assert_eq!(CrdsValue::compute_vote_index(30, vote_refs), 30);
     }
 
+    #[test]
+    fn test_check_duplicate_instance() {
+        fn make_crds_value(node: NodeInstance) -> CrdsValue {
+            CrdsValue::new_unsigned(CrdsData::NodeInstance(node))
+        }
+        let now = timestamp();
+        let mut rng = rand::thread_rng();
+        let pubkey = Pubkey::new_unique();
+        let node = NodeInstance::new(&mut rng, pubkey, now);
+        // Same token is not a duplicate.
+        assert!(!node.check_duplicate(&make_crds_value(NodeInstance {
+            from: pubkey,
+            wallclock: now + 1,
+            timestamp: now + 1,
+            token: node.token,
+        })));
+        // Older timestamp is not a duplicate.
+        assert!(!node.check_duplicate(&make_crds_value(NodeInstance {
+            from: pubkey,
+            wallclock: now + 1,
+            timestamp: now - 1,
+            token: rng.gen(),
+        })));
+        // Updated wallclock is not a duplicate.
+        let other = node.with_wallclock(now + 8);
+        assert_eq!(
+            other,
+            NodeInstance {
+                from: pubkey,
+                wallclock: now + 8,
+                timestamp: now,
+                token: node.token,
+            }
+        );
+        assert!(!node.check_duplicate(&make_crds_value(other)));
+        // Duplicate instance.
+        assert!(node.check_duplicate(&make_crds_value(NodeInstance {
+            from: pubkey,
+            wallclock: 0,
+            timestamp: now,
+            token: rng.gen(),
+        })));
+        // Different pubkey is not a duplicate.
+        assert!(!node.check_duplicate(&make_crds_value(NodeInstance {
+            from: Pubkey::new_unique(),
+            wallclock: now + 1,
+            timestamp: now + 1,
+            token: rng.gen(),
+        })));
+        // Different crds value is not a duplicate.
+        assert!(
+            !node.check_duplicate(&CrdsValue::new_unsigned(CrdsData::ContactInfo(
+                ContactInfo::new_localhost(&pubkey, now)
+            )))
+        );
+    }
+
     fn serialize_deserialize_value(value: &mut CrdsValue, keypair: &Keypair) {
         let num_tries = 10;
         value.sign(keypair);
@@ -0,0 +1,400 @@
+//! Proofs that a slot leader produced two different shreds for the same
+//! slot and index.  A proof is too large for a single gossip packet, so it is
+//! split into chunks, each carried by its own `CrdsData::DuplicateShred`
+//! value and reassembled by the receiving node.
+
+use crate::crds_value::{MAX_SLOT, MAX_WALLCLOCK};
+use bincode::serialize;
+use itertools::Itertools;
+use solana_ledger::shred::{Shred, ShredError, SHRED_PAYLOAD_SIZE};
+use solana_sdk::{
+    clock::Slot,
+    pubkey::Pubkey,
+    sanitize::{Sanitize, SanitizeError},
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    convert::TryFrom,
+};
+use thiserror::Error;
+
+/// Serialized size of a `DuplicateShred` without the chunk payload, so that
+/// chunks can be sized to fit within a gossip packet
+const DUPLICATE_SHRED_HEADER_SIZE: usize = 62;
+
+pub type DuplicateShredIndex = u16;
+
+/// Function returning the leader of a given slot, if known
+pub trait LeaderScheduleFn: FnOnce(Slot) -> Option<Pubkey> {}
+impl<F> LeaderScheduleFn for F where F: FnOnce(Slot) -> Option<Pubkey> {}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, AbiExample)]
+pub struct DuplicateShred {
+    pub from: Pubkey,
+    pub wallclock: u64,
+    pub slot: Slot,
+    shred_index: u32,
+    num_chunks: u8,
+    chunk_index: u8,
+    #[serde(with = "serde_bytes")]
+    chunk: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DuplicateSlotProof {
+    #[serde(with = "serde_bytes")]
+    shred1: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    shred2: Vec<u8>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("data chunk mismatch")]
+    DataChunkMismatch,
+    #[error("invalid chunk index")]
+    InvalidChunkIndex,
+    #[error("invalid duplicate shreds")]
+    InvalidDuplicateShreds,
+    #[error("invalid duplicate slot proof")]
+    InvalidDuplicateSlotProof,
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("invalid size limit")]
+    InvalidSizeLimit,
+    #[error("invalid shred")]
+    InvalidShred(#[from] ShredError),
+    #[error("number of chunks mismatch")]
+    NumChunksMismatch,
+    #[error("missing data chunk")]
+    MissingDataChunk,
+    #[error("(de)serialization error")]
+    SerializationError(#[from] bincode::Error),
+    #[error("shred index mismatch")]
+    ShredIndexMismatch,
+    #[error("shred type mismatch")]
+    ShredTypeMismatch,
+    #[error("slot mismatch")]
+    SlotMismatch,
+    #[error("type conversion error")]
+    TryFromIntError(#[from] std::num::TryFromIntError),
+    #[error("unknown slot leader")]
+    UnknownSlotLeader,
+}
+
+/// Check that `shred1` and `shred2` indeed constitute a valid duplicate proof:
+/// same slot, index and type, different payloads, both signed by `leader`.
+fn check_shreds(leader: &Pubkey, shred1: &Shred, shred2: &Shred) -> Result<(), Error> {
+    if shred1.slot() != shred2.slot() {
+        Err(Error::SlotMismatch)
+    } else if shred1.index() != shred2.index() {
+        Err(Error::ShredIndexMismatch)
+    } else if shred1.is_data() != shred2.is_data() {
+        Err(Error::ShredTypeMismatch)
+    } else if shred1.payload == shred2.payload {
+        Err(Error::InvalidDuplicateShreds)
+    } else if !shred1.verify(leader) || !shred2.verify(leader) {
+        Err(Error::InvalidSignature)
+    } else {
+        Ok(())
+    }
+}
+
+fn deserialize_shred(payload: Vec<u8>) -> Result<Shred, Error> {
+    // Shred::new_from_serialized_shred asserts on undersized payloads
+    if payload.len() < SHRED_PAYLOAD_SIZE {
+        return Err(Error::InvalidDuplicateSlotProof);
+    }
+    Ok(Shred::new_from_serialized_shred(payload)?)
+}
+
+/// Splits the proof that `shred` and `other_payload` are duplicates into
+/// chunks, each of which fits in a `DuplicateShred` of at most `max_size`
+/// serialized bytes.
+pub fn from_duplicate_slot_proof(
+    shred: &Shred,
+    other_payload: Vec<u8>,
+    self_pubkey: Pubkey,
+    wallclock: u64,
+    max_size: usize,
+) -> Result<impl Iterator<Item = DuplicateShred>, Error> {
+    if shred.payload == other_payload {
+        return Err(Error::InvalidDuplicateShreds);
+    }
+    let other_shred = deserialize_shred(other_payload.clone())?;
+    if shred.slot() != other_shred.slot() {
+        return Err(Error::SlotMismatch);
+    }
+    if shred.index() != other_shred.index() {
+        return Err(Error::ShredIndexMismatch);
+    }
+    let proof = DuplicateSlotProof {
+        shred1: shred.payload.clone(),
+        shred2: other_payload,
+    };
+    let data = serialize(&proof)?;
+    let chunk_size = if DUPLICATE_SHRED_HEADER_SIZE < max_size {
+        max_size - DUPLICATE_SHRED_HEADER_SIZE
+    } else {
+        return Err(Error::InvalidSizeLimit);
+    };
+    let chunks: Vec<_> = data.chunks(chunk_size).map(Vec::from).collect();
+    let num_chunks = u8::try_from(chunks.len())?;
+    let slot = shred.slot();
+    let shred_index = shred.index();
+    let chunks = chunks
+        .into_iter()
+        .enumerate()
+        .map(move |(i, chunk)| DuplicateShred {
+            from: self_pubkey,
+            wallclock,
+            slot,
+            shred_index,
+            num_chunks,
+            chunk_index: i as u8,
+            chunk,
+        });
+    Ok(chunks)
+}
+
+fn check_chunk(
+    slot: Slot,
+    shred_index: u32,
+    num_chunks: u8,
+) -> impl Fn(&DuplicateShred) -> Result<(), Error> {
+    move |dup| {
+        if dup.slot != slot {
+            Err(Error::SlotMismatch)
+        } else if dup.shred_index != shred_index {
+            Err(Error::ShredIndexMismatch)
+        } else if dup.num_chunks != num_chunks {
+            Err(Error::NumChunksMismatch)
+        } else if dup.chunk_index >= num_chunks {
+            Err(Error::InvalidChunkIndex)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reassembles the chunks into the shreds they prove duplicate and verifies
+/// the proof against the slot leader.
+pub fn into_shreds(
+    chunks: impl IntoIterator<Item = DuplicateShred>,
+    leader: impl LeaderScheduleFn,
+) -> Result<(Shred, Shred), Error> {
+    let mut chunks = chunks.into_iter();
+    let first_chunk = chunks.next().ok_or(Error::InvalidDuplicateShreds)?;
+    let DuplicateShred {
+        slot,
+        shred_index,
+        num_chunks,
+        ..
+    } = first_chunk;
+    let slot_leader = leader(slot).ok_or(Error::UnknownSlotLeader)?;
+    let check_chunk = check_chunk(slot, shred_index, num_chunks);
+    let mut data = HashMap::new();
+    for chunk in std::iter::once(first_chunk).chain(chunks) {
+        check_chunk(&chunk)?;
+        match data.entry(chunk.chunk_index) {
+            Entry::Vacant(entry) => {
+                entry.insert(chunk.chunk);
+            }
+            Entry::Occupied(entry) => {
+                if *entry.get() != chunk.chunk {
+                    return Err(Error::DataChunkMismatch);
+                }
+            }
+        }
+    }
+    if num_chunks as usize != data.len() {
+        return Err(Error::MissingDataChunk);
+    }
+    let data: Vec<_> = (0..num_chunks).map(|k| data.remove(&k).unwrap()).collect();
+    let data = data.concat();
+    let proof: DuplicateSlotProof = bincode::deserialize(&data)?;
+    let shred1 = deserialize_shred(proof.shred1)?;
+    let shred2 = deserialize_shred(proof.shred2)?;
+    if shred1.slot() != slot || shred2.slot() != slot {
+        Err(Error::SlotMismatch)
+    } else if shred1.index() != shred_index || shred2.index() != shred_index {
+        Err(Error::ShredIndexMismatch)
+    } else {
+        check_shreds(&slot_leader, &shred1, &shred2)?;
+        Ok((shred1, shred2))
+    }
+}
+
+/// Groups duplicate shred chunks by the node which sent them and the slot
+/// they are for, keeping only the groups for which every chunk is present.
+pub fn complete_proofs(
+    chunks: impl IntoIterator<Item = DuplicateShred>,
+) -> Vec<Vec<DuplicateShred>> {
+    chunks
+        .into_iter()
+        .map(|chunk| ((chunk.from, chunk.slot, chunk.shred_index), chunk))
+        .into_group_map()
+        .into_iter()
+        .filter_map(|(_, chunks)| {
+            let num_chunks = chunks.first()?.num_chunks as usize;
+            let num_unique = chunks
+                .iter()
+                .map(|chunk| chunk.chunk_index)
+                .unique()
+                .count();
+            if num_unique == num_chunks {
+                Some(chunks)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+impl Sanitize for DuplicateShred {
+    fn sanitize(&self) -> Result<(), SanitizeError> {
+        if self.wallclock >= MAX_WALLCLOCK {
+            return Err(SanitizeError::ValueOutOfBounds);
+        }
+        if self.slot >= MAX_SLOT {
+            return Err(SanitizeError::ValueOutOfBounds);
+        }
+        if self.chunk_index >= self.num_chunks {
+            return Err(SanitizeError::IndexOutOfBounds);
+        }
+        self.from.sanitize()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rand::Rng;
+    use solana_ledger::{entry::Entry, shred::Shredder};
+    use solana_sdk::{
+        hash,
+        signature::{Keypair, Signer},
+        system_transaction,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_duplicate_shred_header_size() {
+        let dup = DuplicateShred {
+            from: Pubkey::new_unique(),
+            wallclock: u64::MAX,
+            slot: Slot::MAX,
+            shred_index: u32::MAX,
+            num_chunks: u8::MAX,
+            chunk_index: u8::MAX,
+            chunk: Vec::default(),
+        };
+        assert_eq!(
+            bincode::serialize(&dup).unwrap().len(),
+            DUPLICATE_SHRED_HEADER_SIZE
+        );
+        assert_eq!(
+            bincode::serialized_size(&dup).unwrap(),
+            DUPLICATE_SHRED_HEADER_SIZE as u64
+        );
+    }
+
+    pub(crate) fn new_rand_shred<R: Rng>(
+        rng: &mut R,
+        next_shred_index: u32,
+        shredder: &Shredder,
+    ) -> Shred {
+        let entries: Vec<_> = std::iter::repeat_with(|| {
+            let tx = system_transaction::transfer(
+                &Keypair::new(),       // from
+                &Pubkey::new_unique(), // to
+                rng.gen(),             // lamports
+                hash::new_rand(rng),   // recent blockhash
+            );
+            Entry::new(
+                &hash::new_rand(rng), // prev_hash
+                1,                    // num_hashes,
+                vec![tx],             // transactions
+            )
+        })
+        .take(5)
+        .collect();
+        let (mut data_shreds, _coding_shreds, _last_shred_index) = shredder.entries_to_shreds(
+            &entries,
+            true, // is_last_in_slot
+            next_shred_index,
+        );
+        data_shreds.swap_remove(0)
+    }
+
+    #[test]
+    fn test_duplicate_shred_round_trip() {
+        let mut rng = rand::thread_rng();
+        let leader = Arc::new(Keypair::new());
+        let (slot, parent_slot, fec_rate, reference_tick, version) =
+            (53084024, 53084023, 0.0, 0, 0);
+        let shredder = Shredder::new(
+            slot,
+            parent_slot,
+            fec_rate,
+            leader.clone(),
+            reference_tick,
+            version,
+        )
+        .unwrap();
+        let next_shred_index = rng.gen();
+        let shred1 = new_rand_shred(&mut rng, next_shred_index, &shredder);
+        let shred2 = new_rand_shred(&mut rng, next_shred_index, &shredder);
+        let leader_schedule = |s| {
+            if s == slot {
+                Some(leader.pubkey())
+            } else {
+                None
+            }
+        };
+        let chunks: Vec<_> = from_duplicate_slot_proof(
+            &shred1,
+            shred2.payload.clone(),
+            Pubkey::new_unique(), // self_pubkey
+            rng.gen(),            // wallclock
+            512,                  // max_size
+        )
+        .unwrap()
+        .collect();
+        assert!(chunks.len() > 4);
+        assert!(chunks
+            .iter()
+            .all(|chunk| bincode::serialized_size(chunk).unwrap() <= 512));
+        assert_eq!(complete_proofs(chunks.clone()).len(), 1);
+        assert!(complete_proofs(chunks[1..].to_vec()).is_empty());
+
+        let (shred3, shred4) = into_shreds(chunks.clone(), leader_schedule).unwrap();
+        assert_eq!(shred1, shred3);
+        assert_eq!(shred2, shred4);
+
+        // Missing a chunk
+        assert!(matches!(
+            into_shreds(chunks[1..].to_vec(), leader_schedule),
+            Err(Error::MissingDataChunk)
+        ));
+
+        // Signed by someone other than the slot leader
+        let impostor = Keypair::new();
+        assert!(matches!(
+            into_shreds(chunks, |_| Some(impostor.pubkey())),
+            Err(Error::InvalidSignature)
+        ));
+
+        // Same payload is not a duplicate
+        assert!(matches!(
+            from_duplicate_slot_proof(
+                &shred1,
+                shred1.payload.clone(),
+                Pubkey::new_unique(),
+                rng.gen(),
+                512,
+            ),
+            Err(Error::InvalidDuplicateShreds)
+        ));
+    }
+}
@@ -24,29 +24,45 @@ impl FetchStage {
     pub fn new(
         sockets: Vec<UdpSocket>,
         tpu_forwards_sockets: Vec<UdpSocket>,
+        tpu_vote_sockets: Vec<UdpSocket>,
         exit: &Arc<AtomicBool>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
-    ) -> (Self, PacketReceiver) {
+    ) -> (Self, PacketReceiver, PacketReceiver) {
         let (sender, receiver) = channel();
+        let (vote_sender, vote_receiver) = channel();
         (
-            Self::new_with_sender(sockets, tpu_forwards_sockets, exit, &sender, &poh_recorder),
+            Self::new_with_sender(
+                sockets,
+                tpu_forwards_sockets,
+                tpu_vote_sockets,
+                exit,
+                &sender,
+                &vote_sender,
+                &poh_recorder,
+            ),
             receiver,
+            vote_receiver,
         )
     }
     pub fn new_with_sender(
         sockets: Vec<UdpSocket>,
         tpu_forwards_sockets: Vec<UdpSocket>,
+        tpu_vote_sockets: Vec<UdpSocket>,
         exit: &Arc<AtomicBool>,
         sender: &PacketSender,
+        vote_sender: &PacketSender,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
     ) -> Self {
         let tx_sockets = sockets.into_iter().map(Arc::new).collect();
         let tpu_forwards_sockets = tpu_forwards_sockets.into_iter().map(Arc::new).collect();
+        let tpu_vote_sockets = tpu_vote_sockets.into_iter().map(Arc::new).collect();
         Self::new_multi_socket(
             tx_sockets,
             tpu_forwards_sockets,
+            tpu_vote_sockets,
             exit,
             &sender,
+            &vote_sender,
             &poh_recorder,
         )
     }
@@ -89,8 +105,10 @@ impl FetchStage {
     fn new_multi_socket(
         sockets: Vec<Arc<UdpSocket>>,
         tpu_forwards_sockets: Vec<Arc<UdpSocket>>,
+        tpu_vote_sockets: Vec<Arc<UdpSocket>>,
         exit: &Arc<AtomicBool>,
         sender: &PacketSender,
+        vote_sender: &PacketSender,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
     ) -> Self {
         let recycler: PacketsRecycler = Recycler::warmed(1000, 1024);
@@ -105,6 +123,18 @@ impl FetchStage {
             )
         });
 
+        // Votes get their own channel, so that they are not queued behind
+        // user transactions
+        let tpu_vote_threads = tpu_vote_sockets.into_iter().map(|socket| {
+            streamer::receiver(
+                socket,
+                &exit,
+                vote_sender.clone(),
+                recycler.clone(),
+                "fetch_vote_stage",
+            )
+        });
+
         let (forward_sender, forward_receiver) = channel();
         let tpu_forwards_threads = tpu_forwards_sockets.into_iter().map(|socket| {
             streamer::receiver(
@@ -137,7 +167,10 @@ impl FetchStage {
             })
             .unwrap();
 
-        let mut thread_hdls: Vec<_> = tpu_threads.chain(tpu_forwards_threads).collect();
+        let mut thread_hdls: Vec<_> = tpu_threads
+            .chain(tpu_forwards_threads)
+            .chain(tpu_vote_threads)
+            .collect();
         thread_hdls.push(fwd_thread_hdl);
         Self { thread_hdls }
     }
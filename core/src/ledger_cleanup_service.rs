@@ -4,6 +4,7 @@ use solana_ledger::blockstore::{Blockstore, PurgeType};
 use solana_ledger::blockstore_db::Result as BlockstoreResult;
 use solana_measure::measure::Measure;
 use solana_sdk::clock::{Slot, DEFAULT_TICKS_PER_SLOT, TICKS_PER_DAY};
+use solana_sdk::timing::timestamp;
 use std::string::ToString;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
@@ -33,6 +34,29 @@ pub const DEFAULT_PURGE_SLOT_INTERVAL: u64 = 512;
 // Once a day should be ample
 const DEFAULT_COMPACTION_SLOT_INTERVAL: u64 = TICKS_PER_DAY / DEFAULT_TICKS_PER_SLOT;
 
+/// Limits on how much ledger is kept. Slots older than the newest ones fitting within every
+/// configured limit are purged, from all columns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LedgerCleanupConfig {
+    /// Number of data shreds to keep
+    pub max_ledger_shreds: Option<u64>,
+    /// Total size of the blockstore on disk, in bytes
+    pub max_ledger_bytes: Option<u64>,
+    /// Number of slots to keep behind the latest root
+    pub max_ledger_age_slots: Option<u64>,
+    /// How long ago a slot may have been received and still be kept
+    pub max_ledger_age: Option<Duration>,
+}
+
+impl LedgerCleanupConfig {
+    pub fn with_max_ledger_shreds(max_ledger_shreds: u64) -> Self {
+        Self {
+            max_ledger_shreds: Some(max_ledger_shreds),
+            ..Self::default()
+        }
+    }
+}
+
 pub struct LedgerCleanupService {
     t_cleanup: JoinHandle<()>,
 }
@@ -41,13 +65,10 @@ impl LedgerCleanupService {
     pub fn new(
         new_root_receiver: Receiver<Slot>,
         blockstore: Arc<Blockstore>,
-        max_ledger_shreds: u64,
+        config: LedgerCleanupConfig,
         exit: &Arc<AtomicBool>,
     ) -> Self {
-        info!(
-            "LedgerCleanupService active. Max Ledger Slots {}",
-            max_ledger_shreds
-        );
+        info!("LedgerCleanupService active. {:?}", config);
         let exit = exit.clone();
         let mut last_purge_slot = 0;
         let mut last_compaction_slot = 0;
@@ -61,7 +82,7 @@ impl LedgerCleanupService {
                 if let Err(e) = Self::cleanup_ledger(
                     &new_root_receiver,
                     &blockstore,
-                    max_ledger_shreds,
+                    &config,
                     &mut last_purge_slot,
                     DEFAULT_PURGE_SLOT_INTERVAL,
                     &mut last_compaction_slot,
@@ -77,10 +98,26 @@ impl LedgerCleanupService {
         Self { t_cleanup }
     }
 
+    // Returns the newest slot which must be purged for `total_slots` to hold at most
+    // `max_ledger_shreds`
+    fn lowest_cleanup_slot_by_shreds(
+        total_slots: &[(Slot, u64, u64)],
+        max_ledger_shreds: u64,
+    ) -> Option<Slot> {
+        let mut num_shreds_to_keep = 0;
+        for (slot, num_shreds, _) in total_slots.iter().rev() {
+            num_shreds_to_keep += *num_shreds;
+            if num_shreds_to_keep > max_ledger_shreds {
+                return Some(*slot);
+            }
+        }
+        None
+    }
+
     fn find_slots_to_clean(
         blockstore: &Arc<Blockstore>,
         root: Slot,
-        max_ledger_shreds: u64,
+        config: &LedgerCleanupConfig,
     ) -> (bool, Slot, Slot, u64) {
         let mut total_slots = Vec::new();
         let mut iterate_time = Measure::start("iterate_time");
@@ -93,34 +130,79 @@ impl LedgerCleanupService {
             }
             // Not exact since non-full slots will have holes
             total_shreds += meta.received;
-            total_slots.push((slot, meta.received));
+            total_slots.push((slot, meta.received, meta.first_shred_timestamp));
             if slot > root {
                 break;
             }
         }
         iterate_time.stop();
         info!(
-            "first_slot={} total_slots={} total_shreds={} max_ledger_shreds={}, {}",
+            "first_slot={} total_slots={} total_shreds={} config={:?}, {}",
             first_slot,
             total_slots.len(),
             total_shreds,
-            max_ledger_shreds,
+            config,
             iterate_time
         );
-        if (total_shreds as u64) < max_ledger_shreds {
-            return (false, 0, 0, total_shreds);
+
+        let mut lowest_cleanup_slot: Option<Slot> = None;
+        let mut clean_up_to = |slot: Option<Slot>| {
+            if let Some(slot) = slot {
+                lowest_cleanup_slot = Some(lowest_cleanup_slot.unwrap_or_default().max(slot));
+            }
+        };
+
+        if let Some(max_ledger_shreds) = config.max_ledger_shreds {
+            clean_up_to(Self::lowest_cleanup_slot_by_shreds(
+                &total_slots,
+                max_ledger_shreds,
+            ));
         }
-        let mut num_shreds_to_clean = 0;
-        let mut lowest_cleanup_slot = total_slots[0].0;
-        for (slot, num_shreds) in total_slots.iter().rev() {
-            num_shreds_to_clean += *num_shreds as u64;
-            if num_shreds_to_clean > max_ledger_shreds {
-                lowest_cleanup_slot = *slot;
-                break;
+        if let Some(max_ledger_bytes) = config.max_ledger_bytes {
+            // The live data covers every column, not just shreds, so the byte limit is turned
+            // into a shred limit using the average footprint of a shred. Slots purged earlier
+            // only leave the files on disk once compacted, so counting the files would make
+            // shreds look larger than they are and purge far too much
+            match blockstore.live_data_size() {
+                Ok(live_data_size) if live_data_size > max_ledger_bytes && total_shreds > 0 => {
+                    let bytes_per_shred = (live_data_size / total_shreds).max(1);
+                    clean_up_to(Self::lowest_cleanup_slot_by_shreds(
+                        &total_slots,
+                        max_ledger_bytes / bytes_per_shred,
+                    ));
+                }
+                Ok(_) => (),
+                Err(err) => warn!("purge: unable to get the ledger live data size: {:?}", err),
             }
         }
+        if let Some(max_ledger_age_slots) = config.max_ledger_age_slots {
+            let oldest_slot_to_keep = root.saturating_sub(max_ledger_age_slots);
+            clean_up_to(
+                total_slots
+                    .iter()
+                    .take_while(|(slot, _, _)| *slot < oldest_slot_to_keep)
+                    .last()
+                    .map(|(slot, _, _)| *slot),
+            );
+        }
+        if let Some(max_ledger_age) = config.max_ledger_age {
+            let oldest_timestamp_to_keep =
+                timestamp().saturating_sub(max_ledger_age.as_millis() as u64);
+            clean_up_to(
+                total_slots
+                    .iter()
+                    .take_while(|(slot, _, first_shred_timestamp)| {
+                        *slot < root && *first_shred_timestamp < oldest_timestamp_to_keep
+                    })
+                    .last()
+                    .map(|(slot, _, _)| *slot),
+            );
+        }
 
-        (true, first_slot, lowest_cleanup_slot, total_shreds)
+        match lowest_cleanup_slot {
+            Some(lowest_cleanup_slot) => (true, first_slot, lowest_cleanup_slot, total_shreds),
+            None => (false, 0, 0, total_shreds),
+        }
     }
 
     fn receive_new_roots(new_root_receiver: &Receiver<Slot>) -> Result<Slot, RecvTimeoutError> {
@@ -135,7 +217,7 @@ impl LedgerCleanupService {
     pub fn cleanup_ledger(
         new_root_receiver: &Receiver<Slot>,
         blockstore: &Arc<Blockstore>,
-        max_ledger_shreds: u64,
+        config: &LedgerCleanupConfig,
         last_purge_slot: &mut u64,
         purge_interval: u64,
         last_compaction_slot: &mut u64,
@@ -147,6 +229,7 @@ impl LedgerCleanupService {
         }
 
         let disk_utilization_pre = blockstore.storage_size();
+        let transaction_history_pre = blockstore.transaction_history_live_data_sizes();
         info!(
             "purge: last_root={}, last_purge_slot={}, purge_interval={}, last_compaction_slot={}, disk_utilization={:?}",
             root, last_purge_slot, purge_interval, last_compaction_slot, disk_utilization_pre
@@ -155,7 +238,7 @@ impl LedgerCleanupService {
         *last_purge_slot = root;
 
         let (slots_to_clean, purge_first_slot, lowest_cleanup_slot, total_shreds) =
-            Self::find_slots_to_clean(&blockstore, root, max_ledger_shreds);
+            Self::find_slots_to_clean(&blockstore, root, config);
 
         if slots_to_clean {
             let mut compact_first_slot = std::u64::MAX;
@@ -217,8 +300,22 @@ impl LedgerCleanupService {
             }
         }
 
+        let num_slots_purged = if slots_to_clean {
+            lowest_cleanup_slot.saturating_sub(purge_first_slot) + 1
+        } else {
+            0
+        };
         let disk_utilization_post = blockstore.storage_size();
-        Self::report_disk_metrics(disk_utilization_pre, disk_utilization_post, total_shreds);
+        Self::report_disk_metrics(
+            disk_utilization_pre,
+            disk_utilization_post,
+            total_shreds,
+            num_slots_purged,
+        );
+        Self::report_transaction_history_metrics(
+            transaction_history_pre,
+            blockstore.transaction_history_live_data_sizes(),
+        );
 
         Ok(())
     }
@@ -227,6 +324,7 @@ impl LedgerCleanupService {
         pre: BlockstoreResult<u64>,
         post: BlockstoreResult<u64>,
         total_shreds: u64,
+        num_slots_purged: u64,
     ) {
         if let (Ok(pre), Ok(post)) = (pre, post) {
             datapoint_info!(
@@ -234,7 +332,43 @@ impl LedgerCleanupService {
                 ("disk_utilization_pre", pre as i64, i64),
                 ("disk_utilization_post", post as i64, i64),
                 ("disk_utilization_delta", (pre as i64 - post as i64), i64),
+                ("reclaimed_bytes", pre.saturating_sub(post) as i64, i64),
                 ("total_shreds", total_shreds, i64),
+                ("purged_slots", num_slots_purged as i64, i64),
+            );
+        }
+    }
+
+    // Reports the space reclaimed in the transaction history columns, which is only reflected
+    // once the purged slots are compacted
+    fn report_transaction_history_metrics(
+        pre: BlockstoreResult<(u64, u64, u64)>,
+        post: BlockstoreResult<(u64, u64, u64)>,
+    ) {
+        if let (Ok(pre), Ok(post)) = (pre, post) {
+            datapoint_info!(
+                "ledger_transaction_history_utilization",
+                ("transaction_status_pre", pre.0 as i64, i64),
+                ("transaction_status_post", post.0 as i64, i64),
+                (
+                    "transaction_status_reclaimed_bytes",
+                    pre.0.saturating_sub(post.0) as i64,
+                    i64
+                ),
+                ("address_signatures_pre", pre.1 as i64, i64),
+                ("address_signatures_post", post.1 as i64, i64),
+                (
+                    "address_signatures_reclaimed_bytes",
+                    pre.1.saturating_sub(post.1) as i64,
+                    i64
+                ),
+                ("rewards_pre", pre.2 as i64, i64),
+                ("rewards_post", post.2 as i64, i64),
+                (
+                    "rewards_reclaimed_bytes",
+                    pre.2.saturating_sub(post.2) as i64,
+                    i64
+                ),
             );
         }
     }
@@ -267,7 +401,7 @@ mod tests {
         LedgerCleanupService::cleanup_ledger(
             &receiver,
             &blockstore,
-            5,
+            &LedgerCleanupConfig::with_max_ledger_shreds(5),
             &mut last_purge_slot,
             10,
             &mut last_compaction_slot,
@@ -285,6 +419,52 @@ mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_find_slots_to_clean() {
+        solana_logger::setup();
+        let blockstore_path = get_tmp_ledger_path!();
+        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let (shreds, _) = make_many_slot_entries(0, 50, 5);
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+        let blockstore = Arc::new(blockstore);
+
+        let unlimited = LedgerCleanupConfig::default();
+        let (slots_to_clean, ..) =
+            LedgerCleanupService::find_slots_to_clean(&blockstore, 50, &unlimited);
+        assert!(!slots_to_clean);
+
+        let by_age_slots = LedgerCleanupConfig {
+            max_ledger_age_slots: Some(10),
+            ..LedgerCleanupConfig::default()
+        };
+        let (slots_to_clean, first_slot, lowest_cleanup_slot, _) =
+            LedgerCleanupService::find_slots_to_clean(&blockstore, 50, &by_age_slots);
+        assert!(slots_to_clean);
+        assert_eq!((first_slot, lowest_cleanup_slot), (0, 39));
+
+        // Every shred has to go to fit in a single byte
+        let by_bytes = LedgerCleanupConfig {
+            max_ledger_bytes: Some(1),
+            ..LedgerCleanupConfig::default()
+        };
+        let (slots_to_clean, _, lowest_cleanup_slot, _) =
+            LedgerCleanupService::find_slots_to_clean(&blockstore, 50, &by_bytes);
+        assert!(slots_to_clean);
+        assert_eq!(lowest_cleanup_slot, 49);
+
+        // The strictest limit wins
+        let combined = LedgerCleanupConfig {
+            max_ledger_age_slots: Some(10),
+            ..LedgerCleanupConfig::with_max_ledger_shreds(100_000)
+        };
+        let (_, _, lowest_cleanup_slot, _) =
+            LedgerCleanupService::find_slots_to_clean(&blockstore, 50, &combined);
+        assert_eq!(lowest_cleanup_slot, 39);
+
+        drop(blockstore);
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_cleanup_speed() {
         solana_logger::setup();
@@ -324,7 +504,7 @@ mod tests {
             LedgerCleanupService::cleanup_ledger(
                 &receiver,
                 &blockstore,
-                initial_slots,
+                &LedgerCleanupConfig::with_max_ledger_shreds(initial_slots),
                 &mut last_purge_slot,
                 10,
                 &mut last_compaction_slot,
@@ -7,6 +7,8 @@
 //!
 
 pub mod accounts_hash_verifier;
+pub mod ancestor_hashes_service;
+pub mod bank_hash_details_service;
 pub mod banking_stage;
 pub mod bigtable_upload_service;
 pub mod broadcast_stage;
@@ -32,6 +34,7 @@ pub mod crds_gossip_push;
 pub mod crds_shards;
 pub mod crds_value;
 pub mod data_budget;
+pub mod duplicate_shred;
 pub mod epoch_slots;
 pub mod fetch_stage;
 pub mod fork_choice;
@@ -68,6 +71,7 @@ pub mod sigverify;
 pub mod sigverify_shreds;
 pub mod sigverify_stage;
 pub mod snapshot_packager_service;
+pub mod staked_nodes_updater_service;
 pub mod test_validator;
 pub mod tpu;
 pub mod transaction_status_service;
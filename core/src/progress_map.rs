@@ -61,6 +61,20 @@ impl ReplaySlotStats {
             ),
             ("total_entries", num_entries as i64, i64),
             ("total_shreds", num_shreds as i64, i64),
+            (
+                "total_transactions",
+                self.cost_tracker.transaction_count() as i64,
+                i64
+            ),
+            ("block_cost", self.cost_tracker.block_cost() as i64, i64),
+            (
+                "max_account_cost",
+                self.cost_tracker
+                    .max_account_cost()
+                    .map(|(_, cost)| cost)
+                    .unwrap_or_default() as i64,
+                i64
+            ),
         );
     }
 }
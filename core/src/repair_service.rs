@@ -1,6 +1,9 @@
 //! The `repair_service` module implements the tools necessary to generate a thread which
 //! regularly finds missing shreds in the ledger and sends repair requests for those shreds
 use crate::{
+    ancestor_hashes_service::{
+        AncestorDuplicateSlotsReceiver, AncestorHashesReplayUpdateReceiver, AncestorHashesService,
+    },
     cluster_info::ClusterInfo,
     cluster_info_vote_listener::VerifiedVoteReceiver,
     cluster_slots::ClusterSlots,
@@ -9,7 +12,7 @@ use crate::{
     result::Result,
     serve_repair::{RepairType, ServeRepair, DEFAULT_NONCE},
 };
-use crossbeam_channel::{Receiver as CrossbeamReceiver, Sender as CrossbeamSender};
+use crossbeam_channel::{unbounded, Receiver as CrossbeamReceiver, Sender as CrossbeamSender};
 use solana_ledger::{
     blockstore::{Blockstore, SlotMeta},
     shred::Nonce,
@@ -133,18 +136,34 @@ pub struct DuplicateSlotRepairStatus {
 
 pub struct RepairService {
     t_repair: JoinHandle<()>,
+    ancestor_hashes_service: AncestorHashesService,
 }
 
 impl RepairService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         blockstore: Arc<Blockstore>,
         exit: Arc<AtomicBool>,
         repair_socket: Arc<UdpSocket>,
+        ancestor_hashes_socket: Arc<UdpSocket>,
         cluster_info: Arc<ClusterInfo>,
         repair_info: RepairInfo,
         cluster_slots: Arc<ClusterSlots>,
         verified_vote_receiver: VerifiedVoteReceiver,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
     ) -> Self {
+        let (ancestor_duplicate_slots_sender, ancestor_duplicate_slots_receiver) = unbounded();
+        let ancestor_hashes_service = AncestorHashesService::new(
+            exit.clone(),
+            blockstore.clone(),
+            ancestor_hashes_socket,
+            cluster_info.clone(),
+            repair_info.bank_forks.clone(),
+            repair_info.repair_validators.clone(),
+            ancestor_hashes_replay_update_receiver,
+            ancestor_duplicate_slots_sender,
+        );
+
         let t_repair = Builder::new()
             .name("solana-repair-service".to_string())
             .spawn(move || {
@@ -156,13 +175,18 @@ impl RepairService {
                     repair_info,
                     &cluster_slots,
                     verified_vote_receiver,
+                    ancestor_duplicate_slots_receiver,
                 )
             })
             .unwrap();
 
-        RepairService { t_repair }
+        RepairService {
+            t_repair,
+            ancestor_hashes_service,
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn run(
         blockstore: &Blockstore,
         exit: &AtomicBool,
@@ -171,14 +195,16 @@ impl RepairService {
         repair_info: RepairInfo,
         cluster_slots: &ClusterSlots,
         verified_vote_receiver: VerifiedVoteReceiver,
+        ancestor_duplicate_slots_receiver: AncestorDuplicateSlotsReceiver,
     ) {
         let mut repair_weight = RepairWeight::new(repair_info.bank_forks.read().unwrap().root());
-        let serve_repair = ServeRepair::new(cluster_info.clone());
+        let serve_repair =
+            ServeRepair::new(cluster_info.clone(), Some(repair_info.bank_forks.clone()));
         let id = cluster_info.id();
         let mut repair_stats = RepairStats::default();
         let mut repair_timing = RepairTiming::default();
         let mut last_stats = Instant::now();
-        let duplicate_slot_repair_statuses: HashMap<Slot, DuplicateSlotRepairStatus> =
+        let mut duplicate_slot_repair_statuses: HashMap<Slot, DuplicateSlotRepairStatus> =
             HashMap::new();
 
         loop {
@@ -221,21 +247,13 @@ impl RepairService {
                     root_bank.epoch_schedule(),
                 );
                 add_votes_elapsed.stop();
-                /*let new_duplicate_slots = Self::find_new_duplicate_slots(
-                    &duplicate_slot_repair_statuses,
-                    blockstore,
-                    cluster_slots,
-                    &root_bank,
-                );
-                Self::process_new_duplicate_slots(
-                    &new_duplicate_slots,
+                Self::process_ancestor_duplicate_slots(
+                    &ancestor_duplicate_slots_receiver,
                     &mut duplicate_slot_repair_statuses,
-                    cluster_slots,
                     &root_bank,
+                    &repair_info.bank_forks,
                     blockstore,
-                    &serve_repair,
                     &repair_info.duplicate_slots_reset_sender,
-                    &repair_info.repair_validators,
                 );
                 Self::generate_and_send_duplicate_repairs(
                     &mut duplicate_slot_repair_statuses,
@@ -245,7 +263,7 @@ impl RepairService {
                     &mut repair_stats,
                     &repair_socket,
                     &repair_info.repair_validators,
-                );*/
+                );
 
                 repair_weight.get_best_weighted_repairs(
                     blockstore,
@@ -415,7 +433,6 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
     fn generate_duplicate_repairs_for_slot(
         blockstore: &Blockstore,
         slot: Slot,
@@ -440,7 +457,6 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
     fn generate_and_send_duplicate_repairs(
         duplicate_slot_repair_statuses: &mut HashMap<Slot, DuplicateSlotRepairStatus>,
         cluster_slots: &ClusterSlots,
@@ -488,7 +504,6 @@ impl RepairService {
         })
     }
 
-    #[allow(dead_code)]
     fn serialize_and_send_request(
         repair_type: &RepairType,
         repair_socket: &UdpSocket,
@@ -504,7 +519,6 @@ impl RepairService {
         Ok(())
     }
 
-    #[allow(dead_code)]
     fn update_duplicate_slot_repair_addr(
         slot: Slot,
         status: &mut DuplicateSlotRepairStatus,
@@ -526,6 +540,49 @@ impl RepairService {
         }
     }
 
+    // Dumps the slots the ancestor hashes service found this node to have
+    // replayed a different version of than the cluster, and repairs them from
+    // one of the peers that reported the cluster's version
+    fn process_ancestor_duplicate_slots(
+        ancestor_duplicate_slots_receiver: &AncestorDuplicateSlotsReceiver,
+        duplicate_slot_repair_statuses: &mut HashMap<Slot, DuplicateSlotRepairStatus>,
+        root_bank: &Bank,
+        bank_forks: &RwLock<BankForks>,
+        blockstore: &Blockstore,
+        duplicate_slots_reset_sender: &DuplicateSlotsResetSender,
+    ) {
+        for (slot, repair_pubkey_and_addr) in ancestor_duplicate_slots_receiver.try_iter() {
+            if slot <= root_bank.slot() || duplicate_slot_repair_statuses.contains_key(&slot) {
+                continue;
+            }
+            warn!(
+                "Cluster confirmed a different version of slot: {}, dumping our current version and repairing from {}",
+                slot, repair_pubkey_and_addr.0
+            );
+            // Descendants were replayed on top of the wrong version, so their
+            // state has to go as well
+            let slot_descendants = bank_forks
+                .read()
+                .unwrap()
+                .descendants()
+                .remove(&slot)
+                .unwrap_or_default();
+            for d in slot_descendants.iter().chain(std::iter::once(&slot)) {
+                root_bank.clear_slot_signatures(*d);
+                root_bank.remove_unrooted_slot(*d);
+            }
+            blockstore.clear_unconfirmed_slot(slot);
+            let _ = duplicate_slots_reset_sender.send(slot);
+            duplicate_slot_repair_statuses.insert(
+                slot,
+                DuplicateSlotRepairStatus {
+                    start: timestamp(),
+                    repair_pubkey_and_addr: Some(repair_pubkey_and_addr),
+                },
+            );
+        }
+    }
+
     #[allow(dead_code)]
     fn process_new_duplicate_slots(
         new_duplicate_slots: &[Slot],
@@ -631,7 +688,8 @@ impl RepairService {
     }
 
     pub fn join(self) -> thread::Result<()> {
-        self.t_repair.join()
+        self.t_repair.join()?;
+        self.ancestor_hashes_service.join()
     }
 }
 
@@ -1024,7 +1082,7 @@ mod test {
         let cluster_info = Arc::new(ClusterInfo::new_with_invalid_keypair(
             Node::new_localhost().info,
         ));
-        let serve_repair = ServeRepair::new(cluster_info.clone());
+        let serve_repair = ServeRepair::new(cluster_info.clone(), None);
         let valid_repair_peer = Node::new_localhost().info;
 
         // Signal that this peer has completed the dead slot, and is thus
@@ -1154,6 +1212,95 @@ mod test {
         assert!(reset_receiver.try_recv().is_ok());
     }
 
+    #[test]
+    pub fn test_process_ancestor_duplicate_slots() {
+        let blockstore_path = get_tmp_ledger_path!();
+        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let mut duplicate_slot_repair_statuses = HashMap::new();
+        let duplicate_slot = 9;
+        let (shreds, _) = make_slot_entries(duplicate_slot, 0, 1);
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+
+        let keypairs = ValidatorVoteKeypairs::new_rand();
+        let (reset_sender, reset_receiver) = unbounded();
+        let (ancestor_duplicate_slots_sender, ancestor_duplicate_slots_receiver) = unbounded();
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = genesis_utils::create_genesis_config_with_vote_accounts(
+            1_000_000_000,
+            &[&keypairs],
+            vec![10000],
+        );
+        let bank0 = Bank::new(&genesis_config);
+        let bank_forks = RwLock::new(BankForks::new(bank0));
+        let bank0 = bank_forks.read().unwrap().root_bank().clone();
+        let bank9 = bank_forks.write().unwrap().insert(Bank::new_from_parent(
+            &bank0,
+            &Pubkey::default(),
+            duplicate_slot,
+        ));
+        bank9
+            .transfer(10_000, &mint_keypair, &keypairs.node_keypair.pubkey())
+            .unwrap();
+        bank9.freeze();
+        blockstore.insert_bank_hash(duplicate_slot, bank9.hash());
+        let bank10 = bank_forks.write().unwrap().insert(Bank::new_from_parent(
+            &bank9,
+            &Pubkey::default(),
+            duplicate_slot + 1,
+        ));
+        let old_balance = bank0.get_balance(&keypairs.node_keypair.pubkey());
+        let tx_signature = bank10
+            .transfer(10_000, &mint_keypair, &keypairs.node_keypair.pubkey())
+            .unwrap();
+        assert_eq!(
+            bank10.get_balance(&keypairs.node_keypair.pubkey()),
+            old_balance + 20_000
+        );
+
+        // Slots at or below the root are ignored
+        let repair_peer = (Pubkey::new_unique(), socketaddr!("127.0.0.1:1234"));
+        ancestor_duplicate_slots_sender
+            .send((0, repair_peer))
+            .unwrap();
+        ancestor_duplicate_slots_sender
+            .send((duplicate_slot, repair_peer))
+            .unwrap();
+        RepairService::process_ancestor_duplicate_slots(
+            &ancestor_duplicate_slots_receiver,
+            &mut duplicate_slot_repair_statuses,
+            &bank0,
+            &bank_forks,
+            &blockstore,
+            &reset_sender,
+        );
+
+        // Blockstore should have been cleared
+        assert!(blockstore.get_bank_hash(duplicate_slot).is_none());
+
+        // State of the duplicate slot and its descendants is gone
+        assert!(bank10.get_signature_status(&tx_signature).is_none());
+        assert_eq!(
+            bank10.get_balance(&keypairs.node_keypair.pubkey()),
+            old_balance
+        );
+
+        // The slot is repaired from the peer that reported the cluster's version
+        assert_eq!(duplicate_slot_repair_statuses.len(), 1);
+        assert_eq!(
+            duplicate_slot_repair_statuses[&duplicate_slot].repair_pubkey_and_addr,
+            Some(repair_peer)
+        );
+
+        // A signal should be sent to clear ReplayStage
+        assert_eq!(
+            reset_receiver.try_iter().collect::<Vec<_>>(),
+            vec![duplicate_slot]
+        );
+    }
+
     #[test]
     pub fn test_find_new_duplicate_slots() {
         let blockstore_path = get_tmp_ledger_path!();
@@ -1,6 +1,10 @@
 //! The `replay_stage` replays transactions broadcast by the leader.
 
 use crate::{
+    ancestor_hashes_service::AncestorHashesReplayUpdateSender,
+    bank_hash_details_service::{
+        BankHashDetailsSender, BankHashDetailsService, DEFAULT_MAX_BANK_HASH_DETAILS_SLOTS,
+    },
     bank_weight_fork_choice::BankWeightForkChoice,
     broadcast_stage::RetransmitSlotsSender,
     cache_block_time_service::CacheBlockTimeSender,
@@ -19,6 +23,7 @@ use crate::{
     result::Result,
     rewards_recorder_service::RewardsRecorderSender,
     rpc_subscriptions::RpcSubscriptions,
+    window_service::GossipDuplicateSlotsReceiver,
 };
 use solana_ledger::{
     block_error::BlockError,
@@ -30,8 +35,11 @@ use solana_ledger::{
 use solana_measure::{measure::Measure, thread_mem_usage};
 use solana_metrics::inc_new_counter_info;
 use solana_runtime::{
-    accounts_background_service::SnapshotRequestSender, bank::Bank, bank_forks::BankForks,
-    commitment::BlockCommitmentCache, vote_sender_types::ReplayVoteSender,
+    accounts_background_service::SnapshotRequestSender,
+    bank::Bank,
+    bank_forks::BankForks,
+    commitment::{BlockCommitmentCache, VOTE_THRESHOLD_SIZE},
+    vote_sender_types::ReplayVoteSender,
 };
 use solana_sdk::{
     clock::{Slot, NUM_CONSECUTIVE_LEADER_SLOTS},
@@ -47,8 +55,9 @@ use solana_vote_program::{
     vote_state::{Vote, VoteState},
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     ops::Deref,
+    path::PathBuf,
     result,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -69,6 +78,7 @@ pub(crate) enum HeaviestForkFailures {
     FailedThreshold(u64),
     FailedSwitchThreshold(u64),
     NoPropagatedConfirmation(u64),
+    UnconfirmedDuplicateSlot(u64),
 }
 
 // Implement a destructor for the ReplayStage thread to signal it exited
@@ -110,6 +120,7 @@ pub struct ReplayStageConfig {
     pub rewards_recorder_sender: Option<RewardsRecorderSender>,
     pub cache_block_time_sender: Option<CacheBlockTimeSender>,
     pub bank_notification_sender: Option<BankNotificationSender>,
+    pub bank_hash_details_dir: Option<PathBuf>,
 }
 
 #[derive(Default)]
@@ -210,6 +221,7 @@ impl ReplayTiming {
 pub struct ReplayStage {
     t_replay: JoinHandle<Result<()>>,
     commitment_service: AggregateCommitmentService,
+    bank_hash_details_service: Option<BankHashDetailsService>,
 }
 
 impl ReplayStage {
@@ -226,6 +238,8 @@ impl ReplayStage {
         cluster_slots: Arc<ClusterSlots>,
         retransmit_slots_sender: RetransmitSlotsSender,
         duplicate_slots_reset_receiver: DuplicateSlotsResetReceiver,
+        gossip_duplicate_slots_receiver: GossipDuplicateSlotsReceiver,
+        ancestor_hashes_replay_update_sender: AncestorHashesReplayUpdateSender,
         replay_vote_sender: ReplayVoteSender,
     ) -> Self {
         let ReplayStageConfig {
@@ -242,6 +256,7 @@ impl ReplayStage {
             rewards_recorder_sender,
             cache_block_time_sender,
             bank_notification_sender,
+            bank_hash_details_dir,
         } = config;
 
         trace!("replay stage");
@@ -251,6 +266,19 @@ impl ReplayStage {
             block_commitment_cache.clone(),
             subscriptions.clone(),
         );
+        let (bank_hash_details_sender, bank_hash_details_service) = match bank_hash_details_dir {
+            Some(bank_hash_details_dir) => {
+                let (sender, receiver) = crossbeam_channel::unbounded();
+                let service = BankHashDetailsService::new(
+                    receiver,
+                    bank_hash_details_dir,
+                    DEFAULT_MAX_BANK_HASH_DETAILS_SLOTS,
+                    &exit,
+                );
+                (Some(sender), Some(service))
+            }
+            None => (None, None),
+        };
 
         #[allow(clippy::cognitive_complexity)]
         let t_replay = Builder::new()
@@ -274,6 +302,8 @@ impl ReplayStage {
                 let mut partition_exists = false;
                 let mut skipped_slots_info = SkippedSlotsInfo::default();
                 let mut replay_timing = ReplayTiming::default();
+                let mut gossip_duplicate_slots = BTreeSet::new();
+                let mut ancestor_hashes_requested_slots = BTreeSet::new();
                 loop {
                     let allocated = thread_mem_usage::Allocatedp::default();
 
@@ -313,6 +343,7 @@ impl ReplayStage {
                         &replay_vote_sender,
                         &bank_notification_sender,
                         &rewards_recorder_sender,
+                        &bank_hash_details_sender,
                     );
                     replay_active_banks_time.stop();
                     Self::report_memory(&allocated, "replay_active_banks", start);
@@ -332,6 +363,20 @@ impl ReplayStage {
                         &mut descendants,
                         &mut progress,
                         &bank_forks,
+                        &mut ancestor_hashes_requested_slots,
+                    );
+                    Self::update_gossip_duplicate_slots(
+                        &gossip_duplicate_slots_receiver,
+                        forks_root,
+                        &mut gossip_duplicate_slots,
+                    );
+                    Self::request_ancestor_hashes_for_mismatched_slots(
+                        &ancestor_hashes_replay_update_sender,
+                        forks_root,
+                        &progress,
+                        &bank_forks,
+                        &vote_tracker,
+                        &mut ancestor_hashes_requested_slots,
                     );
                     reset_duplicate_slots_time.stop();
 
@@ -399,9 +444,9 @@ impl ReplayStage {
                     let mut select_vote_and_reset_forks_time =
                         Measure::start("select_vote_and_reset_forks");
                     let SelectVoteAndResetForkResult {
-                        vote_bank,
+                        mut vote_bank,
                         reset_bank,
-                        heaviest_fork_failures,
+                        mut heaviest_fork_failures,
                     } = Self::select_vote_and_reset_forks(
                         &heaviest_bank,
                         &heaviest_bank_on_same_voted_fork,
@@ -410,6 +455,21 @@ impl ReplayStage {
                         &progress,
                         &mut tower,
                     );
+                    // Don't vote on a fork the cluster has proven to contain a
+                    // duplicate slot until that slot is confirmed by the network
+                    if let Some((bank, _)) = &vote_bank {
+                        if let Some(duplicate_slot) = Self::unconfirmed_gossip_duplicate_slot(
+                            bank.slot(),
+                            &ancestors,
+                            &progress,
+                            &gossip_duplicate_slots,
+                        ) {
+                            heaviest_fork_failures.push(
+                                HeaviestForkFailures::UnconfirmedDuplicateSlot(duplicate_slot),
+                            );
+                            vote_bank = None;
+                        }
+                    }
                     select_vote_and_reset_forks_time.stop();
 
                     if tower.is_recent(heaviest_bank.slot()) && !heaviest_fork_failures.is_empty() {
@@ -604,6 +664,7 @@ impl ReplayStage {
         Self {
             t_replay,
             commitment_service,
+            bank_hash_details_service,
         }
     }
 
@@ -695,6 +756,7 @@ impl ReplayStage {
         descendants: &mut HashMap<Slot, HashSet<Slot>>,
         progress: &mut ProgressMap,
         bank_forks: &RwLock<BankForks>,
+        ancestor_hashes_requested_slots: &mut BTreeSet<Slot>,
     ) {
         for duplicate_slot in duplicate_slots_reset_receiver.try_iter() {
             Self::purge_unconfirmed_duplicate_slot(
@@ -704,7 +766,100 @@ impl ReplayStage {
                 progress,
                 bank_forks,
             );
+            // The replayed version of this slot is about to be replaced, so
+            // allow it to be checked against the cluster again
+            ancestor_hashes_requested_slots.remove(&duplicate_slot);
+        }
+    }
+
+    // Notify the ancestor hashes service of any slot for which the cluster
+    // has optimistically confirmed a version other than the one we replayed,
+    // or for which our version is dead
+    fn request_ancestor_hashes_for_mismatched_slots(
+        ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
+        root: Slot,
+        progress: &ProgressMap,
+        bank_forks: &RwLock<BankForks>,
+        vote_tracker: &VoteTracker,
+        ancestor_hashes_requested_slots: &mut BTreeSet<Slot>,
+    ) {
+        *ancestor_hashes_requested_slots = ancestor_hashes_requested_slots.split_off(&(root + 1));
+        let r_bank_forks = bank_forks.read().unwrap();
+        let supermajority_stake =
+            (r_bank_forks.root_bank().total_epoch_stake() as f64 * VOTE_THRESHOLD_SIZE) as u64;
+        for (slot, fork_progress) in progress.iter() {
+            if *slot <= root || ancestor_hashes_requested_slots.contains(slot) {
+                continue;
+            }
+            let confirmed_hashes = match vote_tracker.get_slot_vote_tracker(*slot) {
+                Some(slot_vote_tracker) => slot_vote_tracker
+                    .read()
+                    .unwrap()
+                    .optimistically_confirmed_hashes(supermajority_stake),
+                None => continue,
+            };
+            if confirmed_hashes.is_empty() {
+                continue;
+            }
+            let is_mismatched = fork_progress.is_dead
+                || r_bank_forks
+                    .get(*slot)
+                    .map(|bank| bank.is_frozen() && !confirmed_hashes.contains(&bank.hash()))
+                    .unwrap_or(false);
+            if is_mismatched {
+                warn!(
+                    "slot {} was confirmed by the cluster with a different version, \
+                    requesting ancestor hashes",
+                    slot
+                );
+                datapoint_info!(
+                    "replay_stage-ancestor_hashes_request",
+                    ("slot", *slot as i64, i64)
+                );
+                ancestor_hashes_requested_slots.insert(*slot);
+                let _ = ancestor_hashes_replay_update_sender.send(*slot);
+            }
+        }
+    }
+
+    fn update_gossip_duplicate_slots(
+        gossip_duplicate_slots_receiver: &GossipDuplicateSlotsReceiver,
+        root: Slot,
+        gossip_duplicate_slots: &mut BTreeSet<Slot>,
+    ) {
+        for duplicate_slot in gossip_duplicate_slots_receiver.try_iter() {
+            if duplicate_slot > root {
+                datapoint_info!(
+                    "replay_stage-gossip_duplicate_slot",
+                    ("slot", duplicate_slot as i64, i64)
+                );
+                gossip_duplicate_slots.insert(duplicate_slot);
+            }
         }
+        *gossip_duplicate_slots = gossip_duplicate_slots.split_off(&(root + 1));
+    }
+
+    // Returns the earliest slot on the fork ending at `slot` that gossip has
+    // proven duplicate and that has not yet been confirmed by the cluster
+    fn unconfirmed_gossip_duplicate_slot(
+        slot: Slot,
+        ancestors: &HashMap<Slot, HashSet<Slot>>,
+        progress: &ProgressMap,
+        gossip_duplicate_slots: &BTreeSet<Slot>,
+    ) -> Option<Slot> {
+        let slot_ancestors = ancestors.get(&slot)?;
+        gossip_duplicate_slots
+            .iter()
+            .filter(|duplicate_slot| {
+                **duplicate_slot == slot || slot_ancestors.contains(duplicate_slot)
+            })
+            .find(|duplicate_slot| {
+                progress
+                    .get_fork_stats(**duplicate_slot)
+                    .map(|fork_stats| !fork_stats.confirmation_reported)
+                    .unwrap_or(false)
+            })
+            .copied()
     }
 
     fn purge_unconfirmed_duplicate_slot(
@@ -980,6 +1135,7 @@ impl ReplayStage {
             Some(replay_vote_sender),
             None,
             verify_recyclers,
+            false,
         );
         let tx_count_after = bank_progress.replay_progress.num_txs;
         let tx_count = tx_count_after - tx_count_before;
@@ -1263,6 +1419,7 @@ impl ReplayStage {
         replay_vote_sender: &ReplayVoteSender,
         bank_notification_sender: &Option<BankNotificationSender>,
         rewards_recorder_sender: &Option<RewardsRecorderSender>,
+        bank_hash_details_sender: &Option<BankHashDetailsSender>,
     ) -> bool {
         let mut did_complete_bank = false;
         let mut tx_count = 0;
@@ -1331,6 +1488,10 @@ impl ReplayStage {
                 did_complete_bank = true;
                 info!("bank frozen: {}", bank.slot());
                 bank.freeze();
+                if let Some(bank_hash_details_sender) = bank_hash_details_sender {
+                    let _ = bank_hash_details_sender.send(bank.clone());
+                }
+                blockstore.insert_bank_hash(bank.slot(), bank.hash());
                 heaviest_subtree_fork_choice
                     .add_new_leaf_slot(bank.slot(), Some(bank.parent_slot()));
                 if let Some(sender) = bank_notification_sender {
@@ -1960,6 +2121,9 @@ impl ReplayStage {
 
     pub fn join(self) -> thread::Result<()> {
         self.commitment_service.join()?;
+        if let Some(bank_hash_details_service) = self.bank_hash_details_service {
+            bank_hash_details_service.join()?;
+        }
         self.t_replay.join().map(|_| ())
     }
 }
@@ -3668,6 +3832,102 @@ pub(crate) mod tests {
         ));
     }
 
+    #[test]
+    fn test_gossip_duplicate_slots() {
+        let (sender, receiver) = unbounded();
+        let mut gossip_duplicate_slots = BTreeSet::new();
+        sender.send(2).unwrap();
+        sender.send(5).unwrap();
+        ReplayStage::update_gossip_duplicate_slots(&receiver, 0, &mut gossip_duplicate_slots);
+        assert_eq!(gossip_duplicate_slots, vec![2, 5].into_iter().collect());
+
+        // 0 <- 1 <- 2 <- 3
+        //      \
+        //       <- 4
+        let mut ancestors = HashMap::new();
+        ancestors.insert(0, HashSet::new());
+        ancestors.insert(1, vec![0].into_iter().collect());
+        ancestors.insert(2, vec![0, 1].into_iter().collect());
+        ancestors.insert(3, vec![0, 1, 2].into_iter().collect());
+        ancestors.insert(4, vec![0, 1].into_iter().collect());
+        let mut progress = ProgressMap::default();
+        for slot in 0..5 {
+            progress.insert(slot, ForkProgress::new(Hash::default(), None, None, 0, 0));
+        }
+        let check = |slot, progress: &ProgressMap| {
+            ReplayStage::unconfirmed_gossip_duplicate_slot(
+                slot,
+                &ancestors,
+                progress,
+                &gossip_duplicate_slots,
+            )
+        };
+        assert_eq!(check(1, &progress), None);
+        assert_eq!(check(2, &progress), Some(2));
+        assert_eq!(check(3, &progress), Some(2));
+        assert_eq!(check(4, &progress), None);
+
+        // Once the cluster confirms the duplicate slot it can be voted on
+        progress
+            .get_fork_stats_mut(2)
+            .unwrap()
+            .confirmation_reported = true;
+        assert_eq!(check(3, &progress), None);
+
+        // Slots at or below the root are pruned
+        ReplayStage::update_gossip_duplicate_slots(&receiver, 2, &mut gossip_duplicate_slots);
+        assert_eq!(gossip_duplicate_slots, vec![5].into_iter().collect());
+    }
+
+    #[test]
+    fn test_request_ancestor_hashes_for_mismatched_slots() {
+        let (bank_forks, mut progress) = setup_forks();
+        let vote_tracker = VoteTracker::new(&bank_forks.read().unwrap().root_bank());
+        let total_stake = bank_forks.read().unwrap().root_bank().total_epoch_stake();
+        let confirm_hash = |slot, hash| {
+            vote_tracker
+                .get_or_insert_slot_tracker(slot)
+                .write()
+                .unwrap()
+                .get_or_insert_optimistic_votes_tracker(hash)
+                .add_vote_pubkey(Arc::new(Pubkey::new_unique()), total_stake, total_stake);
+        };
+        let (sender, receiver) = unbounded();
+        let mut requested_slots = BTreeSet::new();
+
+        // Cluster confirmed the same version of slot 4 that we replayed
+        let bank_4_hash = bank_forks.read().unwrap().get(4).unwrap().hash();
+        confirm_hash(4, bank_4_hash);
+        // Cluster confirmed a different version of slot 5
+        confirm_hash(5, Hash::new_unique());
+        // Our version of slot 6 is dead
+        confirm_hash(6, Hash::new_unique());
+        progress.get_mut(&6).unwrap().is_dead = true;
+
+        let request = |root, requested_slots: &mut BTreeSet<Slot>| {
+            ReplayStage::request_ancestor_hashes_for_mismatched_slots(
+                &sender,
+                root,
+                &progress,
+                &bank_forks,
+                &vote_tracker,
+                requested_slots,
+            );
+            let mut sent: Vec<_> = receiver.try_iter().collect();
+            sent.sort_unstable();
+            sent
+        };
+        assert_eq!(request(0, &mut requested_slots), vec![5, 6]);
+        assert_eq!(requested_slots, vec![5, 6].into_iter().collect());
+
+        // Slots are only requested once
+        assert_eq!(request(0, &mut requested_slots), Vec::<Slot>::new());
+
+        // Slots at or below the root are pruned
+        assert_eq!(request(5, &mut requested_slots), Vec::<Slot>::new());
+        assert_eq!(requested_slots, vec![6].into_iter().collect());
+    }
+
     #[test]
     fn test_purge_unconfirmed_duplicate_slot() {
         let (bank_forks, mut progress) = setup_forks();
@@ -59,6 +59,7 @@ use solana_sdk::{
     transaction::{self, Transaction},
 };
 use solana_stake_program::stake_state::StakeState;
+use solana_storage_bigtable::{LedgerStorageAdapter, LedgerStorageConfig};
 use solana_transaction_status::{
     EncodedConfirmedBlock, EncodedConfirmedTransaction, TransactionStatus, UiTransactionEncoding,
 };
@@ -103,11 +104,13 @@ pub struct JsonRpcConfig {
     pub enable_validator_exit: bool,
     pub enable_set_log_filter: bool,
     pub enable_rpc_transaction_history: bool,
+    pub enable_rpc_account_proofs: bool,
     pub identity_pubkey: Pubkey,
     pub faucet_addr: Option<SocketAddr>,
     pub health_check_slot_distance: u64,
     pub enable_bigtable_ledger_storage: bool,
     pub enable_bigtable_ledger_upload: bool,
+    pub ledger_storage_config: LedgerStorageConfig,
 }
 
 #[derive(Clone)]
@@ -122,7 +125,7 @@ pub struct JsonRpcRequestProcessor {
     genesis_hash: Hash,
     transaction_sender: Arc<Mutex<Sender<TransactionInfo>>>,
     runtime_handle: runtime::Handle,
-    bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+    bigtable_ledger_storage: Option<Arc<dyn LedgerStorageAdapter>>,
     optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
 }
 impl Metadata for JsonRpcRequestProcessor {}
@@ -201,7 +204,7 @@ impl JsonRpcRequestProcessor {
         cluster_info: Arc<ClusterInfo>,
         genesis_hash: Hash,
         runtime: &runtime::Runtime,
-        bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+        bigtable_ledger_storage: Option<Arc<dyn LedgerStorageAdapter>>,
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
     ) -> (Self, Receiver<TransactionInfo>) {
         let (sender, receiver) = channel();
@@ -294,6 +297,61 @@ impl JsonRpcRequestProcessor {
         Ok(new_response(&bank, accounts))
     }
 
+    pub fn get_account_proof(
+        &self,
+        pubkey: &Pubkey,
+        config: Option<RpcAccountProofConfig>,
+    ) -> Result<Option<RpcAccountProof>> {
+        if !self.config.enable_rpc_account_proofs {
+            return Err(Error::method_not_found());
+        }
+        let encoding = config
+            .unwrap_or_default()
+            .encoding
+            .unwrap_or(UiAccountEncoding::Base64);
+        if encoding != UiAccountEncoding::Base58 && encoding != UiAccountEncoding::Base64 {
+            return Err(Error::invalid_params(
+                "Invalid param: only base58 and base64 encodings are supported".to_string(),
+            ));
+        }
+
+        let bank = self.bank(None);
+        let account_proof = bank.get_account_proof(pubkey).map_err(|err| {
+            RpcCustomError::AccountProofNotAvailable {
+                message: err.to_string(),
+            }
+        })?;
+        account_proof
+            .map(|account_proof| {
+                if encoding == UiAccountEncoding::Base58 && account_proof.account.data.len() > 128
+                {
+                    return Err(Error::invalid_params(
+                        "Encoded binary (base 58) data should be less than 128 bytes, please use Base64 encoding.".to_string(),
+                    ));
+                }
+                Ok(RpcAccountProof {
+                    slot: account_proof.accounts_hash_slot,
+                    accounts_hash: account_proof.accounts_hash.to_string(),
+                    account_hash: account_proof.account_hash.to_string(),
+                    account_slot: account_proof.account_slot,
+                    account: UiAccount::encode(
+                        pubkey,
+                        account_proof.account,
+                        encoding,
+                        None,
+                        None,
+                    ),
+                    proof: account_proof
+                        .proof
+                        .levels
+                        .iter()
+                        .map(RpcMerkleProofLevel::from)
+                        .collect(),
+                })
+            })
+            .transpose()
+    }
+
     pub fn get_minimum_balance_for_rent_exemption(
         &self,
         data_len: usize,
@@ -1267,7 +1325,7 @@ impl JsonRpcRequestProcessor {
     }
 }
 
-fn verify_transaction(transaction: &Transaction) -> Result<()> {
+fn verify_transaction(transaction: &Transaction, ed25519_program_enabled: bool) -> Result<()> {
     if transaction.verify().is_err() {
         return Err(RpcCustomError::TransactionSignatureVerificationFailure.into());
     }
@@ -1276,6 +1334,12 @@ fn verify_transaction(transaction: &Transaction) -> Result<()> {
         return Err(RpcCustomError::TransactionPrecompileVerificationFailure(e).into());
     }
 
+    if ed25519_program_enabled {
+        if let Err(e) = transaction.verify_ed25519_instructions() {
+            return Err(RpcCustomError::TransactionPrecompileVerificationFailure(e).into());
+        }
+    }
+
     Ok(())
 }
 
@@ -1521,6 +1585,14 @@ pub trait RpcSol {
         config: Option<RpcAccountInfoConfig>,
     ) -> Result<RpcResponse<Option<UiAccount>>>;
 
+    #[rpc(meta, name = "getAccountProof")]
+    fn get_account_proof(
+        &self,
+        meta: Self::Metadata,
+        pubkey_str: String,
+        config: Option<RpcAccountProofConfig>,
+    ) -> Result<Option<RpcAccountProof>>;
+
     #[rpc(meta, name = "getMultipleAccounts")]
     fn get_multiple_accounts(
         &self,
@@ -1879,6 +1951,17 @@ impl RpcSol for RpcSolImpl {
         meta.get_account_info(&pubkey, config)
     }
 
+    fn get_account_proof(
+        &self,
+        meta: Self::Metadata,
+        pubkey_str: String,
+        config: Option<RpcAccountProofConfig>,
+    ) -> Result<Option<RpcAccountProof>> {
+        debug!("get_account_proof rpc request received: {:?}", pubkey_str);
+        let pubkey = verify_pubkey(pubkey_str)?;
+        meta.get_account_proof(&pubkey, config)
+    }
+
     fn get_multiple_accounts(
         &self,
         meta: Self::Metadata,
@@ -2286,7 +2369,7 @@ impl RpcSol for RpcSolImpl {
             .unwrap_or(0);
 
         if !config.skip_preflight {
-            if let Err(e) = verify_transaction(&transaction) {
+            if let Err(e) = verify_transaction(&transaction, bank.ed25519_program_enabled()) {
                 return Err(e);
             }
 
@@ -2324,13 +2407,13 @@ impl RpcSol for RpcSolImpl {
         let encoding = config.encoding.unwrap_or(UiTransactionEncoding::Base58);
         let (_, transaction) = deserialize_transaction(data, encoding)?;
 
+        let bank = &*meta.bank(config.commitment);
         if config.sig_verify {
-            if let Err(e) = verify_transaction(&transaction) {
+            if let Err(e) = verify_transaction(&transaction, bank.ed25519_program_enabled()) {
                 return Err(e);
             }
         }
 
-        let bank = &*meta.bank(config.commitment);
         let (result, logs) = bank.simulate_transaction(transaction);
 
         Ok(new_response(
@@ -2687,7 +2770,10 @@ pub mod tests {
         futures::future::Future, ErrorCode, MetaIoHandler, Output, Response, Value,
     };
     use jsonrpc_core_client::transports::local;
-    use solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes};
+    use solana_client::{
+        rpc_custom_error::JSON_RPC_SERVER_ERROR_ACCOUNT_PROOF_NOT_AVAILABLE,
+        rpc_filter::{Memcmp, MemcmpEncodedBytes},
+    };
     use solana_ledger::{
         blockstore::entries_to_test_shreds,
         blockstore_meta::PerfSample,
@@ -3459,6 +3545,65 @@ pub mod tests {
         result["error"].as_object().unwrap();
     }
 
+    #[test]
+    fn test_rpc_get_account_proof() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let genesis = create_genesis_config(100);
+        let bank = Arc::new(Bank::new(&genesis.genesis_config));
+        bank.transfer(20, &genesis.mint_keypair, &bob_pubkey)
+            .unwrap();
+        let mut meta = JsonRpcRequestProcessor::new_from_bank(&bank);
+
+        let mut io = MetaIoHandler::default();
+        io.extend_with(RpcSolImpl.to_delegate());
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getAccountProof","params":["{}"]}}"#,
+            bob_pubkey
+        );
+        let get_account_proof = |meta: &JsonRpcRequestProcessor| -> Value {
+            let res = io.handle_request_sync(&req, meta.clone());
+            serde_json::from_str(&res.expect("actual response"))
+                .expect("actual response deserialization")
+        };
+
+        let result = get_account_proof(&meta);
+        assert_eq!(
+            result["error"]["code"],
+            json!(ErrorCode::MethodNotFound.code())
+        );
+
+        meta.config.enable_rpc_account_proofs = true;
+        let result = get_account_proof(&meta);
+        assert_eq!(
+            result["error"]["code"],
+            json!(JSON_RPC_SERVER_ERROR_ACCOUNT_PROOF_NOT_AVAILABLE)
+        );
+
+        bank.rc
+            .accounts
+            .accounts_db
+            .retain_accounts_hash_tree
+            .store(true, Ordering::Relaxed);
+        bank.freeze();
+        let accounts_hash = bank.update_accounts_hash();
+        let result = get_account_proof(&meta);
+        let account_proof: RpcAccountProof =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        assert_eq!(account_proof.accounts_hash, accounts_hash.to_string());
+        assert_eq!(account_proof.account.decode().unwrap().lamports, 20);
+        let account_hash = Hash::from_str(&account_proof.account_hash).unwrap();
+        assert!(account_proof
+            .merkle_proof()
+            .unwrap()
+            .verify(&account_hash, &accounts_hash));
+        assert!(account_proof.verify(&bob_pubkey, &bank.cluster_type(), &accounts_hash));
+        assert!(!account_proof.verify(
+            &solana_sdk::pubkey::new_rand(),
+            &bank.cluster_type(),
+            &accounts_hash
+        ));
+    }
+
     #[test]
     fn test_rpc_get_multiple_accounts() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -275,11 +275,15 @@ impl JsonRpcService {
         let (bigtable_ledger_storage, _bigtable_ledger_upload_service) =
             if config.enable_bigtable_ledger_storage || config.enable_bigtable_ledger_upload {
                 runtime
-                    .block_on(solana_storage_bigtable::LedgerStorage::new(
+                    .block_on(solana_storage_bigtable::new_ledger_storage(
+                        &config.ledger_storage_config,
                         !config.enable_bigtable_ledger_upload,
                     ))
                     .map(|bigtable_ledger_storage| {
-                        info!("BigTable ledger storage initialized");
+                        info!(
+                            "Ledger storage initialized: {:?}",
+                            config.ledger_storage_config
+                        );
 
                         let bigtable_ledger_upload_service = Arc::new(BigTableUploadService::new(
                             runtime.handle().clone(),
@@ -295,7 +299,7 @@ impl JsonRpcService {
                         )
                     })
                     .unwrap_or_else(|err| {
-                        error!("Failed to initialize BigTable ledger storage: {:?}", err);
+                        error!("Failed to initialize ledger storage: {:?}", err);
                         (None, None)
                     })
             } else {
@@ -508,6 +512,7 @@ mod tests {
                 snapshot_path: PathBuf::from("/"),
                 compression: CompressionType::Bzip2,
                 snapshot_version: SnapshotVersion::default(),
+                include_storage_indexes: false,
             }),
             bank_forks,
             RpcHealth::stub(),
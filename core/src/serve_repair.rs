@@ -8,20 +8,26 @@ use crate::{
     weighted_shuffle::weighted_best,
 };
 use bincode::serialize;
-use solana_ledger::{blockstore::Blockstore, shred::Nonce};
+use solana_ledger::{
+    blockstore::Blockstore,
+    shred::{Nonce, SIZE_OF_NONCE, SIZE_OF_SIGNATURE},
+};
 use solana_measure::measure::Measure;
 use solana_measure::thread_mem_usage;
 use solana_metrics::{datapoint_debug, inc_new_counter_debug};
-use solana_perf::packet::{limited_deserialize, Packets, PacketsRecycler};
+use solana_perf::packet::{limited_deserialize, Packet, Packets, PacketsRecycler};
+use solana_runtime::{bank_forks::BankForks, feature_set};
 use solana_sdk::{
     clock::Slot,
+    hash::{Hash, HASH_BYTES},
+    packet::PACKET_DATA_SIZE,
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    timing::duration_as_ms,
+    signature::{Keypair, Signature, Signer},
+    timing::{duration_as_ms, timestamp},
 };
 use solana_streamer::streamer::{PacketReceiver, PacketSender};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     net::SocketAddr,
     sync::atomic::{AtomicBool, Ordering},
     sync::{Arc, RwLock},
@@ -32,6 +38,31 @@ use std::{
 /// the number of slots to respond with when responding to `Orphan` requests
 pub const MAX_ORPHAN_REPAIR_RESPONSES: usize = 10;
 pub const DEFAULT_NONCE: u32 = 42;
+/// the number of (slot, hash) pairs that fit in a response to an
+/// `AncestorHashes` request, after the enum tag, vector length and nonce
+pub const MAX_ANCESTOR_RESPONSES: usize =
+    (PACKET_DATA_SIZE - SIZE_OF_NONCE - 4 - 8) / (std::mem::size_of::<Slot>() + HASH_BYTES);
+/// signed requests start with their signature, right after the enum tag
+const SIGNED_REPAIR_REQUEST_SIGNATURE_OFFSET: usize = 4;
+/// signed requests are only served within this many ms of their timestamp
+pub const SIGNED_REPAIR_TIME_WINDOW_MS: u64 = 5 * 1000;
+/// repair request budgets are refilled at this interval
+const REPAIR_REQUEST_BUDGET_INTERVAL: Duration = Duration::from_secs(1);
+/// requests per interval that staked requesters share in proportion to stake
+const STAKED_REPAIR_REQUESTS_PER_INTERVAL: usize = 8_192;
+/// requests per interval that any staked requester may send regardless of
+/// how small its stake is
+const MIN_STAKED_REPAIR_REQUESTS_PER_INTERVAL: usize = 32;
+/// requests per interval shared by all unstaked or unauthenticated requesters
+const UNSTAKED_REPAIR_REQUESTS_PER_INTERVAL: usize = 512;
+/// signatures of served requests are pruned from the replay cache at this
+/// interval
+const REPAIR_REPLAY_CACHE_PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+/// the replay cache evicts its oldest signatures beyond this many, which is
+/// more than the request budgets let through within the time window
+const MAX_REPAIR_REPLAY_CACHE_ENTRIES: usize = 64 * 1024;
+/// number of busiest requesters reported in metrics
+const MAX_REPORTED_REPAIR_PEERS: usize = 10;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum RepairType {
@@ -50,6 +81,25 @@ impl RepairType {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum AncestorHashesResponseVersion {
+    Current(Vec<(Slot, Hash)>),
+}
+
+impl AncestorHashesResponseVersion {
+    pub fn into_slot_hashes(self) -> Vec<(Slot, Hash)> {
+        match self {
+            AncestorHashesResponseVersion::Current(slot_hashes) => slot_hashes,
+        }
+    }
+
+    pub fn slot_hashes(&self) -> &[(Slot, Hash)] {
+        match self {
+            AncestorHashesResponseVersion::Current(slot_hashes) => slot_hashes,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct ServeRepairStats {
     pub total_packets: usize,
@@ -59,6 +109,50 @@ pub struct ServeRepairStats {
     pub window_index: usize,
     pub highest_window_index: usize,
     pub orphan: usize,
+    pub ancestor_hashes: usize,
+    pub err_sig_verify: usize,
+    pub err_id_mismatch: usize,
+    pub err_time_skew: usize,
+    pub err_addr_mismatch: usize,
+    pub err_replayed: usize,
+    pub dropped_requests_staked: usize,
+    pub dropped_requests_unstaked: usize,
+    /// keyed by the sender of signed requests, with all unsigned requests
+    /// counted under `None` since their sender is not authenticated
+    pub served_by_peer: HashMap<Option<Pubkey>, usize>,
+    pub dropped_by_peer: HashMap<Option<Pubkey>, usize>,
+}
+
+/// Identifies the sender and the intended recipient of a signed repair
+/// request, and the address the response must be sent to. The signature
+/// covers the whole serialized request except for the signature itself.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RepairRequestHeader {
+    signature: Signature,
+    sender: Pubkey,
+    recipient: Pubkey,
+    response_addr: SocketAddr,
+    timestamp: u64,
+    nonce: Nonce,
+}
+
+impl RepairRequestHeader {
+    pub fn new(
+        sender: Pubkey,
+        recipient: Pubkey,
+        response_addr: SocketAddr,
+        timestamp: u64,
+        nonce: Nonce,
+    ) -> Self {
+        Self {
+            signature: Signature::default(),
+            sender,
+            recipient,
+            response_addr,
+            timestamp,
+            nonce,
+        }
+    }
 }
 
 /// Window protocol messages
@@ -70,6 +164,179 @@ pub enum RepairProtocol {
     WindowIndexWithNonce(ContactInfo, u64, u64, Nonce),
     HighestWindowIndexWithNonce(ContactInfo, u64, u64, Nonce),
     OrphanWithNonce(ContactInfo, u64, Nonce),
+    AncestorHashes(ContactInfo, Slot, Nonce),
+    WindowIndexSigned(RepairRequestHeader, Slot, u64),
+    HighestWindowIndexSigned(RepairRequestHeader, Slot, u64),
+    OrphanSigned(RepairRequestHeader, Slot),
+    AncestorHashesSigned(RepairRequestHeader, Slot),
+}
+
+impl RepairProtocol {
+    fn sender(&self) -> &Pubkey {
+        match self {
+            RepairProtocol::WindowIndex(from, _, _) => &from.id,
+            RepairProtocol::HighestWindowIndex(from, _, _) => &from.id,
+            RepairProtocol::Orphan(from, _) => &from.id,
+            RepairProtocol::WindowIndexWithNonce(from, _, _, _) => &from.id,
+            RepairProtocol::HighestWindowIndexWithNonce(from, _, _, _) => &from.id,
+            RepairProtocol::OrphanWithNonce(from, _, _) => &from.id,
+            RepairProtocol::AncestorHashes(from, _, _) => &from.id,
+            RepairProtocol::WindowIndexSigned(header, _, _) => &header.sender,
+            RepairProtocol::HighestWindowIndexSigned(header, _, _) => &header.sender,
+            RepairProtocol::OrphanSigned(header, _) => &header.sender,
+            RepairProtocol::AncestorHashesSigned(header, _) => &header.sender,
+        }
+    }
+
+    fn header(&self) -> Option<&RepairRequestHeader> {
+        match self {
+            RepairProtocol::WindowIndexSigned(header, _, _)
+            | RepairProtocol::HighestWindowIndexSigned(header, _, _)
+            | RepairProtocol::OrphanSigned(header, _)
+            | RepairProtocol::AncestorHashesSigned(header, _) => Some(header),
+            _ => None,
+        }
+    }
+}
+
+/// Budgets of repair requests, refilled every `REPAIR_REQUEST_BUDGET_INTERVAL`.
+/// Requesters which signed their requests get a budget of their own in
+/// proportion to their stake, while unstaked and unauthenticated requesters
+/// all draw from one small shared budget.
+/// Requests are only limited once the `sign_repair_requests` feature is
+/// active, since until then no requester can authenticate itself.
+#[derive(Default)]
+struct RepairRequestBudgets {
+    enabled: bool,
+    stakes: HashMap<Pubkey, u64>,
+    total_stake: u64,
+    staked_remaining: HashMap<Pubkey, usize>,
+    unstaked_remaining: usize,
+    last_refill: Option<Instant>,
+}
+
+impl RepairRequestBudgets {
+    fn maybe_refill(&mut self, serve_repair: &ServeRepair) {
+        let should_refill = self.last_refill.map_or(true, |last_refill| {
+            last_refill.elapsed() >= REPAIR_REQUEST_BUDGET_INTERVAL
+        });
+        if !should_refill {
+            return;
+        }
+        if serve_repair.should_sign_repair_requests() {
+            let (stakes, total_stake) = serve_repair.staked_nodes();
+            self.refill(stakes, total_stake);
+        } else {
+            self.disable();
+        }
+    }
+
+    fn refill(&mut self, stakes: HashMap<Pubkey, u64>, total_stake: u64) {
+        self.enabled = true;
+        self.stakes = stakes;
+        self.total_stake = total_stake;
+        self.staked_remaining.clear();
+        self.unstaked_remaining = UNSTAKED_REPAIR_REQUESTS_PER_INTERVAL;
+        self.last_refill = Some(Instant::now());
+    }
+
+    fn disable(&mut self) {
+        *self = Self {
+            last_refill: Some(Instant::now()),
+            ..Self::default()
+        };
+    }
+
+    fn staked_budget(&self, stake: u64) -> usize {
+        let budget = STAKED_REPAIR_REQUESTS_PER_INTERVAL as u128 * stake as u128
+            / self.total_stake.max(1) as u128;
+        (budget as usize).max(MIN_STAKED_REPAIR_REQUESTS_PER_INTERVAL)
+    }
+
+    /// Returns true if a request may be served, charging it to the budget of
+    /// `requester`, which is None if the request could not be authenticated
+    fn try_consume(&mut self, requester: Option<&Pubkey>) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let staked_requester = requester.and_then(|requester| {
+            let stake = self.stakes.get(requester).copied().unwrap_or_default();
+            if stake > 0 {
+                Some((requester, stake))
+            } else {
+                None
+            }
+        });
+        let remaining = match staked_requester {
+            Some((requester, stake)) => {
+                let budget = self.staked_budget(stake);
+                self.staked_remaining.entry(*requester).or_insert(budget)
+            }
+            None => &mut self.unstaked_remaining,
+        };
+        if *remaining == 0 {
+            return false;
+        }
+        *remaining -= 1;
+        true
+    }
+
+    fn is_staked(&self, requester: &Pubkey) -> bool {
+        self.stakes.get(requester).copied().unwrap_or_default() > 0
+    }
+}
+
+/// Signatures of the signed requests served recently, along with the
+/// timestamp in their header, so that a captured request cannot be replayed
+/// while its timestamp is still within `SIGNED_REPAIR_TIME_WINDOW_MS`. At most
+/// `MAX_REPAIR_REPLAY_CACHE_ENTRIES` are kept, evicting the oldest first.
+#[derive(Default)]
+struct RepairReplayCache {
+    signatures: HashMap<Signature, u64>,
+    // signatures in the order they were inserted
+    order: VecDeque<Signature>,
+    last_prune: Option<Instant>,
+}
+
+impl RepairReplayCache {
+    fn insert(&mut self, header: &RepairRequestHeader) {
+        if self
+            .signatures
+            .insert(header.signature, header.timestamp)
+            .is_none()
+        {
+            self.order.push_back(header.signature);
+        }
+        while self.order.len() > MAX_REPAIR_REPLAY_CACHE_ENTRIES {
+            if let Some(signature) = self.order.pop_front() {
+                self.signatures.remove(&signature);
+            }
+        }
+    }
+
+    fn contains(&self, header: &RepairRequestHeader) -> bool {
+        self.signatures.contains_key(&header.signature)
+    }
+
+    fn maybe_prune(&mut self) {
+        let should_prune = self.last_prune.map_or(true, |last_prune| {
+            last_prune.elapsed() >= REPAIR_REPLAY_CACHE_PRUNE_INTERVAL
+        });
+        if should_prune {
+            self.prune(timestamp());
+        }
+    }
+
+    /// Drops the signatures of requests which would now be rejected as stale
+    fn prune(&mut self, now: u64) {
+        self.signatures.retain(|_, request_timestamp| {
+            request_timestamp.saturating_add(SIGNED_REPAIR_TIME_WINDOW_MS) >= now
+        });
+        let signatures = &self.signatures;
+        self.order
+            .retain(|signature| signatures.contains_key(signature));
+        self.last_prune = Some(Instant::now());
+    }
 }
 
 #[derive(Clone)]
@@ -78,6 +345,9 @@ pub struct ServeRepair {
     keypair: Arc<Keypair>,
     my_info: ContactInfo,
     cluster_info: Arc<ClusterInfo>,
+    /// used to look up the stake of requesters and whether requests should
+    /// be signed
+    bank_forks: Option<Arc<RwLock<BankForks>>>,
 }
 
 type RepairCache = HashMap<Slot, (Vec<ContactInfo>, Vec<(u64, usize)>)>;
@@ -85,17 +355,19 @@ type RepairCache = HashMap<Slot, (Vec<ContactInfo>, Vec<(u64, usize)>)>;
 impl ServeRepair {
     /// Without a valid keypair gossip will not function. Only useful for tests.
     pub fn new_with_invalid_keypair(contact_info: ContactInfo) -> Self {
-        Self::new(Arc::new(ClusterInfo::new_with_invalid_keypair(
-            contact_info,
-        )))
+        Self::new(
+            Arc::new(ClusterInfo::new_with_invalid_keypair(contact_info)),
+            None,
+        )
     }
 
-    pub fn new(cluster_info: Arc<ClusterInfo>) -> Self {
+    pub fn new(cluster_info: Arc<ClusterInfo>, bank_forks: Option<Arc<RwLock<BankForks>>>) -> Self {
         let (keypair, my_info) = { (cluster_info.keypair.clone(), cluster_info.my_contact_info()) };
         Self {
             keypair,
             my_info,
             cluster_info,
+            bank_forks,
         }
     }
 
@@ -107,15 +379,90 @@ impl ServeRepair {
         &self.keypair
     }
 
-    fn get_repair_sender(request: &RepairProtocol) -> &ContactInfo {
-        match request {
-            RepairProtocol::WindowIndex(ref from, _, _) => from,
-            RepairProtocol::HighestWindowIndex(ref from, _, _) => from,
-            RepairProtocol::Orphan(ref from, _) => from,
-            RepairProtocol::WindowIndexWithNonce(ref from, _, _, _) => from,
-            RepairProtocol::HighestWindowIndexWithNonce(ref from, _, _, _) => from,
-            RepairProtocol::OrphanWithNonce(ref from, _, _) => from,
+    /// Returns the stake of each node in the current epoch of the root bank,
+    /// and the total stake
+    fn staked_nodes(&self) -> (HashMap<Pubkey, u64>, u64) {
+        let root_bank = match &self.bank_forks {
+            Some(bank_forks) => bank_forks.read().unwrap().root_bank().clone(),
+            None => return (HashMap::new(), 0),
+        };
+        match root_bank.epoch_stakes(root_bank.epoch()) {
+            Some(epoch_stakes) => (
+                epoch_stakes
+                    .node_id_to_vote_accounts()
+                    .iter()
+                    .map(|(node_id, vote_accounts)| (*node_id, vote_accounts.total_stake))
+                    .collect(),
+                epoch_stakes.total_stake(),
+            ),
+            None => (HashMap::new(), 0),
+        }
+    }
+
+    fn should_sign_repair_requests(&self) -> bool {
+        self.bank_forks
+            .as_ref()
+            .map(|bank_forks| {
+                bank_forks
+                    .read()
+                    .unwrap()
+                    .root_bank()
+                    .feature_set
+                    .is_active(&feature_set::sign_repair_requests::id())
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the signed `request`, deserialized from `packet`, was
+    /// signed by its sender, recently meant for `my_id` and asks for the
+    /// response to go back to where the packet came from
+    fn verify_signed_request(
+        my_id: &Pubkey,
+        request: &RepairProtocol,
+        packet: &Packet,
+        stats: &mut ServeRepairStats,
+    ) -> bool {
+        let header = match request.header() {
+            Some(header) => header,
+            None => return false,
+        };
+        if header.recipient != *my_id {
+            stats.err_id_mismatch += 1;
+            return false;
         }
+        if header.response_addr != packet.meta.addr() {
+            stats.err_addr_mismatch += 1;
+            return false;
+        }
+        let now = timestamp();
+        let time_diff_ms = if now > header.timestamp {
+            now - header.timestamp
+        } else {
+            header.timestamp - now
+        };
+        if time_diff_ms > SIGNED_REPAIR_TIME_WINDOW_MS {
+            stats.err_time_skew += 1;
+            return false;
+        }
+        let data = &packet.data[..packet.meta.size];
+        let signature_end = SIGNED_REPAIR_REQUEST_SIGNATURE_OFFSET + SIZE_OF_SIGNATURE;
+        if data.len() < signature_end {
+            stats.err_sig_verify += 1;
+            return false;
+        }
+        let signed_data = [
+            &data[..SIGNED_REPAIR_REQUEST_SIGNATURE_OFFSET],
+            &data[signature_end..],
+        ]
+        .concat();
+        if !header
+            .signature
+            .verify(header.sender.as_ref(), &signed_data)
+        {
+            stats.err_sig_verify += 1;
+            return false;
+        }
+        true
     }
 
     fn handle_repair(
@@ -128,10 +475,9 @@ impl ServeRepair {
     ) -> Option<Packets> {
         let now = Instant::now();
 
-        //TODO verify from is signed
         let my_id = me.read().unwrap().keypair.pubkey();
-        let from = Self::get_repair_sender(&request);
-        if from.id == my_id {
+        let from = request.sender();
+        if *from == my_id {
             stats.self_repair += 1;
             return None;
         }
@@ -154,6 +500,63 @@ impl ServeRepair {
                         "WindowIndexWithNonce",
                     )
                 }
+                RepairProtocol::WindowIndexSigned(header, slot, shred_index) => {
+                    stats.window_index += 1;
+                    (
+                        Self::run_window_request(
+                            recycler,
+                            from,
+                            &from_addr,
+                            blockstore,
+                            &me.read().unwrap().my_info,
+                            *slot,
+                            *shred_index,
+                            header.nonce,
+                        ),
+                        "WindowIndexSigned",
+                    )
+                }
+                RepairProtocol::HighestWindowIndexSigned(header, slot, highest_index) => {
+                    stats.highest_window_index += 1;
+                    (
+                        Self::run_highest_window_request(
+                            recycler,
+                            &from_addr,
+                            blockstore,
+                            *slot,
+                            *highest_index,
+                            header.nonce,
+                        ),
+                        "HighestWindowIndexSigned",
+                    )
+                }
+                RepairProtocol::OrphanSigned(header, slot) => {
+                    stats.orphan += 1;
+                    (
+                        Self::run_orphan(
+                            recycler,
+                            &from_addr,
+                            blockstore,
+                            *slot,
+                            MAX_ORPHAN_REPAIR_RESPONSES,
+                            header.nonce,
+                        ),
+                        "OrphanSigned",
+                    )
+                }
+                RepairProtocol::AncestorHashesSigned(header, slot) => {
+                    stats.ancestor_hashes += 1;
+                    (
+                        Self::run_ancestor_hashes(
+                            recycler,
+                            &from_addr,
+                            blockstore,
+                            *slot,
+                            header.nonce,
+                        ),
+                        "AncestorHashesSigned",
+                    )
+                }
                 RepairProtocol::HighestWindowIndexWithNonce(_, slot, highest_index, nonce) => {
                     stats.highest_window_index += 1;
                     (
@@ -182,6 +585,13 @@ impl ServeRepair {
                         "OrphanWithNonce",
                     )
                 }
+                RepairProtocol::AncestorHashes(_, slot, nonce) => {
+                    stats.ancestor_hashes += 1;
+                    (
+                        Self::run_ancestor_hashes(recycler, &from_addr, blockstore, *slot, *nonce),
+                        "AncestorHashes",
+                    )
+                }
                 _ => (None, "Unsupported repair type"),
             }
         };
@@ -206,6 +616,8 @@ impl ServeRepair {
         requests_receiver: &PacketReceiver,
         response_sender: &PacketSender,
         stats: &mut ServeRepairStats,
+        budgets: &mut RepairRequestBudgets,
+        replay_cache: &mut RepairReplayCache,
         max_packets: &mut usize,
     ) -> Result<()> {
         //TODO cache connections
@@ -227,9 +639,20 @@ impl ServeRepair {
         stats.dropped_packets += dropped_packets;
         stats.total_packets += total_packets;
 
+        budgets.maybe_refill(&obj.read().unwrap());
+        replay_cache.maybe_prune();
         let mut time = Measure::start("repair::handle_packets");
         for reqs in reqs_v {
-            Self::handle_packets(obj, &recycler, blockstore, reqs, response_sender, stats);
+            Self::handle_packets(
+                obj,
+                &recycler,
+                blockstore,
+                reqs,
+                response_sender,
+                stats,
+                budgets,
+                replay_cache,
+            );
         }
         time.stop();
         if total_packets >= *max_packets {
@@ -266,6 +689,46 @@ impl ServeRepair {
             stats.highest_window_index
         );
         inc_new_counter_debug!("serve_repair-request-orphan", stats.orphan);
+        inc_new_counter_debug!(
+            "serve_repair-request-ancestor-hashes",
+            stats.ancestor_hashes
+        );
+        inc_new_counter_info!("serve_repair-err-sig-verify", stats.err_sig_verify);
+        inc_new_counter_info!("serve_repair-err-id-mismatch", stats.err_id_mismatch);
+        inc_new_counter_info!("serve_repair-err-time-skew", stats.err_time_skew);
+        inc_new_counter_info!("serve_repair-err-addr-mismatch", stats.err_addr_mismatch);
+        inc_new_counter_info!("serve_repair-err-replayed", stats.err_replayed);
+        inc_new_counter_info!(
+            "serve_repair-dropped-requests-staked",
+            stats.dropped_requests_staked
+        );
+        inc_new_counter_info!(
+            "serve_repair-dropped-requests-unstaked",
+            stats.dropped_requests_unstaked
+        );
+
+        let mut peers: Vec<_> = stats
+            .served_by_peer
+            .keys()
+            .chain(stats.dropped_by_peer.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|peer| {
+                let served = stats.served_by_peer.get(peer).copied().unwrap_or_default();
+                let dropped = stats.dropped_by_peer.get(peer).copied().unwrap_or_default();
+                let peer = peer.map_or_else(|| "unsigned".to_string(), |peer| peer.to_string());
+                (peer, served, dropped)
+            })
+            .collect();
+        peers.sort_unstable_by_key(|(_, served, dropped)| std::cmp::Reverse(served + dropped));
+        for (peer, served, dropped) in peers.into_iter().take(MAX_REPORTED_REPAIR_PEERS) {
+            datapoint_info!(
+                "serve_repair-requests_by_peer",
+                ("peer", peer, String),
+                ("served", served as i64, i64),
+                ("dropped", dropped as i64, i64),
+            );
+        }
 
         *stats = ServeRepairStats::default();
     }
@@ -284,6 +747,8 @@ impl ServeRepair {
             .spawn(move || {
                 let mut last_print = Instant::now();
                 let mut stats = ServeRepairStats::default();
+                let mut budgets = RepairRequestBudgets::default();
+                let mut replay_cache = RepairReplayCache::default();
                 let mut max_packets = 1024;
                 loop {
                     let result = Self::run_listen(
@@ -293,6 +758,8 @@ impl ServeRepair {
                         &requests_receiver,
                         &response_sender,
                         &mut stats,
+                        &mut budgets,
+                        &mut replay_cache,
                         &mut max_packets,
                     );
                     match result {
@@ -319,7 +786,10 @@ impl ServeRepair {
         packets: Packets,
         response_sender: &PacketSender,
         stats: &mut ServeRepairStats,
+        budgets: &mut RepairRequestBudgets,
+        replay_cache: &mut RepairReplayCache,
     ) {
+        let my_id = me.read().unwrap().keypair.pubkey();
         // iter over the packets
         let allocated = thread_mem_usage::Allocatedp::default();
         packets.packets.iter().for_each(|packet| {
@@ -327,7 +797,38 @@ impl ServeRepair {
             let from_addr = packet.meta.addr();
             limited_deserialize(&packet.data[..packet.meta.size])
                 .into_iter()
-                .for_each(|request| {
+                .for_each(|request: RepairProtocol| {
+                    // Only the sender of a signed request is known for sure,
+                    // anyone else draws from the unstaked budget
+                    let requester = match request.header() {
+                        Some(header) => {
+                            if !Self::verify_signed_request(&my_id, &request, packet, stats) {
+                                return;
+                            }
+                            if replay_cache.contains(header) {
+                                stats.err_replayed += 1;
+                                return;
+                            }
+                            Some(*request.sender())
+                        }
+                        None => None,
+                    };
+                    if !budgets.try_consume(requester.as_ref()) {
+                        let is_staked = requester
+                            .map(|requester| budgets.is_staked(&requester))
+                            .unwrap_or(false);
+                        if is_staked {
+                            stats.dropped_requests_staked += 1;
+                        } else {
+                            stats.dropped_requests_unstaked += 1;
+                        }
+                        *stats.dropped_by_peer.entry(requester).or_default() += 1;
+                        return;
+                    }
+                    if let Some(header) = request.header() {
+                        replay_cache.insert(header);
+                    }
+                    *stats.served_by_peer.entry(requester).or_default() += 1;
                     stats.processed += 1;
                     let rsp =
                         Self::handle_repair(me, recycler, &from_addr, blockstore, request, stats);
@@ -342,12 +843,55 @@ impl ServeRepair {
         });
     }
 
+    /// Returns the header of a signed request whose response is sent back to
+    /// `response_addr`
+    fn repair_request_header(
+        &self,
+        recipient: &Pubkey,
+        response_addr: SocketAddr,
+        nonce: Nonce,
+    ) -> RepairRequestHeader {
+        RepairRequestHeader::new(
+            self.my_info.id,
+            *recipient,
+            response_addr,
+            timestamp(),
+            nonce,
+        )
+    }
+
+    /// Serializes `request` and signs everything but the signature in its
+    /// header
+    fn sign_repair_request(&self, request: &RepairProtocol) -> Result<Vec<u8>> {
+        let mut out = serialize(request)?;
+        let signature_end = SIGNED_REPAIR_REQUEST_SIGNATURE_OFFSET + SIZE_OF_SIGNATURE;
+        let signed_data = [
+            &out[..SIGNED_REPAIR_REQUEST_SIGNATURE_OFFSET],
+            &out[signature_end..],
+        ]
+        .concat();
+        let signature = self.keypair.sign_message(&signed_data);
+        out[SIGNED_REPAIR_REQUEST_SIGNATURE_OFFSET..signature_end]
+            .copy_from_slice(signature.as_ref());
+        Ok(out)
+    }
+
     fn window_index_request_bytes(
         &self,
         slot: Slot,
         shred_index: u64,
+        recipient: &Pubkey,
         nonce: Nonce,
+        sign: bool,
     ) -> Result<Vec<u8>> {
+        if sign {
+            let header = self.repair_request_header(recipient, self.my_info.repair, nonce);
+            return self.sign_repair_request(&RepairProtocol::WindowIndexSigned(
+                header,
+                slot,
+                shred_index,
+            ));
+        }
         let req =
             RepairProtocol::WindowIndexWithNonce(self.my_info.clone(), slot, shred_index, nonce);
         let out = serialize(&req)?;
@@ -358,8 +902,18 @@ impl ServeRepair {
         &self,
         slot: Slot,
         shred_index: u64,
+        recipient: &Pubkey,
         nonce: Nonce,
+        sign: bool,
     ) -> Result<Vec<u8>> {
+        if sign {
+            let header = self.repair_request_header(recipient, self.my_info.repair, nonce);
+            return self.sign_repair_request(&RepairProtocol::HighestWindowIndexSigned(
+                header,
+                slot,
+                shred_index,
+            ));
+        }
         let req = RepairProtocol::HighestWindowIndexWithNonce(
             self.my_info.clone(),
             slot,
@@ -370,12 +924,41 @@ impl ServeRepair {
         Ok(out)
     }
 
-    fn orphan_bytes(&self, slot: Slot, nonce: Nonce) -> Result<Vec<u8>> {
+    fn orphan_bytes(
+        &self,
+        slot: Slot,
+        recipient: &Pubkey,
+        nonce: Nonce,
+        sign: bool,
+    ) -> Result<Vec<u8>> {
+        if sign {
+            let header = self.repair_request_header(recipient, self.my_info.repair, nonce);
+            return self.sign_repair_request(&RepairProtocol::OrphanSigned(header, slot));
+        }
         let req = RepairProtocol::OrphanWithNonce(self.my_info.clone(), slot, nonce);
         let out = serialize(&req)?;
         Ok(out)
     }
 
+    /// Returns an `AncestorHashes` request, whose response is sent back to
+    /// `response_addr` if the request is signed
+    pub fn ancestor_repair_request_bytes(
+        &self,
+        request_slot: Slot,
+        recipient: &Pubkey,
+        response_addr: SocketAddr,
+        nonce: Nonce,
+    ) -> Result<Vec<u8>> {
+        if self.should_sign_repair_requests() {
+            let header = self.repair_request_header(recipient, response_addr, nonce);
+            return self
+                .sign_repair_request(&RepairProtocol::AncestorHashesSigned(header, request_slot));
+        }
+        let req = RepairProtocol::AncestorHashes(self.my_info.clone(), request_slot, nonce);
+        let out = serialize(&req)?;
+        Ok(out)
+    }
+
     pub fn repair_request(
         &self,
         cluster_slots: &ClusterSlots,
@@ -430,22 +1013,35 @@ impl ServeRepair {
         repair_stats: &mut RepairStats,
         nonce: Nonce,
     ) -> Result<Vec<u8>> {
+        let sign = self.should_sign_repair_requests();
         match repair_request {
             RepairType::Shred(slot, shred_index) => {
                 repair_stats
                     .shred
                     .update(repair_peer_id, *slot, *shred_index);
-                Ok(self.window_index_request_bytes(*slot, *shred_index, nonce)?)
+                Ok(self.window_index_request_bytes(
+                    *slot,
+                    *shred_index,
+                    repair_peer_id,
+                    nonce,
+                    sign,
+                )?)
             }
             RepairType::HighestShred(slot, shred_index) => {
                 repair_stats
                     .highest_shred
                     .update(repair_peer_id, *slot, *shred_index);
-                Ok(self.window_highest_index_request_bytes(*slot, *shred_index, nonce)?)
+                Ok(self.window_highest_index_request_bytes(
+                    *slot,
+                    *shred_index,
+                    repair_peer_id,
+                    nonce,
+                    sign,
+                )?)
             }
             RepairType::Orphan(slot) => {
                 repair_stats.orphan.update(repair_peer_id, *slot, 0);
-                Ok(self.orphan_bytes(*slot, nonce)?)
+                Ok(self.orphan_bytes(*slot, repair_peer_id, nonce, sign)?)
             }
         }
     }
@@ -473,7 +1069,7 @@ impl ServeRepair {
 
     fn run_window_request(
         recycler: &PacketsRecycler,
-        from: &ContactInfo,
+        from: &Pubkey,
         from_addr: &SocketAddr,
         blockstore: Option<&Arc<Blockstore>>,
         me: &ContactInfo,
@@ -505,7 +1101,7 @@ impl ServeRepair {
         trace!(
             "{}: failed WindowIndex {} {} {}",
             me.id,
-            from.id,
+            from,
             slot,
             shred_index,
         );
@@ -581,6 +1177,50 @@ impl ServeRepair {
         }
         Some(res)
     }
+
+    fn run_ancestor_hashes(
+        recycler: &PacketsRecycler,
+        from_addr: &SocketAddr,
+        blockstore: Option<&Arc<Blockstore>>,
+        slot: Slot,
+        nonce: Nonce,
+    ) -> Option<Packets> {
+        let blockstore = blockstore?;
+        // Only answer for slots this node has replayed, walking back through
+        // the parents for as long as they have been replayed too
+        let mut ancestor_slot_hashes = vec![];
+        let mut next_slot = Some(slot);
+        while let Some(slot) = next_slot {
+            if ancestor_slot_hashes.len() >= MAX_ANCESTOR_RESPONSES {
+                break;
+            }
+            let hash = match blockstore.get_bank_hash(slot) {
+                Some(hash) => hash,
+                None => break,
+            };
+            ancestor_slot_hashes.push((slot, hash));
+            next_slot = blockstore
+                .meta(slot)
+                .ok()?
+                .filter(|meta| meta.is_parent_set() && meta.parent_slot != slot)
+                .map(|meta| meta.parent_slot);
+        }
+        if ancestor_slot_hashes.is_empty() {
+            return None;
+        }
+        let response = AncestorHashesResponseVersion::Current(ancestor_slot_hashes);
+        let serialized_response = serialize(&response).ok()?;
+        let packet = repair_response::repair_response_packet_from_shred(
+            serialized_response,
+            from_addr,
+            nonce,
+        )?;
+        Some(Packets::new_with_recycler_data(
+            recycler,
+            "run_ancestor_hashes",
+            vec![packet],
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -694,7 +1334,7 @@ mod tests {
             };
             let rv = ServeRepair::run_window_request(
                 &recycler,
-                &me,
+                &me.id,
                 &socketaddr_any!(),
                 Some(&blockstore),
                 &me,
@@ -721,7 +1361,7 @@ mod tests {
             let index = 1;
             let rv = ServeRepair::run_window_request(
                 &recycler,
-                &me,
+                &me.id,
                 &socketaddr_any!(),
                 Some(&blockstore),
                 &me,
@@ -750,7 +1390,7 @@ mod tests {
         let cluster_slots = ClusterSlots::default();
         let me = ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp());
         let cluster_info = Arc::new(ClusterInfo::new_with_invalid_keypair(me));
-        let serve_repair = ServeRepair::new(cluster_info.clone());
+        let serve_repair = ServeRepair::new(cluster_info.clone(), None);
         let rv = serve_repair.repair_request(
             &cluster_slots,
             RepairType::Shred(0, 0),
@@ -904,6 +1544,69 @@ mod tests {
         Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_run_ancestor_hashes() {
+        solana_logger::setup();
+        let recycler = PacketsRecycler::default();
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let nonce = 9;
+            let blockstore = Arc::new(Blockstore::open(&ledger_path).unwrap());
+            let num_slots = MAX_ANCESTOR_RESPONSES as u64 + 5;
+            let (shreds, _) = make_many_slot_entries(0, num_slots, 1);
+            blockstore
+                .insert_shreds(shreds, None, false)
+                .expect("Expect successful ledger write");
+
+            // Slots that haven't been replayed aren't served
+            let rv = ServeRepair::run_ancestor_hashes(
+                &recycler,
+                &socketaddr_any!(),
+                Some(&blockstore),
+                num_slots - 1,
+                nonce,
+            );
+            assert!(rv.is_none());
+
+            let hashes: Vec<_> = (0..num_slots).map(|_| Hash::new_unique()).collect();
+            for (slot, hash) in hashes.iter().enumerate() {
+                blockstore.insert_bank_hash(slot as Slot, *hash);
+            }
+
+            let get_response = |slot| {
+                let packets = ServeRepair::run_ancestor_hashes(
+                    &recycler,
+                    &socketaddr_any!(),
+                    Some(&blockstore),
+                    slot,
+                    nonce,
+                )
+                .expect("run_ancestor_hashes packets");
+                assert_eq!(packets.packets.len(), 1);
+                let packet = &packets.packets[0];
+                let data = &packet.data[..packet.meta.size];
+                assert_eq!(repair_response::nonce(data), Some(nonce));
+                let response: AncestorHashesResponseVersion =
+                    limited_deserialize(&data[..data.len() - SIZE_OF_NONCE]).unwrap();
+                response.into_slot_hashes()
+            };
+
+            // Ancestors are returned newest first, stopping at slot 0
+            let expected: Vec<_> = (0..4)
+                .rev()
+                .map(|slot| (slot, hashes[slot as usize]))
+                .collect();
+            assert_eq!(get_response(3), expected);
+
+            // The response is capped so that it fits in a single packet
+            let response = get_response(num_slots - 1);
+            assert_eq!(response.len(), MAX_ANCESTOR_RESPONSES);
+            assert_eq!(response[0], (num_slots - 1, hashes[num_slots as usize - 1]));
+        }
+
+        Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn run_orphan_corrupted_shred_size() {
         solana_logger::setup();
@@ -977,7 +1680,7 @@ mod tests {
             ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp());
         cluster_info.insert_info(contact_info2.clone());
         cluster_info.insert_info(contact_info3.clone());
-        let serve_repair = ServeRepair::new(cluster_info);
+        let serve_repair = ServeRepair::new(cluster_info, None);
 
         // If:
         // 1) repair validator set doesn't exist in gossip
@@ -1032,4 +1735,211 @@ mod tests {
             )
             .is_ok());
     }
+
+    fn to_packet(bytes: &[u8], from_addr: &SocketAddr) -> Packet {
+        let mut packet = Packet::default();
+        packet.data[..bytes.len()].copy_from_slice(bytes);
+        packet.meta.size = bytes.len();
+        packet.meta.set_addr(from_addr);
+        packet
+    }
+
+    #[test]
+    fn test_verify_signed_request() {
+        let keypair = Arc::new(Keypair::new());
+        let me = ContactInfo::new_localhost(&keypair.pubkey(), timestamp());
+        let serve_repair = ServeRepair::new(Arc::new(ClusterInfo::new(me, keypair)), None);
+        let recipient = solana_sdk::pubkey::new_rand();
+        let from_addr = serve_repair.my_info().repair;
+        let mut stats = ServeRepairStats::default();
+
+        let header = serve_repair.repair_request_header(&recipient, from_addr, 7);
+        let bytes = serve_repair
+            .sign_repair_request(&RepairProtocol::WindowIndexSigned(header, 5, 3))
+            .unwrap();
+        let packet = to_packet(&bytes, &from_addr);
+        let request: RepairProtocol = limited_deserialize(&bytes).unwrap();
+        assert_eq!(request.sender(), &serve_repair.my_info().id);
+        assert!(ServeRepair::verify_signed_request(
+            &recipient, &request, &packet, &mut stats
+        ));
+
+        // Requests meant for another node are rejected
+        assert!(!ServeRepair::verify_signed_request(
+            &solana_sdk::pubkey::new_rand(),
+            &request,
+            &packet,
+            &mut stats
+        ));
+        assert_eq!(stats.err_id_mismatch, 1);
+
+        // Requests coming from another address than the signed one are
+        // rejected, so that responses cannot be reflected to a victim
+        let other_addr = socketaddr!([127, 0, 0, 1], from_addr.port() + 1);
+        assert!(!ServeRepair::verify_signed_request(
+            &recipient,
+            &request,
+            &to_packet(&bytes, &other_addr),
+            &mut stats
+        ));
+        assert_eq!(stats.err_addr_mismatch, 1);
+
+        // Tampering with the request invalidates the signature
+        let mut tampered = bytes.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let tampered_request: RepairProtocol = limited_deserialize(&tampered).unwrap();
+        assert!(!ServeRepair::verify_signed_request(
+            &recipient,
+            &tampered_request,
+            &to_packet(&tampered, &from_addr),
+            &mut stats
+        ));
+        assert_eq!(stats.err_sig_verify, 1);
+
+        // Stale requests are rejected even if correctly signed
+        let header = RepairRequestHeader::new(
+            serve_repair.my_info().id,
+            recipient,
+            from_addr,
+            timestamp() - 2 * SIGNED_REPAIR_TIME_WINDOW_MS,
+            7,
+        );
+        let bytes = serve_repair
+            .sign_repair_request(&RepairProtocol::OrphanSigned(header, 5))
+            .unwrap();
+        let request: RepairProtocol = limited_deserialize(&bytes).unwrap();
+        assert!(!ServeRepair::verify_signed_request(
+            &recipient,
+            &request,
+            &to_packet(&bytes, &from_addr),
+            &mut stats
+        ));
+        assert_eq!(stats.err_time_skew, 1);
+
+        // Unsigned requests carry no header to verify
+        let request = RepairProtocol::OrphanWithNonce(serve_repair.my_info().clone(), 5, 7);
+        let bytes = serialize(&request).unwrap();
+        assert!(!ServeRepair::verify_signed_request(
+            &recipient,
+            &request,
+            &to_packet(&bytes, &from_addr),
+            &mut stats
+        ));
+    }
+
+    #[test]
+    fn test_repair_replay_cache() {
+        let keypair = Arc::new(Keypair::new());
+        let me = ContactInfo::new_localhost(&keypair.pubkey(), timestamp());
+        let serve_repair = ServeRepair::new(Arc::new(ClusterInfo::new(me, keypair)), None);
+        let recipient = solana_sdk::pubkey::new_rand();
+        let from_addr = serve_repair.my_info().repair;
+        let mut replay_cache = RepairReplayCache::default();
+
+        let header = serve_repair.repair_request_header(&recipient, from_addr, 7);
+        let bytes = serve_repair
+            .sign_repair_request(&RepairProtocol::OrphanSigned(header, 5))
+            .unwrap();
+        let request: RepairProtocol = limited_deserialize(&bytes).unwrap();
+        let header = request.header().unwrap();
+        assert!(!replay_cache.contains(header));
+        replay_cache.insert(header);
+        assert!(replay_cache.contains(header));
+
+        // Signatures are kept as long as the request could pass verification
+        replay_cache.prune(header.timestamp + SIGNED_REPAIR_TIME_WINDOW_MS);
+        assert!(replay_cache.contains(header));
+        replay_cache.prune(header.timestamp + SIGNED_REPAIR_TIME_WINDOW_MS + 1);
+        assert!(!replay_cache.contains(header));
+        assert!(replay_cache.order.is_empty());
+    }
+
+    #[test]
+    fn test_repair_replay_cache_evicts_oldest() {
+        let mut replay_cache = RepairReplayCache::default();
+        let headers: Vec<_> = (0..MAX_REPAIR_REPLAY_CACHE_ENTRIES + 2)
+            .map(|i| {
+                let mut header = RepairRequestHeader::new(
+                    solana_sdk::pubkey::new_rand(),
+                    solana_sdk::pubkey::new_rand(),
+                    socketaddr_any!(),
+                    timestamp(),
+                    7,
+                );
+                let mut signature = [0u8; SIZE_OF_SIGNATURE];
+                signature[..8].copy_from_slice(&(i as u64).to_le_bytes());
+                header.signature = Signature::new(&signature);
+                header
+            })
+            .collect();
+        for header in &headers {
+            replay_cache.insert(header);
+        }
+        assert_eq!(
+            replay_cache.signatures.len(),
+            MAX_REPAIR_REPLAY_CACHE_ENTRIES
+        );
+        assert_eq!(replay_cache.order.len(), MAX_REPAIR_REPLAY_CACHE_ENTRIES);
+        assert!(!replay_cache.contains(&headers[0]));
+        assert!(!replay_cache.contains(&headers[1]));
+        assert!(headers[2..]
+            .iter()
+            .all(|header| replay_cache.contains(header)));
+    }
+
+    #[test]
+    fn test_repair_request_budgets_disabled() {
+        // Until requests are signed, nobody is rate limited
+        let mut budgets = RepairRequestBudgets::default();
+        for _ in 0..2 * UNSTAKED_REPAIR_REQUESTS_PER_INTERVAL {
+            assert!(budgets.try_consume(None));
+        }
+        budgets.refill(HashMap::new(), 0);
+        for _ in 0..UNSTAKED_REPAIR_REQUESTS_PER_INTERVAL {
+            assert!(budgets.try_consume(None));
+        }
+        assert!(!budgets.try_consume(None));
+        budgets.disable();
+        assert!(budgets.try_consume(None));
+    }
+
+    #[test]
+    fn test_repair_request_budgets() {
+        let big_node = solana_sdk::pubkey::new_rand();
+        let small_node = solana_sdk::pubkey::new_rand();
+        let unstaked_node = solana_sdk::pubkey::new_rand();
+        let stakes: HashMap<_, _> = vec![(big_node, 900), (small_node, 1)].into_iter().collect();
+        let mut budgets = RepairRequestBudgets::default();
+        budgets.refill(stakes.clone(), 1_000);
+
+        let big_budget = STAKED_REPAIR_REQUESTS_PER_INTERVAL * 9 / 10;
+        for _ in 0..big_budget {
+            assert!(budgets.try_consume(Some(&big_node)));
+        }
+        assert!(!budgets.try_consume(Some(&big_node)));
+
+        // Small stakes still get the minimum budget
+        for _ in 0..MIN_STAKED_REPAIR_REQUESTS_PER_INTERVAL {
+            assert!(budgets.try_consume(Some(&small_node)));
+        }
+        assert!(!budgets.try_consume(Some(&small_node)));
+
+        // Unstaked and unauthenticated requesters share one budget
+        for i in 0..UNSTAKED_REPAIR_REQUESTS_PER_INTERVAL {
+            let requester = if i % 2 == 0 {
+                Some(&unstaked_node)
+            } else {
+                None
+            };
+            assert!(budgets.try_consume(requester));
+        }
+        assert!(!budgets.try_consume(Some(&unstaked_node)));
+        assert!(!budgets.try_consume(None));
+        assert!(budgets.is_staked(&big_node));
+        assert!(!budgets.is_staked(&unstaked_node));
+
+        budgets.refill(stakes, 1_000);
+        assert!(budgets.try_consume(Some(&big_node)));
+        assert!(budgets.try_consume(None));
+    }
 }
@@ -3,8 +3,8 @@
 use bv::BitVec;
 use solana_ledger::blockstore::MAX_DATA_SHREDS_PER_SLOT;
 use solana_ledger::shred::{
-    CODING_SHRED, DATA_SHRED, OFFSET_OF_SHRED_INDEX, OFFSET_OF_SHRED_SLOT, OFFSET_OF_SHRED_TYPE,
-    SIZE_OF_SHRED_INDEX, SIZE_OF_SHRED_SLOT,
+    ShredType, CODING_SHRED, DATA_SHRED, OFFSET_OF_SHRED_INDEX, OFFSET_OF_SHRED_SLOT,
+    OFFSET_OF_SHRED_TYPE, SIZE_OF_SHRED_INDEX, SIZE_OF_SHRED_SLOT,
 };
 use solana_perf::cuda_runtime::PinnedVec;
 use solana_perf::packet::{limited_deserialize, Packet, PacketsRecycler};
@@ -83,8 +83,16 @@ impl ShredFetchStage {
                 && slot < (last_slot + 2 * slots_per_epoch)
                 && p.meta.size > OFFSET_OF_SHRED_TYPE
             {
-                let shred_type = p.data[OFFSET_OF_SHRED_TYPE];
-                if shred_type == DATA_SHRED || shred_type == CODING_SHRED {
+                let shred_type = ShredType(p.data[OFFSET_OF_SHRED_TYPE]);
+                // Merkle variants share the filter with their legacy type
+                let shred_type = if shred_type.is_data() {
+                    Some(DATA_SHRED)
+                } else if shred_type.is_code() {
+                    Some(CODING_SHRED)
+                } else {
+                    None
+                };
+                if let Some(shred_type) = shred_type {
                     // Shred filter
                     let slot_received =
                         shreds_received
@@ -6,17 +6,31 @@
 
 use crate::sigverify_stage::SigVerifier;
 use solana_perf::cuda_runtime::PinnedVec;
-use solana_perf::packet::Packets;
+use solana_perf::packet::{limited_deserialize, Packet, Packets};
 use solana_perf::recycler::Recycler;
 use solana_perf::sigverify;
 pub use solana_perf::sigverify::{
     batch_size, ed25519_verify_cpu, ed25519_verify_disabled, init, TxOffset,
 };
+use solana_sdk::transaction::Transaction;
+use solana_vote_program::vote_transaction::parse_vote_transaction;
 
 #[derive(Clone)]
 pub struct TransactionSigVerifier {
     recycler: Recycler<TxOffset>,
     recycler_out: Recycler<PinnedVec<u8>>,
+    reject_non_vote: bool,
+}
+
+impl TransactionSigVerifier {
+    /// Verifier for the TPU vote port, which discards anything but simple
+    /// vote transactions
+    pub fn new_reject_non_vote() -> Self {
+        TransactionSigVerifier {
+            reject_non_vote: true,
+            ..TransactionSigVerifier::default()
+        }
+    }
 }
 
 impl Default for TransactionSigVerifier {
@@ -25,12 +39,17 @@ impl Default for TransactionSigVerifier {
         Self {
             recycler: Recycler::warmed(50, 4096),
             recycler_out: Recycler::warmed(50, 4096),
+            reject_non_vote: false,
         }
     }
 }
 
 impl SigVerifier for TransactionSigVerifier {
     fn verify_batch(&self, mut batch: Vec<Packets>) -> Vec<Packets> {
+        if self.reject_non_vote {
+            // Discarded packets are skipped by the verifier
+            mark_non_votes_discarded(&mut batch);
+        }
         let r = sigverify::ed25519_verify(&batch, &self.recycler, &self.recycler_out);
         mark_disabled(&mut batch, &r);
         batch
@@ -46,10 +65,29 @@ pub fn mark_disabled(batches: &mut Vec<Packets>, r: &[Vec<u8>]) {
     });
 }
 
+/// A simple vote transaction carries a single vote instruction
+pub fn is_simple_vote_transaction(packet: &Packet) -> bool {
+    limited_deserialize::<Transaction>(&packet.data[..packet.meta.size])
+        .map(|tx| tx.message.instructions.len() == 1 && parse_vote_transaction(&tx).is_some())
+        .unwrap_or(false)
+}
+
+fn mark_non_votes_discarded(batches: &mut [Packets]) {
+    for packet in batches.iter_mut().flat_map(|b| b.packets.iter_mut()) {
+        if !packet.meta.discard && !is_simple_vote_transaction(packet) {
+            packet.meta.discard = true;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use solana_perf::packet::Packet;
+    use solana_perf::packet::to_packets;
+    use solana_perf::test_tx::test_tx;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::Keypair;
+    use solana_vote_program::vote_transaction;
 
     #[test]
     fn test_mark_disabled() {
@@ -61,4 +99,28 @@ mod tests {
         mark_disabled(&mut batches, &[vec![1]]);
         assert_eq!(batches[0].packets[0].meta.discard, false);
     }
+
+    #[test]
+    fn test_reject_non_vote() {
+        let node_keypair = Keypair::new();
+        let vote_keypair = Keypair::new();
+        let authorized_voter_keypair = Keypair::new();
+        let vote_tx = vote_transaction::new_vote_transaction(
+            vec![0],
+            Hash::default(),
+            Hash::default(),
+            &node_keypair,
+            &vote_keypair,
+            &authorized_voter_keypair,
+            None,
+        );
+        let mut batches = to_packets(&[vote_tx, test_tx()]);
+        assert!(is_simple_vote_transaction(&batches[0].packets[0]));
+        assert!(!is_simple_vote_transaction(&batches[0].packets[1]));
+
+        let verifier = TransactionSigVerifier::new_reject_non_vote();
+        batches = verifier.verify_batch(batches);
+        assert!(!batches[0].packets[0].meta.discard);
+        assert!(batches[0].packets[1].meta.discard);
+    }
 }
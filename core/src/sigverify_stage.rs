@@ -3,24 +3,43 @@
 //! top-level list with a list of booleans, telling the next stage whether the
 //! signature in that packet is valid. It assumes each packet contains one
 //! transaction. All processing is done on the CPU by default and on a GPU
-//! if perf-libs are available
+//! if perf-libs are available. Transaction packets can additionally be
+//! deduplicated and shed under load before they are verified
 
 use crate::sigverify;
 use crossbeam_channel::{SendError, Sender as CrossbeamSender};
+use rand::{seq::SliceRandom, thread_rng, Rng};
 use solana_measure::measure::Measure;
-use solana_metrics::datapoint_debug;
-use solana_perf::packet::Packets;
+use solana_metrics::{datapoint_debug, datapoint_info};
+use solana_perf::packet::{Packet, Packets};
 use solana_perf::perf_libs;
 use solana_sdk::timing;
 use solana_streamer::streamer::{self, PacketReceiver, StreamerError};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::{self, Builder, JoinHandle};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 const RECV_BATCH_MAX_CPU: usize = 1_000;
 const RECV_BATCH_MAX_GPU: usize = 5_000;
 
+/// Default number of packets a verifier keeps out of what it drains from the
+/// queue at once when shedding load. The rest are discarded unverified
+pub const DEFAULT_MAX_SIGVERIFY_BACKLOG_PACKETS: usize = 10_000;
+
+// 64M bits, i.e. 8MB of filter
+const DEDUPER_NUM_BITS: u64 = 63_999_979;
+// The filter is reset once this fraction of bits is set, which bounds the
+// rate of unique packets dropped as false positives
+const DEDUPER_FALSE_POSITIVE_RATE: f64 = 0.001;
+const DEDUPER_MAX_AGE: Duration = Duration::from_secs(2);
+
 #[derive(Error, Debug)]
 pub enum SigVerifyServiceError {
     #[error("send packets batch error")]
@@ -51,6 +70,187 @@ impl SigVerifier for DisabledSigVerifier {
     }
 }
 
+/// Rotating bloom filter over the data of each packet. Bits are only ever
+/// set, so the filter is cleared and re-seeded once it is too full or too old
+/// to keep false positives rare. Packets are keyed on their full contents
+/// rather than a signature, which is not verified yet and could be copied
+/// into a bogus packet to suppress the real transaction
+pub struct Deduper {
+    filter: Vec<AtomicU64>,
+    seed: (u64, u64),
+    age: Instant,
+    max_age: Duration,
+    num_bits_set: AtomicU64,
+}
+
+impl Deduper {
+    pub fn new(num_bits: u64, max_age: Duration) -> Self {
+        let size = (num_bits + 63) / 64;
+        let filter = (0..size).map(|_| AtomicU64::default()).collect();
+        Self {
+            filter,
+            seed: thread_rng().gen(),
+            age: Instant::now(),
+            max_age,
+            num_bits_set: AtomicU64::default(),
+        }
+    }
+
+    fn num_bits(&self) -> u64 {
+        self.filter.len() as u64 * 64
+    }
+
+    fn is_saturated(&self) -> bool {
+        let max_bits_set = (self.num_bits() as f64 * DEDUPER_FALSE_POSITIVE_RATE) as u64;
+        self.num_bits_set.load(Ordering::Relaxed) >= max_bits_set
+    }
+
+    pub fn should_reset(&self) -> bool {
+        self.age.elapsed() >= self.max_age || self.is_saturated()
+    }
+
+    /// Clear the filter if it has outlived `max_age` or is saturated
+    pub fn maybe_reset(&mut self) {
+        if self.should_reset() {
+            for bits in &self.filter {
+                bits.store(0, Ordering::Relaxed);
+            }
+            self.seed = thread_rng().gen();
+            self.age = Instant::now();
+            self.num_bits_set.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Mark the packet as discarded if the same data was seen before.
+    /// Returns true if the packet is a duplicate
+    fn dedup_packet(&self, packet: &mut Packet) -> bool {
+        let data = &packet.data[..packet.meta.size.min(packet.data.len())];
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.seed.0);
+        hasher.write(data);
+        hasher.write_u64(self.seed.1);
+        let pos = hasher.finish() % self.num_bits();
+        let mask = 1u64 << (pos % 64);
+        let prev = self.filter[(pos / 64) as usize].fetch_or(mask, Ordering::Relaxed);
+        if prev & mask != 0 {
+            packet.meta.discard = true;
+            return true;
+        }
+        self.num_bits_set.fetch_add(1, Ordering::Relaxed);
+        false
+    }
+
+    /// Returns the number of packets discarded as duplicates
+    pub fn dedup_packets(&self, batches: &mut [Packets]) -> usize {
+        let mut num_deduped = 0;
+        for packet in batches
+            .iter_mut()
+            .flat_map(|batch| batch.packets.iter_mut())
+        {
+            if !packet.meta.discard && self.dedup_packet(packet) {
+                num_deduped += 1;
+            }
+        }
+        num_deduped
+    }
+}
+
+/// Stake of the nodes behind each IP address, see `StakedNodesUpdaterService`
+pub type StakedNodes = HashMap<IpAddr, u64>;
+
+/// Discards packets in excess of `max_packets`. Senders whose IP belongs to a
+/// staked node first keep a share of `max_packets` in proportion to their
+/// stake. The remaining room is filled round-robin by sender IP, so a single
+/// sender cannot crowd out the others, with senders served in random order
+/// within the last round. Returns the number of packets discarded
+pub fn shed_packets(
+    batches: &mut [Packets],
+    max_packets: usize,
+    staked_nodes: &StakedNodes,
+) -> usize {
+    let mut senders: HashMap<IpAddr, Vec<(usize, usize)>> = HashMap::new();
+    for (i, batch) in batches.iter().enumerate() {
+        for (j, packet) in batch.packets.iter().enumerate() {
+            if !packet.meta.discard {
+                senders
+                    .entry(packet.meta.addr().ip())
+                    .or_default()
+                    .push((i, j));
+            }
+        }
+    }
+    let num_packets: usize = senders.values().map(Vec::len).sum();
+    if num_packets <= max_packets {
+        return 0;
+    }
+    let mut senders: Vec<_> = senders.into_iter().collect();
+    senders.shuffle(&mut thread_rng());
+
+    let total_stake: u64 = senders
+        .iter()
+        .filter_map(|(ip, _)| staked_nodes.get(ip))
+        .sum();
+    let mut keep: Vec<_> = senders
+        .iter()
+        .map(|(ip, packets)| match staked_nodes.get(ip) {
+            Some(stake) if total_stake > 0 => {
+                let share = max_packets as u128 * *stake as u128 / total_stake as u128;
+                packets.len().min(share as usize)
+            }
+            _ => 0,
+        })
+        .collect();
+    let mut num_kept: usize = keep.iter().sum();
+    while num_kept < max_packets {
+        for ((_, packets), keep) in senders.iter().zip(keep.iter_mut()) {
+            if num_kept == max_packets {
+                break;
+            }
+            if *keep < packets.len() {
+                *keep += 1;
+                num_kept += 1;
+            }
+        }
+    }
+    for ((_, packets), keep) in senders.iter().zip(keep) {
+        for (i, j) in &packets[keep..] {
+            batches[*i].packets[*j].meta.discard = true;
+        }
+    }
+    num_packets - num_kept
+}
+
+/// Deduplication and load shedding applied to packets before verification
+pub struct PacketFilter {
+    deduper: RwLock<Deduper>,
+    max_backlog_packets: usize,
+    staked_nodes: Arc<RwLock<StakedNodes>>,
+}
+
+impl PacketFilter {
+    pub fn new(max_backlog_packets: usize, staked_nodes: Arc<RwLock<StakedNodes>>) -> Self {
+        Self {
+            deduper: RwLock::new(Deduper::new(DEDUPER_NUM_BITS, DEDUPER_MAX_AGE)),
+            max_backlog_packets,
+            staked_nodes,
+        }
+    }
+
+    /// Returns the number of packets deduplicated and shed
+    fn filter_packets(&self, batches: &mut [Packets]) -> (usize, usize) {
+        if self.deduper.read().unwrap().should_reset() {
+            self.deduper.write().unwrap().maybe_reset();
+        }
+        let num_deduped = self.deduper.read().unwrap().dedup_packets(batches);
+        let num_shed = shed_packets(
+            batches,
+            self.max_backlog_packets,
+            &self.staked_nodes.read().unwrap(),
+        );
+        (num_deduped, num_shed)
+    }
+}
+
 impl SigVerifyStage {
     #[allow(clippy::new_ret_no_self)]
     pub fn new<T: SigVerifier + 'static + Send + Clone>(
@@ -58,7 +258,24 @@ impl SigVerifyStage {
         verified_sender: CrossbeamSender<Vec<Packets>>,
         verifier: T,
     ) -> Self {
-        let thread_hdls = Self::verifier_services(packet_receiver, verified_sender, verifier);
+        Self::new_with_packet_filter(packet_receiver, verified_sender, verifier, None)
+    }
+
+    /// With a `packet_filter` each verifier drains all queued packets at once
+    /// and sheds those in excess of its backlog limit, so that excess load is
+    /// discarded here rather than queued
+    pub fn new_with_packet_filter<T: SigVerifier + 'static + Send + Clone>(
+        packet_receiver: Receiver<Packets>,
+        verified_sender: CrossbeamSender<Vec<Packets>>,
+        verifier: T,
+        packet_filter: Option<PacketFilter>,
+    ) -> Self {
+        let thread_hdls = Self::verifier_services(
+            packet_receiver,
+            verified_sender,
+            verifier,
+            packet_filter.map(Arc::new),
+        );
         Self { thread_hdls }
     }
 
@@ -67,16 +284,33 @@ impl SigVerifyStage {
         sendr: &CrossbeamSender<Vec<Packets>>,
         id: usize,
         verifier: &T,
+        packet_filter: Option<&PacketFilter>,
     ) -> Result<()> {
-        let (batch, len, recv_time) = streamer::recv_batch(
+        let max_batch = match packet_filter {
+            Some(_) => usize::MAX,
+            None if perf_libs::api().is_some() => RECV_BATCH_MAX_GPU,
+            None => RECV_BATCH_MAX_CPU,
+        };
+        let (mut batch, len, recv_time) = streamer::recv_batch(
             &recvr.lock().expect("'recvr' lock in fn verifier"),
-            if perf_libs::api().is_some() {
-                RECV_BATCH_MAX_GPU
-            } else {
-                RECV_BATCH_MAX_CPU
-            },
+            max_batch,
         )?;
 
+        if let Some(packet_filter) = packet_filter {
+            let mut filter_time = Measure::start("sigverify_filter_time");
+            let (num_deduped, num_shed) = packet_filter.filter_packets(&mut batch);
+            filter_time.stop();
+            if num_deduped > 0 || num_shed > 0 {
+                datapoint_info!(
+                    "sigverify_stage-packet_filter",
+                    ("num_packets", len, i64),
+                    ("num_deduped", num_deduped, i64),
+                    ("num_shed", num_shed, i64),
+                    ("filter_time_us", filter_time.as_us(), i64),
+                );
+            }
+        }
+
         let mut verify_batch_time = Measure::start("sigverify_batch_time");
         let batch_len = batch.len();
         debug!(
@@ -120,12 +354,19 @@ impl SigVerifyStage {
         verified_sender: CrossbeamSender<Vec<Packets>>,
         id: usize,
         verifier: &T,
+        packet_filter: Option<Arc<PacketFilter>>,
     ) -> JoinHandle<()> {
         let verifier = verifier.clone();
         Builder::new()
             .name(format!("solana-verifier-{}", id))
             .spawn(move || loop {
-                if let Err(e) = Self::verifier(&packet_receiver, &verified_sender, id, &verifier) {
+                if let Err(e) = Self::verifier(
+                    &packet_receiver,
+                    &verified_sender,
+                    id,
+                    &verifier,
+                    packet_filter.as_deref(),
+                ) {
                     match e {
                         SigVerifyServiceError::StreamerError(StreamerError::RecvTimeoutError(
                             RecvTimeoutError::Disconnected,
@@ -147,11 +388,18 @@ impl SigVerifyStage {
         packet_receiver: PacketReceiver,
         verified_sender: CrossbeamSender<Vec<Packets>>,
         verifier: T,
+        packet_filter: Option<Arc<PacketFilter>>,
     ) -> Vec<JoinHandle<()>> {
         let receiver = Arc::new(Mutex::new(packet_receiver));
         (0..4)
             .map(|id| {
-                Self::verifier_service(receiver.clone(), verified_sender.clone(), id, &verifier)
+                Self::verifier_service(
+                    receiver.clone(),
+                    verified_sender.clone(),
+                    id,
+                    &verifier,
+                    packet_filter.clone(),
+                )
             })
             .collect()
     }
@@ -163,3 +411,138 @@ impl SigVerifyStage {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_perf::test_tx::test_tx;
+    use std::net::SocketAddr;
+
+    fn make_packets(senders: &[(SocketAddr, usize)]) -> Packets {
+        let packets = senders
+            .iter()
+            .flat_map(|(addr, count)| (0..*count).map(move |_| Packet::from_data(addr, test_tx())))
+            .collect();
+        Packets::new(packets)
+    }
+
+    fn count_kept(batches: &[Packets], addr: &SocketAddr) -> usize {
+        batches
+            .iter()
+            .flat_map(|batch| batch.packets.iter())
+            .filter(|packet| !packet.meta.discard && packet.meta.addr() == *addr)
+            .count()
+    }
+
+    #[test]
+    fn test_dedup_packets() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
+        let batch = make_packets(&[(addr, 10)]);
+        let mut batches = vec![batch.clone(), batch];
+        let deduper = Deduper::new(DEDUPER_NUM_BITS, DEDUPER_MAX_AGE);
+        assert_eq!(deduper.dedup_packets(&mut batches), 10);
+        assert_eq!(count_kept(&batches[..1], &addr), 10);
+        assert_eq!(count_kept(&batches[1..], &addr), 0);
+
+        let mut batches = vec![make_packets(&[(addr, 10)])];
+        assert_eq!(deduper.dedup_packets(&mut batches), 0);
+    }
+
+    #[test]
+    fn test_dedup_packets_same_signature() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
+        let mut batch = make_packets(&[(addr, 1)]);
+        // A packet that only copies the signature of another must not cause
+        // the original to be dropped
+        let mut forged = batch.packets[0].clone();
+        let last = forged.meta.size - 1;
+        forged.data[last] ^= 0xff;
+        batch.packets.insert(0, forged);
+        let mut batches = vec![batch];
+        let deduper = Deduper::new(DEDUPER_NUM_BITS, DEDUPER_MAX_AGE);
+        assert_eq!(deduper.dedup_packets(&mut batches), 0);
+        assert_eq!(count_kept(&batches, &addr), 2);
+    }
+
+    #[test]
+    fn test_deduper_reset() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
+        let batch = make_packets(&[(addr, 1)]);
+        // Small enough to saturate after a single packet
+        let mut deduper = Deduper::new(1_024, DEDUPER_MAX_AGE);
+        assert!(!deduper.should_reset());
+        assert_eq!(deduper.dedup_packets(&mut [batch.clone()]), 0);
+        assert!(deduper.should_reset());
+        deduper.maybe_reset();
+        assert!(!deduper.should_reset());
+        assert_eq!(deduper.dedup_packets(&mut [batch]), 0);
+    }
+
+    #[test]
+    fn test_shed_packets() {
+        let sender = SocketAddr::from(([10, 0, 0, 1], 8000));
+        let spammer = SocketAddr::from(([10, 0, 0, 2], 8000));
+        let other = SocketAddr::from(([10, 0, 0, 3], 8000));
+
+        let mut batches = vec![make_packets(&[(spammer, 100), (sender, 10), (other, 1)])];
+        assert_eq!(shed_packets(&mut batches, 200, &StakedNodes::default()), 0);
+        assert_eq!(shed_packets(&mut batches, 21, &StakedNodes::default()), 90);
+        assert_eq!(count_kept(&batches, &sender), 10);
+        assert_eq!(count_kept(&batches, &other), 1);
+        assert_eq!(count_kept(&batches, &spammer), 10);
+
+        // Only the order within the cutoff round is random
+        let mut batches = vec![make_packets(&[(spammer, 10), (sender, 10)])];
+        assert_eq!(shed_packets(&mut batches, 5, &StakedNodes::default()), 15);
+        let kept = count_kept(&batches, &sender);
+        assert!(kept == 2 || kept == 3);
+        assert_eq!(count_kept(&batches, &spammer), 5 - kept);
+    }
+
+    #[test]
+    fn test_shed_packets_staked() {
+        let staked = SocketAddr::from(([10, 0, 0, 1], 8000));
+        let less_staked = SocketAddr::from(([10, 0, 0, 2], 8000));
+        let unstaked = SocketAddr::from(([10, 0, 0, 3], 8000));
+        let staked_nodes: StakedNodes = vec![(staked.ip(), 300), (less_staked.ip(), 100)]
+            .into_iter()
+            .collect();
+
+        // Staked senders keep their share of the limit by stake, and the rest
+        // is shared round-robin
+        let mut batches = vec![make_packets(&[
+            (unstaked, 100),
+            (staked, 100),
+            (less_staked, 100),
+        ])];
+        assert_eq!(shed_packets(&mut batches, 100, &staked_nodes), 200);
+        assert_eq!(count_kept(&batches, &staked), 75);
+        assert_eq!(count_kept(&batches, &less_staked), 25);
+        assert_eq!(count_kept(&batches, &unstaked), 0);
+
+        // Room a staked sender does not use goes to the others
+        let mut batches = vec![make_packets(&[
+            (unstaked, 100),
+            (staked, 10),
+            (less_staked, 10),
+        ])];
+        assert_eq!(shed_packets(&mut batches, 100, &staked_nodes), 20);
+        assert_eq!(count_kept(&batches, &staked), 10);
+        assert_eq!(count_kept(&batches, &less_staked), 10);
+        assert_eq!(count_kept(&batches, &unstaked), 80);
+    }
+
+    #[test]
+    fn test_packet_filter_backlog() {
+        let addr = SocketAddr::from(([10, 0, 0, 1], 8000));
+        let packet_filter = PacketFilter::new(20, Arc::default());
+        // Packets are only shed past the backlog limit, and only down to it
+        let mut batches = vec![make_packets(&[(addr, 20)])];
+        assert_eq!(packet_filter.filter_packets(&mut batches), (0, 0));
+        assert_eq!(count_kept(&batches, &addr), 20);
+
+        let mut batches = vec![make_packets(&[(addr, 10)]), make_packets(&[(addr, 15)])];
+        assert_eq!(packet_filter.filter_packets(&mut batches), (0, 5));
+        assert_eq!(count_kept(&batches, &addr), 20);
+    }
+}
@@ -175,6 +175,7 @@ mod tests {
             Hash::default(),
             CompressionType::Bzip2,
             SnapshotVersion::default(),
+            false,
         );
 
         // Make tarball from packageable snapshot
@@ -0,0 +1,75 @@
+//! The `staked_nodes_updater_service` keeps the stake of each peer's TPU IP
+//! address current, so that sigverify can prefer packets from staked peers
+//! when it sheds load.
+
+use crate::{cluster_info::ClusterInfo, sigverify_stage::StakedNodes};
+use solana_runtime::bank_forks::BankForks;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const IP_TO_STAKE_REFRESH_DURATION: Duration = Duration::from_secs(5);
+
+pub struct StakedNodesUpdaterService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl StakedNodesUpdaterService {
+    pub fn new(
+        exit: Arc<AtomicBool>,
+        cluster_info: Arc<ClusterInfo>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        staked_nodes: Arc<RwLock<StakedNodes>>,
+    ) -> Self {
+        let thread_hdl = Builder::new()
+            .name("solana-staked-nodes-updater".to_string())
+            .spawn(move || {
+                let mut last_update = None;
+                while !exit.load(Ordering::Relaxed) {
+                    let refresh = last_update.map_or(true, |last_update: Instant| {
+                        last_update.elapsed() >= IP_TO_STAKE_REFRESH_DURATION
+                    });
+                    if refresh {
+                        let ip_to_stake = Self::compute_ip_to_stake(&cluster_info, &bank_forks);
+                        *staked_nodes.write().unwrap() = ip_to_stake;
+                        last_update = Some(Instant::now());
+                    }
+                    sleep(Duration::from_millis(100));
+                }
+            })
+            .unwrap();
+
+        Self { thread_hdl }
+    }
+
+    fn compute_ip_to_stake(
+        cluster_info: &ClusterInfo,
+        bank_forks: &RwLock<BankForks>,
+    ) -> StakedNodes {
+        let root_bank = bank_forks.read().unwrap().root_bank().clone();
+        let epoch_stakes = match root_bank.epoch_stakes(root_bank.epoch()) {
+            Some(epoch_stakes) => epoch_stakes,
+            None => return StakedNodes::new(),
+        };
+        let node_stakes = epoch_stakes.node_id_to_vote_accounts();
+        let mut ip_to_stake = StakedNodes::new();
+        for node in cluster_info.all_tvu_peers() {
+            if let Some(node_vote_accounts) = node_stakes.get(&node.id) {
+                if node_vote_accounts.total_stake > 0 {
+                    *ip_to_stake.entry(node.tpu.ip()).or_insert(0) +=
+                        node_vote_accounts.total_stake;
+                }
+            }
+        }
+        ip_to_stake
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
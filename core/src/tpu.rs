@@ -11,7 +11,8 @@ use crate::{
     poh_recorder::{PohRecorder, WorkingBankEntry},
     rpc_subscriptions::RpcSubscriptions,
     sigverify::TransactionSigVerifier,
-    sigverify_stage::SigVerifyStage,
+    sigverify_stage::{PacketFilter, SigVerifyStage, StakedNodes},
+    staked_nodes_updater_service::StakedNodesUpdaterService,
 };
 use crossbeam_channel::unbounded;
 use solana_ledger::{blockstore::Blockstore, blockstore_processor::TransactionStatusSender};
@@ -29,9 +30,15 @@ use std::{
     thread,
 };
 
+// Vote packets are shed against a backlog limit of their own, independently of
+// the transaction load on the TPU port
+const MAX_VOTE_SIGVERIFY_BACKLOG_PACKETS: usize = 10_000;
+
 pub struct Tpu {
     fetch_stage: FetchStage,
+    staked_nodes_updater_service: StakedNodesUpdaterService,
     sigverify_stage: SigVerifyStage,
+    vote_sigverify_stage: SigVerifyStage,
     banking_stage: BankingStage,
     cluster_info_vote_listener: ClusterInfoVoteListener,
     broadcast_stage: BroadcastStage,
@@ -46,6 +53,7 @@ impl Tpu {
         retransmit_slots_receiver: RetransmitSlotsReceiver,
         transactions_sockets: Vec<UdpSocket>,
         tpu_forwards_sockets: Vec<UdpSocket>,
+        tpu_vote_sockets: Vec<UdpSocket>,
         broadcast_sockets: Vec<UdpSocket>,
         subscriptions: &Arc<RpcSubscriptions>,
         transaction_status_sender: Option<TransactionStatusSender>,
@@ -59,20 +67,53 @@ impl Tpu {
         replay_vote_receiver: ReplayVoteReceiver,
         replay_vote_sender: ReplayVoteSender,
         bank_notification_sender: Option<BankNotificationSender>,
+        max_sigverify_backlog_packets: usize,
     ) -> Self {
         let (packet_sender, packet_receiver) = channel();
+        let (vote_packet_sender, vote_packet_receiver) = channel();
         let fetch_stage = FetchStage::new_with_sender(
             transactions_sockets,
             tpu_forwards_sockets,
+            tpu_vote_sockets,
             &exit,
             &packet_sender,
+            &vote_packet_sender,
             &poh_recorder,
         );
+
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let staked_nodes_updater_service = StakedNodesUpdaterService::new(
+            exit.clone(),
+            cluster_info.clone(),
+            bank_forks.clone(),
+            Arc::clone(&staked_nodes),
+        );
+
         let (verified_sender, verified_receiver) = unbounded();
 
         let sigverify_stage = {
             let verifier = TransactionSigVerifier::default();
-            SigVerifyStage::new(packet_receiver, verified_sender, verifier)
+            let packet_filter =
+                PacketFilter::new(max_sigverify_backlog_packets, staked_nodes.clone());
+            SigVerifyStage::new_with_packet_filter(
+                packet_receiver,
+                verified_sender,
+                verifier,
+                Some(packet_filter),
+            )
+        };
+
+        let (verified_tpu_vote_packets_sender, verified_tpu_vote_packets_receiver) = unbounded();
+
+        let vote_sigverify_stage = {
+            let verifier = TransactionSigVerifier::new_reject_non_vote();
+            let packet_filter = PacketFilter::new(MAX_VOTE_SIGVERIFY_BACKLOG_PACKETS, staked_nodes);
+            SigVerifyStage::new_with_packet_filter(
+                vote_packet_receiver,
+                verified_tpu_vote_packets_sender,
+                verifier,
+                Some(packet_filter),
+            )
         };
 
         let (verified_vote_packets_sender, verified_vote_packets_receiver) = unbounded();
@@ -94,6 +135,7 @@ impl Tpu {
             &cluster_info,
             poh_recorder,
             verified_receiver,
+            verified_tpu_vote_packets_receiver,
             verified_vote_packets_receiver,
             transaction_status_sender,
             replay_vote_sender,
@@ -111,7 +153,9 @@ impl Tpu {
 
         Self {
             fetch_stage,
+            staked_nodes_updater_service,
             sigverify_stage,
+            vote_sigverify_stage,
             banking_stage,
             cluster_info_vote_listener,
             broadcast_stage,
@@ -121,7 +165,9 @@ impl Tpu {
     pub fn join(self) -> thread::Result<()> {
         let mut results = vec![];
         results.push(self.fetch_stage.join());
+        results.push(self.staked_nodes_updater_service.join());
         results.push(self.sigverify_stage.join());
+        results.push(self.vote_sigverify_stage.join());
         results.push(self.cluster_info_vote_listener.join());
         results.push(self.banking_stage.join());
         let broadcast_result = self.broadcast_stage.join();
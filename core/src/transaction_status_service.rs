@@ -57,6 +57,7 @@ impl TransactionStatusService {
             balances,
             inner_instructions,
             transaction_logs,
+            ..
         } = write_transaction_status_receiver.recv_timeout(Duration::from_secs(1))?;
 
         let slot = bank.slot();
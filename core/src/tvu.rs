@@ -10,7 +10,7 @@ use crate::{
     cluster_slots::ClusterSlots,
     completed_data_sets_service::CompletedDataSetsSender,
     consensus::Tower,
-    ledger_cleanup_service::LedgerCleanupService,
+    ledger_cleanup_service::{LedgerCleanupConfig, LedgerCleanupService},
     optimistically_confirmed_bank_tracker::BankNotificationSender,
     poh_recorder::PohRecorder,
     replay_stage::{ReplayStage, ReplayStageConfig},
@@ -41,6 +41,7 @@ use solana_sdk::{
 use std::{
     collections::HashSet,
     net::UdpSocket,
+    path::PathBuf,
     sync::{
         atomic::AtomicBool,
         mpsc::{channel, Receiver},
@@ -62,18 +63,20 @@ pub struct Tvu {
 pub struct Sockets {
     pub fetch: Vec<UdpSocket>,
     pub repair: UdpSocket,
+    pub ancestor_hashes_requests: UdpSocket,
     pub retransmit: Vec<UdpSocket>,
     pub forwards: Vec<UdpSocket>,
 }
 
 #[derive(Default)]
 pub struct TvuConfig {
-    pub max_ledger_shreds: Option<u64>,
+    pub ledger_cleanup_config: Option<LedgerCleanupConfig>,
     pub shred_version: u16,
     pub halt_on_trusted_validators_accounts_hash_mismatch: bool,
     pub trusted_validators: Option<HashSet<Pubkey>>,
     pub repair_validators: Option<HashSet<Pubkey>>,
     pub accounts_hash_fault_injection_slots: u64,
+    pub bank_hash_details_dir: Option<PathBuf>,
 }
 
 impl Tvu {
@@ -116,6 +119,7 @@ impl Tvu {
 
         let Sockets {
             repair: repair_socket,
+            ancestor_hashes_requests: ancestor_hashes_socket,
             fetch: fetch_sockets,
             retransmit: retransmit_sockets,
             forwards: tvu_forward_sockets,
@@ -124,6 +128,7 @@ impl Tvu {
         let (fetch_sender, fetch_receiver) = channel();
 
         let repair_socket = Arc::new(repair_socket);
+        let ancestor_hashes_socket = Arc::new(ancestor_hashes_socket);
         let fetch_sockets: Vec<Arc<UdpSocket>> = fetch_sockets.into_iter().map(Arc::new).collect();
         let forward_sockets: Vec<Arc<UdpSocket>> =
             tvu_forward_sockets.into_iter().map(Arc::new).collect();
@@ -145,6 +150,9 @@ impl Tvu {
 
         let cluster_slots = Arc::new(ClusterSlots::default());
         let (duplicate_slots_reset_sender, duplicate_slots_reset_receiver) = unbounded();
+        let (gossip_duplicate_slots_sender, gossip_duplicate_slots_receiver) = unbounded();
+        let (ancestor_hashes_replay_update_sender, ancestor_hashes_replay_update_receiver) =
+            unbounded();
         let retransmit_stage = RetransmitStage::new(
             bank_forks.clone(),
             leader_schedule_cache,
@@ -152,6 +160,7 @@ impl Tvu {
             &cluster_info,
             Arc::new(retransmit_sockets),
             repair_socket,
+            ancestor_hashes_socket,
             verified_receiver,
             &exit,
             completed_slots_receiver,
@@ -163,6 +172,8 @@ impl Tvu {
             verified_vote_receiver,
             tvu_config.repair_validators,
             completed_data_sets_sender,
+            gossip_duplicate_slots_sender,
+            ancestor_hashes_replay_update_receiver,
         );
 
         let (ledger_cleanup_slot_sender, ledger_cleanup_slot_receiver) = channel();
@@ -222,6 +233,7 @@ impl Tvu {
             rewards_recorder_sender,
             cache_block_time_sender,
             bank_notification_sender,
+            bank_hash_details_dir: tvu_config.bank_hash_details_dir,
         };
 
         let replay_stage = ReplayStage::new(
@@ -236,14 +248,16 @@ impl Tvu {
             cluster_slots,
             retransmit_slots_sender,
             duplicate_slots_reset_receiver,
+            gossip_duplicate_slots_receiver,
+            ancestor_hashes_replay_update_sender,
             replay_vote_sender,
         );
 
-        let ledger_cleanup_service = tvu_config.max_ledger_shreds.map(|max_ledger_shreds| {
+        let ledger_cleanup_service = tvu_config.ledger_cleanup_config.map(|config| {
             LedgerCleanupService::new(
                 ledger_cleanup_slot_receiver,
                 blockstore.clone(),
-                max_ledger_shreds,
+                config,
                 &exit,
             )
         });
@@ -341,6 +355,7 @@ pub mod tests {
             {
                 Sockets {
                     repair: target1.sockets.repair,
+                    ancestor_hashes_requests: target1.sockets.ancestor_hashes_requests,
                     retransmit: target1.sockets.retransmit_sockets,
                     fetch: target1.sockets.tvu,
                     forwards: target1.sockets.tvu_forwards,
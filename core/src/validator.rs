@@ -9,6 +9,7 @@ use crate::{
     consensus::{reconcile_blockstore_roots_with_tower, Tower},
     contact_info::ContactInfo,
     gossip_service::GossipService,
+    ledger_cleanup_service::LedgerCleanupConfig,
     optimistically_confirmed_bank_tracker::{
         OptimisticallyConfirmedBank, OptimisticallyConfirmedBankTracker,
     },
@@ -23,6 +24,7 @@ use crate::{
     serve_repair::ServeRepair,
     serve_repair_service::ServeRepairService,
     sigverify,
+    sigverify_stage::DEFAULT_MAX_SIGVERIFY_BACKLOG_PACKETS,
     snapshot_packager_service::SnapshotPackagerService,
     tpu::Tpu,
     transaction_status_service::TransactionStatusService,
@@ -84,7 +86,7 @@ pub struct ValidatorConfig {
     pub rpc_addrs: Option<(SocketAddr, SocketAddr, SocketAddr)>, // (JsonRpc, JsonRpcPubSub, Banks)
     pub pubsub_config: PubSubConfig,
     pub snapshot_config: Option<SnapshotConfig>,
-    pub max_ledger_shreds: Option<u64>,
+    pub ledger_cleanup_config: Option<LedgerCleanupConfig>,
     pub broadcast_stage_type: BroadcastStageType,
     pub enable_partition: Option<Arc<AtomicBool>>,
     pub fixed_leader_schedule: Option<FixedSchedule>,
@@ -104,6 +106,8 @@ pub struct ValidatorConfig {
     pub cuda: bool,
     pub require_tower: bool,
     pub debug_keys: Option<Arc<HashSet<Pubkey>>>,
+    pub bank_hash_details_dir: Option<PathBuf>,
+    pub max_sigverify_backlog_packets: usize,
 }
 
 impl Default for ValidatorConfig {
@@ -114,7 +118,7 @@ impl Default for ValidatorConfig {
             expected_bank_hash: None,
             expected_shred_version: None,
             voting_disabled: false,
-            max_ledger_shreds: None,
+            ledger_cleanup_config: None,
             account_paths: Vec::new(),
             rpc_config: JsonRpcConfig::default(),
             rpc_addrs: None,
@@ -139,6 +143,8 @@ impl Default for ValidatorConfig {
             cuda: false,
             require_tower: false,
             debug_keys: None,
+            bank_hash_details_dir: None,
+            max_sigverify_backlog_packets: DEFAULT_MAX_SIGVERIFY_BACKLOG_PACKETS,
         }
     }
 }
@@ -295,6 +301,14 @@ impl Validator {
         let bank = bank_forks.working_bank();
         let bank_forks = Arc::new(RwLock::new(bank_forks));
 
+        if config.rpc_addrs.is_some() && config.rpc_config.enable_rpc_account_proofs {
+            bank.rc
+                .accounts
+                .accounts_db
+                .retain_accounts_hash_tree
+                .store(true, Ordering::Relaxed);
+        }
+
         let sample_performance_service =
             if config.rpc_addrs.is_some() && config.rpc_config.enable_rpc_transaction_history {
                 Some(SamplePerformanceService::new(
@@ -332,10 +346,12 @@ impl Validator {
             }
         }
 
-        let cluster_info = Arc::new(ClusterInfo::new(
-            node.info.clone(),
-            identity_keypair.clone(),
-        ));
+        let mut cluster_info = ClusterInfo::new(node.info.clone(), identity_keypair.clone());
+        if ContactInfo::is_valid_address(&node.tpu_vote) {
+            cluster_info.set_tpu_vote_address(node.tpu_vote);
+        }
+        cluster_info.restore_crds_backup(ledger_path);
+        let cluster_info = Arc::new(cluster_info);
         let mut block_commitment_cache = BlockCommitmentCache::default();
         block_commitment_cache.initialize_slots(bank.slot());
         let block_commitment_cache = Arc::new(RwLock::new(block_commitment_cache));
@@ -473,7 +489,10 @@ impl Validator {
             &exit,
         );
 
-        let serve_repair = Arc::new(RwLock::new(ServeRepair::new(cluster_info.clone())));
+        let serve_repair = Arc::new(RwLock::new(ServeRepair::new(
+            cluster_info.clone(),
+            Some(bank_forks.clone()),
+        )));
         let serve_repair_service = ServeRepairService::new(
             &serve_repair,
             Some(blockstore.clone()),
@@ -527,6 +546,11 @@ impl Validator {
                     .repair
                     .try_clone()
                     .expect("Failed to clone repair socket"),
+                ancestor_hashes_requests: node
+                    .sockets
+                    .ancestor_hashes_requests
+                    .try_clone()
+                    .expect("Failed to clone ancestor_hashes_requests socket"),
                 retransmit: node
                     .sockets
                     .retransmit_sockets
@@ -567,13 +591,14 @@ impl Validator {
             completed_data_sets_sender,
             bank_notification_sender.clone(),
             TvuConfig {
-                max_ledger_shreds: config.max_ledger_shreds,
+                ledger_cleanup_config: config.ledger_cleanup_config.clone(),
                 halt_on_trusted_validators_accounts_hash_mismatch: config
                     .halt_on_trusted_validators_accounts_hash_mismatch,
                 shred_version: node.info.shred_version,
                 trusted_validators: config.trusted_validators.clone(),
                 repair_validators: config.repair_validators.clone(),
                 accounts_hash_fault_injection_slots: config.accounts_hash_fault_injection_slots,
+                bank_hash_details_dir: config.bank_hash_details_dir.clone(),
             },
         );
 
@@ -584,6 +609,7 @@ impl Validator {
             retransmit_slots_receiver,
             node.sockets.tpu,
             node.sockets.tpu_forwards,
+            node.sockets.tpu_vote,
             node.sockets.broadcast,
             &subscriptions,
             transaction_status_sender,
@@ -597,6 +623,7 @@ impl Validator {
             replay_vote_receiver,
             replay_vote_sender,
             bank_notification_sender,
+            config.max_sigverify_backlog_packets,
         );
 
         datapoint_info!("validator-new", ("id", id.to_string(), String));
@@ -659,6 +686,12 @@ impl Validator {
 
     pub fn join(self) -> Result<()> {
         self.poh_service.join()?;
+        // The exit flag may have been set internally, e.g. by gossip on
+        // detecting a duplicate instance of this node, so make sure the
+        // services registered with `validator_exit` are shut down as well
+        if let Some(validator_exit) = self.validator_exit.write().unwrap().take() {
+            validator_exit.exit();
+        }
         drop(self.poh_recorder);
         if let Some(RpcServices {
             json_rpc_service,
@@ -832,6 +865,7 @@ fn new_banks_from_ledger(
         new_hard_forks: config.new_hard_forks.clone(),
         frozen_accounts: config.frozen_accounts.clone(),
         debug_keys: config.debug_keys.clone(),
+        bank_hash_details_dir: config.bank_hash_details_dir.clone(),
         ..blockstore_processor::ProcessOptions::default()
     };
 
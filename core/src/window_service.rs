@@ -2,6 +2,7 @@
 //!   blockstore and retransmitting where required
 //!
 use crate::{
+    ancestor_hashes_service::AncestorHashesReplayUpdateReceiver,
     cluster_info::ClusterInfo,
     cluster_info_vote_listener::VerifiedVoteReceiver,
     cluster_slots::ClusterSlots,
@@ -25,8 +26,8 @@ use solana_ledger::{
 use solana_metrics::{inc_new_counter_debug, inc_new_counter_error};
 use solana_perf::packet::Packets;
 use solana_rayon_threadlimit::get_thread_count;
-use solana_runtime::{bank::Bank, bank_forks::BankForks};
-use solana_sdk::{packet::PACKET_DATA_SIZE, pubkey::Pubkey, timing::duration_as_ms};
+use solana_runtime::{bank::Bank, bank_forks::BankForks, feature_set};
+use solana_sdk::{clock::Slot, packet::PACKET_DATA_SIZE, pubkey::Pubkey, timing::duration_as_ms};
 use solana_streamer::streamer::PacketSender;
 use std::{
     net::{SocketAddr, UdpSocket},
@@ -56,6 +57,13 @@ pub fn should_retransmit_and_persist(
     root: u64,
     shred_version: u16,
 ) -> bool {
+    let merkle_shreds_enabled = bank
+        .as_ref()
+        .map(|bank| {
+            bank.feature_set
+                .is_active(&feature_set::merkle_shreds::id())
+        })
+        .unwrap_or(false);
     let slot_leader_pubkey = match bank {
         None => leader_schedule_cache.slot_leader_at(shred.slot(), None),
         Some(bank) => leader_schedule_cache.slot_leader_at(shred.slot(), Some(&bank)),
@@ -73,6 +81,9 @@ pub fn should_retransmit_and_persist(
         } else if shred.index() >= MAX_DATA_SHREDS_PER_SLOT as u32 {
             inc_new_counter_warn!("streamer-recv_window-shred_index_overrun", 1);
             false
+        } else if shred.is_merkle() && !merkle_shreds_enabled {
+            inc_new_counter_debug!("streamer-recv_window-merkle_shred_inactive", 1);
+            false
         } else {
             true
         }
@@ -82,7 +93,11 @@ pub fn should_retransmit_and_persist(
     }
 }
 
+pub type GossipDuplicateSlotsSender = CrossbeamSender<Slot>;
+pub type GossipDuplicateSlotsReceiver = CrossbeamReceiver<Slot>;
+
 fn run_check_duplicate(
+    cluster_info: &ClusterInfo,
     blockstore: &Arc<Blockstore>,
     shred_receiver: &CrossbeamReceiver<Shred>,
 ) -> Result<()> {
@@ -91,6 +106,14 @@ fn run_check_duplicate(
             if let Some(existing_shred_payload) =
                 blockstore.is_shred_duplicate(shred.slot(), shred.index(), &shred.payload)
             {
+                if let Err(err) = cluster_info.push_duplicate_shred(&shred, &existing_shred_payload)
+                {
+                    warn!(
+                        "failed to push duplicate shred proof for slot {}: {}",
+                        shred.slot(),
+                        err
+                    );
+                }
                 blockstore.store_duplicate_slot(
                     shred.slot(),
                     existing_shred_payload,
@@ -111,6 +134,31 @@ fn run_check_duplicate(
     Ok(())
 }
 
+/// Stores duplicate slot proofs received through gossip, and notifies replay
+/// of any slot not already known to be duplicate.
+fn run_check_gossip_duplicates(
+    cluster_info: &ClusterInfo,
+    blockstore: &Blockstore,
+    leader_schedule_cache: &LeaderScheduleCache,
+    gossip_duplicate_slots_sender: &GossipDuplicateSlotsSender,
+    cursor: &mut usize,
+) -> Result<()> {
+    let (proofs, new_cursor) = cluster_info.get_duplicate_shreds_since(*cursor, |slot| {
+        leader_schedule_cache.slot_leader_at(slot, None)
+    });
+    *cursor = new_cursor;
+    for (shred1, shred2) in proofs {
+        let slot = shred1.slot();
+        if blockstore.has_duplicate_shreds_in_slot(slot) {
+            continue;
+        }
+        inc_new_counter_info!("window_service-gossip_duplicate_slot", 1);
+        blockstore.store_duplicate_slot(slot, shred1.payload, shred2.payload)?;
+        gossip_duplicate_slots_sender.send(slot)?;
+    }
+    Ok(())
+}
+
 fn verify_repair(repair_info: &Option<RepairMeta>) -> bool {
     repair_info
         .as_ref()
@@ -307,6 +355,7 @@ impl WindowService {
         verified_receiver: CrossbeamReceiver<Vec<Packets>>,
         retransmit: PacketSender,
         repair_socket: Arc<UdpSocket>,
+        ancestor_hashes_socket: Arc<UdpSocket>,
         exit: &Arc<AtomicBool>,
         repair_info: RepairInfo,
         leader_schedule_cache: &Arc<LeaderScheduleCache>,
@@ -314,6 +363,8 @@ impl WindowService {
         cluster_slots: Arc<ClusterSlots>,
         verified_vote_receiver: VerifiedVoteReceiver,
         completed_data_sets_sender: CompletedDataSetsSender,
+        gossip_duplicate_slots_sender: GossipDuplicateSlotsSender,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
     ) -> WindowService
     where
         F: 'static
@@ -327,17 +378,25 @@ impl WindowService {
             blockstore.clone(),
             exit.clone(),
             repair_socket,
+            ancestor_hashes_socket,
             cluster_info.clone(),
             repair_info,
             cluster_slots,
             verified_vote_receiver,
+            ancestor_hashes_replay_update_receiver,
         );
 
         let (insert_sender, insert_receiver) = unbounded();
         let (duplicate_sender, duplicate_receiver) = unbounded();
 
-        let t_check_duplicate =
-            Self::start_check_duplicate_thread(exit, &blockstore, duplicate_receiver);
+        let t_check_duplicate = Self::start_check_duplicate_thread(
+            cluster_info.clone(),
+            exit,
+            &blockstore,
+            leader_schedule_cache,
+            duplicate_receiver,
+            gossip_duplicate_slots_sender,
+        );
 
         let t_insert = Self::start_window_insert_thread(
             exit,
@@ -368,12 +427,17 @@ impl WindowService {
     }
 
     fn start_check_duplicate_thread(
+        cluster_info: Arc<ClusterInfo>,
         exit: &Arc<AtomicBool>,
         blockstore: &Arc<Blockstore>,
+        leader_schedule_cache: &Arc<LeaderScheduleCache>,
         duplicate_receiver: CrossbeamReceiver<Shred>,
+        gossip_duplicate_slots_sender: GossipDuplicateSlotsSender,
     ) -> JoinHandle<()> {
         let exit = exit.clone();
         let blockstore = blockstore.clone();
+        let leader_schedule_cache = leader_schedule_cache.clone();
+        let mut cursor = 0;
         let handle_error = || {
             inc_new_counter_error!("solana-check-duplicate-error", 1, 1);
         };
@@ -385,7 +449,19 @@ impl WindowService {
                 }
 
                 let mut noop = || {};
-                if let Err(e) = run_check_duplicate(&blockstore, &duplicate_receiver) {
+                if let Err(e) = run_check_gossip_duplicates(
+                    &cluster_info,
+                    &blockstore,
+                    &leader_schedule_cache,
+                    &gossip_duplicate_slots_sender,
+                    &mut cursor,
+                ) {
+                    if Self::should_exit_on_error(e, &mut noop, &handle_error) {
+                        break;
+                    }
+                }
+                if let Err(e) = run_check_duplicate(&cluster_info, &blockstore, &duplicate_receiver)
+                {
                     if Self::should_exit_on_error(e, &mut noop, &handle_error) {
                         break;
                     }
@@ -548,6 +624,7 @@ impl WindowService {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::contact_info::ContactInfo;
     use solana_ledger::{
         blockstore::{make_many_slot_entries, Blockstore},
         entry::{create_ticks, Entry},
@@ -668,6 +745,8 @@ mod test {
     fn test_run_check_duplicate() {
         let blockstore_path = get_tmp_ledger_path!();
         let blockstore = Arc::new(Blockstore::open(&blockstore_path).unwrap());
+        let contact_info = ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), 0);
+        let cluster_info = ClusterInfo::new_with_invalid_keypair(contact_info);
         let (sender, receiver) = unbounded();
         let (shreds, _) = make_many_slot_entries(5, 5, 10);
         blockstore
@@ -678,7 +757,14 @@ mod test {
         let duplicate_shred_slot = duplicate_shred.slot();
         sender.send(duplicate_shred).unwrap();
         assert!(!blockstore.has_duplicate_shreds_in_slot(duplicate_shred_slot));
-        run_check_duplicate(&blockstore, &receiver).unwrap();
+        run_check_duplicate(&cluster_info, &blockstore, &receiver).unwrap();
         assert!(blockstore.has_duplicate_shreds_in_slot(duplicate_shred_slot));
+        cluster_info.flush_push_queue();
+        let gossip = cluster_info.gossip.read().unwrap();
+        assert!(gossip.crds.table.values().any(|value| value
+            .value
+            .duplicate_shred()
+            .map(|chunk| chunk.slot == duplicate_shred_slot)
+            .unwrap_or_default()));
     }
 }
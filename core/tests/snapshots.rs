@@ -104,6 +104,7 @@ mod tests {
                 snapshot_path: PathBuf::from(snapshot_dir.path()),
                 compression: CompressionType::Bzip2,
                 snapshot_version,
+                include_storage_indexes: false,
             };
             bank_forks.set_snapshot_config(Some(snapshot_config.clone()));
             SnapshotTestConfig {
@@ -223,6 +224,7 @@ mod tests {
             last_bank.get_snapshot_storages(),
             CompressionType::Bzip2,
             snapshot_version,
+            false,
         )
         .unwrap();
         snapshot_utils::archive_snapshot_package(&snapshot_package).unwrap();
@@ -343,6 +345,7 @@ mod tests {
                 &snapshot_package_output_path,
                 snapshot_config.snapshot_version,
                 &snapshot_config.compression,
+                snapshot_config.include_storage_indexes,
             )
             .unwrap();
 
@@ -175,6 +175,7 @@ module.exports = {
       "implemented-proposals/commitment",
       "implemented-proposals/cross-program-invocation",
       "implemented-proposals/durable-tx-nonces",
+      "implemented-proposals/ed25519_instruction",
       "implemented-proposals/installer",
       "implemented-proposals/instruction_introspection",
       "implemented-proposals/leader-leader-transition",
@@ -0,0 +1,388 @@
+use serde_json::{json, Value};
+use solana_account_decoder::parse_account_data::parse_account_data;
+use solana_runtime::bank_hash_details::BankHashDetails;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+};
+
+pub struct AccountDiffConfig {
+    /// Only consider accounts owned by one of these programs on either side of the diff.
+    /// All accounts are considered if empty
+    pub program_ids: HashSet<Pubkey>,
+    /// Include the full account data of accounts that could not be parsed
+    pub include_data: bool,
+}
+
+/// Returns the added, removed and modified accounts between `from` and `to`
+pub fn diff_accounts(
+    from: &BTreeMap<Pubkey, Account>,
+    to: &BTreeMap<Pubkey, Account>,
+    config: &AccountDiffConfig,
+) -> Value {
+    let is_selected = |account: &Account| {
+        config.program_ids.is_empty() || config.program_ids.contains(&account.owner)
+    };
+
+    let mut added = vec![];
+    let mut modified = vec![];
+    for (pubkey, to_account) in to {
+        match from.get(pubkey) {
+            None => {
+                if is_selected(to_account) {
+                    added.push(account_json(pubkey, to_account, config));
+                }
+            }
+            Some(from_account) => {
+                if from_account != to_account
+                    && (is_selected(from_account) || is_selected(to_account))
+                {
+                    modified.push(modified_account_json(
+                        pubkey,
+                        from_account,
+                        to_account,
+                        config,
+                    ));
+                }
+            }
+        }
+    }
+    let removed: Vec<_> = from
+        .iter()
+        .filter(|(pubkey, from_account)| !to.contains_key(*pubkey) && is_selected(*from_account))
+        .map(|(pubkey, from_account)| account_json(pubkey, from_account, config))
+        .collect();
+
+    json!({
+        "added": added,
+        "removed": removed,
+        "modified": modified,
+    })
+}
+
+fn account_json(pubkey: &Pubkey, account: &Account, config: &AccountDiffConfig) -> Value {
+    json!({
+        "pubkey": pubkey.to_string(),
+        "lamports": account.lamports,
+        "owner": account.owner.to_string(),
+        "executable": account.executable,
+        "rentEpoch": account.rent_epoch,
+        "dataLen": account.data.len(),
+        "data": data_json(pubkey, account, config.include_data),
+    })
+}
+
+fn data_json(pubkey: &Pubkey, account: &Account, include_data: bool) -> Value {
+    match parse_account_data(pubkey, &account.owner, &account.data, None) {
+        Ok(parsed_account) => json!({
+            "program": parsed_account.program,
+            "parsed": parsed_account.parsed,
+        }),
+        Err(_) if include_data => json!(bs58::encode(&account.data).into_string()),
+        Err(_) => Value::Null,
+    }
+}
+
+fn modified_account_json(
+    pubkey: &Pubkey,
+    from: &Account,
+    to: &Account,
+    config: &AccountDiffConfig,
+) -> Value {
+    let mut diff = json!({
+        "pubkey": pubkey.to_string(),
+        "lamports": {
+            "from": from.lamports,
+            "to": to.lamports,
+            "delta": to.lamports as i128 - from.lamports as i128,
+        },
+    });
+    let fields = diff.as_object_mut().unwrap();
+    if from.owner != to.owner {
+        fields.insert(
+            "owner".to_string(),
+            json!({ "from": from.owner.to_string(), "to": to.owner.to_string() }),
+        );
+    }
+    if from.executable != to.executable {
+        fields.insert(
+            "executable".to_string(),
+            json!({ "from": from.executable, "to": to.executable }),
+        );
+    }
+    if from.rent_epoch != to.rent_epoch {
+        fields.insert(
+            "rentEpoch".to_string(),
+            json!({ "from": from.rent_epoch, "to": to.rent_epoch }),
+        );
+    }
+    if from.data != to.data {
+        let changed_ranges: Vec<_> = changed_byte_ranges(&from.data, &to.data)
+            .into_iter()
+            .map(|range| json!({ "offset": range.start, "len": range.end - range.start }))
+            .collect();
+        fields.insert(
+            "data".to_string(),
+            json!({
+                "fromLen": from.data.len(),
+                "toLen": to.data.len(),
+                "changedRanges": changed_ranges,
+                "from": data_json(pubkey, from, config.include_data),
+                "to": data_json(pubkey, to, config.include_data),
+            }),
+        );
+    }
+    diff
+}
+
+// Contiguous byte ranges that differ between `a` and `b`.  Bytes past the end of the
+// shorter buffer are always considered changed
+fn changed_byte_ranges(a: &[u8], b: &[u8]) -> Vec<Range<usize>> {
+    let len = a.len().max(b.len());
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for i in 0..len {
+        if a.get(i) == b.get(i) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(range) if range.end == i => range.end += 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+/// Compares two bank hash details dumps, reporting the differing bank hash inputs and the
+/// differing accounts
+pub fn compare_bank_hash_details(from: &BankHashDetails, to: &BankHashDetails) -> Value {
+    let mut differences = serde_json::Map::new();
+    let mut compare = |field: &str, from_value: String, to_value: String| {
+        if from_value != to_value {
+            differences.insert(
+                field.to_string(),
+                json!({ "from": from_value, "to": to_value }),
+            );
+        }
+    };
+    compare("slot", from.slot.to_string(), to.slot.to_string());
+    compare(
+        "bankHash",
+        from.bank_hash.to_string(),
+        to.bank_hash.to_string(),
+    );
+    compare(
+        "parentSlot",
+        from.parent_slot.to_string(),
+        to.parent_slot.to_string(),
+    );
+    compare(
+        "parentBankHash",
+        from.parent_bank_hash.to_string(),
+        to.parent_bank_hash.to_string(),
+    );
+    compare(
+        "accountsDeltaHash",
+        from.accounts_delta_hash.to_string(),
+        to.accounts_delta_hash.to_string(),
+    );
+    compare(
+        "signatureCount",
+        from.signature_count.to_string(),
+        to.signature_count.to_string(),
+    );
+    compare(
+        "lastBlockhash",
+        from.last_blockhash.to_string(),
+        to.last_blockhash.to_string(),
+    );
+    compare(
+        "stats",
+        format!("{:?}", from.stats),
+        format!("{:?}", to.stats),
+    );
+
+    let to_accounts = |details: &BankHashDetails| -> BTreeMap<Pubkey, Account> {
+        details
+            .accounts
+            .iter()
+            .map(|details| (details.pubkey, details.account.clone()))
+            .collect()
+    };
+    let config = AccountDiffConfig {
+        program_ids: HashSet::new(),
+        include_data: true,
+    };
+    let accounts = diff_accounts(&to_accounts(from), &to_accounts(to), &config);
+
+    // Identical accounts that were hashed differently
+    let to_hashes: HashMap<_, _> = to
+        .accounts
+        .iter()
+        .map(|details| (details.pubkey, details))
+        .collect();
+    let hash_mismatches: Vec<_> = from
+        .accounts
+        .iter()
+        .filter_map(|from_details| {
+            let to_details = to_hashes.get(&from_details.pubkey)?;
+            if from_details.account == to_details.account && from_details.hash != to_details.hash {
+                Some(json!({
+                    "pubkey": from_details.pubkey.to_string(),
+                    "fromHash": from_details.hash.to_string(),
+                    "toHash": to_details.hash.to_string(),
+                }))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    json!({
+        "differences": differences,
+        "accounts": accounts,
+        "accountHashMismatches": hash_mismatches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_config(program_ids: &[Pubkey], include_data: bool) -> AccountDiffConfig {
+        AccountDiffConfig {
+            program_ids: program_ids.iter().copied().collect(),
+            include_data,
+        }
+    }
+
+    fn pubkeys(accounts: &Value) -> Vec<String> {
+        accounts
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|account| account["pubkey"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_changed_byte_ranges() {
+        assert!(changed_byte_ranges(&[], &[]).is_empty());
+        assert!(changed_byte_ranges(&[1, 2, 3], &[1, 2, 3]).is_empty());
+        assert_eq!(changed_byte_ranges(&[1, 2, 3], &[1, 9, 3]), vec![1..2]);
+        // Adjacent changed bytes are merged into a single range
+        assert_eq!(
+            changed_byte_ranges(&[1, 2, 3, 4, 5, 6], &[9, 9, 3, 9, 9, 9]),
+            vec![0..2, 3..6]
+        );
+        // Bytes past the end of the shorter buffer are changed
+        assert_eq!(changed_byte_ranges(&[1, 2], &[1, 2, 0, 0]), vec![2..4]);
+        assert_eq!(changed_byte_ranges(&[1, 2, 3], &[1]), vec![1..3]);
+        assert_eq!(changed_byte_ranges(&[1, 2], &[1, 9, 0]), vec![1..3]);
+    }
+
+    #[test]
+    fn test_diff_accounts() {
+        let owner = solana_sdk::pubkey::new_rand();
+        let unchanged = solana_sdk::pubkey::new_rand();
+        let removed = solana_sdk::pubkey::new_rand();
+        let added = solana_sdk::pubkey::new_rand();
+        let modified = solana_sdk::pubkey::new_rand();
+
+        let from: BTreeMap<_, _> = vec![
+            (unchanged, Account::new(1, 4, &owner)),
+            (removed, Account::new(2, 4, &owner)),
+            (modified, Account::new(3, 4, &owner)),
+        ]
+        .into_iter()
+        .collect();
+        let mut to = from.clone();
+        to.remove(&removed);
+        to.insert(added, Account::new(4, 4, &owner));
+        let modified_account = to.get_mut(&modified).unwrap();
+        modified_account.lamports = 1;
+        modified_account.rent_epoch = 7;
+        modified_account.data = vec![0, 5, 5, 0, 5];
+
+        let diff = diff_accounts(&from, &to, &new_config(&[], false));
+        assert_eq!(pubkeys(&diff["added"]), vec![added.to_string()]);
+        assert_eq!(pubkeys(&diff["removed"]), vec![removed.to_string()]);
+        assert_eq!(pubkeys(&diff["modified"]), vec![modified.to_string()]);
+
+        let added_json = &diff["added"][0];
+        assert_eq!(added_json["lamports"], 4);
+        assert_eq!(added_json["owner"], owner.to_string());
+        assert_eq!(added_json["dataLen"], 4);
+        // Data which cannot be parsed is only included on request
+        assert_eq!(added_json["data"], Value::Null);
+
+        let modified_json = &diff["modified"][0];
+        assert_eq!(
+            modified_json["lamports"],
+            json!({ "from": 3, "to": 1, "delta": -2 })
+        );
+        assert_eq!(modified_json["rentEpoch"], json!({ "from": 0, "to": 7 }));
+        assert!(modified_json.get("owner").is_none());
+        assert!(modified_json.get("executable").is_none());
+        assert_eq!(modified_json["data"]["fromLen"], 4);
+        assert_eq!(modified_json["data"]["toLen"], 5);
+        assert_eq!(
+            modified_json["data"]["changedRanges"],
+            json!([{ "offset": 1, "len": 2 }, { "offset": 4, "len": 1 }])
+        );
+
+        let diff = diff_accounts(&from, &to, &new_config(&[], true));
+        assert_eq!(
+            diff["added"][0]["data"],
+            json!(bs58::encode(&[0; 4]).into_string())
+        );
+        assert_eq!(
+            diff["modified"][0]["data"]["to"],
+            json!(bs58::encode(&[0, 5, 5, 0, 5]).into_string())
+        );
+
+        // Identical sides have no differences
+        let diff = diff_accounts(&from, &from, &new_config(&[], false));
+        assert_eq!(diff, json!({ "added": [], "removed": [], "modified": [] }));
+    }
+
+    #[test]
+    fn test_diff_accounts_program_filter() {
+        let owner = solana_sdk::pubkey::new_rand();
+        let other_owner = solana_sdk::pubkey::new_rand();
+        let added = solana_sdk::pubkey::new_rand();
+        let added_other = solana_sdk::pubkey::new_rand();
+        let removed_other = solana_sdk::pubkey::new_rand();
+        let reassigned = solana_sdk::pubkey::new_rand();
+        let modified_other = solana_sdk::pubkey::new_rand();
+
+        let from: BTreeMap<_, _> = vec![
+            (removed_other, Account::new(1, 0, &other_owner)),
+            (reassigned, Account::new(1, 0, &other_owner)),
+            (modified_other, Account::new(1, 0, &other_owner)),
+        ]
+        .into_iter()
+        .collect();
+        let mut to = from.clone();
+        to.remove(&removed_other);
+        to.insert(added, Account::new(1, 0, &owner));
+        to.insert(added_other, Account::new(1, 0, &other_owner));
+        to.get_mut(&reassigned).unwrap().owner = owner;
+        to.get_mut(&modified_other).unwrap().lamports = 2;
+
+        // Accounts owned by a selected program on either side of the diff are included
+        let diff = diff_accounts(&from, &to, &new_config(&[owner], false));
+        assert_eq!(pubkeys(&diff["added"]), vec![added.to_string()]);
+        assert!(pubkeys(&diff["removed"]).is_empty());
+        assert_eq!(pubkeys(&diff["modified"]), vec![reassigned.to_string()]);
+        assert_eq!(
+            diff["modified"][0]["owner"],
+            json!({ "from": other_owner.to_string(), "to": owner.to_string() })
+        );
+
+        let diff = diff_accounts(&from, &to, &new_config(&[owner, other_owner], false));
+        assert_eq!(pubkeys(&diff["added"]).len(), 2);
+        assert_eq!(pubkeys(&diff["removed"]), vec![removed_other.to_string()]);
+        assert_eq!(pubkeys(&diff["modified"]).len(), 2);
+    }
+}
@@ -7,21 +7,23 @@ use solana_clap_utils::{
 use solana_cli_output::display::println_transaction;
 use solana_ledger::{blockstore::Blockstore, blockstore_db::AccessType};
 use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
+use solana_storage_bigtable::{new_ledger_storage, LedgerStorageConfig};
 use solana_transaction_status::ConfirmedBlock;
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     process::exit,
     result::Result,
     sync::{atomic::AtomicBool, Arc},
 };
 
 async fn upload(
+    config: &LedgerStorageConfig,
     blockstore: Blockstore,
     starting_slot: Slot,
     ending_slot: Option<Slot>,
     allow_missing_metadata: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+    let bigtable = new_ledger_storage(config, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -36,8 +38,10 @@ async fn upload(
     .await
 }
 
-async fn first_available_block() -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(true).await?;
+async fn first_available_block(
+    config: &LedgerStorageConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = new_ledger_storage(config, true).await?;
     match bigtable.get_first_available_block().await? {
         Some(block) => println!("{}", block),
         None => println!("No blocks available"),
@@ -46,8 +50,8 @@ async fn first_available_block() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn block(slot: Slot) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+async fn block(config: &LedgerStorageConfig, slot: Slot) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = new_ledger_storage(config, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -74,8 +78,12 @@ async fn block(slot: Slot) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn blocks(starting_slot: Slot, limit: usize) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+async fn blocks(
+    config: &LedgerStorageConfig,
+    starting_slot: Slot,
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = new_ledger_storage(config, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -86,8 +94,12 @@ async fn blocks(starting_slot: Slot, limit: usize) -> Result<(), Box<dyn std::er
     Ok(())
 }
 
-async fn confirm(signature: &Signature, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+async fn confirm(
+    config: &LedgerStorageConfig,
+    signature: &Signature,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = new_ledger_storage(config, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -119,6 +131,7 @@ async fn confirm(signature: &Signature, verbose: bool) -> Result<(), Box<dyn std
 }
 
 pub async fn transaction_history(
+    config: &LedgerStorageConfig,
     address: &Pubkey,
     mut limit: usize,
     mut before: Option<Signature>,
@@ -127,7 +140,7 @@ pub async fn transaction_history(
     show_transactions: bool,
     query_chunk_size: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(true).await?;
+    let bigtable = new_ledger_storage(config, true).await?;
 
     let mut loaded_block: Option<(Slot, ConfirmedBlock)> = None;
     while limit > 0 {
@@ -215,6 +228,16 @@ impl BigTableSubCommand for App<'_, '_> {
             SubCommand::with_name("bigtable")
                 .about("Ledger data on a BigTable instance")
                 .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("storage_path")
+                        .long("storage-path")
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .help(
+                            "Use the long-term ledger storage in this directory \
+                             instead of BigTable",
+                        ),
+                )
                 .subcommand(
                     SubCommand::with_name("upload")
                         .about("Upload the ledger to BigTable")
@@ -379,6 +402,10 @@ impl BigTableSubCommand for App<'_, '_> {
 
 pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
     let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    let config = matches
+        .value_of("storage_path")
+        .map(|path| LedgerStorageConfig::FileSystem(PathBuf::from(path)))
+        .unwrap_or_default();
 
     let future = match matches.subcommand() {
         ("upload", Some(arg_matches)) => {
@@ -389,22 +416,25 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 crate::open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
 
             runtime.block_on(upload(
+                &config,
                 blockstore,
                 starting_slot,
                 ending_slot,
                 allow_missing_metadata,
             ))
         }
-        ("first-available-block", Some(_arg_matches)) => runtime.block_on(first_available_block()),
+        ("first-available-block", Some(_arg_matches)) => {
+            runtime.block_on(first_available_block(&config))
+        }
         ("block", Some(arg_matches)) => {
             let slot = value_t_or_exit!(arg_matches, "slot", Slot);
-            runtime.block_on(block(slot))
+            runtime.block_on(block(&config, slot))
         }
         ("blocks", Some(arg_matches)) => {
             let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
             let limit = value_t_or_exit!(arg_matches, "limit", usize);
 
-            runtime.block_on(blocks(starting_slot, limit))
+            runtime.block_on(blocks(&config, starting_slot, limit))
         }
         ("confirm", Some(arg_matches)) => {
             let signature = arg_matches
@@ -414,7 +444,7 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 .expect("Invalid signature");
             let verbose = arg_matches.is_present("verbose");
 
-            runtime.block_on(confirm(&signature, verbose))
+            runtime.block_on(confirm(&config, &signature, verbose))
         }
         ("transaction-history", Some(arg_matches)) => {
             let address = pubkey_of(arg_matches, "address").unwrap();
@@ -430,6 +460,7 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
             let show_transactions = arg_matches.is_present("show_transactions");
 
             runtime.block_on(transaction_history(
+                &config,
                 &address,
                 limit,
                 before,
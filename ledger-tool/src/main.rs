@@ -8,20 +8,23 @@ use regex::Regex;
 use serde_json::json;
 use solana_clap_utils::{
     input_parsers::{cluster_type_of, pubkey_of, pubkeys_of},
-    input_validators::{is_parsable, is_pubkey_or_keypair, is_slot, is_valid_percentage},
+    input_validators::{
+        is_parsable, is_pubkey, is_pubkey_or_keypair, is_slot, is_valid_percentage,
+    },
 };
 use solana_ledger::entry::Entry;
 use solana_ledger::{
     ancestor_iterator::AncestorIterator,
     bank_forks_utils,
-    blockstore::{create_new_ledger, Blockstore, PurgeType},
+    blockstore::{blockstore_check::SlotInconsistency, create_new_ledger, Blockstore, PurgeType},
     blockstore_db::{self, AccessType, BlockstoreRecoveryMode, Column, Database},
-    blockstore_processor::ProcessOptions,
+    blockstore_processor::{ProcessOptions, TransactionStatusSender},
     rooted_slot_iterator::RootedSlotIterator,
 };
 use solana_runtime::{
     bank::Bank,
     bank_forks::{BankForks, CompressionType, SnapshotConfig},
+    bank_hash_details::BankHashDetails,
     hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     snapshot_utils,
     snapshot_utils::SnapshotVersion,
@@ -55,9 +58,15 @@ use std::{
     sync::Arc,
 };
 
+mod account_diff;
+use account_diff::{compare_bank_hash_details, diff_accounts, AccountDiffConfig};
+
 mod bigtable;
 use bigtable::*;
 
+mod trace;
+use trace::{TraceFormat, TransactionTracer};
+
 #[derive(PartialEq)]
 enum LedgerOutputMethod {
     Print,
@@ -645,6 +654,7 @@ fn hardforks_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<Slot>> {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn load_bank_forks(
     arg_matches: &ArgMatches,
     ledger_path: &PathBuf,
@@ -653,6 +663,7 @@ fn load_bank_forks(
     access_type: AccessType,
     wal_recovery_mode: Option<BlockstoreRecoveryMode>,
     snapshot_archive_path: Option<PathBuf>,
+    transaction_status_sender: Option<TransactionStatusSender>,
 ) -> bank_forks_utils::LoadResult {
     let blockstore = open_blockstore(&ledger_path, access_type, wal_recovery_mode);
     let snapshot_path = ledger_path.clone().join(if blockstore.is_primary_access() {
@@ -671,6 +682,7 @@ fn load_bank_forks(
             snapshot_path,
             compression: CompressionType::Bzip2,
             snapshot_version: SnapshotVersion::default(),
+            include_storage_indexes: false,
         })
     };
     let account_paths = if let Some(account_paths) = arg_matches.value_of("account_paths") {
@@ -698,10 +710,93 @@ fn load_bank_forks(
         account_paths,
         snapshot_config.as_ref(),
         process_options,
-        None,
+        transaction_status_sender,
     )
 }
 
+// Loads the accounts of one side of an account diff, either by replaying the ledger to
+// `slot_arg` or by unpacking the snapshot archive given in `snapshot_arg`
+fn load_accounts_for_diff(
+    arg_matches: &ArgMatches,
+    ledger_path: &PathBuf,
+    genesis_config: &GenesisConfig,
+    wal_recovery_mode: Option<BlockstoreRecoveryMode>,
+    snapshot_archive_path: Option<PathBuf>,
+    slot_arg: &str,
+    snapshot_arg: &str,
+) -> (Slot, Hash, BTreeMap<Pubkey, Account>) {
+    let include_sysvars = arg_matches.is_present("include_sysvars");
+    let collect_accounts = |bank: &Bank| -> BTreeMap<Pubkey, Account> {
+        bank.get_all_accounts_with_modified_slots()
+            .into_iter()
+            .filter(|(pubkey, _account, _slot)| {
+                include_sysvars || !solana_sdk::sysvar::is_sysvar_id(pubkey)
+            })
+            .map(|(pubkey, account, _slot)| (pubkey, account))
+            .collect()
+    };
+
+    if let Some(snapshot_archive) = arg_matches.value_of(snapshot_arg) {
+        let snapshot_archive = PathBuf::from(snapshot_archive);
+        let compression = snapshot_archive
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(snapshot_utils::snapshot_hash_of)
+            .map(|(_slot, _hash, compression)| compression)
+            .unwrap_or_else(|| {
+                eprintln!("Error: {:?} is not a snapshot archive", snapshot_archive);
+                exit(1);
+            });
+        let temp_dir = tempfile::tempdir_in(ledger_path).unwrap_or_else(|err| {
+            eprintln!("Unable to create temporary directory: {}", err);
+            exit(1);
+        });
+        let accounts_path = temp_dir.path().join("accounts");
+        let bank = snapshot_utils::bank_from_archive(
+            &[accounts_path],
+            &[],
+            &temp_dir.path().to_path_buf(),
+            &snapshot_archive,
+            compression,
+            genesis_config,
+            None,
+            Some(&solana_ledger::builtins::get(genesis_config.cluster_type)),
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to load {:?}: {:?}", snapshot_archive, err);
+            exit(1);
+        });
+        (bank.slot(), bank.hash(), collect_accounts(&bank))
+    } else {
+        let slot = value_t_or_exit!(arg_matches, slot_arg, Slot);
+        let process_options = ProcessOptions {
+            dev_halt_at_slot: Some(slot),
+            new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+            poh_verify: false,
+            ..ProcessOptions::default()
+        };
+        let (bank_forks, _, _) = load_bank_forks(
+            arg_matches,
+            ledger_path,
+            genesis_config,
+            process_options,
+            AccessType::TryPrimaryThenSecondary,
+            wal_recovery_mode,
+            snapshot_archive_path,
+            None,
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to load ledger: {:?}", err);
+            exit(1);
+        });
+        let bank = bank_forks.get(slot).unwrap_or_else(|| {
+            eprintln!("Error: Slot {} is not available", slot);
+            exit(1);
+        });
+        (bank.slot(), bank.hash(), collect_accounts(&bank))
+    }
+}
+
 fn open_genesis_config_by(ledger_path: &Path, matches: &ArgMatches<'_>) -> GenesisConfig {
     let max_genesis_archive_unpacked_size =
         value_t_or_exit!(matches, "max_genesis_archive_unpacked_size", u64);
@@ -909,6 +1004,39 @@ fn main() {
                     .help("Slots to mark dead"),
             )
         )
+        .subcommand(
+            SubCommand::with_name("check")
+            .about("Check the shreds and shred metadata of each slot for inconsistencies")
+            .arg(&starting_slot_arg)
+            .arg(
+                Arg::with_name("ending_slot")
+                    .long("ending-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .help("The last slot to check [default: the highest slot in the ledger]"),
+            )
+            .arg(
+                Arg::with_name("fix_metadata")
+                    .long("fix-metadata")
+                    .takes_value(false)
+                    .help("Rewrite the shred index and slot metadata to match the shreds \
+                           present in the ledger"),
+            )
+            .arg(
+                Arg::with_name("recover")
+                    .long("recover")
+                    .takes_value(false)
+                    .help("Recover missing data shreds from coding shreds where possible, \
+                           rewriting the metadata of the recovered slots"),
+            )
+            .arg(
+                Arg::with_name("mark_dead")
+                    .long("mark-dead")
+                    .takes_value(false)
+                    .help("Mark slots that cannot be completed dead, unless they are rooted"),
+            )
+        )
         .subcommand(
             SubCommand::with_name("genesis")
             .about("Prints the ledger's genesis config")
@@ -1007,6 +1135,54 @@ fn main() {
                     .takes_value(false)
                     .help("After verifying the ledger, print some information about the account stores."),
             )
+            .arg(
+                Arg::with_name("trace_transactions")
+                    .long("trace-transactions")
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .help("Write the slot, signature, fee, status, compute units, locked accounts \
+                           and execution time of every replayed transaction to FILE, \
+                           and print a summary of per-program statistics"),
+            )
+            .arg(
+                Arg::with_name("trace_format")
+                    .long("trace-format")
+                    .value_name("FORMAT")
+                    .takes_value(true)
+                    .possible_values(&["json", "csv"])
+                    .default_value("json")
+                    .requires("trace_transactions")
+                    .help("Format of the transaction trace. \
+                           json writes one JSON object per line"),
+            )
+            .arg(
+                Arg::with_name("bank_hash_details_dir")
+                    .long("bank-hash-details-dir")
+                    .value_name("DIR")
+                    .takes_value(true)
+                    .help("Write the accounts and statistics that contribute to the hash of \
+                           every replayed bank to DIR"),
+            )
+        ).subcommand(
+            SubCommand::with_name("compare-bank-hash-details")
+            .about("Compare two bank hash details files to find the accounts that caused \
+                    a bank hash mismatch")
+            .arg(
+                Arg::with_name("from_details")
+                    .index(1)
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Bank hash details file to compare from"),
+            )
+            .arg(
+                Arg::with_name("to_details")
+                    .index(2)
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Bank hash details file to compare to"),
+            )
         ).subcommand(
             SubCommand::with_name("graph")
             .about("Create a Graphviz rendering of the ledger")
@@ -1160,6 +1336,68 @@ fn main() {
                     .help("Exclude account data (useful for large number of accounts)"),
             )
             .arg(&max_genesis_archive_unpacked_size_arg)
+        ).subcommand(
+            SubCommand::with_name("diff-accounts")
+            .about("Print the accounts added, removed and modified between two slots or snapshots")
+            .arg(&no_snapshot_arg)
+            .arg(&account_paths_arg)
+            .arg(&hard_forks_arg)
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(
+                Arg::with_name("from_slot")
+                    .long("from-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .required_unless("from_snapshot")
+                    .help("Replay the ledger to this slot to load the accounts to diff from"),
+            )
+            .arg(
+                Arg::with_name("from_snapshot")
+                    .long("from-snapshot")
+                    .value_name("ARCHIVE")
+                    .takes_value(true)
+                    .conflicts_with("from_slot")
+                    .help("Load the accounts to diff from out of this snapshot archive"),
+            )
+            .arg(
+                Arg::with_name("to_slot")
+                    .long("to-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .required_unless("to_snapshot")
+                    .help("Replay the ledger to this slot to load the accounts to diff to"),
+            )
+            .arg(
+                Arg::with_name("to_snapshot")
+                    .long("to-snapshot")
+                    .value_name("ARCHIVE")
+                    .takes_value(true)
+                    .conflicts_with("to_slot")
+                    .help("Load the accounts to diff to out of this snapshot archive"),
+            )
+            .arg(
+                Arg::with_name("program_id")
+                    .long("program-id")
+                    .value_name("PUBKEY")
+                    .validator(is_pubkey)
+                    .takes_value(true)
+                    .multiple(true)
+                    .help("Only include accounts owned by this program"),
+            )
+            .arg(
+                Arg::with_name("include_sysvars")
+                    .long("include-sysvars")
+                    .takes_value(false)
+                    .help("Include sysvars too"),
+            )
+            .arg(
+                Arg::with_name("include_data")
+                    .long("include-data")
+                    .takes_value(false)
+                    .help("Include the data of accounts that cannot be decoded"),
+            )
         ).subcommand(
             SubCommand::with_name("capitalization")
             .about("Print capitalization (aka, total suppy) while checksumming it")
@@ -1265,6 +1503,10 @@ fn main() {
             SubCommand::with_name("analyze-storage")
                 .about("Output statistics in JSON format about all column families in the ledger rocksDB")
         )
+        .subcommand(
+            SubCommand::with_name("migrate-to-protobuf")
+                .about("Re-encode transaction statuses and rewards stored as bincode in protobuf")
+        )
         .get_matches();
 
     info!("{} {}", crate_name!(), solana_version::version!());
@@ -1386,6 +1628,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     println!(
@@ -1418,6 +1661,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     println!("{}", &bank_forks.working_bank().hash());
@@ -1488,6 +1732,79 @@ fn main() {
                 }
             }
         }
+        ("check", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).unwrap_or(std::u64::MAX);
+            let fix_metadata = arg_matches.is_present("fix_metadata");
+            let recover = arg_matches.is_present("recover");
+            let mark_dead = arg_matches.is_present("mark_dead");
+            let access_type = if fix_metadata || recover || mark_dead {
+                AccessType::PrimaryOnly
+            } else {
+                AccessType::TryPrimaryThenSecondary
+            };
+            let blockstore = open_blockstore(&ledger_path, access_type, wal_recovery_mode);
+
+            let slot_checks = blockstore
+                .check_slots(starting_slot, ending_slot)
+                .unwrap_or_else(|err| {
+                    eprintln!("Failed to check the ledger: {:?}", err);
+                    exit(1);
+                });
+            let mut num_unrecoverable = 0;
+            for slot_check in &slot_checks {
+                let slot = slot_check.slot;
+                println!("Slot {}:", slot);
+                for inconsistency in &slot_check.inconsistencies {
+                    println!("  {:?}", inconsistency);
+                }
+
+                if fix_metadata {
+                    match blockstore.repair_slot_metadata(slot) {
+                        Ok(()) => println!("  Rewrote slot metadata"),
+                        Err(err) => eprintln!("  Failed to rewrite slot metadata: {:?}", err),
+                    }
+                }
+                if recover
+                    && slot_check.inconsistencies.iter().any(|inconsistency| {
+                        matches!(inconsistency, SlotInconsistency::RecoverableErasureSet(_))
+                    })
+                {
+                    match blockstore.recover_slot_shreds(slot) {
+                        Ok(num_recovered) => println!("  Recovered {} data shreds", num_recovered),
+                        Err(err) => eprintln!("  Failed to recover shreds: {:?}", err),
+                    }
+                }
+
+                let is_unrecoverable = if fix_metadata || recover {
+                    blockstore
+                        .check_slot(slot)
+                        .map(|slot_check| slot_check.is_unrecoverable)
+                        .unwrap_or(slot_check.is_unrecoverable)
+                } else {
+                    slot_check.is_unrecoverable
+                };
+                if is_unrecoverable {
+                    num_unrecoverable += 1;
+                    println!("  Slot cannot be completed from the shreds in the ledger");
+                    if mark_dead {
+                        if blockstore.is_root(slot) {
+                            println!("  Not marking rooted slot dead");
+                        } else if !blockstore.is_dead(slot) {
+                            match blockstore.set_dead_slot(slot) {
+                                Ok(()) => println!("  Marked slot dead"),
+                                Err(err) => eprintln!("  Failed to mark slot dead: {:?}", err),
+                            }
+                        }
+                    }
+                }
+            }
+            println!(
+                "{} slots with inconsistencies, {} cannot be completed",
+                slot_checks.len(),
+                num_unrecoverable
+            );
+        }
         ("parse_full_frozen", Some(arg_matches)) => {
             let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
             let ending_slot = value_t_or_exit!(arg_matches, "ending_slot", Slot);
@@ -1561,6 +1878,8 @@ fn main() {
                 dev_halt_at_slot: value_t!(arg_matches, "halt_at_slot", Slot).ok(),
                 new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
                 poh_verify: !arg_matches.is_present("skip_poh_verify"),
+                bank_hash_details_dir: value_t!(arg_matches, "bank_hash_details_dir", PathBuf).ok(),
+                enable_execution_traces: arg_matches.is_present("trace_transactions"),
                 ..ProcessOptions::default()
             };
             let print_accounts_stats = arg_matches.is_present("print_accounts_stats");
@@ -1569,6 +1888,21 @@ fn main() {
                 open_genesis_config_by(&ledger_path, arg_matches).hash()
             );
 
+            let (tracer, transaction_status_sender) =
+                match value_t!(arg_matches, "trace_transactions", PathBuf) {
+                    Ok(trace_path) => {
+                        let trace_format =
+                            value_t_or_exit!(arg_matches, "trace_format", TraceFormat);
+                        let (tracer, sender) = TransactionTracer::new(&trace_path, trace_format)
+                            .unwrap_or_else(|err| {
+                                eprintln!("Unable to create {:?}: {}", trace_path, err);
+                                exit(1);
+                            });
+                        (Some(tracer), Some(sender))
+                    }
+                    Err(_) => (None, None),
+                };
+
             let (bank_forks, _, _) = load_bank_forks(
                 arg_matches,
                 &ledger_path,
@@ -1577,17 +1911,37 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                transaction_status_sender,
             )
             .unwrap_or_else(|err| {
                 eprintln!("Ledger verification failed: {:?}", err);
                 exit(1);
             });
+            if let Some(tracer) = tracer {
+                tracer.join().unwrap_or_else(|err| {
+                    eprintln!("Failed to write transaction trace: {}", err);
+                    exit(1);
+                });
+            }
             if print_accounts_stats {
                 let working_bank = bank_forks.working_bank();
                 working_bank.print_accounts_stats();
             }
             println!("Ok");
         }
+        ("compare-bank-hash-details", Some(arg_matches)) => {
+            let read_details = |name: &str| {
+                let path = value_t_or_exit!(arg_matches, name, PathBuf);
+                BankHashDetails::read(&path).unwrap_or_else(|err| {
+                    eprintln!("Failed to read {:?}: {}", path, err);
+                    exit(1);
+                })
+            };
+            let from_details = read_details("from_details");
+            let to_details = read_details("to_details");
+            let comparison = compare_bank_hash_details(&from_details, &to_details);
+            println!("{}", serde_json::to_string_pretty(&comparison).unwrap());
+        }
         ("graph", Some(arg_matches)) => {
             let output_file = value_t_or_exit!(arg_matches, "graph_filename", String);
 
@@ -1606,6 +1960,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     let dot = graph_forks(&bank_forks, arg_matches.is_present("include_all_votes"));
@@ -1682,6 +2037,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     let mut bank = bank_forks
@@ -1862,6 +2218,7 @@ fn main() {
                                 storages,
                                 CompressionType::Bzip2,
                                 snapshot_version,
+                                false,
                             )
                         })
                         .and_then(|package| {
@@ -1912,6 +2269,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     let slot = bank_forks.working_bank().slot();
@@ -1950,6 +2308,40 @@ fn main() {
                 }
             }
         }
+        ("diff-accounts", Some(arg_matches)) => {
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            let config = AccountDiffConfig {
+                program_ids: pubkeys_of(arg_matches, "program_id")
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+                include_data: arg_matches.is_present("include_data"),
+            };
+
+            let (from_slot, from_hash, from_accounts) = load_accounts_for_diff(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                wal_recovery_mode.clone(),
+                snapshot_archive_path.clone(),
+                "from_slot",
+                "from_snapshot",
+            );
+            let (to_slot, to_hash, to_accounts) = load_accounts_for_diff(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                wal_recovery_mode,
+                snapshot_archive_path,
+                "to_slot",
+                "to_snapshot",
+            );
+
+            let mut diff = diff_accounts(&from_accounts, &to_accounts, &config);
+            diff["from"] = json!({ "slot": from_slot, "bankHash": from_hash.to_string() });
+            diff["to"] = json!({ "slot": to_slot, "bankHash": to_hash.to_string() });
+            println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+        }
         ("capitalization", Some(arg_matches)) => {
             let dev_halt_at_slot = value_t!(arg_matches, "halt_at_slot", Slot).ok();
             let process_options = ProcessOptions {
@@ -1967,6 +2359,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     let slot = bank_forks.working_bank().slot();
@@ -2259,6 +2652,22 @@ fn main() {
                 }
             }
         }
+        ("migrate-to-protobuf", _) => {
+            let blockstore =
+                open_blockstore(&ledger_path, AccessType::PrimaryOnly, wal_recovery_mode);
+            match blockstore.migrate_to_protobuf() {
+                Ok((transaction_statuses, rewards)) => {
+                    println!(
+                        "Migrated {} transaction statuses and {} rewards",
+                        transaction_statuses, rewards
+                    );
+                }
+                Err(err) => {
+                    eprintln!("Unable to migrate the Ledger: {:?}", err);
+                    exit(1);
+                }
+            }
+        }
         ("", _) => {
             eprintln!("{}", matches.usage());
             exit(1);
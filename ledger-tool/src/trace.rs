@@ -0,0 +1,461 @@
+use crossbeam_channel::{unbounded, Receiver};
+use itertools::izip;
+use serde_json::json;
+use solana_ledger::blockstore_processor::{TransactionStatusBatch, TransactionStatusSender};
+use solana_runtime::{bank::HashAgeKind, transaction_utils::OrderedIterator};
+use solana_sdk::{nonce, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+    thread::{Builder, JoinHandle},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(TraceFormat::Json),
+            "csv" => Ok(TraceFormat::Csv),
+            _ => Err(format!("Unsupported trace format: {}", s)),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "slot,signature,fee,status,execution_us,compute_units,\
+                          instruction_programs,instruction_compute_units,\
+                          instruction_execution_us,writable_accounts,readonly_accounts";
+
+struct ProgramStats {
+    instructions: u64,
+    failed_instructions: u64,
+    total_compute_units: u64,
+    total_execution_us: u64,
+    compute_units: histogram::Histogram,
+    execution_us: histogram::Histogram,
+}
+
+impl ProgramStats {
+    fn new() -> Self {
+        Self {
+            instructions: 0,
+            failed_instructions: 0,
+            total_compute_units: 0,
+            total_execution_us: 0,
+            compute_units: histogram::Histogram::new(),
+            execution_us: histogram::Histogram::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct TraceSummary {
+    transactions: u64,
+    failed_transactions: u64,
+    programs: HashMap<Pubkey, ProgramStats>,
+}
+
+/// Writes a record for every transaction replayed by the blockstore processor, and
+/// accumulates per-program compute and latency statistics for a final summary
+pub struct TransactionTracer {
+    thread_hdl: JoinHandle<io::Result<TraceSummary>>,
+}
+
+impl TransactionTracer {
+    pub fn new(path: &Path, format: TraceFormat) -> io::Result<(Self, TransactionStatusSender)> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == TraceFormat::Csv {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+
+        let (sender, receiver) = unbounded();
+        let thread_hdl = Builder::new()
+            .name("solana-ledger-tool-tracer".to_string())
+            .spawn(move || Self::trace_transactions(receiver, writer, format))?;
+        Ok((Self { thread_hdl }, sender))
+    }
+
+    fn trace_transactions(
+        receiver: Receiver<TransactionStatusBatch>,
+        mut writer: BufWriter<File>,
+        format: TraceFormat,
+    ) -> io::Result<TraceSummary> {
+        let mut summary = TraceSummary::default();
+        // The blockstore processor drops its senders once replay is complete
+        for batch in receiver.iter() {
+            Self::trace_batch(batch, &mut writer, format, &mut summary)?;
+        }
+        writer.flush()?;
+        Ok(summary)
+    }
+
+    fn trace_batch(
+        batch: TransactionStatusBatch,
+        writer: &mut impl Write,
+        format: TraceFormat,
+        summary: &mut TraceSummary,
+    ) -> io::Result<()> {
+        let TransactionStatusBatch {
+            bank,
+            transactions,
+            iteration_order,
+            statuses,
+            execution_traces,
+            ..
+        } = batch;
+
+        let slot = bank.slot();
+        for ((_, transaction), (status, hash_age_kind), execution_trace) in izip!(
+            OrderedIterator::new(&transactions, iteration_order.as_deref()),
+            statuses,
+            execution_traces
+        ) {
+            // Transactions that failed to load were never executed
+            let execution_trace = match execution_trace {
+                Some(execution_trace) => execution_trace,
+                None => continue,
+            };
+
+            let fee = match hash_age_kind {
+                Some(HashAgeKind::DurableNonce(_, account)) => {
+                    nonce::utils::fee_calculator_of(&account)
+                }
+                _ => bank.get_fee_calculator(&transaction.message().recent_blockhash),
+            }
+            .map(|fee_calculator| fee_calculator.calculate_fee(transaction.message()))
+            .unwrap_or_default();
+            let signature = transaction
+                .signatures
+                .get(0)
+                .map(|signature| signature.to_string())
+                .unwrap_or_default();
+            let (writable_keys, readonly_keys) =
+                transaction.message.get_account_keys_by_lock_type();
+
+            summary.transactions += 1;
+            // Execution stops at the failing instruction, so it is always the last one traced
+            let failed_instruction = if status.is_err() {
+                summary.failed_transactions += 1;
+                execution_trace.instructions.len().checked_sub(1)
+            } else {
+                None
+            };
+            let status = match status {
+                Ok(()) => "Ok".to_string(),
+                Err(err) => format!("{:?}", err),
+            };
+            for (index, instruction) in execution_trace.instructions.iter().enumerate() {
+                let stats = summary
+                    .programs
+                    .entry(instruction.program_id)
+                    .or_insert_with(ProgramStats::new);
+                stats.instructions += 1;
+                if failed_instruction == Some(index) {
+                    stats.failed_instructions += 1;
+                }
+                stats.total_compute_units += instruction.compute_units;
+                stats.total_execution_us += instruction.execution_us;
+                let _ = stats.compute_units.increment(instruction.compute_units);
+                let _ = stats.execution_us.increment(instruction.execution_us);
+            }
+
+            match format {
+                TraceFormat::Json => {
+                    let instructions: Vec<_> = execution_trace
+                        .instructions
+                        .iter()
+                        .map(|instruction| {
+                            json!({
+                                "programId": instruction.program_id.to_string(),
+                                "computeUnits": instruction.compute_units,
+                                "executionUs": instruction.execution_us,
+                            })
+                        })
+                        .collect();
+                    let record = json!({
+                        "slot": slot,
+                        "signature": signature,
+                        "fee": fee,
+                        "status": status,
+                        "executionUs": execution_trace.execution_us,
+                        "computeUnits": execution_trace.compute_units(),
+                        "instructions": instructions,
+                        "writableAccounts": keys_to_strings(&writable_keys),
+                        "readonlyAccounts": keys_to_strings(&readonly_keys),
+                    });
+                    serde_json::to_writer(&mut *writer, &record)?;
+                    writeln!(writer)?;
+                }
+                TraceFormat::Csv => {
+                    let instructions = &execution_trace.instructions;
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{},{},{},{},{},{},{}",
+                        slot,
+                        signature,
+                        fee,
+                        csv_escape(&status),
+                        execution_trace.execution_us,
+                        execution_trace.compute_units(),
+                        join(instructions.iter().map(|i| i.program_id)),
+                        join(instructions.iter().map(|i| i.compute_units)),
+                        join(instructions.iter().map(|i| i.execution_us)),
+                        join(writable_keys.iter()),
+                        join(readonly_keys.iter()),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for all traced transactions to be written and prints the per-program summary
+    pub fn join(self) -> io::Result<()> {
+        let summary = self
+            .thread_hdl
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "tracer panicked")))?;
+        print_summary(&summary);
+        Ok(())
+    }
+}
+
+fn keys_to_strings(keys: &[&Pubkey]) -> Vec<String> {
+    keys.iter().map(|key| key.to_string()).collect()
+}
+
+// Multiple values within a single CSV field are separated by semicolons
+fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn percentiles(histogram: &histogram::Histogram) -> String {
+    let percentile = |p| histogram.percentile(p).unwrap_or_default();
+    format!(
+        "{}/{}/{}/{}",
+        percentile(50.0),
+        percentile(90.0),
+        percentile(99.0),
+        histogram.maximum().unwrap_or_default(),
+    )
+}
+
+fn print_summary(summary: &TraceSummary) {
+    println!(
+        "Traced {} transactions, {} failed",
+        summary.transactions, summary.failed_transactions
+    );
+
+    let mut programs: Vec<_> = summary.programs.iter().collect();
+    programs.sort_by(|a, b| b.1.total_compute_units.cmp(&a.1.total_compute_units));
+
+    println!(
+        "{:<44} {:>10} {:>8} {:>14} {:>28} {:>14} {:>28}",
+        "Program",
+        "Instrs",
+        "Failed",
+        "Total CUs",
+        "CUs p50/p90/p99/max",
+        "Total us",
+        "us p50/p90/p99/max",
+    );
+    for (program_id, stats) in programs {
+        println!(
+            "{:<44} {:>10} {:>8} {:>14} {:>28} {:>14} {:>28}",
+            program_id.to_string(),
+            stats.instructions,
+            stats.failed_instructions,
+            stats.total_compute_units,
+            percentiles(&stats.compute_units),
+            stats.total_execution_us,
+            percentiles(&stats.execution_us),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use solana_runtime::{
+        bank::{Bank, TransactionBalancesSet},
+        execution_trace::{InstructionTrace, TransactionExecutionTrace},
+        genesis_utils::create_genesis_config,
+    };
+    use solana_sdk::{
+        instruction::InstructionError,
+        system_program, system_transaction,
+        transaction::{Transaction, TransactionError},
+    };
+    use std::sync::Arc;
+
+    fn new_execution_trace(compute_units: u64, execution_us: u64) -> TransactionExecutionTrace {
+        TransactionExecutionTrace {
+            instructions: vec![InstructionTrace {
+                program_id: system_program::id(),
+                compute_units,
+                execution_us,
+            }],
+            execution_us: execution_us + 1,
+        }
+    }
+
+    // Returns a batch of a successful, a failed and a not executed transaction, along with the
+    // transactions
+    fn new_batch() -> (TransactionStatusBatch, Vec<Transaction>) {
+        let genesis_config_info = create_genesis_config(10_000);
+        let bank = Arc::new(Bank::new(&genesis_config_info.genesis_config));
+        let mint_keypair = genesis_config_info.mint_keypair;
+        let transactions: Vec<_> = (1..=3)
+            .map(|lamports| {
+                system_transaction::transfer(
+                    &mint_keypair,
+                    &solana_sdk::pubkey::new_rand(),
+                    lamports,
+                    bank.last_blockhash(),
+                )
+            })
+            .collect();
+        let failure = TransactionError::InstructionError(0, InstructionError::Custom(1));
+        let batch = TransactionStatusBatch {
+            bank,
+            transactions: transactions.clone(),
+            iteration_order: None,
+            statuses: vec![
+                (Ok(()), Some(HashAgeKind::Extant)),
+                (Err(failure), Some(HashAgeKind::Extant)),
+                (Err(TransactionError::AccountNotFound), None),
+            ],
+            balances: TransactionBalancesSet::new(vec![], vec![]),
+            inner_instructions: vec![],
+            transaction_logs: vec![],
+            execution_traces: vec![
+                Some(new_execution_trace(150, 10)),
+                Some(new_execution_trace(50, 20)),
+                None,
+            ],
+        };
+        (batch, transactions)
+    }
+
+    fn trace_batch(format: TraceFormat) -> (Vec<String>, TraceSummary, Vec<Transaction>) {
+        let (batch, transactions) = new_batch();
+        let mut output = vec![];
+        let mut summary = TraceSummary::default();
+        TransactionTracer::trace_batch(batch, &mut output, format, &mut summary).unwrap();
+        let lines = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        (lines, summary, transactions)
+    }
+
+    #[test]
+    fn test_trace_batch_json() {
+        let (lines, summary, transactions) = trace_batch(TraceFormat::Json);
+        // The transaction which was not executed is left out
+        assert_eq!(lines.len(), 2);
+
+        let record: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(record["slot"], 0);
+        assert_eq!(
+            record["signature"],
+            transactions[0].signatures[0].to_string()
+        );
+        assert_eq!(record["status"], "Ok");
+        assert_eq!(record["executionUs"], 11);
+        assert_eq!(record["computeUnits"], 150);
+        assert_eq!(
+            record["instructions"][0]["programId"],
+            system_program::id().to_string()
+        );
+        assert_eq!(record["instructions"][0]["computeUnits"], 150);
+        assert_eq!(record["instructions"][0]["executionUs"], 10);
+        assert_eq!(
+            record["writableAccounts"],
+            serde_json::json!(transactions[0].message.account_keys[..2]
+                .iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<_>>())
+        );
+        assert_eq!(
+            record["readonlyAccounts"],
+            serde_json::json!([system_program::id().to_string()])
+        );
+
+        let record: Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(record["status"], "InstructionError(0, Custom(1))");
+        assert_eq!(record["computeUnits"], 50);
+
+        assert_eq!(summary.transactions, 2);
+        assert_eq!(summary.failed_transactions, 1);
+        let stats = &summary.programs[&system_program::id()];
+        assert_eq!(stats.instructions, 2);
+        assert_eq!(stats.failed_instructions, 1);
+        assert_eq!(stats.total_compute_units, 200);
+        assert_eq!(stats.total_execution_us, 30);
+    }
+
+    #[test]
+    fn test_trace_batch_csv() {
+        let (lines, _, transactions) = trace_batch(TraceFormat::Csv);
+        assert_eq!(lines.len(), 2);
+
+        let keys = &transactions[0].message.account_keys;
+        assert_eq!(
+            lines[0],
+            format!(
+                "0,{},0,Ok,11,150,{},150,10,{};{},{}",
+                transactions[0].signatures[0],
+                system_program::id(),
+                keys[0],
+                keys[1],
+                system_program::id(),
+            )
+        );
+        let keys = &transactions[1].message.account_keys;
+        assert_eq!(
+            lines[1],
+            format!(
+                "0,{},0,\"InstructionError(0, Custom(1))\",21,50,{},50,20,{};{},{}",
+                transactions[1].signatures[0],
+                system_program::id(),
+                keys[0],
+                keys[1],
+                system_program::id(),
+            )
+        );
+        // Every record has as many fields as the header
+        assert_eq!(CSV_HEADER.split(',').count(), 11);
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("Ok"), "Ok");
+        assert_eq!(csv_escape(""), "");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
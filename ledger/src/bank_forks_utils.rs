@@ -102,11 +102,12 @@ pub fn load(
 
     info!("Processing ledger from genesis");
     to_loadresult(
-        blockstore_processor::process_blockstore(
+        blockstore_processor::process_blockstore_from_genesis(
             &genesis_config,
             &blockstore,
             account_paths,
             process_options,
+            transaction_status_sender,
         ),
         None,
     )
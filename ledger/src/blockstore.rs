@@ -12,7 +12,7 @@ use crate::{
     erasure::ErasureConfig,
     leader_schedule_cache::LeaderScheduleCache,
     next_slots_iterator::NextSlotsIterator,
-    shred::{Result as ShredResult, Shred, Shredder},
+    shred::{merkle_proof_size, Result as ShredResult, Shred, Shredder},
 };
 use bincode::deserialize;
 use log::*;
@@ -20,7 +20,6 @@ use rayon::{
     iter::{IntoParallelRefIterator, ParallelIterator},
     ThreadPool,
 };
-use rocksdb::DBRawIterator;
 use solana_measure::measure::Measure;
 use solana_metrics::{datapoint_debug, datapoint_error};
 use solana_rayon_threadlimit::get_thread_count;
@@ -47,6 +46,7 @@ use std::{
     cell::RefCell,
     cmp,
     collections::{HashMap, HashSet},
+    convert::TryInto,
     fs,
     io::{Error as IOError, ErrorKind},
     path::{Path, PathBuf},
@@ -60,6 +60,7 @@ use std::{
 use thiserror::Error;
 use trees::{Tree, TreeWalk};
 
+pub mod blockstore_check;
 pub mod blockstore_purge;
 
 pub const BLOCKSTORE_DIRECTORY: &str = "rocksdb";
@@ -138,6 +139,7 @@ pub struct Blockstore {
     rewards_cf: LedgerColumn<cf::Rewards>,
     blocktime_cf: LedgerColumn<cf::Blocktime>,
     perf_samples_cf: LedgerColumn<cf::PerfSamples>,
+    bank_hash_cf: LedgerColumn<cf::BankHash>,
     last_root: Arc<RwLock<Slot>>,
     insert_shreds_lock: Arc<Mutex<()>>,
     pub new_shreds_signals: Vec<SyncSender<bool>>,
@@ -260,6 +262,11 @@ impl Blockstore {
         Self::do_open(ledger_path, access_type, recovery_mode)
     }
 
+    /// Opens an empty Blockstore which is never written to disk and goes away when dropped
+    pub fn open_in_memory() -> Result<Blockstore> {
+        Self::from_database(Database::open_in_memory())
+    }
+
     fn do_open(
         ledger_path: &Path,
         access_type: AccessType,
@@ -274,7 +281,13 @@ impl Blockstore {
         let mut measure = Measure::start("open");
         info!("Opening database at {:?}", blockstore_path);
         let db = Database::open(&blockstore_path, access_type, recovery_mode)?;
+        let blockstore = Self::from_database(db)?;
+        measure.stop();
+        info!("{:?} {}", blockstore_path, measure);
+        Ok(blockstore)
+    }
 
+    fn from_database(db: Database) -> Result<Blockstore> {
         // Create the metadata column family
         let meta_cf = db.column();
 
@@ -297,6 +310,7 @@ impl Blockstore {
         let rewards_cf = db.column();
         let blocktime_cf = db.column();
         let perf_samples_cf = db.column();
+        let bank_hash_cf = db.column();
 
         let db = Arc::new(db);
 
@@ -324,8 +338,6 @@ impl Blockstore {
             })
             .unwrap_or(0);
 
-        measure.stop();
-        info!("{:?} {}", blockstore_path, measure);
         let blockstore = Blockstore {
             db,
             meta_cf,
@@ -343,6 +355,7 @@ impl Blockstore {
             rewards_cf,
             blocktime_cf,
             perf_samples_cf,
+            bank_hash_cf,
             new_shreds_signals: vec![],
             completed_slots_senders: vec![],
             insert_shreds_lock: Arc::new(Mutex::new(())),
@@ -360,19 +373,28 @@ impl Blockstore {
         ledger_path: &Path,
         recovery_mode: Option<BlockstoreRecoveryMode>,
     ) -> Result<BlockstoreSignals> {
-        let mut blockstore =
+        let blockstore =
             Self::open_with_access_type(ledger_path, AccessType::PrimaryOnly, recovery_mode)?;
+        Ok(Self::with_signals(blockstore))
+    }
+
+    /// Same as `open_with_signal()`, for a Blockstore which only lives in memory
+    pub fn open_in_memory_with_signal() -> Result<BlockstoreSignals> {
+        Ok(Self::with_signals(Self::open_in_memory()?))
+    }
+
+    fn with_signals(mut blockstore: Blockstore) -> BlockstoreSignals {
         let (ledger_signal_sender, ledger_signal_receiver) = sync_channel(1);
         let (completed_slots_sender, completed_slots_receiver) =
             sync_channel(MAX_COMPLETED_SLOTS_IN_CHANNEL);
         blockstore.new_shreds_signals = vec![ledger_signal_sender];
         blockstore.completed_slots_senders = vec![completed_slots_sender];
 
-        Ok(BlockstoreSignals {
+        BlockstoreSignals {
             blockstore,
             ledger_signal_receiver,
             completed_slots_receiver,
-        })
+        }
     }
 
     pub fn add_tree(
@@ -1147,6 +1169,16 @@ impl Blockstore {
             return false;
         }
 
+        // Merkle variant coding shreds must carry a proof sized for their
+        // erasure set, otherwise recovery cannot rebuild the tree
+        if let Some(proof_size) = shred.merkle_proof_size() {
+            let fec_set_size = usize::from(shred.coding_header.num_data_shreds)
+                + usize::from(shred.coding_header.num_coding_shreds);
+            if proof_size != merkle_proof_size(fec_set_size) {
+                return false;
+            }
+        }
+
         let set_index = shred.common_header.fec_set_index;
         !(shred.coding_header.num_coding_shreds == 0
             || shred.coding_header.position >= shred.coding_header.num_coding_shreds
@@ -1471,17 +1503,14 @@ impl Blockstore {
     // Given a start and end entry index, find all the missing
     // indexes in the ledger in the range [start_index, end_index)
     // for the slot with the specified slot
-    fn find_missing_indexes<C>(
-        db_iterator: &mut DBRawIterator,
+    fn find_missing_indexes(
+        mut db_iterator: impl Iterator<Item = ((Slot, u64), Box<[u8]>)>,
         slot: Slot,
         first_timestamp: u64,
         start_index: u64,
         end_index: u64,
         max_missing: usize,
-    ) -> Vec<u64>
-    where
-        C: Column<Index = (u64, u64)>,
-    {
+    ) -> Vec<u64> {
         if start_index >= end_index || max_missing == 0 {
             return vec![];
         }
@@ -1490,22 +1519,21 @@ impl Blockstore {
         let ticks_since_first_insert =
             DEFAULT_TICKS_PER_SECOND * (timestamp() - first_timestamp) / 1000;
 
-        // Seek to the first shred with index >= start_index
-        db_iterator.seek(&C::key((slot, start_index)));
-
         // The index of the first missing shred in the slot
         let mut prev_index = start_index;
         'outer: loop {
-            if !db_iterator.valid() {
-                for i in prev_index..end_index {
-                    missing_indexes.push(i);
-                    if missing_indexes.len() == max_missing {
-                        break;
+            let ((current_slot, index), shred) = match db_iterator.next() {
+                Some(entry) => entry,
+                None => {
+                    for i in prev_index..end_index {
+                        missing_indexes.push(i);
+                        if missing_indexes.len() == max_missing {
+                            break;
+                        }
                     }
+                    break;
                 }
-                break;
-            }
-            let (current_slot, index) = C::index(&db_iterator.key().expect("Expect a valid key"));
+            };
 
             let current_index = {
                 if current_slot > slot {
@@ -1517,9 +1545,7 @@ impl Blockstore {
 
             let upper_index = cmp::min(current_index, end_index);
             // the tick that will be used to figure out the timeout for this hole
-            let reference_tick = u64::from(Shred::reference_tick_from_data(
-                &db_iterator.value().expect("couldn't read value"),
-            ));
+            let reference_tick = u64::from(Shred::reference_tick_from_data(&shred));
 
             if ticks_since_first_insert < reference_tick + MAX_TURBINE_DELAY_IN_TICKS {
                 // The higher index holes have not timed out yet
@@ -1541,7 +1567,6 @@ impl Blockstore {
             }
 
             prev_index = current_index + 1;
-            db_iterator.next();
         }
 
         missing_indexes
@@ -1555,12 +1580,13 @@ impl Blockstore {
         end_index: u64,
         max_missing: usize,
     ) -> Vec<u64> {
-        if let Ok(mut db_iterator) = self
-            .db
-            .raw_iterator_cf(self.db.cf_handle::<cf::ShredData>())
-        {
-            Self::find_missing_indexes::<cf::ShredData>(
-                &mut db_iterator,
+        // Start from the first shred with index >= start_index
+        if let Ok(db_iterator) = self.db.iter::<cf::ShredData>(IteratorMode::From(
+            (slot, start_index),
+            IteratorDirection::Forward,
+        )) {
+            Self::find_missing_indexes(
+                db_iterator,
                 slot,
                 first_timestamp,
                 start_index,
@@ -1745,9 +1771,9 @@ impl Blockstore {
         self.transaction_status_index_cf
             .put(1, &TransactionStatusIndexMeta::default())?;
         // This dummy status improves compaction performance
-        self.transaction_status_cf.put(
+        self.transaction_status_cf.put_protobuf(
             cf::TransactionStatus::as_index(2),
-            &TransactionStatusMeta::default(),
+            &TransactionStatusMeta::default().into(),
         )?;
         self.address_signatures_cf.put(
             cf::AddressSignatures::as_index(2),
@@ -1824,12 +1850,15 @@ impl Blockstore {
         index: (Signature, Slot),
     ) -> Result<Option<TransactionStatusMeta>> {
         let (signature, slot) = index;
-        let result = self.transaction_status_cf.get((0, signature, slot))?;
+        let mut result = self
+            .transaction_status_cf
+            .get_protobuf_or_bincode::<TransactionStatusMeta>((0, signature, slot))?;
         if result.is_none() {
-            Ok(self.transaction_status_cf.get((1, signature, slot))?)
-        } else {
-            Ok(result)
+            result = self
+                .transaction_status_cf
+                .get_protobuf_or_bincode::<TransactionStatusMeta>((1, signature, slot))?;
         }
+        Ok(result.map(|status| status.try_into()).transpose()?)
     }
 
     pub fn write_transaction_status(
@@ -1846,7 +1875,7 @@ impl Blockstore {
             self.active_transaction_status_index.write().unwrap();
         let primary_index = self.get_primary_index(slot, &mut w_active_transaction_status_index)?;
         self.transaction_status_cf
-            .put((primary_index, signature, slot), status)?;
+            .put_protobuf((primary_index, signature, slot), &status.clone().into())?;
         for address in writable_keys {
             self.address_signatures_cf.put(
                 (primary_index, *address, slot, signature),
@@ -1880,7 +1909,10 @@ impl Blockstore {
                     break;
                 }
                 if self.is_root(slot) {
-                    let status: TransactionStatusMeta = deserialize(&data)?;
+                    let status: TransactionStatusMeta = self
+                        .transaction_status_cf
+                        .deserialize_protobuf_or_bincode::<TransactionStatusMeta>(&data)?
+                        .try_into()?;
                     return Ok((Some((slot, status)), counter));
                 }
             }
@@ -2271,6 +2303,18 @@ impl Blockstore {
         self.rewards_cf.put_protobuf(index, &rewards)
     }
 
+    /// Re-encodes the transaction statuses and rewards written as bincode by older versions in
+    /// protobuf. Returns the number of (transaction status, rewards) entries converted.
+    pub fn migrate_to_protobuf(&self) -> Result<(usize, usize)> {
+        let transaction_statuses = self
+            .transaction_status_cf
+            .migrate_bincode_to_protobuf::<TransactionStatusMeta>()?;
+        let rewards = self
+            .rewards_cf
+            .migrate_bincode_to_protobuf::<StoredExtendedRewards>()?;
+        Ok((transaction_statuses, rewards))
+    }
+
     fn get_block_timestamps(&self, slot: Slot) -> Result<Vec<(Pubkey, (Slot, UnixTimestamp))>> {
         let slot_entries = self.get_slot_entries(slot, 0)?;
         Ok(slot_entries
@@ -2580,6 +2624,18 @@ impl Blockstore {
         self.dead_slots_cf.put(slot, &true)
     }
 
+    pub fn insert_bank_hash(&self, slot: Slot, frozen_hash: Hash) {
+        let data = FrozenHashVersioned::Current(FrozenHashStatus { frozen_hash });
+        self.bank_hash_cf.put(slot, &data).unwrap()
+    }
+
+    pub fn get_bank_hash(&self, slot: Slot) -> Option<Hash> {
+        self.bank_hash_cf
+            .get(slot)
+            .expect("fetch from BankHash column family failed")
+            .map(|frozen_hash_versioned| frozen_hash_versioned.frozen_hash())
+    }
+
     pub fn store_duplicate_slot(&self, slot: Slot, shred1: Vec<u8>, shred2: Vec<u8>) -> Result<()> {
         let duplicate_slot_proof = DuplicateSlotProof::new(shred1, shred2);
         self.duplicate_slots_cf.put(slot, &duplicate_slot_proof)
@@ -2656,6 +2712,21 @@ impl Blockstore {
         self.db.storage_size()
     }
 
+    /// Estimated size of the ledger once everything purged has been compacted away
+    pub fn live_data_size(&self) -> Result<u64> {
+        self.db.live_data_size()
+    }
+
+    /// Estimated live data sizes of the transaction status, address signatures and rewards
+    /// columns, which are purged along with the shreds of a slot
+    pub fn transaction_history_live_data_sizes(&self) -> Result<(u64, u64, u64)> {
+        Ok((
+            self.transaction_status_cf.live_data_size()?,
+            self.address_signatures_cf.live_data_size()?,
+            self.rewards_cf.live_data_size()?,
+        ))
+    }
+
     pub fn is_primary_access(&self) -> bool {
         self.db.is_primary_access()
     }
@@ -3896,6 +3967,43 @@ pub mod tests {
         }
     */
 
+    #[test]
+    fn test_in_memory_blockstore() {
+        let blockstore = Blockstore::open_in_memory().unwrap();
+        let (shreds, entries) = make_slot_entries(1, 0, 10);
+        let num_shreds = shreds.len() as u64;
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+        assert_eq!(blockstore.get_slot_entries(1, 0).unwrap(), entries);
+        assert_eq!(blockstore.meta(1).unwrap().unwrap().consumed, num_shreds);
+        assert!(blockstore.storage_size().unwrap() > 0);
+
+        blockstore.set_roots(&[1]).unwrap();
+        assert!(blockstore.is_root(1));
+
+        blockstore.purge_slots(0, 1, PurgeType::Exact);
+        assert!(blockstore.meta(1).unwrap().is_none());
+        assert!(blockstore.get_slot_entries(1, 0).unwrap().is_empty());
+        assert!(!blockstore.is_root(1));
+        assert!(blockstore.data_shred_cf.is_empty().unwrap());
+    }
+
+    #[test]
+    fn test_in_memory_blockstore_signals() {
+        let BlockstoreSignals {
+            blockstore,
+            ledger_signal_receiver,
+            completed_slots_receiver,
+        } = Blockstore::open_in_memory_with_signal().unwrap();
+        let (shreds, _) = make_slot_entries(0, 0, 10);
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+        let timer = Duration::new(1, 0);
+        assert!(ledger_signal_receiver.recv_timeout(timer).is_ok());
+        assert_eq!(
+            completed_slots_receiver.recv_timeout(timer).unwrap(),
+            vec![0]
+        );
+    }
+
     #[test]
     pub fn test_get_slot_entries1() {
         let blockstore_path = get_tmp_ledger_path!();
@@ -5659,7 +5767,7 @@ pub mod tests {
                 let signature = transaction.signatures[0];
                 ledger
                     .transaction_status_cf
-                    .put(
+                    .put_protobuf(
                         (0, signature, slot),
                         &TransactionStatusMeta {
                             status: Ok(()),
@@ -5668,12 +5776,13 @@ pub mod tests {
                             post_balances: post_balances.clone(),
                             inner_instructions: Some(vec![]),
                             log_messages: Some(vec![]),
-                        },
+                        }
+                        .into(),
                     )
                     .unwrap();
                 ledger
                     .transaction_status_cf
-                    .put(
+                    .put_protobuf(
                         (0, signature, slot + 1),
                         &TransactionStatusMeta {
                             status: Ok(()),
@@ -5682,7 +5791,8 @@ pub mod tests {
                             post_balances: post_balances.clone(),
                             inner_instructions: Some(vec![]),
                             log_messages: Some(vec![]),
-                        },
+                        }
+                        .into(),
                     )
                     .unwrap();
                 TransactionWithStatusMeta {
@@ -5887,13 +5997,13 @@ pub mod tests {
 
             // result not found
             assert!(transaction_status_cf
-                .get((0, Signature::default(), 0))
+                .get_protobuf((0, Signature::default(), 0))
                 .unwrap()
                 .is_none());
 
             // insert value
             assert!(transaction_status_cf
-                .put(
+                .put_protobuf(
                     (0, Signature::default(), 0),
                     &TransactionStatusMeta {
                         status: solana_sdk::transaction::Result::<()>::Err(
@@ -5904,7 +6014,8 @@ pub mod tests {
                         post_balances: post_balances_vec.clone(),
                         inner_instructions: Some(inner_instructions_vec.clone()),
                         log_messages: Some(log_messages_vec.clone()),
-                    },
+                    }
+                    .into(),
                 )
                 .is_ok());
 
@@ -5917,8 +6028,10 @@ pub mod tests {
                 inner_instructions,
                 log_messages,
             } = transaction_status_cf
-                .get((0, Signature::default(), 0))
+                .get_protobuf_or_bincode::<TransactionStatusMeta>((0, Signature::default(), 0))
                 .unwrap()
+                .unwrap()
+                .try_into()
                 .unwrap();
             assert_eq!(status, Err(TransactionError::AccountNotFound));
             assert_eq!(fee, 5u64);
@@ -5929,7 +6042,7 @@ pub mod tests {
 
             // insert value
             assert!(transaction_status_cf
-                .put(
+                .put_protobuf(
                     (0, Signature::new(&[2u8; 64]), 9),
                     &TransactionStatusMeta {
                         status: solana_sdk::transaction::Result::<()>::Ok(()),
@@ -5938,7 +6051,8 @@ pub mod tests {
                         post_balances: post_balances_vec.clone(),
                         inner_instructions: Some(inner_instructions_vec.clone()),
                         log_messages: Some(log_messages_vec.clone()),
-                    },
+                    }
+                    .into(),
                 )
                 .is_ok());
 
@@ -5951,8 +6065,14 @@ pub mod tests {
                 inner_instructions,
                 log_messages,
             } = transaction_status_cf
-                .get((0, Signature::new(&[2u8; 64]), 9))
+                .get_protobuf_or_bincode::<TransactionStatusMeta>((
+                    0,
+                    Signature::new(&[2u8; 64]),
+                    9,
+                ))
+                .unwrap()
                 .unwrap()
+                .try_into()
                 .unwrap();
 
             // deserialize
@@ -6204,46 +6324,46 @@ pub mod tests {
             //   signature4 in 2 non-roots,
             //   extra entries
             transaction_status_cf
-                .put((0, signature2, 1), &status)
+                .put_protobuf((0, signature2, 1), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((0, signature2, 2), &status)
+                .put_protobuf((0, signature2, 2), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((0, signature4, 0), &status)
+                .put_protobuf((0, signature4, 0), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((0, signature4, 1), &status)
+                .put_protobuf((0, signature4, 1), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((0, signature5, 0), &status)
+                .put_protobuf((0, signature5, 0), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((0, signature5, 1), &status)
+                .put_protobuf((0, signature5, 1), &status.clone().into())
                 .unwrap();
 
             // Initialize index 1, including:
             //   signature4 in non-root and root,
             //   extra entries
             transaction_status_cf
-                .put((1, signature4, 1), &status)
+                .put_protobuf((1, signature4, 1), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((1, signature4, 2), &status)
+                .put_protobuf((1, signature4, 2), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((1, signature5, 0), &status)
+                .put_protobuf((1, signature5, 0), &status.clone().into())
                 .unwrap();
 
             transaction_status_cf
-                .put((1, signature5, 1), &status)
+                .put_protobuf((1, signature5, 1), &status.clone().into())
                 .unwrap();
 
             blockstore.set_roots(&[2]).unwrap();
@@ -6327,7 +6447,7 @@ pub mod tests {
                 let signature = transaction.signatures[0];
                 blockstore
                     .transaction_status_cf
-                    .put(
+                    .put_protobuf(
                         (0, signature, slot),
                         &TransactionStatusMeta {
                             status: Ok(()),
@@ -6336,7 +6456,8 @@ pub mod tests {
                             post_balances: post_balances.clone(),
                             inner_instructions: inner_instructions.clone(),
                             log_messages: log_messages.clone(),
-                        },
+                        }
+                        .into(),
                     )
                     .unwrap();
                 TransactionWithStatusMeta {
@@ -6778,7 +6899,7 @@ pub mod tests {
                     vec![CompiledInstruction::new(1, &(), vec![0])],
                 );
                 transaction_status_cf
-                    .put(
+                    .put_protobuf(
                         (0, transaction.signatures[0], slot),
                         &TransactionStatusMeta {
                             status: solana_sdk::transaction::Result::<()>::Err(
@@ -6789,7 +6910,8 @@ pub mod tests {
                             post_balances: vec![],
                             inner_instructions: Some(vec![]),
                             log_messages: Some(vec![]),
-                        },
+                        }
+                        .into(),
                     )
                     .unwrap();
                 transactions.push(transaction);
@@ -7094,6 +7216,30 @@ pub mod tests {
         assert_eq!(num_coding_in_index, num_coding);
     }
 
+    #[test]
+    fn test_bank_hash() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let hash = Hash::new_unique();
+            assert!(blockstore.get_bank_hash(1).is_none());
+            blockstore.insert_bank_hash(1, hash);
+            assert_eq!(blockstore.get_bank_hash(1), Some(hash));
+
+            // A later version of the slot replaces the earlier one
+            let hash2 = Hash::new_unique();
+            blockstore.insert_bank_hash(1, hash2);
+            assert_eq!(blockstore.get_bank_hash(1), Some(hash2));
+
+            // Dumping the slot clears its bank hash
+            let (shreds, _) = make_slot_entries(1, 0, 1);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            blockstore.clear_unconfirmed_slot(1);
+            assert!(blockstore.get_bank_hash(1).is_none());
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_duplicate_slot() {
         let slot = 0;
@@ -7288,4 +7434,61 @@ pub mod tests {
         }
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
+
+    #[test]
+    fn test_transaction_status_protobuf_backward_compatability() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let status = TransactionStatusMeta {
+                status: Err(TransactionError::AccountNotFound),
+                fee: 42,
+                pre_balances: vec![1, 2, 3],
+                post_balances: vec![3, 2, 1],
+                inner_instructions: Some(vec![InnerInstructions {
+                    index: 0,
+                    instructions: vec![CompiledInstruction::new(1, &(), vec![0])],
+                }]),
+                log_messages: Some(vec![String::from("Test message\n")]),
+            };
+            let signature = Signature::new(&[1u8; 64]);
+            for slot in 0..2 {
+                let data = serialize(&status).unwrap();
+                blockstore
+                    .transaction_status_cf
+                    .put_bytes((0, signature, slot), &data)
+                    .unwrap();
+            }
+            for slot in 2..4 {
+                blockstore
+                    .transaction_status_cf
+                    .put_protobuf((0, signature, slot), &status.clone().into())
+                    .unwrap();
+            }
+            for slot in 0..4 {
+                assert_eq!(
+                    blockstore
+                        .read_transaction_status((signature, slot))
+                        .unwrap()
+                        .unwrap(),
+                    status
+                );
+            }
+
+            // Only the bincode entries get rewritten
+            assert_eq!(blockstore.migrate_to_protobuf().unwrap(), (2, 0));
+            assert_eq!(blockstore.migrate_to_protobuf().unwrap(), (0, 0));
+            for slot in 0..4 {
+                assert_eq!(
+                    blockstore
+                        .transaction_status_cf
+                        .get_protobuf((0, signature, slot))
+                        .unwrap()
+                        .unwrap(),
+                    generated::TransactionStatusMeta::from(status.clone())
+                );
+            }
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
 }
@@ -0,0 +1,513 @@
+use super::*;
+use std::collections::BTreeSet;
+
+/// An inconsistency between the shred columns of a slot and the metadata describing them
+#[derive(Clone, Debug, PartialEq)]
+pub enum SlotInconsistency {
+    /// Shreds or an `Index` exist for the slot, but no `SlotMeta`
+    MissingSlotMeta,
+    /// Shreds exist for the slot, but no `Index`
+    MissingIndex,
+    /// The `Index` marks the data shred as present, but it is not in the blockstore
+    IndexedDataShredMissing(u64),
+    /// The data shred is in the blockstore, but the `Index` does not mark it present
+    UnindexedDataShred(u64),
+    IndexedCodingShredMissing(u64),
+    UnindexedCodingShred(u64),
+    /// The data shred does not deserialize, or belongs to another slot or index
+    InvalidDataShred(u64),
+    /// A data shred exists past the shred flagged as the last in the slot
+    DataShredPastLastIndex(u64),
+    /// `SlotMeta::consumed` does not match the data shreds in the blockstore
+    ConsumedMismatch {
+        stored: u64,
+        actual: u64,
+    },
+    /// `SlotMeta::received` does not match the data shreds in the blockstore
+    ReceivedMismatch {
+        stored: u64,
+        actual: u64,
+    },
+    /// An `ErasureMeta` without any of its coding shreds in the blockstore
+    OrphanedErasureMeta(u64),
+    /// The missing data shreds of this erasure set can be recovered from coding shreds
+    RecoverableErasureSet(u64),
+    /// The erasure set is missing data shreds and needs this many more shreds to recover
+    UnrecoverableErasureSet {
+        set_index: u64,
+        num_needed: usize,
+    },
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SlotCheck {
+    pub slot: Slot,
+    pub inconsistencies: Vec<SlotInconsistency>,
+    /// The slot is missing data shreds that cannot be recovered from the blockstore
+    pub is_unrecoverable: bool,
+}
+
+impl Blockstore {
+    /// Checks every slot in [from_slot, to_slot] that has shreds or shred metadata, returning
+    /// only the slots with inconsistencies
+    pub fn check_slots(&self, from_slot: Slot, to_slot: Slot) -> Result<Vec<SlotCheck>> {
+        let mut slots = BTreeSet::new();
+        for (slot, _) in self
+            .db
+            .iter::<cf::SlotMeta>(IteratorMode::From(from_slot, IteratorDirection::Forward))?
+            .take_while(|(slot, _)| *slot <= to_slot)
+        {
+            slots.insert(slot);
+        }
+        for (slot, _) in self
+            .db
+            .iter::<cf::Index>(IteratorMode::From(from_slot, IteratorDirection::Forward))?
+            .take_while(|(slot, _)| *slot <= to_slot)
+        {
+            slots.insert(slot);
+        }
+
+        let mut slot_checks = vec![];
+        for slot in slots {
+            let slot_check = self.check_slot(slot)?;
+            if !slot_check.inconsistencies.is_empty() {
+                slot_checks.push(slot_check);
+            }
+        }
+        Ok(slot_checks)
+    }
+
+    pub fn check_slot(&self, slot: Slot) -> Result<SlotCheck> {
+        let mut inconsistencies = vec![];
+        let slot_meta = self.meta_cf.get(slot)?;
+        let index = self.index_cf.get(slot)?;
+
+        let (data_indexes, invalid_data_indexes) = self.slot_data_indexes(slot)?;
+        inconsistencies.extend(
+            invalid_data_indexes
+                .into_iter()
+                .map(SlotInconsistency::InvalidDataShred),
+        );
+        let coding_indexes = self.slot_coding_indexes(slot)?;
+        let has_shreds = !data_indexes.is_empty() || !coding_indexes.is_empty();
+
+        if slot_meta.is_none() && (index.is_some() || has_shreds) {
+            inconsistencies.push(SlotInconsistency::MissingSlotMeta);
+        }
+        match &index {
+            Some(index) => {
+                Self::check_shred_index(
+                    index.data(),
+                    &data_indexes,
+                    SlotInconsistency::IndexedDataShredMissing,
+                    SlotInconsistency::UnindexedDataShred,
+                    &mut inconsistencies,
+                );
+                Self::check_shred_index(
+                    index.coding(),
+                    &coding_indexes,
+                    SlotInconsistency::IndexedCodingShredMissing,
+                    SlotInconsistency::UnindexedCodingShred,
+                    &mut inconsistencies,
+                );
+            }
+            None if has_shreds => inconsistencies.push(SlotInconsistency::MissingIndex),
+            None => (),
+        }
+
+        if let Some(slot_meta) = &slot_meta {
+            let (consumed, received) = Self::consumed_and_received(&data_indexes);
+            if slot_meta.consumed != consumed {
+                inconsistencies.push(SlotInconsistency::ConsumedMismatch {
+                    stored: slot_meta.consumed,
+                    actual: consumed,
+                });
+            }
+            if slot_meta.received != received {
+                inconsistencies.push(SlotInconsistency::ReceivedMismatch {
+                    stored: slot_meta.received,
+                    actual: received,
+                });
+            }
+            if slot_meta.last_index != std::u64::MAX {
+                for shred_index in data_indexes.range(slot_meta.last_index + 1..) {
+                    inconsistencies.push(SlotInconsistency::DataShredPastLastIndex(*shred_index));
+                }
+            }
+        }
+
+        // Erasure status is computed from the shreds actually present, not the stored index
+        let actual_index = Self::index_from_shreds(slot, &data_indexes, &coding_indexes);
+        let mut recoverable_data_ranges = vec![];
+        for (set_index, erasure_meta) in self.slot_erasure_metas(slot)? {
+            let num_coding = coding_indexes
+                .range(
+                    erasure_meta.first_coding_index
+                        ..erasure_meta.first_coding_index + erasure_meta.config.num_coding() as u64,
+                )
+                .count();
+            if num_coding == 0 {
+                inconsistencies.push(SlotInconsistency::OrphanedErasureMeta(set_index));
+            }
+            match erasure_meta.status(&actual_index) {
+                ErasureMetaStatus::DataFull => (),
+                ErasureMetaStatus::CanRecover => {
+                    recoverable_data_ranges
+                        .push(set_index..set_index + erasure_meta.config.num_data() as u64);
+                    inconsistencies.push(SlotInconsistency::RecoverableErasureSet(set_index));
+                }
+                ErasureMetaStatus::StillNeed(num_needed) => {
+                    // Sets past the last received data shred may simply not have arrived yet
+                    if slot_meta
+                        .as_ref()
+                        .map(|slot_meta| set_index < slot_meta.received)
+                        .unwrap_or(false)
+                    {
+                        inconsistencies.push(SlotInconsistency::UnrecoverableErasureSet {
+                            set_index,
+                            num_needed,
+                        });
+                    }
+                }
+            }
+        }
+
+        // A slot whose size is known but whose data cannot be completed will never replay.
+        // Recovering some erasure sets does not help if a data shred outside of them is missing.
+        let is_unrecoverable = slot_meta
+            .map(|slot_meta| {
+                slot_meta.last_index != std::u64::MAX
+                    && (0..=slot_meta.last_index)
+                        .filter(|shred_index| !data_indexes.contains(shred_index))
+                        .any(|shred_index| {
+                            !recoverable_data_ranges
+                                .iter()
+                                .any(|range| range.contains(&shred_index))
+                        })
+            })
+            .unwrap_or(false)
+            || inconsistencies
+                .iter()
+                .any(|i| matches!(i, SlotInconsistency::InvalidDataShred(_)));
+
+        Ok(SlotCheck {
+            slot,
+            inconsistencies,
+            is_unrecoverable,
+        })
+    }
+
+    /// Recovers the missing data shreds of every recoverable erasure set in the slot and
+    /// inserts them, returning the number of data shreds that were actually inserted
+    pub fn recover_slot_shreds(&self, slot: Slot) -> Result<usize> {
+        let (data_indexes, _) = self.slot_data_indexes(slot)?;
+        let coding_indexes = self.slot_coding_indexes(slot)?;
+        let index = Self::index_from_shreds(slot, &data_indexes, &coding_indexes);
+
+        let mut recovered_shreds = vec![];
+        for (set_index, erasure_meta) in self.slot_erasure_metas(slot)? {
+            if erasure_meta.status(&index) != ErasureMetaStatus::CanRecover {
+                continue;
+            }
+            let data_range = set_index..set_index + erasure_meta.config.num_data() as u64;
+            let coding_range = erasure_meta.first_coding_index
+                ..erasure_meta.first_coding_index + erasure_meta.config.num_coding() as u64;
+            let mut available_shreds = vec![];
+            for shred_index in data_indexes.range(data_range) {
+                if let Some(payload) = self.data_shred_cf.get_bytes((slot, *shred_index))? {
+                    available_shreds.extend(Shred::new_from_serialized_shred(payload).ok());
+                }
+            }
+            for shred_index in coding_indexes.range(coding_range) {
+                if let Some(payload) = self.code_shred_cf.get_bytes((slot, *shred_index))? {
+                    available_shreds.extend(Shred::new_from_serialized_shred(payload).ok());
+                }
+            }
+            match Shredder::try_recovery(
+                available_shreds,
+                erasure_meta.config.num_data(),
+                erasure_meta.config.num_coding(),
+                set_index as usize,
+                erasure_meta.first_coding_index as usize,
+                slot,
+            ) {
+                Ok(mut shreds) => recovered_shreds.append(&mut shreds),
+                Err(err) => warn!(
+                    "slot {} erasure set {} recovery failed: {:?}",
+                    slot, set_index, err
+                ),
+            }
+        }
+
+        if recovered_shreds.is_empty() {
+            return Ok(0);
+        }
+        // Shreds the stored index already marks present are dropped on insert, so the index
+        // must match the shreds in the blockstore first
+        self.repair_slot_metadata(slot)?;
+        self.insert_shreds(recovered_shreds, None, false)?;
+        let (recovered_data_indexes, _) = self.slot_data_indexes(slot)?;
+        Ok(recovered_data_indexes.difference(&data_indexes).count())
+    }
+
+    /// Rewrites the `Index` and the `consumed` and `received` fields of the `SlotMeta` of the
+    /// slot to match the valid shreds in the blockstore
+    pub fn repair_slot_metadata(&self, slot: Slot) -> Result<()> {
+        let (data_indexes, _) = self.slot_data_indexes(slot)?;
+        let coding_indexes = self.slot_coding_indexes(slot)?;
+
+        let mut write_batch = self.db.batch()?;
+        let index = Self::index_from_shreds(slot, &data_indexes, &coding_indexes);
+        write_batch.put::<cf::Index>(slot, &index)?;
+        if let Some(mut slot_meta) = self.meta_cf.get(slot)? {
+            let (consumed, received) = Self::consumed_and_received(&data_indexes);
+            slot_meta.consumed = consumed;
+            slot_meta.received = received;
+            write_batch.put::<cf::SlotMeta>(slot, &slot_meta)?;
+        }
+        self.db.write(write_batch)
+    }
+
+    /// Returns the indexes of the valid data shreds of the slot, and of the data shreds that
+    /// do not deserialize or belong to another slot or index
+    fn slot_data_indexes(&self, slot: Slot) -> Result<(BTreeSet<u64>, Vec<u64>)> {
+        let mut data_indexes = BTreeSet::new();
+        let mut invalid_data_indexes = vec![];
+        for ((_, shred_index), payload) in self.slot_data_iterator(slot, 0)? {
+            match Shred::new_from_serialized_shred(payload.to_vec()) {
+                Ok(shred) if shred.slot() == slot && u64::from(shred.index()) == shred_index => {
+                    data_indexes.insert(shred_index);
+                }
+                _ => invalid_data_indexes.push(shred_index),
+            }
+        }
+        Ok((data_indexes, invalid_data_indexes))
+    }
+
+    fn slot_coding_indexes(&self, slot: Slot) -> Result<BTreeSet<u64>> {
+        Ok(self
+            .slot_coding_iterator(slot, 0)?
+            .map(|((_, shred_index), _)| shred_index)
+            .collect())
+    }
+
+    fn check_shred_index(
+        shred_index: &ShredIndex,
+        present: &BTreeSet<u64>,
+        missing: fn(u64) -> SlotInconsistency,
+        unindexed: fn(u64) -> SlotInconsistency,
+        inconsistencies: &mut Vec<SlotInconsistency>,
+    ) {
+        let indexed: BTreeSet<u64> = shred_index.present().collect();
+        inconsistencies.extend(indexed.difference(present).map(|i| missing(*i)));
+        inconsistencies.extend(present.difference(&indexed).map(|i| unindexed(*i)));
+    }
+
+    fn consumed_and_received(data_indexes: &BTreeSet<u64>) -> (u64, u64) {
+        let consumed = data_indexes
+            .iter()
+            .enumerate()
+            .take_while(|(i, shred_index)| *i as u64 == **shred_index)
+            .count() as u64;
+        let received = data_indexes
+            .iter()
+            .next_back()
+            .map(|shred_index| shred_index + 1)
+            .unwrap_or(0);
+        (consumed, received)
+    }
+
+    fn index_from_shreds(
+        slot: Slot,
+        data_indexes: &BTreeSet<u64>,
+        coding_indexes: &BTreeSet<u64>,
+    ) -> Index {
+        let mut index = Index::new(slot);
+        index
+            .data_mut()
+            .set_many_present(data_indexes.iter().map(|i| (*i, true)));
+        index
+            .coding_mut()
+            .set_many_present(coding_indexes.iter().map(|i| (*i, true)));
+        index
+    }
+
+    fn slot_erasure_metas(&self, slot: Slot) -> Result<Vec<(u64, ErasureMeta)>> {
+        self.db
+            .iter::<cf::ErasureMeta>(IteratorMode::From((slot, 0), IteratorDirection::Forward))?
+            .take_while(|((erasure_slot, _), _)| *erasure_slot == slot)
+            .map(|((_, set_index), bytes)| {
+                deserialize(&bytes).map(|erasure_meta| (set_index, erasure_meta))
+            })
+            .collect::<bincode::Result<_>>()
+            .map_err(BlockstoreError::from)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{entry::next_entry, get_tmp_ledger_path, shred::MAX_DATA_SHREDS_PER_FEC_BLOCK};
+
+    fn make_slot_shreds(slot: Slot, num_entries: usize) -> (Vec<Shred>, Vec<Shred>) {
+        let entries: Vec<_> = (0..num_entries)
+            .map(|_| next_entry(&Hash::default(), 1, vec![]))
+            .collect();
+        let shredder = Shredder::new(slot, slot - 1, 1.0, Arc::new(Keypair::new()), 0, 0).unwrap();
+        let (data_shreds, coding_shreds, _) = shredder.entries_to_shreds(&entries, true, 0);
+        (data_shreds, coding_shreds)
+    }
+
+    #[test]
+    fn test_check_and_repair_slot() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let slot = 1;
+            let (data_shreds, coding_shreds) = make_slot_shreds(slot, 200);
+            let num_data_shreds = data_shreds.len() as u64;
+            blockstore.insert_shreds(data_shreds, None, false).unwrap();
+            assert!(blockstore.check_slots(0, 10).unwrap().is_empty());
+
+            // Delete a data shred behind the index's back
+            let mut write_batch = blockstore.db.batch().unwrap();
+            write_batch.delete::<cf::ShredData>((slot, 1)).unwrap();
+            blockstore.db.write(write_batch).unwrap();
+            let slot_check = blockstore.check_slot(slot).unwrap();
+            assert!(slot_check
+                .inconsistencies
+                .contains(&SlotInconsistency::IndexedDataShredMissing(1)));
+            assert!(slot_check
+                .inconsistencies
+                .contains(&SlotInconsistency::ConsumedMismatch {
+                    stored: num_data_shreds,
+                    actual: 1,
+                }));
+            assert!(slot_check.is_unrecoverable);
+
+            blockstore.repair_slot_metadata(slot).unwrap();
+            let slot_check = blockstore.check_slot(slot).unwrap();
+            assert_eq!(slot_check.inconsistencies, vec![]);
+            assert!(slot_check.is_unrecoverable);
+            assert!(!blockstore.is_full(slot));
+
+            // Coding shreds make the missing data shred recoverable
+            blockstore
+                .insert_shreds(coding_shreds, None, false)
+                .unwrap();
+            let slot_check = blockstore.check_slot(slot).unwrap();
+            assert!(slot_check
+                .inconsistencies
+                .contains(&SlotInconsistency::RecoverableErasureSet(0)));
+            assert!(!slot_check.is_unrecoverable);
+            assert_eq!(blockstore.recover_slot_shreds(slot).unwrap(), 1);
+            assert!(blockstore.is_full(slot));
+            assert!(blockstore.check_slots(0, 10).unwrap().is_empty());
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_check_slot_with_recoverable_and_unrecoverable_sets() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let slot = 1;
+            let (data_shreds, coding_shreds) = make_slot_shreds(slot, 2000);
+            let num_data_shreds = data_shreds.len() as u64;
+            let second_set_index = u64::from(MAX_DATA_SHREDS_PER_FEC_BLOCK);
+            assert!(num_data_shreds > second_set_index + 1);
+            blockstore.insert_shreds(data_shreds, None, false).unwrap();
+            // Only the first erasure set has its coding shreds
+            blockstore
+                .insert_shreds(
+                    coding_shreds
+                        .into_iter()
+                        .filter(|shred| shred.common_header.fec_set_index == 0)
+                        .collect(),
+                    None,
+                    false,
+                )
+                .unwrap();
+
+            let mut write_batch = blockstore.db.batch().unwrap();
+            write_batch.delete::<cf::ShredData>((slot, 1)).unwrap();
+            write_batch
+                .delete::<cf::ShredData>((slot, second_set_index + 1))
+                .unwrap();
+            blockstore.db.write(write_batch).unwrap();
+            let slot_check = blockstore.check_slot(slot).unwrap();
+            assert!(slot_check
+                .inconsistencies
+                .contains(&SlotInconsistency::RecoverableErasureSet(0)));
+            assert!(slot_check.is_unrecoverable);
+
+            // Recovery fills in the first set, but the second stays incomplete
+            assert_eq!(blockstore.recover_slot_shreds(slot).unwrap(), 1);
+            blockstore.repair_slot_metadata(slot).unwrap();
+            let slot_check = blockstore.check_slot(slot).unwrap();
+            assert!(slot_check.is_unrecoverable);
+            assert!(!blockstore.is_full(slot));
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_recover_slot_shreds_with_stale_index() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let slot = 1;
+            let (data_shreds, coding_shreds) = make_slot_shreds(slot, 200);
+            blockstore.insert_shreds(data_shreds, None, false).unwrap();
+            blockstore
+                .insert_shreds(coding_shreds, None, false)
+                .unwrap();
+
+            // The index still marks the deleted shred present, which recovery must not trust
+            let mut write_batch = blockstore.db.batch().unwrap();
+            write_batch.delete::<cf::ShredData>((slot, 1)).unwrap();
+            blockstore.db.write(write_batch).unwrap();
+            assert_eq!(blockstore.recover_slot_shreds(slot).unwrap(), 1);
+            assert!(blockstore.is_full(slot));
+            assert!(blockstore.check_slots(0, 10).unwrap().is_empty());
+            assert_eq!(blockstore.recover_slot_shreds(slot).unwrap(), 0);
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_repair_slot_metadata_skips_invalid_shreds() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let slot = 1;
+            let (data_shreds, _) = make_slot_shreds(slot, 200);
+            blockstore.insert_shreds(data_shreds, None, false).unwrap();
+
+            let mut write_batch = blockstore.db.batch().unwrap();
+            write_batch
+                .put_bytes::<cf::ShredData>((slot, 2), &[0xff; 16])
+                .unwrap();
+            blockstore.db.write(write_batch).unwrap();
+            let slot_check = blockstore.check_slot(slot).unwrap();
+            assert!(slot_check
+                .inconsistencies
+                .contains(&SlotInconsistency::InvalidDataShred(2)));
+            assert!(slot_check.is_unrecoverable);
+
+            blockstore.repair_slot_metadata(slot).unwrap();
+            let index = blockstore.get_index(slot).unwrap().unwrap();
+            assert!(!index.data().is_present(2));
+            assert!(index.data().is_present(3));
+            let slot_meta = blockstore.meta(slot).unwrap().unwrap();
+            assert_eq!(slot_meta.consumed, 2);
+            let slot_check = blockstore.check_slot(slot).unwrap();
+            assert_eq!(
+                slot_check.inconsistencies,
+                vec![SlotInconsistency::InvalidDataShred(2)]
+            );
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+}
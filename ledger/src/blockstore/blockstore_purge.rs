@@ -122,6 +122,10 @@ impl Blockstore {
                 .db
                 .delete_range_cf::<cf::DuplicateSlots>(&mut write_batch, from_slot, to_slot)
                 .is_ok()
+            & self
+                .db
+                .delete_range_cf::<cf::BankHash>(&mut write_batch, from_slot, to_slot)
+                .is_ok()
             & self
                 .db
                 .delete_range_cf::<cf::ErasureMeta>(&mut write_batch, from_slot, to_slot)
@@ -208,6 +212,10 @@ impl Blockstore {
                 .duplicate_slots_cf
                 .compact_range(from_slot, to_slot)
                 .unwrap_or(false)
+            && self
+                .bank_hash_cf
+                .compact_range(from_slot, to_slot)
+                .unwrap_or(false)
             && self
                 .erasure_meta_cf
                 .compact_range(from_slot, to_slot)
@@ -378,6 +386,13 @@ pub mod tests {
                 .next()
                 .map(|(slot, _)| slot >= min_slot)
                 .unwrap_or(true)
+            & blockstore
+                .db
+                .iter::<cf::BankHash>(IteratorMode::Start)
+                .unwrap()
+                .next()
+                .map(|(slot, _)| slot >= min_slot)
+                .unwrap_or(true)
             & blockstore
                 .db
                 .iter::<cf::ErasureMeta>(IteratorMode::Start)
@@ -5,7 +5,7 @@ use log::*;
 use prost::Message;
 pub use rocksdb::Direction as IteratorDirection;
 use rocksdb::{
-    self, ColumnFamily, ColumnFamilyDescriptor, DBIterator, DBRawIterator, DBRecoveryMode,
+    self, ColumnFamily, ColumnFamilyDescriptor, DBIterator, DBRecoveryMode,
     IteratorMode as RocksIteratorMode, Options, WriteBatch as RWriteBatch, DB,
 };
 use serde::de::DeserializeOwned;
@@ -17,8 +17,14 @@ use solana_sdk::{
     signature::Signature,
 };
 use solana_storage_proto::convert::generated;
-use solana_transaction_status::TransactionStatusMeta;
-use std::{collections::HashMap, fs, marker::PhantomData, path::Path, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    marker::PhantomData,
+    ops::Bound,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 use thiserror::Error;
 
 const MAX_WRITE_BUFFER_SIZE: u64 = 256 * 1024 * 1024; // 256MB
@@ -56,6 +62,8 @@ const REWARDS_CF: &str = "rewards";
 const BLOCKTIME_CF: &str = "blocktime";
 /// Column family for Performance Samples
 const PERF_SAMPLES_CF: &str = "perf_samples";
+/// Column family for the bank hashes of frozen slots
+const BANK_HASH_CF: &str = "bank_hashes";
 
 #[derive(Error, Debug)]
 pub enum BlockstoreError {
@@ -111,6 +119,10 @@ pub mod columns {
     /// The erasure meta column
     pub struct ErasureMeta;
 
+    #[derive(Debug)]
+    /// The bank hash column
+    pub struct BankHash;
+
     #[derive(Debug)]
     /// The root column
     pub struct Root;
@@ -200,8 +212,35 @@ impl Into<DBRecoveryMode> for BlockstoreRecoveryMode {
     }
 }
 
+fn all_column_names() -> Vec<&'static str> {
+    use columns::{
+        AddressSignatures, BankHash, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index,
+        Orphans, PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
+        TransactionStatusIndex,
+    };
+
+    vec![
+        ErasureMeta::NAME,
+        DeadSlots::NAME,
+        DuplicateSlots::NAME,
+        Index::NAME,
+        Orphans::NAME,
+        Root::NAME,
+        SlotMeta::NAME,
+        ShredData::NAME,
+        ShredCode::NAME,
+        TransactionStatus::NAME,
+        AddressSignatures::NAME,
+        TransactionStatusIndex::NAME,
+        Rewards::NAME,
+        Blocktime::NAME,
+        PerfSamples::NAME,
+        BankHash::NAME,
+    ]
+}
+
 #[derive(Debug)]
-struct Rocks(rocksdb::DB, ActualAccessType);
+struct Rocks(rocksdb::DB, ActualAccessType, PathBuf);
 
 impl Rocks {
     fn open(
@@ -210,8 +249,8 @@ impl Rocks {
         recovery_mode: Option<BlockstoreRecoveryMode>,
     ) -> Result<Rocks> {
         use columns::{
-            AddressSignatures, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans,
-            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
+            AddressSignatures, BankHash, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index,
+            Orphans, PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
             TransactionStatusIndex,
         };
 
@@ -257,6 +296,8 @@ impl Rocks {
             ColumnFamilyDescriptor::new(Blocktime::NAME, get_cf_options(&access_type));
         let perf_samples_cf_descriptor =
             ColumnFamilyDescriptor::new(PerfSamples::NAME, get_cf_options(&access_type));
+        let bank_hash_cf_descriptor =
+            ColumnFamilyDescriptor::new(BankHash::NAME, get_cf_options(&access_type));
 
         let cfs = vec![
             (SlotMeta::NAME, meta_cf_descriptor),
@@ -277,6 +318,7 @@ impl Rocks {
             (Rewards::NAME, rewards_cf_descriptor),
             (Blocktime::NAME, blocktime_cf_descriptor),
             (PerfSamples::NAME, perf_samples_cf_descriptor),
+            (BankHash::NAME, bank_hash_cf_descriptor),
         ];
 
         // Open the database
@@ -284,12 +326,13 @@ impl Rocks {
             AccessType::PrimaryOnly | AccessType::PrimaryOnlyForMaintenance => Rocks(
                 DB::open_cf_descriptors(&db_options, path, cfs.into_iter().map(|c| c.1))?,
                 ActualAccessType::Primary,
+                path.to_path_buf(),
             ),
             AccessType::TryPrimaryThenSecondary => {
                 let names: Vec<_> = cfs.iter().map(|c| c.0).collect();
 
                 match DB::open_cf_descriptors(&db_options, path, cfs.into_iter().map(|c| c.1)) {
-                    Ok(db) => Rocks(db, ActualAccessType::Primary),
+                    Ok(db) => Rocks(db, ActualAccessType::Primary, path.to_path_buf()),
                     Err(err) => {
                         let secondary_path = path.join("solana-secondary");
 
@@ -303,6 +346,7 @@ impl Rocks {
                         Rocks(
                             DB::open_cf_as_secondary(&db_options, path, &secondary_path, names)?,
                             ActualAccessType::Secondary,
+                            path.to_path_buf(),
                         )
                     }
                 }
@@ -312,32 +356,6 @@ impl Rocks {
         Ok(db)
     }
 
-    fn columns(&self) -> Vec<&'static str> {
-        use columns::{
-            AddressSignatures, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans,
-            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
-            TransactionStatusIndex,
-        };
-
-        vec![
-            ErasureMeta::NAME,
-            DeadSlots::NAME,
-            DuplicateSlots::NAME,
-            Index::NAME,
-            Orphans::NAME,
-            Root::NAME,
-            SlotMeta::NAME,
-            ShredData::NAME,
-            ShredCode::NAME,
-            TransactionStatus::NAME,
-            AddressSignatures::NAME,
-            TransactionStatusIndex::NAME,
-            Rewards::NAME,
-            Blocktime::NAME,
-            PerfSamples::NAME,
-        ]
-    }
-
     fn destroy(path: &Path) -> Result<()> {
         DB::destroy(&Options::default(), path)?;
 
@@ -381,23 +399,254 @@ impl Rocks {
         Ok(iter)
     }
 
-    fn raw_iterator_cf(&self, cf: &ColumnFamily) -> Result<DBRawIterator> {
-        let raw_iter = self.0.raw_iterator_cf(cf);
+    fn write(&self, batch: RWriteBatch) -> Result<()> {
+        self.0.write(batch)?;
+        Ok(())
+    }
 
-        Ok(raw_iter)
+    // Unlike the size of the files on disk, this leaves out the data which was deleted but not
+    // compacted away yet. Data which was not flushed yet is only in the memtables
+    fn live_data_size_cf(&self, cf: &ColumnFamily) -> Result<u64> {
+        let mut size = 0;
+        for property in &[
+            "rocksdb.estimate-live-data-size",
+            "rocksdb.cur-size-all-mem-tables",
+        ] {
+            size += self
+                .0
+                .property_int_value_cf(cf, property)?
+                .unwrap_or_default();
+        }
+        Ok(size)
     }
 
-    fn batch(&self) -> Result<RWriteBatch> {
-        Ok(RWriteBatch::default())
+    fn is_primary_access(&self) -> bool {
+        self.1 == ActualAccessType::Primary
     }
+}
 
-    fn write(&self, batch: RWriteBatch) -> Result<()> {
-        self.0.write(batch)?;
+enum InMemoryWriteOp {
+    Put(&'static str, Vec<u8>, Vec<u8>),
+    Delete(&'static str, Vec<u8>),
+    DeleteRange(&'static str, Vec<u8>, Vec<u8>),
+}
+
+/// Keeps every column in an ordered map, for tests which don't need the ledger to persist
+#[derive(Debug)]
+struct InMemory(RwLock<HashMap<&'static str, BTreeMap<Vec<u8>, Vec<u8>>>>);
+
+impl InMemory {
+    fn new() -> Self {
+        let columns = all_column_names()
+            .into_iter()
+            .map(|name| (name, BTreeMap::new()))
+            .collect();
+        Self(RwLock::new(columns))
+    }
+
+    fn get_cf(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.0.read().unwrap()[cf].get(key).cloned())
+    }
+
+    fn put_cf(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        self.0
+            .write()
+            .unwrap()
+            .get_mut(cf)
+            .expect("should never get an unknown column")
+            .insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    // Visits the entries in the order RocksDB visits them. The column is only locked while
+    // looking up each entry, so that it can be written to while being iterated over
+    fn iterator_cf<C>(
+        &self,
+        cf: &'static str,
+        iterator_mode: IteratorMode<C::Index>,
+    ) -> InMemoryIterator
+    where
+        C: Column,
+    {
+        let (bound, direction) = match iterator_mode {
+            IteratorMode::Start => (Bound::Unbounded, IteratorDirection::Forward),
+            IteratorMode::End => (Bound::Unbounded, IteratorDirection::Reverse),
+            IteratorMode::From(start_from, direction) => {
+                (Bound::Included(C::key(start_from)), direction)
+            }
+        };
+        InMemoryIterator {
+            in_memory: self,
+            cf,
+            bound,
+            direction,
+        }
+    }
+
+    fn write(&self, batch: Vec<InMemoryWriteOp>) -> Result<()> {
+        let mut columns = self.0.write().unwrap();
+        for op in batch {
+            match op {
+                InMemoryWriteOp::Put(cf, key, value) => {
+                    columns.get_mut(cf).unwrap().insert(key, value);
+                }
+                InMemoryWriteOp::Delete(cf, key) => {
+                    columns.get_mut(cf).unwrap().remove(&key);
+                }
+                InMemoryWriteOp::DeleteRange(cf, from, to) => {
+                    if from < to {
+                        let column = columns.get_mut(cf).unwrap();
+                        let keys: Vec<_> =
+                            column.range(from..to).map(|(key, _)| key.clone()).collect();
+                        for key in keys {
+                            column.remove(&key);
+                        }
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
+    fn storage_size(&self) -> u64 {
+        self.0
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|column| column.iter())
+            .map(|(key, value)| (key.len() + value.len()) as u64)
+            .sum()
+    }
+
+    fn live_data_size_cf(&self, cf: &str) -> u64 {
+        self.0.read().unwrap()[cf]
+            .iter()
+            .map(|(key, value)| (key.len() + value.len()) as u64)
+            .sum()
+    }
+}
+
+struct InMemoryIterator<'a> {
+    in_memory: &'a InMemory,
+    cf: &'static str,
+    // Bounds the keys which are left to visit, on the side the iterator starts from
+    bound: Bound<Vec<u8>>,
+    direction: IteratorDirection,
+}
+
+impl<'a> Iterator for InMemoryIterator<'a> {
+    type Item = (Box<[u8]>, Box<[u8]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let columns = self.in_memory.0.read().unwrap();
+        let column = &columns[self.cf];
+        let (key, value) = match self.direction {
+            IteratorDirection::Forward => column
+                .range((self.bound.clone(), Bound::Unbounded))
+                .next()?,
+            IteratorDirection::Reverse => column
+                .range((Bound::Unbounded, self.bound.clone()))
+                .next_back()?,
+        };
+        self.bound = Bound::Excluded(key.clone());
+        Some((
+            key.clone().into_boxed_slice(),
+            value.clone().into_boxed_slice(),
+        ))
+    }
+}
+
+#[derive(Debug)]
+enum Backend {
+    Rocks(Rocks),
+    InMemory(InMemory),
+}
+
+enum BackendWriteBatch {
+    Rocks(RWriteBatch),
+    InMemory(Vec<InMemoryWriteOp>),
+}
+
+type BackendIterator<'a> = Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
+
+impl Backend {
+    fn get_cf(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self {
+            Backend::Rocks(rocks) => rocks.get_cf(rocks.cf_handle(cf), key),
+            Backend::InMemory(in_memory) => in_memory.get_cf(cf, key),
+        }
+    }
+
+    fn put_cf(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        match self {
+            Backend::Rocks(rocks) => rocks.put_cf(rocks.cf_handle(cf), key, value),
+            Backend::InMemory(in_memory) => in_memory.put_cf(cf, key, value),
+        }
+    }
+
+    fn iterator_cf<C>(
+        &self,
+        cf: &'static str,
+        iterator_mode: IteratorMode<C::Index>,
+    ) -> Result<BackendIterator>
+    where
+        C: Column,
+    {
+        match self {
+            Backend::Rocks(rocks) => Ok(Box::new(
+                rocks.iterator_cf::<C>(rocks.cf_handle(cf), iterator_mode)?,
+            )),
+            Backend::InMemory(in_memory) => {
+                Ok(Box::new(in_memory.iterator_cf::<C>(cf, iterator_mode)))
+            }
+        }
+    }
+
+    fn batch(&self) -> BackendWriteBatch {
+        match self {
+            Backend::Rocks(_) => BackendWriteBatch::Rocks(RWriteBatch::default()),
+            Backend::InMemory(_) => BackendWriteBatch::InMemory(vec![]),
+        }
+    }
+
+    fn write(&self, batch: BackendWriteBatch) -> Result<()> {
+        match (self, batch) {
+            (Backend::Rocks(rocks), BackendWriteBatch::Rocks(batch)) => rocks.write(batch),
+            (Backend::InMemory(in_memory), BackendWriteBatch::InMemory(batch)) => {
+                in_memory.write(batch)
+            }
+            _ => unreachable!("write batch from another backend"),
+        }
+    }
+
+    fn compact_range_cf(&self, cf: &str, from: &[u8], to: &[u8]) {
+        // Deleted entries are dropped from memory right away
+        if let Backend::Rocks(rocks) = self {
+            rocks
+                .0
+                .compact_range_cf(rocks.cf_handle(cf), Some(from), Some(to));
+        }
+    }
+
+    fn storage_size(&self) -> Result<u64> {
+        match self {
+            Backend::Rocks(rocks) => Ok(fs_extra::dir::get_size(&rocks.2)?),
+            Backend::InMemory(in_memory) => Ok(in_memory.storage_size()),
+        }
+    }
+
+    fn live_data_size_cf(&self, cf: &str) -> Result<u64> {
+        match self {
+            Backend::Rocks(rocks) => rocks.live_data_size_cf(rocks.cf_handle(cf)),
+            Backend::InMemory(in_memory) => Ok(in_memory.live_data_size_cf(cf)),
+        }
+    }
+
     fn is_primary_access(&self) -> bool {
-        self.1 == ActualAccessType::Primary
+        match self {
+            Backend::Rocks(rocks) => rocks.is_primary_access(),
+            Backend::InMemory(_) => true,
+        }
     }
 }
 
@@ -422,10 +671,6 @@ pub trait TypedColumn: Column {
     type Type: Serialize + DeserializeOwned;
 }
 
-impl TypedColumn for columns::TransactionStatus {
-    type Type = TransactionStatusMeta;
-}
-
 impl TypedColumn for columns::AddressSignatures {
     type Type = blockstore_meta::AddressSignatureMeta;
 }
@@ -496,6 +741,9 @@ impl Column for columns::TransactionStatus {
 impl ColumnName for columns::TransactionStatus {
     const NAME: &'static str = TRANSACTION_STATUS_CF;
 }
+impl ProtobufColumn for columns::TransactionStatus {
+    type Type = generated::TransactionStatusMeta;
+}
 
 impl Column for columns::AddressSignatures {
     type Index = (u64, Pubkey, Slot, Signature);
@@ -657,6 +905,14 @@ impl TypedColumn for columns::DuplicateSlots {
     type Type = blockstore_meta::DuplicateSlotProof;
 }
 
+impl SlotColumn for columns::BankHash {}
+impl ColumnName for columns::BankHash {
+    const NAME: &'static str = BANK_HASH_CF;
+}
+impl TypedColumn for columns::BankHash {
+    type Type = blockstore_meta::FrozenHashVersioned;
+}
+
 impl SlotColumn for columns::Orphans {}
 impl ColumnName for columns::Orphans {
     const NAME: &'static str = ORPHANS_CF;
@@ -715,8 +971,7 @@ impl TypedColumn for columns::ErasureMeta {
 
 #[derive(Debug, Clone)]
 pub struct Database {
-    backend: Arc<Rocks>,
-    path: Arc<Path>,
+    backend: Arc<Backend>,
 }
 
 #[derive(Debug, Clone)]
@@ -724,13 +979,13 @@ pub struct LedgerColumn<C>
 where
     C: Column,
 {
-    backend: Arc<Rocks>,
+    backend: Arc<Backend>,
     column: PhantomData<C>,
 }
 
 pub struct WriteBatch<'a> {
-    write_batch: RWriteBatch,
-    map: HashMap<&'static str, &'a ColumnFamily>,
+    write_batch: BackendWriteBatch,
+    backend: &'a Backend,
 }
 
 impl Database {
@@ -739,12 +994,20 @@ impl Database {
         access_type: AccessType,
         recovery_mode: Option<BlockstoreRecoveryMode>,
     ) -> Result<Self> {
-        let backend = Arc::new(Rocks::open(path, access_type, recovery_mode)?);
+        let backend = Arc::new(Backend::Rocks(Rocks::open(
+            path,
+            access_type,
+            recovery_mode,
+        )?));
 
-        Ok(Database {
-            backend,
-            path: Arc::from(path),
-        })
+        Ok(Database { backend })
+    }
+
+    /// Opens an empty database which only lives in memory
+    pub fn open_in_memory() -> Self {
+        Database {
+            backend: Arc::new(Backend::InMemory(InMemory::new())),
+        }
     }
 
     pub fn destroy(path: &Path) -> Result<()> {
@@ -757,7 +1020,7 @@ impl Database {
     where
         C: TypedColumn + ColumnName,
     {
-        if let Some(serialized_value) = self.backend.get_cf(self.cf_handle::<C>(), &C::key(key))? {
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
             let value = deserialize(&serialized_value)?;
 
             Ok(Some(value))
@@ -773,19 +1036,10 @@ impl Database {
     where
         C: Column + ColumnName,
     {
-        let cf = self.cf_handle::<C>();
-        let iter = self.backend.iterator_cf::<C>(cf, iterator_mode)?;
+        let iter = self.backend.iterator_cf::<C>(C::NAME, iterator_mode)?;
         Ok(iter.map(|(key, value)| (C::index(&key), value)))
     }
 
-    #[inline]
-    pub fn cf_handle<C: ColumnName>(&self) -> &ColumnFamily
-    where
-        C: Column + ColumnName,
-    {
-        self.backend.cf_handle(C::NAME)
-    }
-
     pub fn column<C>(&self) -> LedgerColumn<C>
     where
         C: Column + ColumnName,
@@ -796,21 +1050,11 @@ impl Database {
         }
     }
 
-    #[inline]
-    pub fn raw_iterator_cf(&self, cf: &ColumnFamily) -> Result<DBRawIterator> {
-        self.backend.raw_iterator_cf(cf)
-    }
-
     pub fn batch(&self) -> Result<WriteBatch> {
-        let write_batch = self.backend.batch()?;
-        let map = self
-            .backend
-            .columns()
-            .into_iter()
-            .map(|desc| (desc, self.backend.cf_handle(desc)))
-            .collect();
-
-        Ok(WriteBatch { write_batch, map })
+        Ok(WriteBatch {
+            write_batch: self.backend.batch(),
+            backend: &self.backend,
+        })
     }
 
     pub fn write(&self, batch: WriteBatch) -> Result<()> {
@@ -818,7 +1062,15 @@ impl Database {
     }
 
     pub fn storage_size(&self) -> Result<u64> {
-        Ok(fs_extra::dir::get_size(&self.path)?)
+        self.backend.storage_size()
+    }
+
+    /// Estimated size of the data in every column which has not been deleted
+    pub fn live_data_size(&self) -> Result<u64> {
+        all_column_names()
+            .into_iter()
+            .map(|cf| self.backend.live_data_size_cf(cf))
+            .sum()
     }
 
     // Adds a range to delete to the given write batch
@@ -826,10 +1078,9 @@ impl Database {
     where
         C: Column + ColumnName,
     {
-        let cf = self.cf_handle::<C>();
         let from_index = C::as_index(from);
         let to_index = C::as_index(to);
-        batch.delete_range_cf::<C>(cf, from_index, to_index)
+        batch.delete_range_cf::<C>(from_index, to_index)
     }
 
     pub fn is_primary_access(&self) -> bool {
@@ -842,15 +1093,14 @@ where
     C: Column + ColumnName,
 {
     pub fn get_bytes(&self, key: C::Index) -> Result<Option<Vec<u8>>> {
-        self.backend.get_cf(self.handle(), &C::key(key))
+        self.backend.get_cf(C::NAME, &C::key(key))
     }
 
     pub fn iter<'a>(
         &'a self,
         iterator_mode: IteratorMode<C::Index>,
     ) -> Result<impl Iterator<Item = (C::Index, Box<[u8]>)> + 'a> {
-        let cf = self.handle();
-        let iter = self.backend.iterator_cf::<C>(cf, iterator_mode)?;
+        let iter = self.backend.iterator_cf::<C>(C::NAME, iterator_mode)?;
         Ok(iter.map(|(key, value)| (C::index(&key), value)))
     }
 
@@ -888,31 +1138,28 @@ where
         Ok(end)
     }
 
+    /// Estimated size of the data in this column which has not been deleted
+    pub fn live_data_size(&self) -> Result<u64> {
+        self.backend.live_data_size_cf(C::NAME)
+    }
+
     pub fn compact_range(&self, from: Slot, to: Slot) -> Result<bool>
     where
         C::Index: PartialOrd + Copy,
     {
-        let cf = self.handle();
-        let from = Some(C::key(C::as_index(from)));
-        let to = Some(C::key(C::as_index(to)));
-        self.backend.0.compact_range_cf(cf, from, to);
+        let from = C::key(C::as_index(from));
+        let to = C::key(C::as_index(to));
+        self.backend.compact_range_cf(C::NAME, &from, &to);
         Ok(true)
     }
 
-    #[inline]
-    pub fn handle(&self) -> &ColumnFamily {
-        self.backend.cf_handle(C::NAME)
-    }
-
     #[cfg(test)]
     pub fn is_empty(&self) -> Result<bool> {
-        let mut iter = self.backend.raw_iterator_cf(self.handle())?;
-        iter.seek_to_first();
-        Ok(!iter.valid())
+        Ok(self.iter(IteratorMode::Start)?.next().is_none())
     }
 
     pub fn put_bytes(&self, key: C::Index, value: &[u8]) -> Result<()> {
-        self.backend.put_cf(self.handle(), &C::key(key), value)
+        self.backend.put_cf(C::NAME, &C::key(key), value)
     }
 }
 
@@ -921,7 +1168,7 @@ where
     C: TypedColumn + ColumnName,
 {
     pub fn get(&self, key: C::Index) -> Result<Option<C::Type>> {
-        if let Some(serialized_value) = self.backend.get_cf(self.handle(), &C::key(key))? {
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
             let value = deserialize(&serialized_value)?;
 
             Ok(Some(value))
@@ -934,7 +1181,7 @@ where
         let serialized_value = serialize(value)?;
 
         self.backend
-            .put_cf(self.handle(), &C::key(key), &serialized_value)
+            .put_cf(C::NAME, &C::key(key), &serialized_value)
     }
 }
 
@@ -942,23 +1189,36 @@ impl<C> LedgerColumn<C>
 where
     C: ProtobufColumn + ColumnName,
 {
+    /// Decodes a value written either in protobuf or, by older versions, as bincode `T`.
+    ///
+    /// Bincode values of the protobuf columns start with a little-endian enum tag or length,
+    /// which never decodes as a valid protobuf field key, so the two encodings can't be
+    /// mistaken for one another.
+    pub fn deserialize_protobuf_or_bincode<T: DeserializeOwned + Into<C::Type>>(
+        &self,
+        serialized_value: &[u8],
+    ) -> Result<C::Type> {
+        match C::Type::decode(serialized_value) {
+            Ok(value) => Ok(value),
+            Err(_) => Ok(deserialize::<T>(serialized_value)?.into()),
+        }
+    }
+
     pub fn get_protobuf_or_bincode<T: DeserializeOwned + Into<C::Type>>(
         &self,
         key: C::Index,
     ) -> Result<Option<C::Type>> {
-        if let Some(serialized_value) = self.backend.get_cf(self.handle(), &C::key(key))? {
-            let value = match C::Type::decode(&serialized_value[..]) {
-                Ok(value) => value,
-                Err(_) => deserialize::<T>(&serialized_value)?.into(),
-            };
-            Ok(Some(value))
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
+            Ok(Some(
+                self.deserialize_protobuf_or_bincode::<T>(&serialized_value)?,
+            ))
         } else {
             Ok(None)
         }
     }
 
     pub fn get_protobuf(&self, key: C::Index) -> Result<Option<C::Type>> {
-        if let Some(serialized_value) = self.backend.get_cf(self.handle(), &C::key(key))? {
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
             Ok(Some(C::Type::decode(&serialized_value[..])?))
         } else {
             Ok(None)
@@ -968,19 +1228,54 @@ where
     pub fn put_protobuf(&self, key: C::Index, value: &C::Type) -> Result<()> {
         let mut buf = Vec::with_capacity(value.encoded_len());
         value.encode(&mut buf)?;
-        self.backend.put_cf(self.handle(), &C::key(key), &buf)
+        self.backend.put_cf(C::NAME, &C::key(key), &buf)
+    }
+
+    /// Rewrites every value of the column still stored as bincode `T` in protobuf, returning
+    /// the number of values converted
+    pub fn migrate_bincode_to_protobuf<T: DeserializeOwned + Into<C::Type>>(
+        &self,
+    ) -> Result<usize> {
+        let mut converted = 0;
+        for (index, serialized_value) in self.iter(IteratorMode::Start)? {
+            if C::Type::decode(&serialized_value[..]).is_err() {
+                let value: C::Type = deserialize::<T>(&serialized_value)?.into();
+                self.put_protobuf(index, &value)?;
+                converted += 1;
+            }
+        }
+        Ok(converted)
     }
 }
 
 impl<'a> WriteBatch<'a> {
+    fn put_cf(&mut self, cf: &'static str, key: Vec<u8>, value: &[u8]) {
+        match (&mut self.write_batch, self.backend) {
+            (BackendWriteBatch::Rocks(batch), Backend::Rocks(rocks)) => {
+                batch.put_cf(rocks.cf_handle(cf), key, value)
+            }
+            (BackendWriteBatch::InMemory(batch), _) => {
+                batch.push(InMemoryWriteOp::Put(cf, key, value.to_vec()))
+            }
+            _ => unreachable!("write batch from another backend"),
+        }
+    }
+
     pub fn put_bytes<C: Column + ColumnName>(&mut self, key: C::Index, bytes: &[u8]) -> Result<()> {
-        self.write_batch
-            .put_cf(self.get_cf::<C>(), &C::key(key), bytes);
+        self.put_cf(C::NAME, C::key(key), bytes);
         Ok(())
     }
 
     pub fn delete<C: Column + ColumnName>(&mut self, key: C::Index) -> Result<()> {
-        self.write_batch.delete_cf(self.get_cf::<C>(), &C::key(key));
+        match (&mut self.write_batch, self.backend) {
+            (BackendWriteBatch::Rocks(batch), Backend::Rocks(rocks)) => {
+                batch.delete_cf(rocks.cf_handle(C::NAME), &C::key(key))
+            }
+            (BackendWriteBatch::InMemory(batch), _) => {
+                batch.push(InMemoryWriteOp::Delete(C::NAME, C::key(key)))
+            }
+            _ => unreachable!("write batch from another backend"),
+        }
         Ok(())
     }
 
@@ -990,24 +1285,26 @@ impl<'a> WriteBatch<'a> {
         value: &C::Type,
     ) -> Result<()> {
         let serialized_value = serialize(&value)?;
-        self.write_batch
-            .put_cf(self.get_cf::<C>(), &C::key(key), &serialized_value);
+        self.put_cf(C::NAME, C::key(key), &serialized_value);
         Ok(())
     }
 
-    #[inline]
-    fn get_cf<C: Column + ColumnName>(&self) -> &'a ColumnFamily {
-        self.map[C::NAME]
-    }
-
-    pub fn delete_range_cf<C: Column>(
+    pub fn delete_range_cf<C: Column + ColumnName>(
         &mut self,
-        cf: &ColumnFamily,
         from: C::Index,
         to: C::Index,
     ) -> Result<()> {
-        self.write_batch
-            .delete_range_cf(cf, C::key(from), C::key(to));
+        match (&mut self.write_batch, self.backend) {
+            (BackendWriteBatch::Rocks(batch), Backend::Rocks(rocks)) => {
+                batch.delete_range_cf(rocks.cf_handle(C::NAME), C::key(from), C::key(to))
+            }
+            (BackendWriteBatch::InMemory(batch), _) => batch.push(InMemoryWriteOp::DeleteRange(
+                C::NAME,
+                C::key(from),
+                C::key(to),
+            )),
+            _ => unreachable!("write batch from another backend"),
+        }
         Ok(())
     }
 }
@@ -1048,3 +1345,74 @@ fn get_db_options(access_type: &AccessType) -> Options {
 
     options
 }
+
+#[cfg(test)]
+mod tests {
+    use super::columns::{Root, ShredData};
+    use super::*;
+
+    #[test]
+    fn test_in_memory_database() {
+        let db = Database::open_in_memory();
+        for slot in 0..10 {
+            db.column::<Root>().put(slot, &true).unwrap();
+        }
+        assert_eq!(db.get::<Root>(3).unwrap(), Some(true));
+        assert_eq!(db.get::<Root>(10).unwrap(), None);
+
+        let slots: Vec<_> = db
+            .iter::<Root>(IteratorMode::Start)
+            .unwrap()
+            .map(|(slot, _)| slot)
+            .collect();
+        assert_eq!(slots, (0..10).collect::<Vec<_>>());
+        let slots: Vec<_> = db
+            .iter::<Root>(IteratorMode::End)
+            .unwrap()
+            .map(|(slot, _)| slot)
+            .collect();
+        assert_eq!(slots, (0..10).rev().collect::<Vec<_>>());
+        let slots: Vec<_> = db
+            .iter::<Root>(IteratorMode::From(7, IteratorDirection::Forward))
+            .unwrap()
+            .map(|(slot, _)| slot)
+            .collect();
+        assert_eq!(slots, vec![7, 8, 9]);
+        let slots: Vec<_> = db
+            .iter::<Root>(IteratorMode::From(2, IteratorDirection::Reverse))
+            .unwrap()
+            .map(|(slot, _)| slot)
+            .collect();
+        assert_eq!(slots, vec![2, 1, 0]);
+
+        // Nothing is visible until the batch is written
+        let mut batch = db.batch().unwrap();
+        batch.delete::<Root>(0).unwrap();
+        db.delete_range_cf::<Root>(&mut batch, 4, 8).unwrap();
+        batch.put::<Root>(10, &true).unwrap();
+        assert_eq!(db.get::<Root>(0).unwrap(), Some(true));
+        db.write(batch).unwrap();
+
+        let slots: Vec<_> = db
+            .iter::<Root>(IteratorMode::Start)
+            .unwrap()
+            .map(|(slot, _)| slot)
+            .collect();
+        assert_eq!(slots, vec![1, 2, 3, 8, 9, 10]);
+
+        // Iterators are lazy, so the column can be written to while iterating over it
+        let mut iter = db.iter::<Root>(IteratorMode::Start).unwrap();
+        assert_eq!(iter.next().map(|(slot, _)| slot), Some(1));
+        db.column::<Root>().put(11, &true).unwrap();
+        let slots: Vec<_> = iter.map(|(slot, _)| slot).collect();
+        assert_eq!(slots, vec![2, 3, 8, 9, 10, 11]);
+        assert!(db.column::<ShredData>().is_empty().unwrap());
+        assert!(db.storage_size().unwrap() > 0);
+        assert_eq!(db.live_data_size().unwrap(), db.storage_size().unwrap());
+        assert_eq!(
+            db.column::<Root>().live_data_size().unwrap(),
+            db.storage_size().unwrap()
+        );
+        assert_eq!(db.column::<ShredData>().live_data_size().unwrap(), 0);
+    }
+}
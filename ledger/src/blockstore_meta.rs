@@ -1,6 +1,6 @@
 use crate::erasure::ErasureConfig;
 use serde::{Deserialize, Serialize};
-use solana_sdk::clock::Slot;
+use solana_sdk::{clock::Slot, hash::Hash};
 use std::{collections::BTreeSet, ops::RangeBounds};
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
@@ -67,6 +67,26 @@ pub struct DuplicateSlotProof {
     pub shred2: Vec<u8>,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub enum FrozenHashVersioned {
+    Current(FrozenHashStatus),
+}
+
+impl FrozenHashVersioned {
+    pub fn frozen_hash(&self) -> Hash {
+        match self {
+            FrozenHashVersioned::Current(frozen_hash_status) => frozen_hash_status.frozen_hash,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+// The BankHash column family
+pub struct FrozenHashStatus {
+    // Hash of the bank the node replayed for this slot
+    pub frozen_hash: Hash,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ErasureMetaStatus {
     CanRecover,
@@ -111,6 +131,11 @@ impl ShredIndex {
         self.index.contains(&index)
     }
 
+    /// Iterates over the indexes of the present shreds in ascending order
+    pub fn present(&self) -> impl Iterator<Item = u64> + '_ {
+        self.index.iter().copied()
+    }
+
     pub fn set_present(&mut self, index: u64, presence: bool) {
         if presence {
             self.index.insert(index);
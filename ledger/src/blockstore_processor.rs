@@ -20,8 +20,11 @@ use solana_runtime::{
         TransactionProcessResult, TransactionResults,
     },
     bank_forks::BankForks,
+    bank_hash_details::write_bank_hash_details,
     bank_utils,
     commitment::VOTE_THRESHOLD_SIZE,
+    cost_tracker::CostTracker,
+    execution_trace::TransactionExecutionTrace,
     transaction_batch::TransactionBatch,
     transaction_utils::OrderedIterator,
     vote_sender_types::ReplayVoteSender,
@@ -102,14 +105,16 @@ fn execute_batch(
     bank: &Arc<Bank>,
     transaction_status_sender: Option<TransactionStatusSender>,
     replay_vote_sender: Option<&ReplayVoteSender>,
+    enable_execution_traces: bool,
 ) -> Result<()> {
-    let (tx_results, balances, inner_instructions, transaction_logs) =
+    let (tx_results, balances, inner_instructions, transaction_logs, execution_traces) =
         batch.bank().load_execute_and_commit_transactions(
             batch,
             MAX_PROCESSING_AGE,
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
+            transaction_status_sender.is_some() && enable_execution_traces,
         );
 
     bank_utils::find_and_send_votes(batch.transactions(), &tx_results, replay_vote_sender);
@@ -129,6 +134,7 @@ fn execute_batch(
             balances,
             inner_instructions,
             transaction_logs,
+            execution_traces,
             sender,
         );
     }
@@ -143,6 +149,7 @@ fn execute_batches(
     entry_callback: Option<&ProcessCallback>,
     transaction_status_sender: Option<TransactionStatusSender>,
     replay_vote_sender: Option<&ReplayVoteSender>,
+    enable_execution_traces: bool,
 ) -> Result<()> {
     inc_new_counter_debug!("bank-par_execute_entries-count", batches.len());
     let results: Vec<Result<()>> = PAR_THREAD_POOL.with(|thread_pool| {
@@ -150,7 +157,13 @@ fn execute_batches(
             batches
                 .into_par_iter()
                 .map_with(transaction_status_sender, |sender, batch| {
-                    let result = execute_batch(batch, bank, sender.clone(), replay_vote_sender);
+                    let result = execute_batch(
+                        batch,
+                        bank,
+                        sender.clone(),
+                        replay_vote_sender,
+                        enable_execution_traces,
+                    );
                     if let Some(entry_callback) = entry_callback {
                         entry_callback(bank);
                     }
@@ -182,6 +195,7 @@ pub fn process_entries(
         None,
         transaction_status_sender,
         replay_vote_sender,
+        false,
     )
 }
 
@@ -192,6 +206,7 @@ fn process_entries_with_callback(
     entry_callback: Option<&ProcessCallback>,
     transaction_status_sender: Option<TransactionStatusSender>,
     replay_vote_sender: Option<&ReplayVoteSender>,
+    enable_execution_traces: bool,
 ) -> Result<()> {
     // accumulator for entries that can be processed in parallel
     let mut batches = vec![];
@@ -209,6 +224,7 @@ fn process_entries_with_callback(
                     entry_callback,
                     transaction_status_sender.clone(),
                     replay_vote_sender,
+                    enable_execution_traces,
                 )?;
                 batches.clear();
                 for hash in &tick_hashes {
@@ -265,6 +281,7 @@ fn process_entries_with_callback(
                     entry_callback,
                     transaction_status_sender.clone(),
                     replay_vote_sender,
+                    enable_execution_traces,
                 )?;
                 batches.clear();
             }
@@ -276,6 +293,7 @@ fn process_entries_with_callback(
         entry_callback,
         transaction_status_sender,
         replay_vote_sender,
+        enable_execution_traces,
     )?;
     for hash in tick_hashes {
         bank.register_tick(&hash);
@@ -320,6 +338,10 @@ pub struct ProcessOptions {
     pub new_hard_forks: Option<Vec<Slot>>,
     pub frozen_accounts: Vec<Pubkey>,
     pub debug_keys: Option<Arc<HashSet<Pubkey>>>,
+    /// Write the hash details of every frozen bank to this directory
+    pub bank_hash_details_dir: Option<PathBuf>,
+    /// Trace the execution of every replayed transaction, for the transaction status sender
+    pub enable_execution_traces: bool,
 }
 
 pub fn process_blockstore(
@@ -327,6 +349,18 @@ pub fn process_blockstore(
     blockstore: &Blockstore,
     account_paths: Vec<PathBuf>,
     opts: ProcessOptions,
+) -> BlockstoreProcessorResult {
+    process_blockstore_from_genesis(genesis_config, blockstore, account_paths, opts, None)
+}
+
+// Process blockstore from genesis, optionally reporting the status of every replayed
+// transaction
+pub(crate) fn process_blockstore_from_genesis(
+    genesis_config: &GenesisConfig,
+    blockstore: &Blockstore,
+    account_paths: Vec<PathBuf>,
+    opts: ProcessOptions,
+    transaction_status_sender: Option<TransactionStatusSender>,
 ) -> BlockstoreProcessorResult {
     if let Some(num_threads) = opts.override_num_threads {
         PAR_THREAD_POOL.with(|pool| {
@@ -348,8 +382,20 @@ pub fn process_blockstore(
     let bank0 = Arc::new(bank0);
     info!("processing ledger for slot 0...");
     let recyclers = VerifyRecyclers::default();
-    process_bank_0(&bank0, blockstore, &opts, &recyclers)?;
-    do_process_blockstore_from_root(blockstore, bank0, &opts, &recyclers, None)
+    process_bank_0(
+        &bank0,
+        blockstore,
+        &opts,
+        &recyclers,
+        transaction_status_sender.clone(),
+    )?;
+    do_process_blockstore_from_root(
+        blockstore,
+        bank0,
+        &opts,
+        &recyclers,
+        transaction_status_sender,
+    )
 }
 
 // Process blockstore from a known root bank
@@ -547,6 +593,7 @@ fn confirm_full_slot(
         replay_vote_sender,
         opts.entry_callback.as_ref(),
         recyclers,
+        opts.enable_execution_traces,
     )?;
 
     if !bank.is_complete() {
@@ -565,6 +612,9 @@ pub struct ConfirmationTiming {
     pub transaction_verify_elapsed: u64,
     pub fetch_elapsed: u64,
     pub fetch_fail_elapsed: u64,
+    /// Cost model estimate of the transactions replayed so far, to compare
+    /// against the time they actually took to replay
+    pub cost_tracker: CostTracker,
 }
 
 impl Default for ConfirmationTiming {
@@ -576,6 +626,7 @@ impl Default for ConfirmationTiming {
             transaction_verify_elapsed: 0,
             fetch_elapsed: 0,
             fetch_fail_elapsed: 0,
+            cost_tracker: CostTracker::default(),
         }
     }
 }
@@ -598,6 +649,7 @@ impl ConfirmationProgress {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn confirm_slot(
     blockstore: &Blockstore,
     bank: &Arc<Bank>,
@@ -608,6 +660,7 @@ pub fn confirm_slot(
     replay_vote_sender: Option<&ReplayVoteSender>,
     entry_callback: Option<&ProcessCallback>,
     recyclers: &VerifyRecyclers,
+    enable_execution_traces: bool,
 ) -> result::Result<(), BlockstoreProcessorError> {
     let slot = bank.slot();
 
@@ -660,6 +713,7 @@ pub fn confirm_slot(
             &progress.last_entry,
             recyclers.clone(),
             bank.secp256k1_program_enabled(),
+            bank.ed25519_program_enabled(),
         );
         if entry_state.status() == EntryVerificationStatus::Failure {
             warn!("Ledger proof of history failed at slot: {}", slot);
@@ -678,6 +732,7 @@ pub fn confirm_slot(
         entry_callback,
         transaction_status_sender,
         replay_vote_sender,
+        enable_execution_traces,
     )
     .map_err(BlockstoreProcessorError::from);
     replay_elapsed.stop();
@@ -695,6 +750,11 @@ pub fn confirm_slot(
 
     process_result?;
 
+    for transaction in entries.iter().flat_map(|entry| entry.transactions.iter()) {
+        let cost = timing.cost_tracker.cost_model().calculate_cost(transaction);
+        timing.cost_tracker.add_transaction_cost(&cost);
+    }
+
     progress.num_shreds += num_shreds;
     progress.num_entries += num_entries;
     progress.num_txs += num_txs;
@@ -711,6 +771,7 @@ fn process_bank_0(
     blockstore: &Blockstore,
     opts: &ProcessOptions,
     recyclers: &VerifyRecyclers,
+    transaction_status_sender: Option<TransactionStatusSender>,
 ) -> result::Result<(), BlockstoreProcessorError> {
     assert_eq!(bank0.slot(), 0);
     let mut progress = ConfirmationProgress::new(bank0.last_blockhash());
@@ -720,11 +781,14 @@ fn process_bank_0(
         opts,
         recyclers,
         &mut progress,
-        None,
+        transaction_status_sender,
         None,
     )
     .expect("processing for bank 0 must succeed");
     bank0.freeze();
+    if let Some(bank_hash_details_dir) = &opts.bank_hash_details_dir {
+        write_bank_hash_details(bank0, bank_hash_details_dir);
+    }
     Ok(())
 }
 
@@ -1021,6 +1085,9 @@ fn process_single_slot(
     })?;
 
     bank.freeze(); // all banks handled by this routine are created from complete slots
+    if let Some(bank_hash_details_dir) = &opts.bank_hash_details_dir {
+        write_bank_hash_details(bank, bank_hash_details_dir);
+    }
 
     Ok(())
 }
@@ -1033,10 +1100,12 @@ pub struct TransactionStatusBatch {
     pub balances: TransactionBalancesSet,
     pub inner_instructions: Vec<Option<InnerInstructionsList>>,
     pub transaction_logs: Vec<TransactionLogMessages>,
+    pub execution_traces: Vec<Option<TransactionExecutionTrace>>,
 }
 
 pub type TransactionStatusSender = Sender<TransactionStatusBatch>;
 
+#[allow(clippy::too_many_arguments)]
 pub fn send_transaction_status_batch(
     bank: Arc<Bank>,
     transactions: &[Transaction],
@@ -1045,6 +1114,7 @@ pub fn send_transaction_status_batch(
     balances: TransactionBalancesSet,
     inner_instructions: Vec<Option<InnerInstructionsList>>,
     transaction_logs: Vec<TransactionLogMessages>,
+    execution_traces: Vec<Option<TransactionExecutionTrace>>,
     transaction_status_sender: TransactionStatusSender,
 ) {
     let slot = bank.slot();
@@ -1056,6 +1126,7 @@ pub fn send_transaction_status_batch(
         balances,
         inner_instructions,
         transaction_logs,
+        execution_traces,
     }) {
         trace!(
             "Slot {} transaction_status send batch failed: {:?}",
@@ -2649,7 +2720,7 @@ pub mod tests {
             ..ProcessOptions::default()
         };
         let recyclers = VerifyRecyclers::default();
-        process_bank_0(&bank0, &blockstore, &opts, &recyclers).unwrap();
+        process_bank_0(&bank0, &blockstore, &opts, &recyclers, None).unwrap();
         let bank1 = Arc::new(Bank::new_from_parent(&bank0, &Pubkey::default(), 1));
         confirm_full_slot(
             &blockstore,
@@ -2900,12 +2971,14 @@ pub mod tests {
             _balances,
             _inner_instructions,
             _log_messages,
+            _execution_traces,
         ) = batch.bank().load_execute_and_commit_transactions(
             &batch,
             MAX_PROCESSING_AGE,
             false,
             false,
             false,
+            false,
         );
         let (err, signature) = get_first_error(&batch, fee_collection_results).unwrap();
         // First error found should be for the 2nd transaction, due to iteration_order
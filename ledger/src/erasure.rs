@@ -120,6 +120,14 @@ impl Session {
 
         Ok(())
     }
+
+    /// Recover all missing data and coding shards in place. Missing shards are
+    /// `None` and are replaced with the reconstructed contents
+    pub fn decode_all_blocks(&self, shards: &mut [Option<Vec<u8>>]) -> Result<()> {
+        self.0.reconstruct(shards)?;
+
+        Ok(())
+    }
 }
 
 impl Default for Session {
@@ -16,12 +16,12 @@ use solana_perf::packet::Packet;
 use solana_rayon_threadlimit::get_thread_count;
 use solana_sdk::{
     clock::Slot,
-    hash::Hash,
+    hash::{hashv, Hash},
     packet::PACKET_DATA_SIZE,
     pubkey::Pubkey,
     signature::{Keypair, Signature, Signer},
 };
-use std::{mem::size_of, sync::Arc, time::Instant};
+use std::{convert::TryInto, mem::size_of, sync::Arc, time::Instant};
 
 use thiserror::Error;
 
@@ -49,8 +49,21 @@ pub const SIZE_OF_DATA_SHRED_PAYLOAD: usize = PACKET_DATA_SIZE
 pub const OFFSET_OF_SHRED_TYPE: usize = SIZE_OF_SIGNATURE;
 pub const OFFSET_OF_SHRED_SLOT: usize = SIZE_OF_SIGNATURE + SIZE_OF_SHRED_TYPE;
 pub const OFFSET_OF_SHRED_INDEX: usize = OFFSET_OF_SHRED_SLOT + SIZE_OF_SHRED_SLOT;
+const OFFSET_OF_SHRED_FEC_SET_INDEX: usize =
+    OFFSET_OF_SHRED_INDEX + SIZE_OF_SHRED_INDEX + size_of::<u16>();
 pub const SHRED_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - SIZE_OF_NONCE;
 
+pub const SIZE_OF_MERKLE_PROOF_ENTRY: usize = 20;
+/// An erasure set holds at most 2 * MAX_DATA_SHREDS_PER_FEC_BLOCK shreds, so
+/// Merkle proofs never have more than log2(64) entries
+pub const MAX_MERKLE_PROOF_SIZE: u8 = 6;
+/// Data capacity of a Merkle variant data shred, sized for the deepest proof
+pub const SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD: usize =
+    merkle_data_shred_capacity(MAX_MERKLE_PROOF_SIZE);
+
+const MERKLE_HASH_PREFIX_LEAF: &[u8] = b"\x00SOLANA_MERKLE_SHREDS_LEAF";
+const MERKLE_HASH_PREFIX_NODE: &[u8] = b"\x01SOLANA_MERKLE_SHREDS_NODE";
+
 thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
                     .num_threads(get_thread_count())
                     .thread_name(|ix| format!("shredder_{}", ix))
@@ -60,6 +73,12 @@ thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::
 /// The constants that define if a shred is data or coding
 pub const DATA_SHRED: u8 = 0b1010_0101;
 pub const CODING_SHRED: u8 = 0b0101_1010;
+/// Merkle variant shreds carry their type in the high nibble and the number of
+/// entries in their Merkle proof in the low nibble
+pub const MERKLE_DATA_SHRED: u8 = 0b1000_0000;
+pub const MERKLE_CODING_SHRED: u8 = 0b0100_0000;
+const MERKLE_SHRED_TYPE_MASK: u8 = 0b1111_0000;
+const MERKLE_PROOF_SIZE_MASK: u8 = 0b0000_1111;
 
 pub const MAX_DATA_SHREDS_PER_FEC_BLOCK: u32 = 32;
 pub const RECOMMENDED_FEC_RATE: f32 = 1.0;
@@ -98,6 +117,25 @@ impl Default for ShredType {
     }
 }
 
+impl ShredType {
+    pub fn is_data(&self) -> bool {
+        self.0 == DATA_SHRED || self.0 & MERKLE_SHRED_TYPE_MASK == MERKLE_DATA_SHRED
+    }
+
+    pub fn is_code(&self) -> bool {
+        self.0 == CODING_SHRED || self.0 & MERKLE_SHRED_TYPE_MASK == MERKLE_CODING_SHRED
+    }
+
+    /// Returns the number of entries in the Merkle proof if this is a Merkle
+    /// variant shred, or None for legacy shreds which are signed individually
+    pub fn merkle_proof_size(&self) -> Option<u8> {
+        match self.0 & MERKLE_SHRED_TYPE_MASK {
+            MERKLE_DATA_SHRED | MERKLE_CODING_SHRED => Some(self.0 & MERKLE_PROOF_SIZE_MASK),
+            _ => None,
+        }
+    }
+}
+
 /// A common header that is present in data and code shred headers
 #[derive(Serialize, Clone, Deserialize, Default, PartialEq, Debug)]
 pub struct ShredCommonHeader {
@@ -249,7 +287,12 @@ impl Shred {
         // the `expected_data_size`.
         assert!(payload.len() >= expected_data_size);
         payload.truncate(expected_data_size);
-        let shred = if common_header.shred_type == ShredType(CODING_SHRED) {
+        if let Some(proof_size) = common_header.shred_type.merkle_proof_size() {
+            if proof_size > MAX_MERKLE_PROOF_SIZE {
+                return Err(ShredError::InvalidShredType);
+            }
+        }
+        let shred = if common_header.shred_type.is_code() {
             let coding_header: CodingShredHeader =
                 Self::deserialize_obj(&mut start, SIZE_OF_CODING_SHRED_HEADER, &payload)?;
             Self {
@@ -258,7 +301,7 @@ impl Shred {
                 coding_header,
                 payload,
             }
-        } else if common_header.shred_type == ShredType(DATA_SHRED) {
+        } else if common_header.shred_type.is_data() {
             let size_of_data_shred_header = SIZE_OF_DATA_SHRED_HEADER;
             let data_header: DataShredHeader =
                 Self::deserialize_obj(&mut start, size_of_data_shred_header, &payload)?;
@@ -295,7 +338,7 @@ impl Shred {
             &common_header,
         )
         .expect("Failed to write header into shred buffer");
-        if common_header.shred_type == ShredType(DATA_SHRED) {
+        if common_header.shred_type.is_data() {
             Self::serialize_obj_into(
                 &mut start,
                 SIZE_OF_DATA_SHRED_HEADER,
@@ -303,7 +346,7 @@ impl Shred {
                 &data_header,
             )
             .expect("Failed to write data header into shred buffer");
-        } else if common_header.shred_type == ShredType(CODING_SHRED) {
+        } else if common_header.shred_type.is_code() {
             Self::serialize_obj_into(
                 &mut start,
                 SIZE_OF_CODING_SHRED_HEADER,
@@ -370,11 +413,32 @@ impl Shred {
         .unwrap();
     }
 
+    fn set_shred_type(&mut self, shred_type: ShredType) {
+        self.common_header.shred_type = shred_type;
+        Self::serialize_obj_into(
+            &mut 0,
+            SIZE_OF_COMMON_SHRED_HEADER,
+            &mut self.payload,
+            &self.common_header,
+        )
+        .unwrap();
+    }
+
     pub fn signature(&self) -> Signature {
         self.common_header.signature
     }
 
     pub fn seed(&self) -> [u8; 32] {
+        if self.is_merkle() {
+            // All shreds of a Merkle erasure set share one signature, so mix
+            // in the index and type to spread the set across peers
+            return hashv(&[
+                self.common_header.signature.as_ref(),
+                &self.index().to_le_bytes(),
+                &[self.common_header.shred_type.0],
+            ])
+            .to_bytes();
+        }
         let mut seed = [0; 32];
         let seed_len = seed.len();
         let sig = self.common_header.signature.as_ref();
@@ -383,10 +447,24 @@ impl Shred {
     }
 
     pub fn is_data(&self) -> bool {
-        self.common_header.shred_type == ShredType(DATA_SHRED)
+        self.common_header.shred_type.is_data()
     }
     pub fn is_code(&self) -> bool {
-        self.common_header.shred_type == ShredType(CODING_SHRED)
+        self.common_header.shred_type.is_code()
+    }
+
+    pub fn is_merkle(&self) -> bool {
+        self.merkle_proof_size().is_some()
+    }
+
+    pub fn merkle_proof_size(&self) -> Option<u8> {
+        self.common_header.shred_type.merkle_proof_size()
+    }
+
+    /// Root of the Merkle tree over the shred's erasure set, which is what
+    /// the leader signs for Merkle variant shreds
+    pub fn merkle_root(&self) -> Option<Hash> {
+        get_merkle_root(&self.payload)
     }
 
     pub fn last_in_slot(&self) -> bool {
@@ -429,8 +507,15 @@ impl Shred {
     }
 
     pub fn verify(&self, pubkey: &Pubkey) -> bool {
-        self.signature()
-            .verify(pubkey.as_ref(), &self.payload[SIZE_OF_SIGNATURE..])
+        if self.is_merkle() {
+            match self.merkle_root() {
+                Some(root) => self.signature().verify(pubkey.as_ref(), root.as_ref()),
+                None => false,
+            }
+        } else {
+            self.signature()
+                .verify(pubkey.as_ref(), &self.payload[SIZE_OF_SIGNATURE..])
+        }
     }
 }
 
@@ -590,6 +675,167 @@ impl Shredder {
         coding_shreds
     }
 
+    /// Generates data and coding shreds where each erasure set carries Merkle
+    /// proofs to a root that is signed once, instead of signing every shred
+    pub fn entries_to_merkle_shreds(
+        &self,
+        entries: &[Entry],
+        is_last_in_slot: bool,
+        next_shred_index: u32,
+    ) -> (Vec<Shred>, Vec<Shred>, u32) {
+        let now = Instant::now();
+        let serialized_shreds =
+            bincode::serialize(entries).expect("Expect to serialize all entries");
+        let serialize_time = now.elapsed().as_millis();
+
+        let now = Instant::now();
+        let no_header_size = SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD;
+        let num_shreds = (serialized_shreds.len() + no_header_size - 1) / no_header_size;
+        let last_shred_index = next_shred_index + num_shreds as u32 - 1;
+        let chunks: Vec<_> = serialized_shreds.chunks(no_header_size).collect();
+        let fec_sets: Vec<(Vec<Shred>, Vec<Shred>)> = PAR_THREAD_POOL.with(|thread_pool| {
+            thread_pool.borrow().install(|| {
+                chunks
+                    .par_chunks(MAX_DATA_SHREDS_PER_FEC_BLOCK as usize)
+                    .enumerate()
+                    .map(|(i, fec_set_chunks)| {
+                        let fec_set_index =
+                            next_shred_index + i as u32 * MAX_DATA_SHREDS_PER_FEC_BLOCK;
+                        self.make_merkle_fec_set(
+                            fec_set_chunks,
+                            fec_set_index,
+                            last_shred_index,
+                            is_last_in_slot,
+                        )
+                    })
+                    .collect()
+            })
+        });
+        let (data_shreds, coding_shreds): (Vec<_>, Vec<_>) = fec_sets.into_iter().unzip();
+        let data_shreds: Vec<_> = data_shreds.into_iter().flatten().collect();
+        let coding_shreds: Vec<_> = coding_shreds.into_iter().flatten().collect();
+        let gen_merkle_time = now.elapsed().as_millis();
+        datapoint_debug!(
+            "shredding-stats",
+            ("slot", self.slot as i64, i64),
+            ("num_data_shreds", data_shreds.len() as i64, i64),
+            ("num_coding_shreds", coding_shreds.len() as i64, i64),
+            ("serializing", serialize_time as i64, i64),
+            ("gen_merkle", gen_merkle_time as i64, i64),
+        );
+        (data_shreds, coding_shreds, last_shred_index + 1)
+    }
+
+    fn make_merkle_fec_set(
+        &self,
+        chunks: &[&[u8]],
+        fec_set_index: u32,
+        last_shred_index: u32,
+        is_last_in_slot: bool,
+    ) -> (Vec<Shred>, Vec<Shred>) {
+        let num_data = chunks.len();
+        let num_coding = if self.fec_rate != 0.0 {
+            Self::calculate_num_coding_shreds(num_data, self.fec_rate)
+        } else {
+            0
+        };
+        let proof_size = merkle_proof_size(num_data + num_coding);
+        let shard_size = merkle_erasure_shard_size(proof_size);
+
+        let mut data_shreds: Vec<_> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, shred_data)| {
+                let shred_index = fec_set_index + i as u32;
+                let (is_last_in_fec_set, is_last_in_slot) = if shred_index == last_shred_index {
+                    (true, is_last_in_slot)
+                } else {
+                    (false, false)
+                };
+                let mut shred = Shred::new_from_data(
+                    self.slot,
+                    shred_index,
+                    (self.slot - self.parent_slot) as u16,
+                    Some(*shred_data),
+                    is_last_in_fec_set,
+                    is_last_in_slot,
+                    self.reference_tick,
+                    self.version,
+                    fec_set_index,
+                );
+                shred.set_shred_type(ShredType(MERKLE_DATA_SHRED | proof_size));
+                shred
+            })
+            .collect();
+
+        let mut coding_shreds: Vec<_> = (0..num_coding)
+            .map(|i| {
+                let (mut common_header, coding_header) = Self::new_coding_shred_header(
+                    self.slot,
+                    fec_set_index + i as u32,
+                    fec_set_index,
+                    num_data,
+                    num_coding,
+                    i,
+                    self.version,
+                );
+                common_header.shred_type = ShredType(MERKLE_CODING_SHRED | proof_size);
+                Shred::new_empty_from_header(
+                    common_header,
+                    DataShredHeader::default(),
+                    coding_header,
+                )
+            })
+            .collect();
+        if num_coding > 0 {
+            let session =
+                Session::new(num_data, num_coding).expect("Failed to create erasure session");
+            let data_ptrs: Vec<_> = data_shreds
+                .iter()
+                .map(|shred| &shred.payload[SIZE_OF_SIGNATURE..SIZE_OF_SIGNATURE + shard_size])
+                .collect();
+            let coding_block_offset = SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_CODING_SHRED_HEADER;
+            let mut coding_ptrs: Vec<_> = coding_shreds
+                .iter_mut()
+                .map(|shred| {
+                    &mut shred.payload[coding_block_offset..coding_block_offset + shard_size]
+                })
+                .collect();
+            session
+                .encode(&data_ptrs, coding_ptrs.as_mut_slice())
+                .expect("Failed in erasure encode");
+        }
+
+        // Sign the root of the erasure set once, and give each shred the
+        // proof of its own leaf
+        let leaves: Vec<_> = data_shreds
+            .iter()
+            .chain(coding_shreds.iter())
+            .map(|shred| merkle_leaf(&shred.payload, proof_size))
+            .collect();
+        let num_leaves = leaves.len();
+        let tree = make_merkle_tree(leaves);
+        let root = tree.last().expect("merkle tree must not be empty");
+        let signature = self.keypair.sign_message(root.as_ref());
+        let proof_offset = merkle_proof_offset(proof_size);
+        for (index, shred) in data_shreds
+            .iter_mut()
+            .chain(coding_shreds.iter_mut())
+            .enumerate()
+        {
+            let proof = make_merkle_proof(index, num_leaves, &tree);
+            for (entry, buf) in proof
+                .iter()
+                .zip(shred.payload[proof_offset..].chunks_mut(SIZE_OF_MERKLE_PROOF_ENTRY))
+            {
+                buf.copy_from_slice(entry);
+            }
+            shred.payload[..SIZE_OF_SIGNATURE].copy_from_slice(signature.as_ref());
+            shred.common_header.signature = signature;
+        }
+        (data_shreds, coding_shreds)
+    }
+
     pub fn sign_shred(signer: &Keypair, shred: &mut Shred) {
         let signature = signer.sign_message(&shred.payload[SIZE_OF_SIGNATURE..]);
         bincode::serialize_into(&mut shred.payload[..SIZE_OF_SIGNATURE], &signature)
@@ -750,6 +996,16 @@ impl Shredder {
         slot: Slot,
     ) -> std::result::Result<Vec<Shred>, reed_solomon_erasure::Error> {
         Self::verify_consistent_shred_payload_sizes(&"try_recovery()", &shreds)?;
+        if shreds[0].is_merkle() {
+            return Self::try_merkle_recovery(
+                shreds,
+                num_data,
+                num_coding,
+                first_index,
+                first_code_index,
+                slot,
+            );
+        }
         let mut recovered_data = vec![];
         let fec_set_size = num_data + num_coding;
 
@@ -837,6 +1093,137 @@ impl Shredder {
         Ok(recovered_data)
     }
 
+    fn try_merkle_recovery(
+        shreds: Vec<Shred>,
+        num_data: usize,
+        num_coding: usize,
+        first_index: usize,
+        first_code_index: usize,
+        slot: Slot,
+    ) -> std::result::Result<Vec<Shred>, reed_solomon_erasure::Error> {
+        let fec_set_size = num_data + num_coding;
+        if num_coding == 0 || shreds.len() >= fec_set_size {
+            return Ok(vec![]);
+        }
+        let proof_size = merkle_proof_size(fec_set_size);
+        if shreds
+            .iter()
+            .any(|shred| shred.merkle_proof_size() != Some(proof_size))
+        {
+            return Err(reed_solomon_erasure::Error::IncorrectShardSize);
+        }
+        let root = shreds[0].merkle_root();
+        if root.is_none() || shreds.iter().any(|shred| shred.merkle_root() != root) {
+            warn!("Merkle roots within erasure set of slot {} mismatch", slot);
+            return Ok(vec![]);
+        }
+        let signature = shreds[0].signature();
+        let version = shreds[0].version();
+        let shard_size = merkle_erasure_shard_size(proof_size);
+        let coding_block_offset = SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_CODING_SHRED_HEADER;
+
+        let mut payloads: Vec<Option<Vec<u8>>> = vec![None; fec_set_size];
+        for shred in shreds {
+            let position = Self::get_shred_index(&shred, num_data, first_index, first_code_index)
+                .wrapping_sub(first_index);
+            if position < fec_set_size {
+                payloads[position] = Some(shred.payload);
+            }
+        }
+        let present: Vec<_> = payloads.iter().map(Option::is_some).collect();
+        let mut shards: Vec<Option<Vec<u8>>> = payloads
+            .iter()
+            .enumerate()
+            .map(|(position, payload)| {
+                let offset = if position < num_data {
+                    SIZE_OF_SIGNATURE
+                } else {
+                    coding_block_offset
+                };
+                payload
+                    .as_ref()
+                    .map(|payload| payload[offset..offset + shard_size].to_vec())
+            })
+            .collect();
+        Session::new(num_data, num_coding)?.decode_all_blocks(&mut shards)?;
+
+        // Rebuild the missing shreds so the Merkle tree can be recomputed
+        for (position, shard) in shards.into_iter().enumerate() {
+            if present[position] {
+                continue;
+            }
+            let shard = shard.ok_or(reed_solomon_erasure::Error::TooFewShardsPresent)?;
+            let payload = if position < num_data {
+                let mut payload = vec![0; SHRED_PAYLOAD_SIZE];
+                payload[SIZE_OF_SIGNATURE..SIZE_OF_SIGNATURE + shard_size].copy_from_slice(&shard);
+                payload
+            } else {
+                let i = position - num_data;
+                let (mut common_header, coding_header) = Self::new_coding_shred_header(
+                    slot,
+                    (first_code_index + i) as u32,
+                    first_index as u32,
+                    num_data,
+                    num_coding,
+                    i,
+                    version,
+                );
+                common_header.shred_type = ShredType(MERKLE_CODING_SHRED | proof_size);
+                let mut payload = Shred::new_empty_from_header(
+                    common_header,
+                    DataShredHeader::default(),
+                    coding_header,
+                )
+                .payload;
+                payload[coding_block_offset..coding_block_offset + shard_size]
+                    .copy_from_slice(&shard);
+                payload
+            };
+            payloads[position] = Some(payload);
+        }
+        let payloads: Vec<Vec<u8>> = payloads.into_iter().map(Option::unwrap).collect();
+        let leaves: Vec<_> = payloads
+            .iter()
+            .map(|payload| merkle_leaf(payload, proof_size))
+            .collect();
+        let tree = make_merkle_tree(leaves);
+        if tree.last() != root.as_ref() {
+            warn!(
+                "Recovered erasure set of slot {} does not match its Merkle root",
+                slot
+            );
+            return Ok(vec![]);
+        }
+
+        let proof_offset = merkle_proof_offset(proof_size);
+        let recovered_data = payloads
+            .into_iter()
+            .take(num_data)
+            .enumerate()
+            .filter(|(position, _)| !present[*position])
+            .filter_map(|(position, mut payload)| {
+                payload[..SIZE_OF_SIGNATURE].copy_from_slice(signature.as_ref());
+                let proof = make_merkle_proof(position, fec_set_size, &tree);
+                for (entry, buf) in proof
+                    .iter()
+                    .zip(payload[proof_offset..].chunks_mut(SIZE_OF_MERKLE_PROOF_ENTRY))
+                {
+                    buf.copy_from_slice(entry);
+                }
+                let shred = Shred::new_from_serialized_shred(payload).ok()?;
+                let shred_index = shred.index() as usize;
+                // Valid shred must be in the same slot and at the expected index
+                if shred.is_data() && shred.slot() == slot && shred_index == first_index + position
+                {
+                    Some(shred)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Ok(recovered_data)
+    }
+
     /// Combines all shreds to recreate the original buffer
     pub fn deshred(shreds: &[Shred]) -> std::result::Result<Vec<u8>, reed_solomon_erasure::Error> {
         let num_data = shreds.len();
@@ -854,7 +1241,7 @@ impl Shredder {
                 return Err(reed_solomon_erasure::Error::TooFewDataShards);
             }
 
-            shreds.iter().map(|shred| &shred.payload).collect()
+            shreds
         };
 
         Ok(Self::reassemble_payload(num_data, data_shred_bufs))
@@ -873,13 +1260,21 @@ impl Shredder {
         }
     }
 
-    fn reassemble_payload(num_data: usize, data_shred_bufs: Vec<&Vec<u8>>) -> Vec<u8> {
+    fn reassemble_payload(num_data: usize, data_shreds: &[Shred]) -> Vec<u8> {
         let valid_data_len = SHRED_PAYLOAD_SIZE - SIZE_OF_DATA_SHRED_IGNORED_TAIL;
-        data_shred_bufs[..num_data]
+        data_shreds[..num_data]
             .iter()
-            .flat_map(|data| {
+            .flat_map(|shred| {
                 let offset = SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_DATA_SHRED_HEADER;
-                data[offset..valid_data_len].iter()
+                // Merkle data shreds are padded up to their erasure shard, so
+                // only the bytes accounted for in the header are entry data
+                let end = match shred.merkle_proof_size() {
+                    Some(_) => (shred.data_header.size as usize)
+                        .max(offset)
+                        .min(offset + SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD),
+                    None => valid_data_len,
+                };
+                shred.payload[offset..end].iter()
             })
             .cloned()
             .collect()
@@ -910,6 +1305,128 @@ impl Shredder {
     }
 }
 
+/// Offset of the Merkle proof, which occupies the tail of the shred payload
+const fn merkle_proof_offset(proof_size: u8) -> usize {
+    SHRED_PAYLOAD_SIZE - proof_size as usize * SIZE_OF_MERKLE_PROOF_ENTRY
+}
+
+/// Size of the erasure coded portion of Merkle variant shreds. For coding
+/// shreds this is everything between the headers and the proof
+const fn merkle_erasure_shard_size(proof_size: u8) -> usize {
+    merkle_proof_offset(proof_size) - SIZE_OF_COMMON_SHRED_HEADER - SIZE_OF_CODING_SHRED_HEADER
+}
+
+/// Number of entry bytes a Merkle variant data shred can hold. The data shard
+/// starts after the signature and includes the data shred headers
+pub const fn merkle_data_shred_capacity(proof_size: u8) -> usize {
+    merkle_erasure_shard_size(proof_size) + SIZE_OF_SIGNATURE
+        - SIZE_OF_COMMON_SHRED_HEADER
+        - SIZE_OF_DATA_SHRED_HEADER
+}
+
+/// Number of proof entries needed for a Merkle tree over `num_shreds` leaves
+pub fn merkle_proof_size(num_shreds: usize) -> u8 {
+    let mut proof_size = 0;
+    while (1usize << proof_size) < num_shreds {
+        proof_size += 1;
+    }
+    proof_size
+}
+
+/// Leaves cover everything the signature would have covered in a legacy shred,
+/// except for the proof itself
+fn merkle_leaf(payload: &[u8], proof_size: u8) -> Hash {
+    hashv(&[
+        MERKLE_HASH_PREFIX_LEAF,
+        &payload[SIZE_OF_SIGNATURE..merkle_proof_offset(proof_size)],
+    ])
+}
+
+fn join_merkle_nodes(node: &[u8], other: &[u8]) -> Hash {
+    hashv(&[
+        MERKLE_HASH_PREFIX_NODE,
+        &node[..SIZE_OF_MERKLE_PROOF_ENTRY],
+        &other[..SIZE_OF_MERKLE_PROOF_ENTRY],
+    ])
+}
+
+/// Returns all nodes of the tree, level by level, with the root last. A node
+/// without a sibling is joined with itself
+fn make_merkle_tree(mut nodes: Vec<Hash>) -> Vec<Hash> {
+    let mut size = nodes.len();
+    while size > 1 {
+        let offset = nodes.len() - size;
+        for index in (offset..offset + size).step_by(2) {
+            let other = (index + 1).min(offset + size - 1);
+            let parent = join_merkle_nodes(nodes[index].as_ref(), nodes[other].as_ref());
+            nodes.push(parent);
+        }
+        size = nodes.len() - offset - size;
+    }
+    nodes
+}
+
+fn make_merkle_proof(mut index: usize, mut size: usize, tree: &[Hash]) -> Vec<&[u8]> {
+    let mut offset = 0;
+    let mut proof = vec![];
+    while size > 1 {
+        let node = &tree[offset + (index ^ 1).min(size - 1)];
+        proof.push(&node.as_ref()[..SIZE_OF_MERKLE_PROOF_ENTRY]);
+        offset += size;
+        size = (size + 1) >> 1;
+        index >>= 1;
+    }
+    proof
+}
+
+fn merkle_root_from_proof(index: usize, leaf: Hash, proof: &[u8]) -> Option<Hash> {
+    let (index, root) =
+        proof
+            .chunks(SIZE_OF_MERKLE_PROOF_ENTRY)
+            .fold((index, leaf), |(index, node), other| {
+                let parent = if index % 2 == 0 {
+                    join_merkle_nodes(node.as_ref(), other)
+                } else {
+                    join_merkle_nodes(other, node.as_ref())
+                };
+                (index >> 1, parent)
+            });
+    if index == 0 {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+/// Computes the Merkle root of a serialized shred from its proof, without
+/// deserializing the shred. Returns None for legacy or malformed shreds
+pub fn get_merkle_root(payload: &[u8]) -> Option<Hash> {
+    let shred_type = ShredType(*payload.get(OFFSET_OF_SHRED_TYPE)?);
+    let proof_size = shred_type.merkle_proof_size()?;
+    if proof_size > MAX_MERKLE_PROOF_SIZE || payload.len() < SHRED_PAYLOAD_SIZE {
+        return None;
+    }
+    let read_u16 = |offset: usize| {
+        u16::from_le_bytes(payload[offset..offset + 2].try_into().unwrap()) as usize
+    };
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes(payload[offset..offset + 4].try_into().unwrap()) as usize
+    };
+    let position = if shred_type.is_data() {
+        let index = read_u32(OFFSET_OF_SHRED_INDEX);
+        index.checked_sub(read_u32(OFFSET_OF_SHRED_FEC_SET_INDEX))?
+    } else {
+        // num_data_shreds + position from the coding shred header
+        read_u16(SIZE_OF_COMMON_SHRED_HEADER) + read_u16(SIZE_OF_COMMON_SHRED_HEADER + 4)
+    };
+    let leaf = merkle_leaf(payload, proof_size);
+    merkle_root_from_proof(
+        position,
+        leaf,
+        &payload[merkle_proof_offset(proof_size)..SHRED_PAYLOAD_SIZE],
+    )
+}
+
 pub fn max_ticks_per_n_shreds(num_shreds: u64, shred_data_size: Option<usize>) -> u64 {
     let ticks = create_ticks(1, 0, Hash::default());
     max_entries_per_n_shred(&ticks[0], num_shreds, shred_data_size)
@@ -1648,4 +2165,98 @@ pub mod tests {
             })
         );
     }
+
+    fn make_merkle_test_entries(num_entries: usize) -> Vec<Entry> {
+        (0..num_entries)
+            .map(|_| {
+                let keypair0 = Keypair::new();
+                let keypair1 = Keypair::new();
+                let tx0 =
+                    system_transaction::transfer(&keypair0, &keypair1.pubkey(), 1, Hash::default());
+                Entry::new(&Hash::default(), 1, vec![tx0])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merkle_shredder() {
+        let keypair = Arc::new(Keypair::new());
+        let slot = 0x1234_5678_9abc_def0;
+        let shredder = Shredder::new(slot, slot - 5, 1.0, keypair.clone(), 0, 0)
+            .expect("Failed in creating shredder");
+        let entries = make_merkle_test_entries(200);
+
+        let (data_shreds, coding_shreds, next_index) =
+            shredder.entries_to_merkle_shreds(&entries, true, 0);
+        assert_eq!(next_index as usize, data_shreds.len());
+        assert_eq!(data_shreds.len(), coding_shreds.len());
+        assert!(data_shreds.len() > MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+
+        for shred in data_shreds.iter().chain(coding_shreds.iter()) {
+            assert!(shred.is_merkle());
+            assert!(shred.verify(&keypair.pubkey()));
+            // Round trip through the wire format keeps the shred verifiable
+            let deserialized = Shred::new_from_serialized_shred(shred.payload.clone()).unwrap();
+            assert_eq!(&deserialized, shred);
+            assert!(deserialized.verify(&keypair.pubkey()));
+            assert!(!shred.verify(&Keypair::new().pubkey()));
+        }
+        // Shreds in the same erasure set share one root and one signature
+        for (shred, other) in data_shreds.iter().zip(data_shreds.iter().skip(1)) {
+            let same_set = shred.common_header.fec_set_index == other.common_header.fec_set_index;
+            assert_eq!(shred.merkle_root() == other.merkle_root(), same_set);
+            assert_eq!(shred.signature() == other.signature(), same_set);
+        }
+        assert!(data_shreds.last().unwrap().last_in_slot());
+
+        let deshred_payload = Shredder::deshred(&data_shreds).unwrap();
+        let deshred_entries: Vec<Entry> = bincode::deserialize(&deshred_payload).unwrap();
+        assert_eq!(entries, deshred_entries);
+
+        // A modified shred no longer verifies against its own proof
+        let mut shred = data_shreds[0].clone();
+        shred.payload[SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_DATA_SHRED_HEADER] ^= 1;
+        assert!(!shred.verify(&keypair.pubkey()));
+    }
+
+    #[test]
+    fn test_merkle_shred_recovery() {
+        let keypair = Arc::new(Keypair::new());
+        let slot = 0x1234_5678_9abc_def0;
+        let shredder = Shredder::new(slot, slot - 5, 1.0, keypair.clone(), 0, 0)
+            .expect("Failed in creating shredder");
+        let entries = make_merkle_test_entries(20);
+
+        let (data_shreds, coding_shreds, _) = shredder.entries_to_merkle_shreds(&entries, true, 0);
+        let num_data = data_shreds.len();
+        let num_coding = coding_shreds.len();
+        assert!(num_data > 2 && num_data <= MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+
+        // Drop every other data shred and recover them from the coding shreds
+        let shreds: Vec<_> = data_shreds
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 2 == 1)
+            .map(|(_, shred)| shred.clone())
+            .chain(coding_shreds.iter().cloned())
+            .collect();
+        let recovered = Shredder::try_recovery(shreds, num_data, num_coding, 0, 0, slot).unwrap();
+        let expected: Vec<_> = data_shreds.iter().step_by(2).cloned().collect();
+        assert_eq!(recovered, expected);
+        assert!(recovered
+            .iter()
+            .all(|shred| shred.verify(&keypair.pubkey())));
+
+        // Recovery from only coding shreds yields the rest of the data shreds
+        let recovered =
+            Shredder::try_recovery(coding_shreds.clone(), num_data, num_coding, 0, 0, slot)
+                .unwrap();
+        assert_eq!(recovered, data_shreds);
+
+        // A coding shred that does not match the signed root aborts recovery
+        let mut shreds = coding_shreds;
+        shreds[0].payload[SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_CODING_SHRED_HEADER] ^= 1;
+        let recovered = Shredder::try_recovery(shreds, num_data, num_coding, 0, 0, slot).unwrap();
+        assert!(recovered.is_empty());
+    }
 }
@@ -1,5 +1,5 @@
 #![allow(clippy::implicit_hasher)]
-use crate::shred::{ShredType, SIZE_OF_NONCE};
+use crate::shred::{get_merkle_root, ShredType, OFFSET_OF_SHRED_TYPE, SIZE_OF_NONCE};
 use rayon::{
     iter::{
         IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
@@ -69,12 +69,30 @@ pub fn verify_shred_cpu(packet: &Packet, slot_leaders: &HashMap<u64, [u8; 32]>)
     }
     let signature = Signature::new(&packet.data[sig_start..sig_end]);
     trace!("signature {}", signature);
+    if is_merkle_shred(packet) {
+        // Merkle variant shreds are signed over the root of their erasure set
+        let root = match get_merkle_root(&packet.data[..msg_end]) {
+            Some(root) => root,
+            None => return Some(0),
+        };
+        if !signature.verify(pubkey, root.as_ref()) {
+            return Some(0);
+        }
+        return Some(1);
+    }
     if !signature.verify(pubkey, &packet.data[msg_start..msg_end]) {
         return Some(0);
     }
     Some(1)
 }
 
+fn is_merkle_shred(packet: &Packet) -> bool {
+    packet.meta.size > OFFSET_OF_SHRED_TYPE
+        && ShredType(packet.data[OFFSET_OF_SHRED_TYPE])
+            .merkle_proof_size()
+            .is_some()
+}
+
 fn verify_shreds_cpu(batches: &[Packets], slot_leaders: &HashMap<u64, [u8; 32]>) -> Vec<Vec<u8>> {
     use rayon::prelude::*;
     let count = batch_size(batches);
@@ -291,6 +309,23 @@ pub fn verify_shreds_gpu(
 
     sigverify::copy_return_values(&v_sig_lens, &out, &mut rvs);
 
+    // The GPU verifies signatures over the whole payload, which does not hold
+    // for Merkle variant shreds, so those are verified on the CPU instead
+    SIGVERIFY_THREAD_POOL.install(|| {
+        batches
+            .into_par_iter()
+            .zip(rvs.par_iter_mut())
+            .for_each(|(p, rv)| {
+                p.packets
+                    .iter()
+                    .zip(rv.iter_mut())
+                    .filter(|(packet, _)| is_merkle_shred(packet))
+                    .for_each(|(packet, rv)| {
+                        *rv = verify_shred_cpu(packet, slot_leaders).unwrap_or(0)
+                    })
+            });
+    });
+
     inc_new_counter_debug!("ed25519_shred_verify_gpu", count);
     rvs
 }
@@ -558,6 +593,44 @@ pub mod tests {
         run_test_sigverify_shreds_cpu(0xdead_c0de);
     }
 
+    #[test]
+    fn test_sigverify_merkle_shreds_cpu() {
+        solana_logger::setup();
+        let slot = 0xdead_c0de;
+        let keypair = Arc::new(Keypair::new());
+        let shredder = Shredder::new(slot, slot - 5, 1.0, keypair.clone(), 0, 0).unwrap();
+        let entries = crate::entry::create_ticks(100, 0, solana_sdk::hash::Hash::default());
+        let (data_shreds, coding_shreds, _) = shredder.entries_to_merkle_shreds(&entries, true, 0);
+        let mut batch = [Packets::default()];
+        for shred in data_shreds.iter().chain(coding_shreds.iter()) {
+            let mut packet = Packet::default();
+            shred.copy_to_packet(&mut packet);
+            batch[0].packets.push(packet);
+        }
+        let num_packets = batch[0].packets.len();
+
+        let leader_slots = [(slot, keypair.pubkey().to_bytes())]
+            .iter()
+            .cloned()
+            .collect();
+        let rv = verify_shreds_cpu(&batch, &leader_slots);
+        assert_eq!(rv, vec![vec![1; num_packets]]);
+
+        // Corrupting a shred invalidates its proof, but not the rest of the set
+        batch[0].packets[0].data[SIZE_OF_DATA_SHRED_PAYLOAD / 2] ^= 1;
+        let rv = verify_shreds_cpu(&batch, &leader_slots);
+        assert_eq!(rv[0][0], 0);
+        assert!(rv[0][1..].iter().all(|rv| *rv == 1));
+
+        let wrong_keypair = Keypair::new();
+        let leader_slots = [(slot, wrong_keypair.pubkey().to_bytes())]
+            .iter()
+            .cloned()
+            .collect();
+        let rv = verify_shreds_cpu(&batch, &leader_slots);
+        assert_eq!(rv, vec![vec![0; num_packets]]);
+    }
+
     fn run_test_sigverify_shreds_gpu(slot: Slot) {
         solana_logger::setup();
         let recycler_cache = RecyclerCache::default();
@@ -8,9 +8,11 @@ use solana_client::{
 };
 use solana_core::{
     broadcast_stage::BroadcastStageType,
-    cluster_info::VALIDATOR_PORT_RANGE,
+    cluster_info::{ClusterInfo, VALIDATOR_PORT_RANGE},
     consensus::{Tower, SWITCH_FORK_THRESHOLD, VOTE_THRESHOLD_DEPTH},
-    gossip_service::discover_cluster,
+    contact_info::ContactInfo,
+    gossip_service::{discover_cluster, GossipService},
+    ledger_cleanup_service::LedgerCleanupConfig,
     optimistic_confirmation_verifier::OptimisticConfirmationVerifier,
     validator::ValidatorConfig,
 };
@@ -63,7 +65,7 @@ fn test_ledger_cleanup_service() {
     error!("test_ledger_cleanup_service");
     let num_nodes = 3;
     let mut validator_config = ValidatorConfig::default();
-    validator_config.max_ledger_shreds = Some(100);
+    validator_config.ledger_cleanup_config = Some(LedgerCleanupConfig::with_max_ledger_shreds(100));
     let config = ClusterConfig {
         cluster_lamports: 10_000,
         poh_config: PohConfig::new_sleep(Duration::from_millis(50)),
@@ -110,6 +112,40 @@ fn test_spend_and_verify_all_nodes_1() {
     );
 }
 
+#[test]
+#[serial]
+fn test_duplicate_node_instance_exits() {
+    solana_logger::setup();
+    error!("test_duplicate_node_instance_exits");
+    let mut cluster = LocalCluster::new_with_equal_stakes(1, 10_000, 100);
+    let node_pubkey = cluster.entry_point_info.id;
+    let node_keypair = cluster.validators[&node_pubkey].info.keypair.clone();
+
+    // Start a second gossip instance with the same identity. The running
+    // validator should observe the newer instance and exit.
+    let exit = Arc::new(AtomicBool::new(false));
+    let (contact_info, gossip_socket, _ip_echo) =
+        ClusterInfo::spy_node(&node_pubkey, cluster.entry_point_info.shred_version);
+    let cluster_info = ClusterInfo::new(contact_info, node_keypair);
+    cluster_info.set_entrypoint(ContactInfo::new_gossip_entry_point(
+        &cluster.entry_point_info.gossip,
+    ));
+    let gossip_service =
+        GossipService::new(&Arc::new(cluster_info), None, gossip_socket, None, &exit);
+
+    let validator = cluster
+        .validators
+        .get_mut(&node_pubkey)
+        .unwrap()
+        .validator
+        .take()
+        .unwrap();
+    validator.join().expect("validator exit");
+
+    exit.store(true, Ordering::Relaxed);
+    gossip_service.join().unwrap();
+}
+
 #[test]
 #[serial]
 fn test_spend_and_verify_all_nodes_2() {
@@ -1946,6 +1982,7 @@ fn setup_snapshot_validator_config(
         snapshot_path: PathBuf::from(snapshot_dir.path()),
         compression: CompressionType::Bzip2,
         snapshot_version: snapshot_utils::SnapshotVersion::default(),
+        include_storage_indexes: false,
     };
 
     // Create the account paths
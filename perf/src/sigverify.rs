@@ -12,6 +12,9 @@ use bincode::serialized_size;
 use rayon::ThreadPool;
 use solana_metrics::inc_new_counter_debug;
 use solana_rayon_threadlimit::get_thread_count;
+use solana_sdk::ed25519_instruction::parse_signature_offsets;
+use solana_sdk::ed25519_program;
+use solana_sdk::hash::Hash;
 use solana_sdk::message::MessageHeader;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::short_vec::decode_len;
@@ -51,6 +54,16 @@ impl PacketOffsets {
     }
 }
 
+/// Location of one signature referenced by an ed25519 program instruction,
+/// relative to the start of the packet
+#[derive(Debug, PartialEq, Eq)]
+struct PrecompileOffsets {
+    pub sig_start: u32,
+    pub pubkey_start: u32,
+    pub msg_start: u32,
+    pub msg_size: u32,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum PacketError {
     InvalidLen,
@@ -59,6 +72,7 @@ pub enum PacketError {
     InvalidSignatureLen,
     MismatchSignatureLen,
     PayerNotWritable,
+    InvalidPrecompileOffsets,
 }
 
 impl std::convert::From<std::boxed::Box<bincode::ErrorKind>> for PacketError {
@@ -80,6 +94,10 @@ pub fn init() {
 }
 
 fn verify_packet(packet: &Packet) -> u8 {
+    // Packets already discarded upstream, e.g. duplicates, are not worth verifying
+    if packet.meta.discard {
+        return 0;
+    }
     let packet_offsets = get_packet_offsets(packet, 0);
     let mut sig_start = packet_offsets.sig_start as usize;
     let mut pubkey_start = packet_offsets.pubkey_start as usize;
@@ -112,6 +130,23 @@ fn verify_packet(packet: &Packet) -> u8 {
         pubkey_start += size_of::<Pubkey>();
         sig_start += size_of::<Signature>();
     }
+
+    let precompile_offsets = match get_ed25519_instruction_offsets(packet, msg_start) {
+        Ok(precompile_offsets) => precompile_offsets,
+        Err(_) => return 0,
+    };
+    for offsets in precompile_offsets {
+        let sig_start = offsets.sig_start as usize;
+        let pubkey_start = offsets.pubkey_start as usize;
+        let msg_start = offsets.msg_start as usize;
+        let signature = Signature::new(&packet.data[sig_start..sig_start + size_of::<Signature>()]);
+        if !signature.verify(
+            &packet.data[pubkey_start..pubkey_start + size_of::<Pubkey>()],
+            &packet.data[msg_start..msg_start + offsets.msg_size as usize],
+        ) {
+            return 0;
+        }
+    }
     1
 }
 
@@ -192,6 +227,95 @@ fn get_packet_offsets(packet: &Packet, current_offset: u32) -> PacketOffsets {
     }
 }
 
+// Walk the instructions of the transaction in `packet`, whose message starts at
+// `msg_start`, and return the signatures referenced by any ed25519 program
+// instructions.  These are verified in the same batch as the transaction's own
+// signatures, so the bounds checks must match `ed25519_instruction::verify_signatures`.
+fn get_ed25519_instruction_offsets(
+    packet: &Packet,
+    msg_start: usize,
+) -> Result<Vec<PrecompileOffsets>, PacketError> {
+    let data = packet
+        .data
+        .get(..packet.meta.size)
+        .ok_or(PacketError::InvalidLen)?;
+    let read_len = |offset: usize| -> Result<(usize, usize), PacketError> {
+        let bytes = data.get(offset..).ok_or(PacketError::InvalidLen)?;
+        decode_len(bytes).map_err(|_| PacketError::InvalidShortVec)
+    };
+
+    let message_header_size = serialized_size(&MessageHeader::default()).unwrap() as usize;
+    let (num_account_keys, size) = read_len(msg_start + message_header_size)?;
+    let account_keys_start = msg_start + message_header_size + size;
+    let mut offset = account_keys_start + num_account_keys * size_of::<Pubkey>();
+    offset += size_of::<Hash>();
+
+    // (program_id_index, data_start, data_len) of each instruction
+    let mut instructions = vec![];
+    let (num_instructions, size) = read_len(offset)?;
+    offset += size;
+    for _ in 0..num_instructions {
+        let program_id_index = *data.get(offset).ok_or(PacketError::InvalidLen)? as usize;
+        let (num_accounts, size) = read_len(offset + 1)?;
+        offset += 1 + size + num_accounts;
+        let (data_len, size) = read_len(offset)?;
+        offset += size;
+        if offset + data_len > data.len() {
+            return Err(PacketError::InvalidLen);
+        }
+        instructions.push((program_id_index, offset, data_len));
+        offset += data_len;
+    }
+
+    let get_data_offset =
+        |instruction_index: u8, start: u16, size: usize| -> Result<u32, PacketError> {
+            let (_, data_start, data_len) = instructions
+                .get(instruction_index as usize)
+                .ok_or(PacketError::InvalidPrecompileOffsets)?;
+            if start as usize + size > *data_len {
+                return Err(PacketError::InvalidPrecompileOffsets);
+            }
+            Ok((data_start + start as usize) as u32)
+        };
+
+    let mut precompile_offsets = vec![];
+    for (program_id_index, data_start, data_len) in &instructions {
+        // Out of range program ids are rejected when the transaction is sanitized
+        if *program_id_index >= num_account_keys {
+            continue;
+        }
+        let program_id_start = account_keys_start + program_id_index * size_of::<Pubkey>();
+        if data[program_id_start..program_id_start + size_of::<Pubkey>()]
+            != ed25519_program::id().to_bytes()
+        {
+            continue;
+        }
+        let signature_offsets = parse_signature_offsets(&data[*data_start..data_start + data_len])
+            .map_err(|_| PacketError::InvalidPrecompileOffsets)?;
+        for offsets in signature_offsets {
+            precompile_offsets.push(PrecompileOffsets {
+                sig_start: get_data_offset(
+                    offsets.signature_instruction_index,
+                    offsets.signature_offset,
+                    size_of::<Signature>(),
+                )?,
+                pubkey_start: get_data_offset(
+                    offsets.public_key_instruction_index,
+                    offsets.public_key_offset,
+                    size_of::<Pubkey>(),
+                )?,
+                msg_start: get_data_offset(
+                    offsets.message_instruction_index,
+                    offsets.message_data_offset,
+                    offsets.message_data_size as usize,
+                )?,
+                msg_size: offsets.message_data_size as u32,
+            });
+        }
+    }
+    Ok(precompile_offsets)
+}
+
 pub fn generate_offsets(
     batches: &[Packets],
     recycler: &Recycler<TxOffset>,
@@ -212,9 +336,19 @@ pub fn generate_offsets(
         p.packets.iter().for_each(|packet| {
             let current_offset = current_packet as u32 * size_of::<Packet>() as u32;
 
-            let packet_offsets = get_packet_offsets(packet, current_offset);
+            let mut packet_offsets = get_packet_offsets(packet, current_offset);
+            let msg_start = packet_offsets.msg_start.saturating_sub(current_offset) as usize;
+            let precompile_offsets = if packet_offsets.sig_len == 0 {
+                vec![]
+            } else if let Ok(offsets) = get_ed25519_instruction_offsets(packet, msg_start) {
+                offsets
+            } else {
+                // force sigverify to fail by returning zeros
+                packet_offsets = PacketOffsets::new(0, 0, 0, 0);
+                vec![]
+            };
 
-            sig_lens.push(packet_offsets.sig_len);
+            sig_lens.push(packet_offsets.sig_len + precompile_offsets.len() as u32);
 
             trace!("pubkey_offset: {}", packet_offsets.pubkey_start);
 
@@ -232,6 +366,12 @@ pub fn generate_offsets(
                 msg_sizes
                     .push(current_offset + (packet.meta.size as u32) - packet_offsets.msg_start);
             }
+            for offsets in precompile_offsets {
+                signature_offsets.push(current_offset + offsets.sig_start);
+                pubkey_offsets.push(current_offset + offsets.pubkey_start);
+                msg_start_offsets.push(current_offset + offsets.msg_start);
+                msg_sizes.push(offsets.msg_size);
+            }
             current_packet += 1;
         });
         v_sig_lens.push(sig_lens);
@@ -265,7 +405,12 @@ pub fn ed25519_verify_disabled(batches: &[Packets]) -> Vec<Vec<u8>> {
     debug!("disabled ECDSA for {}", batch_size(batches));
     let rv = batches
         .into_par_iter()
-        .map(|p| vec![1u8; p.packets.len()])
+        .map(|p| {
+            p.packets
+                .iter()
+                .map(|p| if p.meta.discard { 0u8 } else { 1u8 })
+                .collect()
+        })
         .collect();
     inc_new_counter_debug!("ed25519_verify_disabled", count);
     rv
@@ -392,6 +537,13 @@ pub fn ed25519_verify(
     }
     trace!("done verify");
     copy_return_values(&sig_lens, &out, &mut rvs);
+    for (p, vs) in batches.iter().zip(rvs.iter_mut()) {
+        for (packet, v) in p.packets.iter().zip(vs.iter_mut()) {
+            if packet.meta.discard {
+                *v = 0;
+            }
+        }
+    }
     inc_new_counter_debug!("ed25519_verify_gpu", count);
     rvs
 }
@@ -791,6 +943,70 @@ mod tests {
         test_verify_n(5, true);
     }
 
+    fn ed25519_instruction_packet() -> (Packet, Vec<u8>) {
+        use solana_sdk::ed25519_instruction::new_ed25519_instruction;
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let payer = Keypair::new();
+        let message = b"hello".to_vec();
+        let instruction = new_ed25519_instruction(&Keypair::new(), &message, 0);
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        (sigverify::make_packet_from_transaction(tx), message)
+    }
+
+    #[test]
+    fn test_get_ed25519_instruction_offsets() {
+        let (packet, message) = ed25519_instruction_packet();
+        let packet_offsets = sigverify::get_packet_offsets(&packet, 0);
+        let precompile_offsets =
+            get_ed25519_instruction_offsets(&packet, packet_offsets.msg_start as usize).unwrap();
+        assert_eq!(precompile_offsets.len(), 1);
+        let offsets = &precompile_offsets[0];
+        assert_eq!(
+            memfind(&packet.data[..packet.meta.size], &message),
+            Some(offsets.msg_start as usize)
+        );
+        assert_eq!(offsets.msg_size as usize, message.len());
+        assert_eq!(offsets.sig_start, offsets.pubkey_start + 32);
+
+        // A plain transfer carries no precompile signatures
+        let packet = sigverify::make_packet_from_transaction(test_tx());
+        let packet_offsets = sigverify::get_packet_offsets(&packet, 0);
+        assert_eq!(
+            get_ed25519_instruction_offsets(&packet, packet_offsets.msg_start as usize),
+            Ok(vec![])
+        );
+
+        // Generated offsets count the precompile signature alongside the fee payer's
+        let batches = generate_packet_vec(&ed25519_instruction_packet().0, 1, 1);
+        let (_, _, _, _, sig_lens) = generate_offsets(&batches, &Recycler::default()).unwrap();
+        assert_eq!(sig_lens, vec![vec![2]]);
+    }
+
+    #[test]
+    fn test_verify_ed25519_instruction() {
+        let (packet, message) = ed25519_instruction_packet();
+        let batches = generate_packet_vec(&packet, 1, 1);
+        assert_eq!(ed25519_verify_cpu(&batches), vec![vec![1]]);
+
+        // Tampering with the signed message fails the whole packet
+        let mut packet = packet;
+        let msg_start = memfind(&packet.data[..packet.meta.size], &message).unwrap();
+        packet.data[msg_start] = packet.data[msg_start].wrapping_add(1);
+        let batches = generate_packet_vec(&packet, 1, 1);
+        assert_eq!(ed25519_verify_cpu(&batches), vec![vec![0]]);
+
+        // Matches the GPU path, when perf-libs are available
+        let batches = generate_packet_vec(&packet, 64, 2);
+        let ans = sigverify::ed25519_verify(&batches, &Recycler::default(), &Recycler::default());
+        assert_eq!(ans, vec![vec![0; 64]; 2]);
+    }
+
     #[test]
     fn test_get_checked_scalar() {
         solana_logger::setup();
@@ -0,0 +1,414 @@
+//! The solana-program-test provides a BanksClient-based test framework for
+//! programs, running either natively or as BPF inside an in-process bank.
+
+use {
+    log::*,
+    solana_banks_client::start_client,
+    solana_banks_server::banks_server::start_local_server_with_commitment,
+    solana_runtime::{
+        bank::Bank, bank_forks::BankForks, commitment::BlockCommitmentCache,
+        genesis_utils::create_genesis_config_with_leader, process_instruction::ProcessInstruction,
+    },
+    solana_sdk::{
+        account::{Account, KeyedAccount},
+        account_info::AccountInfo,
+        bpf_loader,
+        clock::Slot,
+        entrypoint::ProcessInstruction as ProgramProcessInstruction,
+        hash::Hash,
+        instruction::InstructionError,
+        native_token::sol_to_lamports,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+    std::{
+        cell::RefCell,
+        collections::HashMap,
+        env,
+        fs::File,
+        io::Read,
+        path::{Path, PathBuf},
+        rc::Rc,
+        sync::{Arc, RwLock},
+    },
+    thiserror::Error,
+};
+
+// Export types so test clients can limit their solana crate dependencies
+pub use solana_banks_client::{BanksClient, BanksClientExt};
+
+/// Errors from the program test environment
+#[derive(Error, Debug, PartialEq)]
+pub enum ProgramTestError {
+    /// The chosen warp slot is not in the future, so warp is not performed
+    #[error("Warp slot not in the future")]
+    InvalidWarpSlot,
+}
+
+/// Adapt a program written against `solana_program`'s entrypoint, which works
+/// on `AccountInfo`s, into a builtin that the runtime can call with
+/// `KeyedAccount`s.  Changes to lamports and data are written back into the
+/// keyed accounts once the program returns.
+pub fn builtin_process_instruction(
+    process_instruction: ProgramProcessInstruction,
+    program_id: &Pubkey,
+    keyed_accounts: &[KeyedAccount],
+    input: &[u8],
+) -> Result<(), InstructionError> {
+    // Copy the accounts into a HashMap so that duplicate keys share one account
+    let mut accounts: HashMap<Pubkey, Account> = keyed_accounts
+        .iter()
+        .map(|keyed_account| {
+            (
+                *keyed_account.unsigned_key(),
+                keyed_account.account.borrow().clone(),
+            )
+        })
+        .collect();
+
+    // Create shared references to each account's lamports and data
+    let account_refs: HashMap<_, _> = accounts
+        .iter_mut()
+        .map(|(key, account)| {
+            (
+                *key,
+                (
+                    Rc::new(RefCell::new(&mut account.lamports)),
+                    Rc::new(RefCell::new(&mut account.data[..])),
+                    &account.owner,
+                ),
+            )
+        })
+        .collect();
+
+    let account_infos = keyed_accounts
+        .iter()
+        .map(|keyed_account| {
+            let key = keyed_account.unsigned_key();
+            let (lamports, data, owner) = &account_refs[key];
+            Ok(AccountInfo {
+                key,
+                is_signer: keyed_account.signer_key().is_some(),
+                is_writable: keyed_account.is_writable(),
+                lamports: lamports.clone(),
+                data: data.clone(),
+                owner,
+                executable: keyed_account.executable()?,
+                rent_epoch: keyed_account.rent_epoch()?,
+            })
+        })
+        .collect::<Result<Vec<_>, InstructionError>>()?;
+
+    process_instruction(program_id, &account_infos, input).map_err(u64::from)?;
+
+    // Commit AccountInfo changes back into the KeyedAccounts
+    for keyed_account in keyed_accounts {
+        let (lamports, data, _owner) = &account_refs[keyed_account.unsigned_key()];
+        let mut account = keyed_account.account.borrow_mut();
+        account.lamports = **lamports.borrow();
+        account.data = data.borrow().to_vec();
+    }
+
+    Ok(())
+}
+
+/// Converts a `solana_program`-style entrypoint into the runtime's entrypoint
+/// style, for use with `ProgramTest::add_program`
+#[macro_export]
+macro_rules! processor {
+    ($process_instruction:expr) => {
+        Some(
+            |program_id: &$crate::__private::Pubkey,
+             keyed_accounts: &[$crate::__private::KeyedAccount],
+             input: &[u8]| {
+                $crate::builtin_process_instruction(
+                    $process_instruction,
+                    program_id,
+                    keyed_accounts,
+                    input,
+                )
+            },
+        )
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use solana_sdk::{account::KeyedAccount, pubkey::Pubkey};
+}
+
+fn find_file(filename: &str, search_path: &[PathBuf]) -> Option<PathBuf> {
+    for path in search_path {
+        let candidate = path.join(&filename);
+        if candidate.exists() {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+fn read_file<P: AsRef<Path>>(path: P) -> Vec<u8> {
+    let path = path.as_ref();
+    let mut file = File::open(path)
+        .unwrap_or_else(|err| panic!("Failed to open \"{}\": {}", path.display(), err));
+
+    let mut file_data = Vec::new();
+    file.read_to_end(&mut file_data)
+        .unwrap_or_else(|err| panic!("Failed to read \"{}\": {}", path.display(), err));
+    file_data
+}
+
+pub struct ProgramTest {
+    accounts: Vec<(Pubkey, Account)>,
+    builtins: Vec<(String, Pubkey, ProcessInstruction)>,
+    bpf_programs: Vec<(String, Pubkey)>,
+    search_path: Vec<PathBuf>,
+}
+
+impl Default for ProgramTest {
+    /// Initialize a new ProgramTest
+    ///
+    /// The `BPF_OUT_DIR` environment variable, `tests/fixtures` and the
+    /// current working directory are searched for BPF programs and account
+    /// data files, in that order.
+    fn default() -> Self {
+        let mut search_path = vec![];
+        if let Ok(bpf_out_dir) = env::var("BPF_OUT_DIR") {
+            search_path.push(PathBuf::from(bpf_out_dir));
+        }
+        search_path.push(PathBuf::from("tests/fixtures"));
+        if let Ok(dir) = env::current_dir() {
+            search_path.push(dir);
+        }
+        debug!("search path: {:?}", search_path);
+
+        Self {
+            accounts: vec![],
+            builtins: vec![],
+            bpf_programs: vec![],
+            search_path,
+        }
+    }
+}
+
+impl ProgramTest {
+    /// Create a `ProgramTest` with a single program under test.  See
+    /// `add_program` for how `process_instruction` is interpreted.
+    pub fn new(
+        program_name: &str,
+        program_id: Pubkey,
+        process_instruction: Option<ProcessInstruction>,
+    ) -> Self {
+        let mut me = Self::default();
+        me.add_program(program_name, program_id, process_instruction);
+        me
+    }
+
+    /// Add an account to the test environment
+    pub fn add_account(&mut self, address: Pubkey, account: Account) {
+        self.accounts.push((address, account));
+    }
+
+    /// Add an account to the test environment with the account data in the
+    /// provided `filename`, which is located on the search path
+    pub fn add_account_with_file_data(
+        &mut self,
+        address: Pubkey,
+        lamports: u64,
+        owner: Pubkey,
+        filename: &str,
+    ) {
+        let path = find_file(filename, &self.search_path)
+            .unwrap_or_else(|| panic!("Unable to locate {}", filename));
+        self.add_account(
+            address,
+            Account {
+                lamports,
+                data: read_file(path),
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    /// Add an account to the test environment with the account data in the
+    /// provided base64 encoded string
+    pub fn add_account_with_base64_data(
+        &mut self,
+        address: Pubkey,
+        lamports: u64,
+        owner: Pubkey,
+        data_base64: &str,
+    ) {
+        self.add_account(
+            address,
+            Account {
+                lamports,
+                data: base64::decode(data_base64)
+                    .unwrap_or_else(|err| panic!("Failed to base64 decode: {}", err)),
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    /// Add a program to the test environment.
+    ///
+    /// If `process_instruction` is provided, the program runs natively in
+    /// the test process.  Otherwise `<program_name>.so` is located on the
+    /// search path and deployed as a BPF program owned by the BPF loader.
+    pub fn add_program(
+        &mut self,
+        program_name: &str,
+        program_id: Pubkey,
+        process_instruction: Option<ProcessInstruction>,
+    ) {
+        match process_instruction {
+            Some(process_instruction) => {
+                self.builtins
+                    .push((program_name.to_string(), program_id, process_instruction));
+            }
+            None => {
+                self.bpf_programs
+                    .push((program_name.to_string(), program_id));
+            }
+        }
+    }
+
+    fn setup_bank(&self) -> (Arc<RwLock<BankForks>>, Keypair) {
+        let bootstrap_validator_pubkey = Pubkey::new_unique();
+        let bootstrap_validator_stake_lamports = 42;
+        let gci = create_genesis_config_with_leader(
+            sol_to_lamports(1_000_000.0),
+            &bootstrap_validator_pubkey,
+            bootstrap_validator_stake_lamports,
+        );
+        let mut genesis_config = gci.genesis_config;
+        let payer = gci.mint_keypair;
+        debug!("Payer address: {}", payer.pubkey());
+        debug!("Genesis config: {}", genesis_config);
+
+        for (program_name, program_id) in self.bpf_programs.iter() {
+            let filename = format!("{}.so", program_name);
+            let path = find_file(&filename, &self.search_path).unwrap_or_else(|| {
+                panic!(
+                    "Unable to locate {} on the search path {:?}",
+                    filename, self.search_path
+                )
+            });
+            info!("\"{}\" BPF program from {}", program_name, path.display());
+            let data = read_file(&path);
+            genesis_config.add_account(
+                *program_id,
+                Account {
+                    lamports: genesis_config.rent.minimum_balance(data.len()).max(1),
+                    data,
+                    owner: bpf_loader::id(),
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            );
+        }
+        for (address, account) in self.accounts.iter() {
+            genesis_config.add_account(*address, account.clone());
+        }
+
+        let mut bank = Bank::new(&genesis_config);
+        bank.add_builtin_loader(
+            "solana_bpf_loader_program",
+            bpf_loader::id(),
+            solana_bpf_loader_program::process_instruction,
+        );
+        for (program_name, program_id, process_instruction) in self.builtins.iter() {
+            info!("\"{}\" builtin program", program_name);
+            bank.add_builtin_program(program_name, *program_id, *process_instruction);
+        }
+
+        (Arc::new(RwLock::new(BankForks::new(bank))), payer)
+    }
+
+    /// Start the test client
+    ///
+    /// Returns a `BanksClient` interface into the test environment as well
+    /// as a payer `Keypair` with SOL for sending transactions and a recent
+    /// blockhash
+    pub async fn start(self) -> (BanksClient, Keypair, Hash) {
+        let context = self.start_with_context().await;
+        (context.banks_client, context.payer, context.last_blockhash)
+    }
+
+    /// Start the test client, retaining access to the underlying `BankForks`
+    /// so that the test can manipulate the bank, e.g. with `warp_to_slot`
+    pub async fn start_with_context(self) -> ProgramTestContext {
+        let (bank_forks, payer) = self.setup_bank();
+        let slot = bank_forks.read().unwrap().working_bank().slot();
+        let block_commitment_cache = Arc::new(RwLock::new(
+            BlockCommitmentCache::new_for_tests_with_slots(slot, slot),
+        ));
+        let transport =
+            start_local_server_with_commitment(&bank_forks, &block_commitment_cache).await;
+        let banks_client = start_client(transport)
+            .await
+            .unwrap_or_else(|err| panic!("Failed to start banks client: {}", err));
+        let last_blockhash = bank_forks.read().unwrap().working_bank().last_blockhash();
+
+        ProgramTestContext {
+            banks_client,
+            payer,
+            last_blockhash,
+            bank_forks,
+            block_commitment_cache,
+        }
+    }
+}
+
+pub struct ProgramTestContext {
+    pub banks_client: BanksClient,
+    pub payer: Keypair,
+    pub last_blockhash: Hash,
+    bank_forks: Arc<RwLock<BankForks>>,
+    block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
+}
+
+impl ProgramTestContext {
+    /// Force the working bank ahead to a new slot, e.g. to test logic that
+    /// depends on the clock or on an epoch boundary.  The bank is advanced
+    /// in one step, without processing the intervening slots.
+    pub fn warp_to_slot(&mut self, warp_slot: Slot) -> Result<(), ProgramTestError> {
+        let mut bank_forks = self.bank_forks.write().unwrap();
+        let bank = bank_forks.working_bank();
+        if warp_slot <= bank.slot() {
+            return Err(ProgramTestError::InvalidWarpSlot);
+        }
+
+        // The warped bank is frozen, so root it and continue from a child
+        let pre_warp_slot = warp_slot - 1;
+        let warp_bank = if pre_warp_slot == bank.slot() {
+            bank.freeze();
+            bank
+        } else {
+            bank_forks.insert(Bank::warp_from_parent(
+                &bank,
+                &Pubkey::default(),
+                pre_warp_slot,
+            ))
+        };
+        bank_forks.set_root(pre_warp_slot, &None, Some(pre_warp_slot));
+        bank_forks.insert(Bank::new_from_parent(
+            &warp_bank,
+            &Pubkey::default(),
+            warp_slot,
+        ));
+
+        // Every commitment level must point at the new working bank, since
+        // transactions are only ever processed there
+        self.block_commitment_cache
+            .write()
+            .unwrap()
+            .set_all_slots(warp_slot, warp_slot);
+
+        Ok(())
+    }
+}
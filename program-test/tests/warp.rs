@@ -0,0 +1,118 @@
+use {
+    solana_program_test::{processor, BanksClientExt, ProgramTest, ProgramTestError},
+    solana_sdk::{
+        account::Account,
+        account_info::{next_account_info, AccountInfo},
+        clock::Clock,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program_error::ProgramError,
+        pubkey::Pubkey,
+        signature::Signer,
+        sysvar::{self, Sysvar},
+        transaction::{Transaction, TransactionError},
+    },
+    std::convert::TryInto,
+    tokio::runtime::Runtime,
+};
+
+// Use a big number to be sure that we get the right error
+const WRONG_SLOT_ERROR: u32 = 123456;
+
+fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let clock_info = next_account_info(account_info_iter)?;
+    let clock = Clock::from_account_info(clock_info)?;
+    let expected_slot = u64::from_le_bytes(input.try_into().unwrap());
+    if clock.slot == expected_slot {
+        Ok(())
+    } else {
+        Err(ProgramError::Custom(WRONG_SLOT_ERROR))
+    }
+}
+
+#[test]
+fn clock_sysvar_updated_from_warp() {
+    let program_id = Pubkey::new_unique();
+    // Initialize and start the test network
+    let program_test =
+        ProgramTest::new("program-test", program_id, processor!(process_instruction));
+
+    Runtime::new().unwrap().block_on(async {
+        let mut context = program_test.start_with_context().await;
+        let expected_slot = 5_000_000;
+        let instruction = Instruction::new(
+            program_id,
+            &expected_slot,
+            vec![AccountMeta::new_readonly(sysvar::clock::id(), false)],
+        );
+
+        // Fail transaction
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction.clone()],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.last_blockhash,
+        );
+        assert_eq!(
+            context
+                .banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(0, InstructionError::Custom(WRONG_SLOT_ERROR))
+        );
+
+        // Warp to success!
+        context.warp_to_slot(expected_slot).unwrap();
+        let instruction = Instruction::new(
+            program_id,
+            &expected_slot,
+            vec![AccountMeta::new_readonly(sysvar::clock::id(), false)],
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.last_blockhash,
+        );
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+
+        // Try warping again to the same slot
+        assert_eq!(
+            context.warp_to_slot(expected_slot).unwrap_err(),
+            ProgramTestError::InvalidWarpSlot,
+        );
+    });
+}
+
+#[test]
+fn preloaded_account_visible_to_client() {
+    let address = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut program_test = ProgramTest::default();
+    program_test.add_account_with_base64_data(address, 42, owner, "AQID");
+
+    Runtime::new().unwrap().block_on(async {
+        let (mut banks_client, _payer, _recent_blockhash) = program_test.start().await;
+        assert_eq!(
+            banks_client.get_account(address).await.unwrap(),
+            Some(Account {
+                lamports: 42,
+                data: vec![1, 2, 3],
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            })
+        );
+    });
+}
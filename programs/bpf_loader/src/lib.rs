@@ -635,6 +635,11 @@ mod tests {
                 max_call_depth: 20,
                 stack_frame_size: 4096,
                 log_pubkey_units: 100,
+                keccak256_base_cost: 85,
+                keccak256_byte_cost: 1,
+                secp256k1_recover_cost: 25_000,
+                log_data_base_cost: 100,
+                log_data_byte_cost: 1,
             },
             Rc::new(RefCell::new(Executors::default())),
             None,
@@ -9,7 +9,8 @@ use solana_rbpf::{
 };
 use solana_runtime::{
     feature_set::{
-        pubkey_log_syscall_enabled, ristretto_mul_syscall_enabled, sha256_syscall_enabled,
+        keccak256_syscall_enabled, log_data_syscall_enabled, pubkey_log_syscall_enabled,
+        ristretto_mul_syscall_enabled, secp256k1_recover_syscall_enabled, sha256_syscall_enabled,
     },
     message_processor::MessageProcessor,
     process_instruction::{ComputeMeter, InvokeContext, Logger},
@@ -22,9 +23,14 @@ use solana_sdk::{
     entrypoint::{MAX_PERMITTED_DATA_INCREASE, SUCCESS},
     hash::{Hasher, HASH_BYTES},
     instruction::{AccountMeta, Instruction, InstructionError},
+    keccak,
     message::Message,
     program_error::ProgramError,
     pubkey::{Pubkey, PubkeyError},
+    secp256k1_recover::{
+        Secp256k1RecoverError, SECP256K1_HASH_LENGTH, SECP256K1_PUBLIC_KEY_LENGTH,
+        SECP256K1_SIGNATURE_LENGTH,
+    },
 };
 use std::{
     alloc::Layout,
@@ -134,6 +140,19 @@ pub fn register_syscalls<'a>(
         )?;
     }
 
+    if invoke_context.is_feature_active(&log_data_syscall_enabled::id()) {
+        vm.register_syscall_with_context_ex(
+            "sol_log_data",
+            Box::new(SyscallLogData {
+                base_cost: compute_budget.log_data_base_cost,
+                byte_cost: compute_budget.log_data_byte_cost,
+                compute_meter: invoke_context.get_compute_meter(),
+                logger: invoke_context.get_logger(),
+                loader_id,
+            }),
+        )?;
+    }
+
     if invoke_context.is_feature_active(&sha256_syscall_enabled::id()) {
         vm.register_syscall_with_context_ex(
             "sol_sha256",
@@ -146,6 +165,29 @@ pub fn register_syscalls<'a>(
         )?;
     }
 
+    if invoke_context.is_feature_active(&keccak256_syscall_enabled::id()) {
+        vm.register_syscall_with_context_ex(
+            "sol_keccak256",
+            Box::new(SyscallKeccak256 {
+                base_cost: compute_budget.keccak256_base_cost,
+                byte_cost: compute_budget.keccak256_byte_cost,
+                compute_meter: invoke_context.get_compute_meter(),
+                loader_id,
+            }),
+        )?;
+    }
+
+    if invoke_context.is_feature_active(&secp256k1_recover_syscall_enabled::id()) {
+        vm.register_syscall_with_context_ex(
+            "sol_secp256k1_recover",
+            Box::new(SyscallSecp256k1Recover {
+                cost: compute_budget.secp256k1_recover_cost,
+                compute_meter: invoke_context.get_compute_meter(),
+                loader_id,
+            }),
+        )?;
+    }
+
     if invoke_context.is_feature_active(&ristretto_mul_syscall_enabled::id()) {
         vm.register_syscall_with_context_ex(
             "sol_ristretto_mul",
@@ -445,6 +487,52 @@ impl<'a> SyscallObject<BPFError> for SyscallLogPubkey<'a> {
     }
 }
 
+/// Log data slices, base64 encoded
+pub struct SyscallLogData<'a> {
+    base_cost: u64,
+    byte_cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    logger: Rc<RefCell<dyn Logger>>,
+    loader_id: &'a Pubkey,
+}
+impl<'a> SyscallObject<BPFError> for SyscallLogData<'a> {
+    fn call(
+        &mut self,
+        vals_addr: u64,
+        vals_len: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        ro_regions: &[MemoryRegion],
+        _rw_regions: &[MemoryRegion],
+    ) -> Result<u64, EbpfError<BPFError>> {
+        self.compute_meter.consume(self.base_cost)?;
+        let vals = translate_slice!(&[u8], vals_addr, vals_len, ro_regions, self.loader_id)?;
+        let mut fields = Vec::with_capacity(vals.len());
+        for val in vals.iter() {
+            let bytes = translate_slice!(u8, val.as_ptr(), val.len(), ro_regions, self.loader_id)?;
+            self.compute_meter
+                .consume(self.byte_cost * bytes.len() as u64)?;
+            fields.push(bytes);
+        }
+        let mut logger = self
+            .logger
+            .try_borrow_mut()
+            .map_err(|_| SyscallError::InvokeContextBorrowFailed)?;
+        if logger.log_enabled() {
+            logger.log(&format!(
+                "Program data: {}",
+                fields
+                    .iter()
+                    .map(base64::encode)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+        Ok(0)
+    }
+}
+
 /// Dynamic memory allocation syscall called when the BPF program calls
 /// `sol_alloc_free_()`.  The allocator is expected to allocate/free
 /// from/to a given chunk of memory and enforce size restrictions.  The
@@ -571,6 +659,115 @@ impl<'a> SyscallObject<BPFError> for SyscallSha256<'a> {
     }
 }
 
+/// Keccak256
+pub struct SyscallKeccak256<'a> {
+    base_cost: u64,
+    byte_cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    loader_id: &'a Pubkey,
+}
+impl<'a> SyscallObject<BPFError> for SyscallKeccak256<'a> {
+    fn call(
+        &mut self,
+        vals_addr: u64,
+        vals_len: u64,
+        result_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+        ro_regions: &[MemoryRegion],
+        rw_regions: &[MemoryRegion],
+    ) -> Result<u64, EbpfError<BPFError>> {
+        self.compute_meter.consume(self.base_cost)?;
+        let hash_result = translate_slice_mut!(
+            u8,
+            result_addr,
+            keccak::HASH_BYTES,
+            rw_regions,
+            self.loader_id
+        )?;
+        let mut hasher = keccak::Hasher::default();
+        if vals_len > 0 {
+            let vals = translate_slice!(&[u8], vals_addr, vals_len, ro_regions, self.loader_id)?;
+            for val in vals.iter() {
+                let bytes =
+                    translate_slice!(u8, val.as_ptr(), val.len(), ro_regions, self.loader_id)?;
+                self.compute_meter
+                    .consume(self.byte_cost * (val.len() as u64 / 2))?;
+                hasher.hash(bytes);
+            }
+        }
+        hash_result.copy_from_slice(&hasher.result().to_bytes());
+        Ok(0)
+    }
+}
+
+/// secp256k1 public key recovery
+pub struct SyscallSecp256k1Recover<'a> {
+    cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    loader_id: &'a Pubkey,
+}
+impl<'a> SyscallObject<BPFError> for SyscallSecp256k1Recover<'a> {
+    fn call(
+        &mut self,
+        hash_addr: u64,
+        recovery_id_val: u64,
+        signature_addr: u64,
+        result_addr: u64,
+        _arg5: u64,
+        ro_regions: &[MemoryRegion],
+        rw_regions: &[MemoryRegion],
+    ) -> Result<u64, EbpfError<BPFError>> {
+        self.compute_meter.consume(self.cost)?;
+
+        let hash = translate_slice!(
+            u8,
+            hash_addr,
+            SECP256K1_HASH_LENGTH,
+            ro_regions,
+            self.loader_id
+        )?;
+        let signature = translate_slice!(
+            u8,
+            signature_addr,
+            SECP256K1_SIGNATURE_LENGTH,
+            ro_regions,
+            self.loader_id
+        )?;
+        let secp256k1_recover_result = translate_slice_mut!(
+            u8,
+            result_addr,
+            SECP256K1_PUBLIC_KEY_LENGTH,
+            rw_regions,
+            self.loader_id
+        )?;
+
+        let message = match secp256k1::Message::parse_slice(hash) {
+            Ok(message) => message,
+            Err(_) => return Ok(Secp256k1RecoverError::InvalidHash.into()),
+        };
+        let recovery_id = match u8::try_from(recovery_id_val)
+            .ok()
+            .and_then(|recovery_id| secp256k1::RecoveryId::parse(recovery_id).ok())
+        {
+            Some(recovery_id) => recovery_id,
+            None => return Ok(Secp256k1RecoverError::InvalidRecoveryId.into()),
+        };
+        let signature = match secp256k1::Signature::parse_slice(signature) {
+            Ok(signature) => signature,
+            Err(_) => return Ok(Secp256k1RecoverError::InvalidSignature.into()),
+        };
+        let public_key = match secp256k1::recover(&message, &signature, &recovery_id) {
+            Ok(key) => key.serialize(),
+            Err(_) => return Ok(Secp256k1RecoverError::InvalidSignature.into()),
+        };
+
+        // Drop the leading 0x04 tag byte of the uncompressed serialization
+        secp256k1_recover_result.copy_from_slice(&public_key[1..65]);
+        Ok(0)
+    }
+}
+
 /// Ristretto point multiply
 pub struct SyscallRistrettoMul<'a> {
     cost: u64,
@@ -1238,7 +1435,7 @@ fn call<'a>(
 mod tests {
     use super::*;
     use crate::tests::{MockComputeMeter, MockLogger};
-    use solana_sdk::hash::hashv;
+    use solana_sdk::{hash::hashv, secp256k1_recover::secp256k1_recover};
     use std::str::FromStr;
 
     macro_rules! assert_access_violation {
@@ -1513,6 +1710,93 @@ mod tests {
         assert_eq!(log.borrow()[0], "Program log: 0x1, 0x2, 0x3, 0x4, 0x5");
     }
 
+    #[test]
+    fn test_syscall_sol_log_data() {
+        let bytes1 = "Gaggablaghblagh!";
+        let bytes2 = "flurbos";
+
+        struct MockSlice {
+            pub addr: u64,
+            pub len: usize,
+        }
+        let mock_slice1 = MockSlice {
+            addr: 4096,
+            len: bytes1.len(),
+        };
+        let mock_slice2 = MockSlice {
+            addr: 8192,
+            len: bytes2.len(),
+        };
+        let bytes_to_log = [mock_slice1, mock_slice2];
+        let ro_len = bytes_to_log.len() as u64;
+        let ro_va = 96;
+        let ro_regions = &mut [
+            MemoryRegion {
+                addr_host: bytes1.as_ptr() as *const _ as u64,
+                addr_vm: 4096,
+                len: bytes1.len() as u64,
+            },
+            MemoryRegion {
+                addr_host: bytes2.as_ptr() as *const _ as u64,
+                addr_vm: 8192,
+                len: bytes2.len() as u64,
+            },
+            MemoryRegion {
+                addr_host: bytes_to_log.as_ptr() as *const _ as u64,
+                addr_vm: 96,
+                len: 32,
+            },
+        ];
+        ro_regions.sort_by(|a, b| a.addr_vm.cmp(&b.addr_vm));
+        let rw_regions = &[MemoryRegion::default()];
+
+        let compute_meter: Rc<RefCell<dyn ComputeMeter>> =
+            Rc::new(RefCell::new(MockComputeMeter {
+                remaining: 2 * (1 + (bytes1.len() + bytes2.len()) as u64),
+            }));
+        let log = Rc::new(RefCell::new(vec![]));
+        let logger: Rc<RefCell<dyn Logger>> =
+            Rc::new(RefCell::new(MockLogger { log: log.clone() }));
+        let mut syscall = SyscallLogData {
+            base_cost: 1,
+            byte_cost: 1,
+            compute_meter,
+            logger,
+            loader_id: &bpf_loader_deprecated::id(),
+        };
+
+        syscall
+            .call(ro_va, ro_len, 0, 0, 0, ro_regions, rw_regions)
+            .unwrap();
+        assert_eq!(log.borrow().len(), 1);
+        assert_eq!(
+            log.borrow()[0],
+            "Program data: R2FnZ2FibGFnaGJsYWdoIQ== Zmx1cmJvcw=="
+        );
+
+        assert_access_violation!(
+            syscall.call(
+                ro_va,
+                ro_len + 1, // AccessViolation
+                0,
+                0,
+                0,
+                ro_regions,
+                rw_regions
+            ),
+            ro_va,
+            ro_len + 1
+        );
+
+        assert_eq!(
+            Err(EbpfError::UserError(BPFError::SyscallError(
+                SyscallError::InstructionError(InstructionError::ComputationalBudgetExceeded)
+            ))),
+            syscall.call(ro_va, ro_len, 0, 0, 0, ro_regions, rw_regions)
+        );
+        assert_eq!(log.borrow().len(), 1);
+    }
+
     #[test]
     fn test_syscall_sol_pubkey() {
         let pubkey = Pubkey::from_str("MoqiU1vryuCGQSxFKA1SZ316JdLEFFhoAu6cKUNk7dN").unwrap();
@@ -1769,4 +2053,167 @@ mod tests {
             syscall.call(ro_va, ro_len, rw_va, 0, 0, ro_regions, rw_regions)
         );
     }
+
+    #[test]
+    fn test_syscall_keccak256() {
+        let bytes1 = "Gaggablaghblagh!";
+        let bytes2 = "flurbos";
+
+        struct MockSlice {
+            pub addr: u64,
+            pub len: usize,
+        }
+        let mock_slice1 = MockSlice {
+            addr: 4096,
+            len: bytes1.len(),
+        };
+        let mock_slice2 = MockSlice {
+            addr: 8192,
+            len: bytes2.len(),
+        };
+        let bytes_to_hash = [mock_slice1, mock_slice2];
+        let ro_len = bytes_to_hash.len() as u64;
+        let ro_va = 96;
+        let ro_regions = &mut [
+            MemoryRegion {
+                addr_host: bytes1.as_ptr() as *const _ as u64,
+                addr_vm: 4096,
+                len: bytes1.len() as u64,
+            },
+            MemoryRegion {
+                addr_host: bytes2.as_ptr() as *const _ as u64,
+                addr_vm: 8192,
+                len: bytes2.len() as u64,
+            },
+            MemoryRegion {
+                addr_host: bytes_to_hash.as_ptr() as *const _ as u64,
+                addr_vm: 96,
+                len: 32,
+            },
+        ];
+        ro_regions.sort_by(|a, b| a.addr_vm.cmp(&b.addr_vm));
+        let hash_result = [0; keccak::HASH_BYTES];
+        let rw_va = 192;
+        let rw_regions = &[MemoryRegion {
+            addr_host: hash_result.as_ptr() as *const _ as u64,
+            addr_vm: rw_va,
+            len: keccak::HASH_BYTES as u64,
+        }];
+        let compute_meter: Rc<RefCell<dyn ComputeMeter>> =
+            Rc::new(RefCell::new(MockComputeMeter {
+                remaining: (bytes1.len() + bytes2.len()) as u64,
+            }));
+        let mut syscall = SyscallKeccak256 {
+            base_cost: 0,
+            byte_cost: 2,
+            compute_meter,
+            loader_id: &bpf_loader_deprecated::id(),
+        };
+
+        syscall
+            .call(ro_va, ro_len, rw_va, 0, 0, ro_regions, rw_regions)
+            .unwrap();
+
+        let hash_local = keccak::hashv(&[bytes1.as_ref(), bytes2.as_ref()]).to_bytes();
+        assert_eq!(hash_result, hash_local);
+
+        assert_access_violation!(
+            syscall.call(
+                ro_va,
+                ro_len,
+                rw_va - 1, // AccessViolation
+                0,
+                0,
+                ro_regions,
+                rw_regions
+            ),
+            rw_va - 1,
+            keccak::HASH_BYTES as u64
+        );
+
+        assert_eq!(
+            Err(EbpfError::UserError(BPFError::SyscallError(
+                SyscallError::InstructionError(InstructionError::ComputationalBudgetExceeded)
+            ))),
+            syscall.call(ro_va, ro_len, rw_va, 0, 0, ro_regions, rw_regions)
+        );
+    }
+
+    #[test]
+    fn test_syscall_secp256k1_recover() {
+        let secret_key = secp256k1::SecretKey::parse(&[7u8; 32]).unwrap();
+        let message_hash = keccak::hash(b"hello world").to_bytes();
+        let message = secp256k1::Message::parse(&message_hash);
+        let (signature, recovery_id) = secp256k1::sign(&message, &secret_key);
+        let signature = signature.serialize();
+
+        let hash_va = 96;
+        let signature_va = 4096;
+        let ro_regions = &[
+            MemoryRegion {
+                addr_host: message_hash.as_ptr() as *const _ as u64,
+                addr_vm: hash_va,
+                len: SECP256K1_HASH_LENGTH as u64,
+            },
+            MemoryRegion {
+                addr_host: signature.as_ptr() as *const _ as u64,
+                addr_vm: signature_va,
+                len: SECP256K1_SIGNATURE_LENGTH as u64,
+            },
+        ];
+        let result = [0u8; SECP256K1_PUBLIC_KEY_LENGTH];
+        let rw_va = 8192;
+        let rw_regions = &[MemoryRegion {
+            addr_host: result.as_ptr() as *const _ as u64,
+            addr_vm: rw_va,
+            len: SECP256K1_PUBLIC_KEY_LENGTH as u64,
+        }];
+        let compute_meter: Rc<RefCell<dyn ComputeMeter>> =
+            Rc::new(RefCell::new(MockComputeMeter { remaining: 2 }));
+        let mut syscall = SyscallSecp256k1Recover {
+            cost: 1,
+            compute_meter,
+            loader_id: &bpf_loader::id(),
+        };
+
+        assert_eq!(
+            syscall
+                .call(
+                    hash_va,
+                    recovery_id.serialize() as u64,
+                    signature_va,
+                    rw_va,
+                    0,
+                    ro_regions,
+                    rw_regions
+                )
+                .unwrap(),
+            0
+        );
+        let expected =
+            secp256k1_recover(&message_hash, recovery_id.serialize(), &signature).unwrap();
+        assert_eq!(result, expected.to_bytes());
+
+        assert_eq!(
+            syscall
+                .call(hash_va, 4, signature_va, rw_va, 0, ro_regions, rw_regions)
+                .unwrap(),
+            u64::from(Secp256k1RecoverError::InvalidRecoveryId)
+        );
+
+        assert_eq!(
+            Err(EbpfError::UserError(BPFError::SyscallError(
+                SyscallError::InstructionError(InstructionError::ComputationalBudgetExceeded)
+            ))),
+            syscall.call(
+                hash_va,
+                recovery_id.serialize() as u64,
+                signature_va,
+                rw_va,
+                0,
+                ro_regions,
+                rw_regions
+            )
+        );
+    }
 }
@@ -0,0 +1,59 @@
+use solana_sdk::{account::KeyedAccount, instruction::InstructionError, pubkey::Pubkey};
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    _keyed_accounts: &[KeyedAccount],
+    _data: &[u8],
+) -> Result<(), InstructionError> {
+    // Should be already checked by now.
+    Ok(())
+}
+
+solana_sdk::declare_program!(
+    solana_sdk::ed25519_program::ID,
+    solana_ed25519_sig_verify_program,
+    process_instruction
+);
+
+#[cfg(test)]
+pub mod test {
+    use rand::{thread_rng, Rng};
+    use solana_sdk::{
+        ed25519_instruction::new_ed25519_instruction,
+        hash::Hash,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    };
+
+    #[test]
+    fn test_ed25519() {
+        solana_logger::setup();
+
+        let privkey = Keypair::new();
+        let message_arr = b"hello";
+        let mut instruction = new_ed25519_instruction(&privkey, message_arr, 0);
+        let mint_keypair = Keypair::new();
+
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction.clone()],
+            Some(&mint_keypair.pubkey()),
+            &[&mint_keypair],
+            Hash::default(),
+        );
+
+        assert!(tx.verify_ed25519_instructions().is_ok());
+
+        let index = thread_rng().gen_range(0, instruction.data.len());
+        instruction.data[index] = instruction.data[index].wrapping_add(12);
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&mint_keypair.pubkey()),
+            &[&mint_keypair],
+            Hash::default(),
+        );
+        assert_eq!(
+            tx.verify_ed25519_instructions(),
+            Err(TransactionError::PrecompileVerificationFailure)
+        );
+    }
+}
@@ -7,6 +7,7 @@ use rand::Rng;
 use solana_runtime::{
     accounts::{create_test_accounts, Accounts},
     bank::*,
+    storage_index::{read_storage_indexes, StorageIndex},
 };
 use solana_sdk::{
     account::Account,
@@ -127,6 +128,55 @@ fn test_accounts_delta_hash(bencher: &mut Bencher) {
     });
 }
 
+fn accounts_with_storage_indexes(name: &str) -> (Accounts, tempfile::TempDir) {
+    let accounts = Accounts::new(vec![PathBuf::from(name)], &ClusterType::Development);
+    let mut pubkeys: Vec<Pubkey> = vec![];
+    for slot in 0..10 {
+        create_test_accounts(&accounts, &mut pubkeys, 10_000, slot);
+        accounts.add_root(slot);
+    }
+    let storage_indexes_dir = tempfile::TempDir::new().unwrap();
+    for storage in accounts
+        .accounts_db
+        .get_snapshot_storages(10)
+        .iter()
+        .flatten()
+    {
+        StorageIndex::new(storage)
+            .write(storage_indexes_dir.path())
+            .unwrap();
+    }
+    (accounts, storage_indexes_dir)
+}
+
+#[bench]
+fn bench_generate_index_by_scanning_storages(bencher: &mut Bencher) {
+    solana_logger::setup();
+    let (accounts, _storage_indexes_dir) =
+        accounts_with_storage_indexes("bench_generate_index_by_scanning_storages");
+    bencher.iter(|| {
+        accounts.accounts_db.generate_index();
+    });
+}
+
+#[bench]
+fn bench_generate_index_from_storage_indexes(bencher: &mut Bencher) {
+    solana_logger::setup();
+    let (accounts, storage_indexes_dir) =
+        accounts_with_storage_indexes("bench_generate_index_from_storage_indexes");
+    let storages = accounts.accounts_db.get_snapshot_storages(10);
+    bencher.iter(|| {
+        let storage_indexes = read_storage_indexes(
+            storage_indexes_dir.path(),
+            storages.iter().flatten().map(|storage| storage.as_ref()),
+        );
+        assert!(storage_indexes.is_some());
+        accounts
+            .accounts_db
+            .generate_index_from_storage_indexes(storage_indexes);
+    });
+}
+
 #[bench]
 fn bench_delete_dependencies(bencher: &mut Bencher) {
     solana_logger::setup();
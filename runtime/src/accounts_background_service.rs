@@ -77,6 +77,7 @@ impl SnapshotRequestHandler {
                     &self.snapshot_config.snapshot_package_output_path,
                     self.snapshot_config.snapshot_version,
                     &self.snapshot_config.compression,
+                    self.snapshot_config.include_storage_indexes,
                 );
                 if r.is_err() {
                     warn!(
@@ -19,10 +19,11 @@
 //! commit for each slot entry would be indexed.
 
 use crate::{
+    accounts_hash_tree::AccountsHashTree,
     accounts_index::{AccountsIndex, Ancestors, SlotList, SlotSlice},
     append_vec::{AppendVec, StoredAccount, StoredMeta},
+    storage_index::StorageIndex,
 };
-use blake3::traits::digest::Digest;
 use dashmap::DashMap;
 use lazy_static::lazy_static;
 use log::*;
@@ -33,12 +34,13 @@ use solana_measure::measure::Measure;
 use solana_rayon_threadlimit::get_thread_count;
 use solana_sdk::{
     account::Account,
-    clock::{Epoch, Slot},
+    account_hash,
+    accounts_merkle_proof::MERKLE_FANOUT,
+    clock::Slot,
     genesis_config::ClusterType,
     hash::{Hash, Hasher},
     pubkey::Pubkey,
 };
-use std::convert::TryFrom;
 use std::{
     collections::{HashMap, HashSet},
     convert::TryInto,
@@ -121,6 +123,12 @@ impl Versioned for (u64, AccountInfo) {
     }
 }
 
+impl Versioned for (u64, Hash, Account) {
+    fn version(&self) -> u64 {
+        self.0
+    }
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct AccountStorage(pub DashMap<Slot, SlotStores>);
 
@@ -437,6 +445,11 @@ pub struct AccountsDB {
     stats: AccountsStats,
 
     pub cluster_type: Option<ClusterType>,
+
+    /// Keep the Merkle tree of the latest accounts hash to serve account inclusion proofs
+    pub retain_accounts_hash_tree: AtomicBool,
+
+    accounts_hash_tree: RwLock<Option<Arc<AccountsHashTree>>>,
 }
 
 #[derive(Debug, Default)]
@@ -508,6 +521,8 @@ impl Default for AccountsDB {
             frozen_accounts: HashMap::new(),
             stats: AccountsStats::default(),
             cluster_type: None,
+            retain_accounts_hash_tree: AtomicBool::new(false),
+            accounts_hash_tree: RwLock::new(None),
         }
     }
 }
@@ -1541,25 +1556,15 @@ impl AccountsDB {
         assert!(self.storage.get_slot_stores(remove_slot).is_none());
     }
 
-    fn include_owner(cluster_type: &ClusterType, slot: Slot) -> bool {
-        // When devnet was moved to stable release channel, it was done without
-        // hashing account.owner. That's because devnet's slot was lower than
-        // 5_800_000 and the release channel's gating lacked ClusterType at the time...
-        match cluster_type {
-            ClusterType::Devnet => slot >= 5_800_000,
-            _ => true,
-        }
-    }
-
     pub fn hash_stored_account(
         slot: Slot,
         account: &StoredAccount,
         cluster_type: &ClusterType,
     ) -> Hash {
-        let include_owner = Self::include_owner(cluster_type, slot);
+        let include_owner = account_hash::include_owner(cluster_type, slot);
 
-        if slot > Self::get_blake3_slot(cluster_type) {
-            Self::blake3_hash_account_data(
+        if slot > account_hash::blake3_slot(cluster_type) {
+            account_hash::blake3_hash_account_data(
                 slot,
                 account.account_meta.lamports,
                 &account.account_meta.owner,
@@ -1570,7 +1575,7 @@ impl AccountsDB {
                 include_owner,
             )
         } else {
-            Self::hash_account_data(
+            account_hash::hash_account_data(
                 slot,
                 account.account_meta.lamports,
                 &account.account_meta.owner,
@@ -1589,31 +1594,7 @@ impl AccountsDB {
         pubkey: &Pubkey,
         cluster_type: &ClusterType,
     ) -> Hash {
-        let include_owner = Self::include_owner(cluster_type, slot);
-
-        if slot > Self::get_blake3_slot(cluster_type) {
-            Self::blake3_hash_account_data(
-                slot,
-                account.lamports,
-                &account.owner,
-                account.executable,
-                account.rent_epoch,
-                &account.data,
-                pubkey,
-                include_owner,
-            )
-        } else {
-            Self::hash_account_data(
-                slot,
-                account.lamports,
-                &account.owner,
-                account.executable,
-                account.rent_epoch,
-                &account.data,
-                pubkey,
-                include_owner,
-            )
-        }
+        account_hash::hash_account(slot, account, pubkey, cluster_type)
     }
 
     fn hash_frozen_account_data(account: &Account) -> Hash {
@@ -1631,94 +1612,6 @@ impl AccountsDB {
         hasher.result()
     }
 
-    pub fn hash_account_data(
-        slot: Slot,
-        lamports: u64,
-        owner: &Pubkey,
-        executable: bool,
-        rent_epoch: Epoch,
-        data: &[u8],
-        pubkey: &Pubkey,
-        include_owner: bool,
-    ) -> Hash {
-        if lamports == 0 {
-            return Hash::default();
-        }
-
-        let mut hasher = Hasher::default();
-
-        hasher.hash(&lamports.to_le_bytes());
-
-        hasher.hash(&slot.to_le_bytes());
-
-        hasher.hash(&rent_epoch.to_le_bytes());
-
-        hasher.hash(&data);
-
-        if executable {
-            hasher.hash(&[1u8; 1]);
-        } else {
-            hasher.hash(&[0u8; 1]);
-        }
-
-        if include_owner {
-            hasher.hash(&owner.as_ref());
-        }
-        hasher.hash(&pubkey.as_ref());
-
-        hasher.result()
-    }
-
-    pub fn blake3_hash_account_data(
-        slot: Slot,
-        lamports: u64,
-        owner: &Pubkey,
-        executable: bool,
-        rent_epoch: Epoch,
-        data: &[u8],
-        pubkey: &Pubkey,
-        include_owner: bool,
-    ) -> Hash {
-        if lamports == 0 {
-            return Hash::default();
-        }
-
-        let mut hasher = blake3::Hasher::new();
-
-        hasher.update(&lamports.to_le_bytes());
-
-        hasher.update(&slot.to_le_bytes());
-
-        hasher.update(&rent_epoch.to_le_bytes());
-
-        hasher.update(&data);
-
-        if executable {
-            hasher.update(&[1u8; 1]);
-        } else {
-            hasher.update(&[0u8; 1]);
-        }
-
-        if include_owner {
-            hasher.update(&owner.as_ref());
-        }
-        hasher.update(&pubkey.as_ref());
-
-        Hash(<[u8; solana_sdk::hash::HASH_BYTES]>::try_from(hasher.finalize().as_slice()).unwrap())
-    }
-
-    fn get_blake3_slot(cluster_type: &ClusterType) -> Slot {
-        match cluster_type {
-            ClusterType::Development => 0,
-            // Epoch 400
-            ClusterType::Devnet => 3_276_800,
-            // Epoch 78
-            ClusterType::MainnetBeta => 33_696_000,
-            // Epoch 95
-            ClusterType::Testnet => 35_516_256,
-        }
-    }
-
     fn bulk_assign_write_version(&self, count: usize) -> u64 {
         self.write_version
             .fetch_add(count as u64, Ordering::Relaxed)
@@ -1942,8 +1835,7 @@ impl AccountsDB {
         sum_time.stop();
 
         let mut hash_time = Measure::start("hash");
-        let fanout = 16;
-        let res = Self::compute_merkle_root(hashes, fanout);
+        let res = Self::compute_merkle_root(hashes, MERKLE_FANOUT);
         hash_time.stop();
 
         debug!("{} {} {}", sort_time, hash_time, sum_time);
@@ -1983,8 +1875,32 @@ impl AccountsDB {
         ancestors: &Ancestors,
         check_hash: bool,
     ) -> Result<(Hash, u64), BankHashVerificationError> {
-        use BankHashVerificationError::*;
         let mut scan = Measure::start("scan");
+        let hashes = self.scan_account_hashes(slot, ancestors, check_hash)?;
+        scan.stop();
+        let hash_total = hashes.len();
+
+        let mut accumulate = Measure::start("accumulate");
+        let (accumulated_hash, total_lamports) =
+            Self::accumulate_account_hashes_and_capitalization(hashes);
+        accumulate.stop();
+        datapoint_info!(
+            "update_accounts_hash",
+            ("accounts_scan", scan.as_us(), i64),
+            ("hash_accumulate", accumulate.as_us(), i64),
+            ("hash_total", hash_total, i64),
+        );
+        Ok((accumulated_hash, total_lamports))
+    }
+
+    // Returns the pubkey, hash and balance for capitalization of every account with lamports
+    // as of `slot`, in no particular order
+    fn scan_account_hashes(
+        &self,
+        slot: Slot,
+        ancestors: &Ancestors,
+        check_hash: bool,
+    ) -> Result<Vec<(Pubkey, Hash, u64)>, BankHashVerificationError> {
         let keys: Vec<_> = self
             .accounts_index
             .account_maps
@@ -2041,23 +1957,14 @@ impl AccountsDB {
                 "{} mismatched account hash(es) found",
                 mismatch_found.load(Ordering::Relaxed)
             );
-            return Err(MismatchedAccountHash);
+            return Err(BankHashVerificationError::MismatchedAccountHash);
         }
+        Ok(hashes)
+    }
 
-        scan.stop();
-        let hash_total = hashes.len();
-
-        let mut accumulate = Measure::start("accumulate");
-        let (accumulated_hash, total_lamports) =
-            Self::accumulate_account_hashes_and_capitalization(hashes);
-        accumulate.stop();
-        datapoint_info!(
-            "update_accounts_hash",
-            ("accounts_scan", scan.as_us(), i64),
-            ("hash_accumulate", accumulate.as_us(), i64),
-            ("hash_total", hash_total, i64),
-        );
-        Ok((accumulated_hash, total_lamports))
+    /// The Merkle tree of the most recently updated accounts hash, if it is being retained
+    pub fn accounts_hash_tree(&self) -> Option<Arc<AccountsHashTree>> {
+        self.accounts_hash_tree.read().unwrap().clone()
     }
 
     pub fn get_accounts_hash(&self, slot: Slot) -> Hash {
@@ -2067,9 +1974,20 @@ impl AccountsDB {
     }
 
     pub fn update_accounts_hash(&self, slot: Slot, ancestors: &Ancestors) -> (Hash, u64) {
-        let (hash, total_lamports) = self
-            .calculate_accounts_hash(slot, ancestors, false)
-            .unwrap();
+        let (hash, total_lamports) = if self.retain_accounts_hash_tree.load(Ordering::Relaxed) {
+            let mut hashes = self.scan_account_hashes(slot, ancestors, false).unwrap();
+            hashes.par_sort_by(|a, b| a.0.cmp(&b.0));
+            let total_lamports = Self::checked_sum_for_capitalization(
+                hashes.iter().map(|(_, _, lamports)| *lamports),
+            );
+            let accounts_hash_tree = AccountsHashTree::new(slot, hashes);
+            let hash = accounts_hash_tree.accounts_hash;
+            *self.accounts_hash_tree.write().unwrap() = Some(Arc::new(accounts_hash_tree));
+            (hash, total_lamports)
+        } else {
+            self.calculate_accounts_hash(slot, ancestors, false)
+                .unwrap()
+        };
         let mut bank_hashes = self.bank_hashes.write().unwrap();
         let mut bank_hash_info = bank_hashes.get_mut(&slot).unwrap();
         bank_hash_info.snapshot_hash = hash;
@@ -2151,6 +2069,44 @@ impl AccountsDB {
         ret
     }
 
+    /// Returns the latest version of every account stored in `slot` along with the hash that
+    /// contributes to the slot's accounts delta hash, sorted by pubkey
+    pub fn get_accounts_delta_hash_details(&self, slot: Slot) -> Vec<(Pubkey, Hash, Account)> {
+        let mut accumulator: Vec<HashMap<Pubkey, (u64, Hash, Account)>> = self
+            .scan_account_storage(
+                slot,
+                |stored_account: &StoredAccount,
+                 _store_id: AppendVecId,
+                 accum: &mut HashMap<Pubkey, (u64, Hash, Account)>| {
+                    let version = stored_account.meta.write_version;
+                    if accum
+                        .get(&stored_account.meta.pubkey)
+                        .map(|item| item.version() < version)
+                        .unwrap_or(true)
+                    {
+                        accum.insert(
+                            stored_account.meta.pubkey,
+                            (
+                                version,
+                                *stored_account.hash,
+                                stored_account.clone_account(),
+                            ),
+                        );
+                    }
+                },
+            );
+        let mut account_maps = HashMap::new();
+        while let Some(maps) = accumulator.pop() {
+            AccountsDB::merge(&mut account_maps, &maps);
+        }
+        let mut accounts: Vec<_> = account_maps
+            .into_iter()
+            .map(|(pubkey, (_, hash, account))| (pubkey, hash, account))
+            .collect();
+        accounts.sort_by(|a, b| a.0.cmp(&b.0));
+        accounts
+    }
+
     fn update_index(
         &self,
         slot: Slot,
@@ -2492,7 +2448,48 @@ impl AccountsDB {
         }
     }
 
+    // Collects the accounts of every storage of `slot` from its index, the same way
+    // `generate_index_from_storage_indexes` collects them when scanning the storages
+    fn accumulate_storage_indexes(
+        &self,
+        slot: Slot,
+        storage_indexes: &HashMap<AppendVecId, StorageIndex>,
+    ) -> Vec<HashMap<Pubkey, Vec<(u64, AccountInfo)>>> {
+        let slot_stores = match self.storage.get_slot_stores(slot) {
+            Some(slot_stores) => slot_stores,
+            None => return vec![],
+        };
+        let slot_stores = slot_stores.read().unwrap();
+        slot_stores
+            .keys()
+            .map(|store_id| {
+                let mut accum: HashMap<Pubkey, Vec<(u64, AccountInfo)>> = HashMap::new();
+                for entry in &storage_indexes[store_id].entries {
+                    let account_info = AccountInfo {
+                        store_id: *store_id,
+                        offset: entry.offset,
+                        lamports: entry.lamports,
+                    };
+                    accum
+                        .entry(entry.pubkey)
+                        .or_insert_with(Vec::new)
+                        .push((entry.write_version, account_info));
+                }
+                accum
+            })
+            .collect()
+    }
+
     pub fn generate_index(&self) {
+        self.generate_index_from_storage_indexes(None)
+    }
+
+    /// Generates the accounts index from the given indexes of every storage, or by scanning the
+    /// storages if there are none
+    pub fn generate_index_from_storage_indexes(
+        &self,
+        storage_indexes: Option<HashMap<AppendVecId, StorageIndex>>,
+    ) {
         let mut slots = self.storage.all_slots();
         slots.sort();
 
@@ -2504,8 +2501,9 @@ impl AccountsDB {
                 last_log_update = now;
             }
 
-            let accumulator: Vec<HashMap<Pubkey, Vec<(u64, AccountInfo)>>> = self
-                .scan_account_storage_inner(
+            let accumulator = match &storage_indexes {
+                Some(storage_indexes) => self.accumulate_storage_indexes(*slot, storage_indexes),
+                None => self.scan_account_storage_inner(
                     *slot,
                     |stored_account: &StoredAccount,
                      store_id: AppendVecId,
@@ -2520,7 +2518,8 @@ impl AccountsDB {
                             .or_insert_with(Vec::new);
                         entry.push((stored_account.meta.write_version, account_info));
                     },
-                );
+                ),
+            };
 
             let mut accounts_map: HashMap<Pubkey, Vec<(u64, AccountInfo)>> = HashMap::new();
             for accumulator_entry in accumulator.iter() {
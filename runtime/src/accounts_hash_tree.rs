@@ -0,0 +1,126 @@
+use rayon::prelude::*;
+use solana_sdk::{
+    account::Account,
+    accounts_merkle_proof::{AccountsMerkleProof, MerkleProofLevel, MERKLE_FANOUT},
+    clock::Slot,
+    hash::{Hash, Hasher},
+    pubkey::Pubkey,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AccountProofError {
+    #[error("no accounts hash Merkle tree has been retained")]
+    TreeNotRetained,
+    #[error("account was modified after the accounts hash of slot {0} was computed")]
+    AccountModified(Slot),
+}
+
+/// An account and the proof of its inclusion in the accounts hash of `accounts_hash_slot`
+#[derive(Debug)]
+pub struct AccountProof {
+    pub accounts_hash_slot: Slot,
+    pub accounts_hash: Hash,
+    pub account: Account,
+    /// The slot the account was last written in, which is hashed into the account hash
+    pub account_slot: Slot,
+    pub account_hash: Hash,
+    pub proof: AccountsMerkleProof,
+}
+
+/// The Merkle tree of an accounts hash, kept so that proofs of inclusion of individual
+/// accounts can be served after the hash is computed
+#[derive(Debug)]
+pub struct AccountsHashTree {
+    pub slot: Slot,
+    pub accounts_hash: Hash,
+    pubkeys: Vec<Pubkey>,
+    /// The hashes of every level of the tree below the root, starting with the account hashes
+    levels: Vec<Vec<Hash>>,
+}
+
+impl AccountsHashTree {
+    /// `hashes` must be sorted by pubkey, as they are for `AccountsDB::compute_merkle_root`
+    pub fn new(slot: Slot, hashes: Vec<(Pubkey, Hash, u64)>) -> Self {
+        let (pubkeys, leaves): (Vec<_>, Vec<_>) = hashes
+            .into_iter()
+            .map(|(pubkey, hash, _lamports)| (pubkey, hash))
+            .unzip();
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > MERKLE_FANOUT {
+            let level = levels
+                .last()
+                .unwrap()
+                .par_chunks(MERKLE_FANOUT)
+                .map(hash_chunk)
+                .collect();
+            levels.push(level);
+        }
+        let accounts_hash = hash_chunk(levels.last().unwrap());
+        Self {
+            slot,
+            accounts_hash,
+            pubkeys,
+            levels,
+        }
+    }
+
+    /// Returns the hash of the account and the proof of its inclusion in the accounts hash, or
+    /// None if the account was not included
+    pub fn prove(&self, pubkey: &Pubkey) -> Option<(Hash, AccountsMerkleProof)> {
+        let mut index = self.pubkeys.binary_search(pubkey).ok()?;
+        let account_hash = self.levels[0][index];
+        let levels = self
+            .levels
+            .iter()
+            .map(|hashes| {
+                let chunk_start = index - index % MERKLE_FANOUT;
+                let chunk_end = std::cmp::min(chunk_start + MERKLE_FANOUT, hashes.len());
+                let position = index - chunk_start;
+                let siblings = hashes[chunk_start..index]
+                    .iter()
+                    .chain(&hashes[index + 1..chunk_end])
+                    .cloned()
+                    .collect();
+                index /= MERKLE_FANOUT;
+                MerkleProofLevel { position, siblings }
+            })
+            .collect();
+        Some((account_hash, AccountsMerkleProof { levels }))
+    }
+}
+
+fn hash_chunk(hashes: &[Hash]) -> Hash {
+    let mut hasher = Hasher::default();
+    hashes.iter().for_each(|hash| hasher.hash(hash.as_ref()));
+    hasher.result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts_db::AccountsDB;
+    use solana_sdk::hash::hash;
+
+    #[test]
+    fn test_accounts_hash_tree() {
+        for num_accounts in &[1, MERKLE_FANOUT, MERKLE_FANOUT + 1, 300] {
+            let mut hashes: Vec<_> = (0..*num_accounts)
+                .map(|i| (Pubkey::new_unique(), hash(&i.to_le_bytes()), 1))
+                .collect();
+            hashes.sort_by(|a, b| a.0.cmp(&b.0));
+            let tree = AccountsHashTree::new(0, hashes.clone());
+            assert_eq!(
+                tree.accounts_hash,
+                AccountsDB::compute_merkle_root(hashes.clone(), MERKLE_FANOUT)
+            );
+
+            for (pubkey, account_hash, _) in &hashes {
+                let (proven_hash, proof) = tree.prove(pubkey).unwrap();
+                assert_eq!(proven_hash, *account_hash);
+                assert!(proof.verify(account_hash, &tree.accounts_hash));
+            }
+            assert!(tree.prove(&Pubkey::new_unique()).is_none());
+        }
+    }
+}
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use solana_sdk::{
     account::Account,
     clock::{Epoch, Slot},
-    hash::Hash,
+    hash::{hash, Hash},
     pubkey::Pubkey,
 };
 use std::{
@@ -241,6 +241,12 @@ impl AppendVec {
         self.len() == 0
     }
 
+    /// Hash of the accounts stored so far, which identifies the contents of an `AppendVec`
+    /// that is no longer appended to
+    pub fn hash(&self) -> Hash {
+        hash(&self.map[..self.len()])
+    }
+
     pub fn capacity(&self) -> u64 {
         self.file_size
     }
@@ -7,11 +7,13 @@ use crate::{
         AccountAddressFilter, Accounts, TransactionAccounts, TransactionLoadResult,
         TransactionLoaders,
     },
-    accounts_db::{ErrorCounters, SnapshotStorages},
+    accounts_db::{AccountsDB, ErrorCounters, SnapshotStorages},
+    accounts_hash_tree::{AccountProof, AccountProofError},
     accounts_index::Ancestors,
     blockhash_queue::BlockhashQueue,
     builtins,
     epoch_stakes::{EpochStakes, NodeVoteAccounts},
+    execution_trace::TransactionExecutionTrace,
     feature::Feature,
     feature_set::{self, FeatureSet},
     instruction_recorder::InstructionRecorder,
@@ -114,7 +116,7 @@ pub const MAX_LEADER_SCHEDULE_STAKES: Epoch = 5;
 pub const TRANSACTION_LOG_MESSAGES_BYTES_LIMIT: usize = 100 * 1000;
 
 type BankStatusCache = StatusCache<Result<()>>;
-#[frozen_abi(digest = "4nZ6EdivqQPcnrnXisbjuTjpcUBoHLDEQWvbZQDCoQQR")]
+#[frozen_abi(digest = "8yUHDhPqHPkZ1DXogkyt5bkSzEsDxuebpXvbHyv8oQtn")]
 pub type BankSlotDelta = SlotDelta<Result<()>>;
 type TransactionAccountRefCells = Vec<Rc<RefCell<Account>>>;
 type TransactionLoaderRefCells = Vec<Vec<(Pubkey, RefCell<Account>)>>;
@@ -1613,6 +1615,10 @@ impl Bank {
         self.parent_slot
     }
 
+    pub fn parent_hash(&self) -> Hash {
+        self.parent_hash
+    }
+
     fn process_genesis_config(&mut self, genesis_config: &GenesisConfig) {
         // Bootstrap validator collects fees until `new_from_parent` is called.
         self.fee_rate_governor = genesis_config.fee_rate_governor.clone();
@@ -1885,10 +1891,11 @@ impl Bank {
             executed,
             _inner_instructions,
             transaction_logs,
+            _execution_traces,
             _retryable_transactions,
             _transaction_count,
             _signature_count,
-        ) = self.load_and_execute_transactions(&batch, MAX_PROCESSING_AGE, false, true);
+        ) = self.load_and_execute_transactions(&batch, MAX_PROCESSING_AGE, false, true, false);
 
         let transaction_result = executed[0].0.clone().map(|_| ());
         let log_messages = transaction_logs
@@ -2316,11 +2323,13 @@ impl Bank {
         max_age: usize,
         enable_cpi_recording: bool,
         enable_log_recording: bool,
+        enable_execution_tracing: bool,
     ) -> (
         Vec<(Result<TransactionLoadResult>, Option<HashAgeKind>)>,
         Vec<TransactionProcessResult>,
         Vec<Option<InnerInstructionsList>>,
         Vec<TransactionLogMessages>,
+        Vec<Option<TransactionExecutionTrace>>,
         Vec<usize>,
         u64,
         u64,
@@ -2364,12 +2373,17 @@ impl Bank {
         let mut inner_instructions: Vec<Option<InnerInstructionsList>> =
             Vec::with_capacity(txs.len());
         let mut transaction_logs: Vec<TransactionLogMessages> = Vec::with_capacity(txs.len());
+        let mut execution_traces: Vec<Option<TransactionExecutionTrace>> =
+            Vec::with_capacity(txs.len());
 
         let executed: Vec<TransactionProcessResult> = loaded_accounts
             .iter_mut()
             .zip(OrderedIterator::new(txs, batch.iteration_order()))
             .map(|(accs, (_, tx))| match accs {
-                (Err(e), hash_age_kind) => (Err(e.clone()), hash_age_kind.clone()),
+                (Err(e), hash_age_kind) => {
+                    execution_traces.push(None);
+                    (Err(e.clone()), hash_age_kind.clone())
+                }
                 (Ok((accounts, loaders, _rents)), hash_age_kind) => {
                     signature_count += u64::from(tx.message().header.num_required_signatures);
 
@@ -2393,6 +2407,13 @@ impl Bank {
                         None
                     };
 
+                    let mut execution_trace = if enable_execution_tracing {
+                        Some(TransactionExecutionTrace::default())
+                    } else {
+                        None
+                    };
+
+                    let mut process_time = Measure::start("process_message");
                     let process_result = self.message_processor.process_message(
                         tx.message(),
                         &loader_refcells,
@@ -2402,7 +2423,16 @@ impl Bank {
                         executors.clone(),
                         instruction_recorders.as_deref(),
                         self.feature_set.clone(),
+                        execution_trace
+                            .as_mut()
+                            .map(|execution_trace| &mut execution_trace.instructions),
                     );
+                    process_time.stop();
+
+                    execution_traces.push(execution_trace.map(|mut execution_trace| {
+                        execution_trace.execution_us = process_time.as_us();
+                        execution_trace
+                    }));
 
                     if enable_log_recording {
                         let mut log_messages: TransactionLogMessages =
@@ -2484,6 +2514,7 @@ impl Bank {
             executed,
             inner_instructions,
             transaction_logs,
+            execution_traces,
             retryable_txs,
             tx_count,
             signature_count,
@@ -2501,6 +2532,7 @@ impl Bank {
 
         let fee_config = FeeConfig {
             secp256k1_program_enabled: self.secp256k1_program_enabled(),
+            ed25519_program_enabled: self.ed25519_program_enabled(),
         };
 
         let results = OrderedIterator::new(txs, iteration_order)
@@ -3172,11 +3204,13 @@ impl Bank {
         collect_balances: bool,
         enable_cpi_recording: bool,
         enable_log_recording: bool,
+        enable_execution_tracing: bool,
     ) -> (
         TransactionResults,
         TransactionBalancesSet,
         Vec<Option<InnerInstructionsList>>,
         Vec<TransactionLogMessages>,
+        Vec<Option<TransactionExecutionTrace>>,
     ) {
         let pre_balances = if collect_balances {
             self.collect_balances(batch)
@@ -3189,6 +3223,7 @@ impl Bank {
             executed,
             inner_instructions,
             transaction_logs,
+            execution_traces,
             _,
             tx_count,
             signature_count,
@@ -3197,6 +3232,7 @@ impl Bank {
             max_age,
             enable_cpi_recording,
             enable_log_recording,
+            enable_execution_tracing,
         );
 
         let results = self.commit_transactions(
@@ -3217,15 +3253,23 @@ impl Bank {
             TransactionBalancesSet::new(pre_balances, post_balances),
             inner_instructions,
             transaction_logs,
+            execution_traces,
         )
     }
 
     #[must_use]
     pub fn process_transactions(&self, txs: &[Transaction]) -> Vec<Result<()>> {
         let batch = self.prepare_batch(txs, None);
-        self.load_execute_and_commit_transactions(&batch, MAX_PROCESSING_AGE, false, false, false)
-            .0
-            .fee_collection_results
+        self.load_execute_and_commit_transactions(
+            &batch,
+            MAX_PROCESSING_AGE,
+            false,
+            false,
+            false,
+            false,
+        )
+        .0
+        .fee_collection_results
     }
 
     /// Create, sign, and process a Transaction from `keypair` to `to` of
@@ -3590,6 +3634,41 @@ impl Bank {
         self.rc.accounts.accounts_db.get_accounts_hash(self.slot)
     }
 
+    /// Proves the inclusion of the account in the latest accounts hash whose Merkle tree was
+    /// retained.  Returns None if the account had no lamports when that hash was computed
+    pub fn get_account_proof(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountProof>, AccountProofError> {
+        let accounts_db = &self.rc.accounts.accounts_db;
+        let accounts_hash_tree = accounts_db
+            .accounts_hash_tree()
+            .ok_or(AccountProofError::TreeNotRetained)?;
+        let (account_hash, proof) = match accounts_hash_tree.prove(pubkey) {
+            Some(proof) => proof,
+            None => return Ok(None),
+        };
+
+        // This bank's version of the account is only the proven one if it hashes the same
+        let modified = || AccountProofError::AccountModified(accounts_hash_tree.slot);
+        let (account, account_slot) = self
+            .get_account_modified_slot(pubkey)
+            .ok_or_else(modified)?;
+        if AccountsDB::hash_account(account_slot, &account, pubkey, &self.cluster_type())
+            != account_hash
+        {
+            return Err(modified());
+        }
+        Ok(Some(AccountProof {
+            accounts_hash_slot: accounts_hash_tree.slot,
+            accounts_hash: accounts_hash_tree.accounts_hash,
+            account,
+            account_slot,
+            account_hash,
+            proof,
+        }))
+    }
+
     pub fn update_accounts_hash(&self) -> Hash {
         let (hash, total_lamports) = self
             .rc
@@ -3884,6 +3963,11 @@ impl Bank {
             .is_active(&feature_set::secp256k1_program_enabled::id())
     }
 
+    pub fn ed25519_program_enabled(&self) -> bool {
+        self.feature_set
+            .is_active(&feature_set::ed25519_program_enabled::id())
+    }
+
     pub fn no_overflow_rent_distribution_enabled(&self) -> bool {
         self.feature_set
             .is_active(&feature_set::no_overflow_rent_distribution::id())
@@ -6649,6 +6733,7 @@ mod tests {
                 false,
                 false,
                 false,
+                false,
             )
             .0
             .fee_collection_results;
@@ -6850,6 +6935,53 @@ mod tests {
         assert!(bank2.verify_bank_hash());
     }
 
+    #[test]
+    fn test_bank_get_account_proof() {
+        let (genesis_config, mint_keypair) = create_genesis_config(2_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let pubkey = solana_sdk::pubkey::new_rand();
+        bank0.transfer(1_000, &mint_keypair, &pubkey).unwrap();
+        assert_eq!(
+            bank0.get_account_proof(&pubkey).unwrap_err(),
+            AccountProofError::TreeNotRetained
+        );
+
+        bank0
+            .rc
+            .accounts
+            .accounts_db
+            .retain_accounts_hash_tree
+            .store(true, Relaxed);
+        bank0.freeze();
+        let accounts_hash = bank0.update_accounts_hash();
+        let account_proof = bank0.get_account_proof(&pubkey).unwrap().unwrap();
+        assert_eq!(account_proof.accounts_hash_slot, 0);
+        assert_eq!(account_proof.accounts_hash, accounts_hash);
+        assert_eq!(account_proof.account.lamports, 1_000);
+        assert!(account_proof
+            .proof
+            .verify(&account_proof.account_hash, &accounts_hash));
+        assert!(solana_sdk::accounts_merkle_proof::verify_account(
+            &pubkey,
+            &account_proof.account,
+            account_proof.account_slot,
+            &bank0.cluster_type(),
+            &account_proof.proof,
+            &accounts_hash,
+        ));
+        assert!(bank0
+            .get_account_proof(&solana_sdk::pubkey::new_rand())
+            .unwrap()
+            .is_none());
+
+        let bank1 = new_from_parent(&bank0);
+        bank1.transfer(10, &mint_keypair, &pubkey).unwrap();
+        assert_eq!(
+            bank1.get_account_proof(&pubkey).unwrap_err(),
+            AccountProofError::AccountModified(0)
+        );
+    }
+
     #[test]
     fn test_bank_hash_internal_state_verify() {
         solana_logger::setup();
@@ -8428,18 +8560,33 @@ mod tests {
         let txs = vec![tx0, tx1, tx2];
 
         let lock_result = bank0.prepare_batch(&txs, None);
-        let (transaction_results, transaction_balances_set, inner_instructions, transaction_logs) =
-            bank0.load_execute_and_commit_transactions(
-                &lock_result,
-                MAX_PROCESSING_AGE,
-                true,
-                false,
-                false,
-            );
+        let (
+            transaction_results,
+            transaction_balances_set,
+            inner_instructions,
+            transaction_logs,
+            execution_traces,
+        ) = bank0.load_execute_and_commit_transactions(
+            &lock_result,
+            MAX_PROCESSING_AGE,
+            true,
+            false,
+            false,
+            true,
+        );
 
         assert!(inner_instructions[0].iter().all(|ix| ix.is_empty()));
         assert_eq!(transaction_logs.len(), 0);
 
+        // Transactions that fail to load are not traced
+        assert_eq!(execution_traces.len(), 3);
+        assert_eq!(
+            execution_traces[0].as_ref().unwrap().instructions[0].program_id,
+            system_program::id()
+        );
+        assert!(execution_traces[1].is_none());
+        assert_eq!(execution_traces[2].as_ref().unwrap().instructions.len(), 1);
+
         assert_eq!(transaction_balances_set.pre_balances.len(), 3);
         assert_eq!(transaction_balances_set.post_balances.len(), 3);
 
@@ -40,6 +40,10 @@ pub struct SnapshotConfig {
 
     // Snapshot version to generate
     pub snapshot_version: SnapshotVersion,
+
+    // Include the index of every account storage in snapshot archives, which only validators
+    // that know about storage indexes can unpack
+    pub include_storage_indexes: bool,
 }
 
 pub struct BankForks {
@@ -0,0 +1,201 @@
+//! Records everything that contributes to a bank hash, so that the banks of two nodes
+//! which disagree on a bank hash can be compared account by account
+use crate::{accounts_db::BankHashStats, bank::Bank};
+use log::*;
+use solana_sdk::{account::Account, clock::Slot, hash::Hash, pubkey::Pubkey};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+const FILE_NAME_PREFIX: &str = "bank-hash-details-";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountHashDetails {
+    pub pubkey: Pubkey,
+    /// The hash of the account as stored, which is what the accounts delta hash is built from
+    pub hash: Hash,
+    pub account: Account,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BankHashDetails {
+    pub slot: Slot,
+    pub bank_hash: Hash,
+    pub parent_slot: Slot,
+    pub parent_bank_hash: Hash,
+    pub accounts_delta_hash: Hash,
+    pub signature_count: u64,
+    pub last_blockhash: Hash,
+    pub stats: BankHashStats,
+    /// Every account stored in this slot, sorted by pubkey
+    pub accounts: Vec<AccountHashDetails>,
+}
+
+impl BankHashDetails {
+    /// Collects the hash details of a frozen bank
+    pub fn new(bank: &Bank) -> Self {
+        let slot = bank.slot();
+        let bank_hash_info = bank.rc.accounts.bank_hash_info_at(slot);
+        let accounts = bank
+            .rc
+            .accounts
+            .accounts_db
+            .get_accounts_delta_hash_details(slot)
+            .into_iter()
+            .map(|(pubkey, hash, account)| AccountHashDetails {
+                pubkey,
+                hash,
+                account,
+            })
+            .collect();
+        Self {
+            slot,
+            bank_hash: bank.hash(),
+            parent_slot: bank.parent_slot(),
+            parent_bank_hash: bank.parent_hash(),
+            accounts_delta_hash: bank_hash_info.hash,
+            signature_count: bank.signature_count(),
+            last_blockhash: bank.last_blockhash(),
+            stats: bank_hash_info.stats,
+            accounts,
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}{}-{}.bin", FILE_NAME_PREFIX, self.slot, self.bank_hash)
+    }
+
+    /// Parses the slot out of a file name produced by `file_name()`
+    fn slot_from_file_name(file_name: &str) -> Option<Slot> {
+        file_name
+            .strip_prefix(FILE_NAME_PREFIX)?
+            .split('-')
+            .next()?
+            .parse()
+            .ok()
+    }
+
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(self.file_name());
+        let writer = BufWriter::new(File::create(&path)?);
+        bincode::serialize_into(writer, self)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        Ok(path)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+/// Writes the hash details of a frozen bank to `dir`, logging rather than failing on error
+pub fn write_bank_hash_details(bank: &Bank, dir: &Path) {
+    match BankHashDetails::new(bank).write(dir) {
+        Ok(path) => debug!(
+            "wrote bank hash details for slot {} to {:?}",
+            bank.slot(),
+            path
+        ),
+        Err(err) => warn!(
+            "failed to write bank hash details for slot {}: {}",
+            bank.slot(),
+            err
+        ),
+    }
+}
+
+/// Removes the bank hash details in `dir` for all but the newest `max_slots` slots
+pub fn remove_old_bank_hash_details(dir: &Path, max_slots: usize) -> io::Result<()> {
+    let files = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let slot = BankHashDetails::slot_from_file_name(path.file_name()?.to_str()?)?;
+            Some((slot, path))
+        })
+        .collect::<Vec<_>>();
+    let slots = files.iter().map(|(slot, _)| *slot).collect::<BTreeSet<_>>();
+    if let Some(oldest_kept_slot) = slots.iter().rev().take(max_slots).last() {
+        for (slot, path) in files {
+            if slot < *oldest_kept_slot {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genesis_utils::create_genesis_config;
+    use solana_sdk::{hash::hash, signature::Signer, system_transaction};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_bank_hash_details() {
+        let genesis_config_info = create_genesis_config(10_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config_info.genesis_config));
+        let bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        let to = solana_sdk::pubkey::new_rand();
+        let tx = system_transaction::transfer(
+            &genesis_config_info.mint_keypair,
+            &to,
+            42,
+            bank1.last_blockhash(),
+        );
+        bank1.process_transaction(&tx).unwrap();
+        bank1.freeze();
+
+        let details = BankHashDetails::new(&bank1);
+        assert_eq!(details.slot, 1);
+        assert_eq!(details.bank_hash, bank1.hash());
+        assert_eq!(details.parent_bank_hash, bank0.hash());
+        assert_eq!(details.signature_count, 1);
+        let mint = genesis_config_info.mint_keypair.pubkey();
+        assert!(details.accounts.iter().any(|a| a.pubkey == mint));
+        let to_details = details.accounts.iter().find(|a| a.pubkey == to).unwrap();
+        assert_eq!(to_details.account.lamports, 42);
+        assert!(details
+            .accounts
+            .windows(2)
+            .all(|pair| pair[0].pubkey < pair[1].pubkey));
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = details.write(dir.path()).unwrap();
+        assert_eq!(path, dir.path().join(details.file_name()));
+        assert_eq!(BankHashDetails::read(&path).unwrap(), details);
+    }
+
+    #[test]
+    fn test_remove_old_bank_hash_details() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for (i, slot) in [3, 1, 4, 4, 5, 2].iter().enumerate() {
+            let details = BankHashDetails {
+                slot: *slot,
+                bank_hash: hash(&[i as u8]),
+                ..BankHashDetails::default()
+            };
+            details.write(dir.path()).unwrap();
+        }
+        let unrelated = dir.path().join("unrelated.bin");
+        File::create(&unrelated).unwrap();
+
+        remove_old_bank_hash_details(dir.path(), 2).unwrap();
+        let mut slots: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| {
+                let file_name = entry.unwrap().file_name();
+                BankHashDetails::slot_from_file_name(file_name.to_str().unwrap())
+            })
+            .collect();
+        slots.sort_unstable();
+        assert_eq!(slots, vec![4, 4, 5]);
+        assert!(unrelated.exists());
+    }
+}
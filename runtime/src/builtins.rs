@@ -32,14 +32,24 @@ fn genesis_builtins() -> Vec<Builtin> {
 
 /// Builtin programs activated dynamically by feature
 fn feature_builtins() -> Vec<(Builtin, Pubkey)> {
-    vec![(
-        Builtin::new(
-            "secp256k1_program",
-            solana_sdk::secp256k1_program::id(),
-            Entrypoint::Program(solana_secp256k1_program::process_instruction),
+    vec![
+        (
+            Builtin::new(
+                "secp256k1_program",
+                solana_sdk::secp256k1_program::id(),
+                Entrypoint::Program(solana_secp256k1_program::process_instruction),
+            ),
+            feature_set::secp256k1_program_enabled::id(),
         ),
-        feature_set::secp256k1_program_enabled::id(),
-    )]
+        (
+            Builtin::new(
+                "ed25519_program",
+                solana_sdk::ed25519_program::id(),
+                Entrypoint::Program(solana_ed25519_program::process_instruction),
+            ),
+            feature_set::ed25519_program_enabled::id(),
+        ),
+    ]
 }
 
 pub(crate) fn get() -> Builtins {
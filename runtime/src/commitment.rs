@@ -192,6 +192,13 @@ impl BlockCommitmentCache {
         self.commitment_slots.slot = slot;
         self.commitment_slots.root = slot;
     }
+
+    pub fn set_all_slots(&mut self, slot: Slot, root: Slot) {
+        self.commitment_slots.slot = slot;
+        self.commitment_slots.highest_confirmed_slot = slot;
+        self.commitment_slots.root = root;
+        self.commitment_slots.highest_confirmed_root = root;
+    }
 }
 
 #[derive(Default, Clone, Copy)]
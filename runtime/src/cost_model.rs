@@ -0,0 +1,191 @@
+//! The `cost_model` estimates how expensive a transaction is to replay. The
+//! cost is expressed in compute units and is made of a fixed cost per
+//! signature and per write lock, plus the execution cost of each instruction,
+//! looked up by program id.
+
+use solana_sdk::{
+    bpf_loader, bpf_loader_deprecated, ed25519_program, pubkey::Pubkey, secp256k1_program,
+    system_program, transaction::Transaction,
+};
+use std::collections::HashMap;
+
+/// Number of compute units which take about a microsecond to replay
+pub const COMPUTE_UNIT_TO_US_RATIO: u64 = 30;
+/// Cost of verifying a single signature
+pub const SIGNATURE_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 24;
+/// Cost of taking a write lock on an account
+pub const WRITE_LOCK_UNITS: u64 = COMPUTE_UNIT_TO_US_RATIO * 10;
+/// Execution cost of an instruction whose program has no entry in the table
+pub const DEFAULT_PROGRAM_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 500;
+
+/// Replaying a block should not take longer than producing it
+pub const MAX_BLOCK_REPLAY_TIME_US: u64 = 400_000;
+/// Number of threads a block is expected to be replayed with
+pub const MAX_CONCURRENCY: u64 = 4;
+pub const MAX_BLOCK_UNITS: u64 = MAX_BLOCK_REPLAY_TIME_US * COMPUTE_UNIT_TO_US_RATIO;
+/// Transactions writing to the same account are replayed one after the
+/// other, so no account may use more than one thread's share of the block
+pub const MAX_WRITABLE_ACCOUNT_UNITS: u64 = MAX_BLOCK_UNITS / MAX_CONCURRENCY;
+
+fn builtin_instruction_costs() -> Vec<(Pubkey, u64)> {
+    vec![
+        (system_program::id(), COMPUTE_UNIT_TO_US_RATIO * 5),
+        (solana_vote_program::id(), COMPUTE_UNIT_TO_US_RATIO * 70),
+        (solana_stake_program::id(), COMPUTE_UNIT_TO_US_RATIO * 25),
+        (solana_config_program::id(), COMPUTE_UNIT_TO_US_RATIO * 15),
+        (bpf_loader::id(), COMPUTE_UNIT_TO_US_RATIO * 1_000),
+        (
+            bpf_loader_deprecated::id(),
+            COMPUTE_UNIT_TO_US_RATIO * 1_000,
+        ),
+        // Precompiles only verify signatures, which are already accounted for
+        (secp256k1_program::id(), 0),
+        (ed25519_program::id(), 0),
+    ]
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransactionCost {
+    pub writable_accounts: Vec<Pubkey>,
+    pub signature_cost: u64,
+    pub write_lock_cost: u64,
+    pub execution_cost: u64,
+}
+
+impl TransactionCost {
+    pub fn sum(&self) -> u64 {
+        self.signature_cost + self.write_lock_cost + self.execution_cost
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    account_cost_limit: u64,
+    block_cost_limit: u64,
+    instruction_execution_cost_table: HashMap<Pubkey, u64>,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self::new(MAX_WRITABLE_ACCOUNT_UNITS, MAX_BLOCK_UNITS)
+    }
+}
+
+impl CostModel {
+    pub fn new(account_cost_limit: u64, block_cost_limit: u64) -> Self {
+        Self {
+            account_cost_limit,
+            block_cost_limit,
+            instruction_execution_cost_table: builtin_instruction_costs().into_iter().collect(),
+        }
+    }
+
+    pub fn account_cost_limit(&self) -> u64 {
+        self.account_cost_limit
+    }
+
+    pub fn block_cost_limit(&self) -> u64 {
+        self.block_cost_limit
+    }
+
+    /// Set the execution cost of instructions of `program_id`
+    pub fn upsert_instruction_cost(&mut self, program_id: &Pubkey, cost: u64) {
+        self.instruction_execution_cost_table
+            .insert(*program_id, cost);
+    }
+
+    pub fn find_instruction_cost(&self, program_id: &Pubkey) -> u64 {
+        self.instruction_execution_cost_table
+            .get(program_id)
+            .copied()
+            .unwrap_or(DEFAULT_PROGRAM_COST)
+    }
+
+    pub fn calculate_cost(&self, transaction: &Transaction) -> TransactionCost {
+        let message = transaction.message();
+        let writable_accounts: Vec<_> = message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| message.is_writable(*i))
+            .map(|(_, key)| *key)
+            .collect();
+        let execution_cost = message
+            .instructions
+            .iter()
+            .map(|instruction| {
+                let program_id = &message.account_keys[instruction.program_id_index as usize];
+                self.find_instruction_cost(program_id)
+            })
+            .sum();
+        TransactionCost {
+            signature_cost: SIGNATURE_COST * transaction.signatures.len() as u64,
+            write_lock_cost: WRITE_LOCK_UNITS * writable_accounts.len() as u64,
+            execution_cost,
+            writable_accounts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        signature::{Keypair, Signer},
+        system_instruction, system_transaction,
+    };
+
+    #[test]
+    fn test_cost_model_transfer() {
+        let from = Keypair::new();
+        let to = solana_sdk::pubkey::new_rand();
+        let tx = system_transaction::transfer(&from, &to, 1, Hash::default());
+
+        let cost = CostModel::default().calculate_cost(&tx);
+        assert_eq!(cost.writable_accounts, vec![from.pubkey(), to]);
+        assert_eq!(cost.signature_cost, SIGNATURE_COST);
+        assert_eq!(cost.write_lock_cost, 2 * WRITE_LOCK_UNITS);
+        assert_eq!(
+            cost.execution_cost,
+            CostModel::default().find_instruction_cost(&system_program::id())
+        );
+        assert_eq!(
+            cost.sum(),
+            cost.signature_cost + cost.write_lock_cost + cost.execution_cost
+        );
+    }
+
+    #[test]
+    fn test_cost_model_unknown_program() {
+        let payer = Keypair::new();
+        let program_id = solana_sdk::pubkey::new_rand();
+        let recipient = solana_sdk::pubkey::new_rand();
+        let instructions = vec![
+            Instruction::new(
+                program_id,
+                &0u8,
+                vec![AccountMeta::new_readonly(recipient, false)],
+            ),
+            system_instruction::transfer(&payer.pubkey(), &recipient, 1),
+        ];
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+        let tx = Transaction::new(&[&payer], message, Hash::default());
+
+        let mut cost_model = CostModel::default();
+        let cost = cost_model.calculate_cost(&tx);
+        assert_eq!(
+            cost.execution_cost,
+            DEFAULT_PROGRAM_COST + cost_model.find_instruction_cost(&system_program::id())
+        );
+
+        cost_model.upsert_instruction_cost(&program_id, 42);
+        let cost = cost_model.calculate_cost(&tx);
+        assert_eq!(
+            cost.execution_cost,
+            42 + cost_model.find_instruction_cost(&system_program::id())
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! The `cost_tracker` accumulates the cost of the transactions packed into a
+//! block, so that the leader stops adding transactions once the block, or any
+//! single writable account in it, reaches its cost limit.
+
+use crate::cost_model::{CostModel, TransactionCost};
+use solana_sdk::{clock::Slot, pubkey::Pubkey, transaction::Transaction};
+use std::{collections::HashMap, sync::Arc};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CostTrackerError {
+    #[error("would exceed block max limit")]
+    WouldExceedBlockMaxLimit,
+
+    #[error("would exceed account max limit")]
+    WouldExceedAccountMaxLimit,
+}
+
+#[derive(Debug)]
+pub struct CostTracker {
+    cost_model: Arc<CostModel>,
+    current_bank_slot: Slot,
+    cost_by_writable_accounts: HashMap<Pubkey, u64>,
+    block_cost: u64,
+    transaction_count: u64,
+}
+
+impl Default for CostTracker {
+    fn default() -> Self {
+        Self::new(Arc::new(CostModel::default()))
+    }
+}
+
+impl CostTracker {
+    pub fn new(cost_model: Arc<CostModel>) -> Self {
+        Self {
+            cost_model,
+            current_bank_slot: 0,
+            cost_by_writable_accounts: HashMap::new(),
+            block_cost: 0,
+            transaction_count: 0,
+        }
+    }
+
+    pub fn cost_model(&self) -> &CostModel {
+        &self.cost_model
+    }
+
+    /// Start accounting for a new block if `slot` differs from the block
+    /// tracked so far
+    pub fn reset_if_new_bank(&mut self, slot: Slot) {
+        if slot != self.current_bank_slot {
+            if self.transaction_count > 0 {
+                self.report_stats();
+            }
+            self.current_bank_slot = slot;
+            self.cost_by_writable_accounts.clear();
+            self.block_cost = 0;
+            self.transaction_count = 0;
+        }
+    }
+
+    pub fn would_fit(&self, cost: &TransactionCost) -> Result<(), CostTrackerError> {
+        let tx_cost = cost.sum();
+        if self.block_cost.saturating_add(tx_cost) > self.cost_model.block_cost_limit() {
+            return Err(CostTrackerError::WouldExceedBlockMaxLimit);
+        }
+        let account_cost_limit = self.cost_model.account_cost_limit();
+        for account in &cost.writable_accounts {
+            let account_cost = self
+                .cost_by_writable_accounts
+                .get(account)
+                .copied()
+                .unwrap_or_default();
+            if account_cost.saturating_add(tx_cost) > account_cost_limit {
+                return Err(CostTrackerError::WouldExceedAccountMaxLimit);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add `cost` to the block without checking it against the limits
+    pub fn add_transaction_cost(&mut self, cost: &TransactionCost) {
+        let tx_cost = cost.sum();
+        for account in &cost.writable_accounts {
+            *self.cost_by_writable_accounts.entry(*account).or_insert(0) += tx_cost;
+        }
+        self.block_cost += tx_cost;
+        self.transaction_count += 1;
+    }
+
+    /// Take `cost` back from the block at `slot`, for a transaction which was
+    /// charged but not committed. Nothing is taken back once the tracker has
+    /// moved on to another block
+    pub fn remove_transaction_cost(&mut self, slot: Slot, cost: &TransactionCost) {
+        if slot != self.current_bank_slot {
+            return;
+        }
+        let tx_cost = cost.sum();
+        for account in &cost.writable_accounts {
+            if let Some(account_cost) = self.cost_by_writable_accounts.get_mut(account) {
+                *account_cost = account_cost.saturating_sub(tx_cost);
+                if *account_cost == 0 {
+                    self.cost_by_writable_accounts.remove(account);
+                }
+            }
+        }
+        self.block_cost = self.block_cost.saturating_sub(tx_cost);
+        self.transaction_count = self.transaction_count.saturating_sub(1);
+    }
+
+    /// Add the cost of `transaction` to the block if it fits within the limits
+    pub fn try_add(&mut self, transaction: &Transaction) -> Result<u64, CostTrackerError> {
+        let cost = self.cost_model.calculate_cost(transaction);
+        self.would_fit(&cost)?;
+        self.add_transaction_cost(&cost);
+        Ok(self.block_cost)
+    }
+
+    pub fn block_cost(&self) -> u64 {
+        self.block_cost
+    }
+
+    pub fn transaction_count(&self) -> u64 {
+        self.transaction_count
+    }
+
+    /// Returns the most expensive writable account of the block and its cost
+    pub fn max_account_cost(&self) -> Option<(Pubkey, u64)> {
+        self.cost_by_writable_accounts
+            .iter()
+            .max_by_key(|(_, cost)| **cost)
+            .map(|(account, cost)| (*account, *cost))
+    }
+
+    fn report_stats(&self) {
+        let (max_account, max_account_cost) = self.max_account_cost().unwrap_or_default();
+        datapoint_info!(
+            "cost_tracker_stats",
+            ("bank_slot", self.current_bank_slot as i64, i64),
+            ("block_cost", self.block_cost as i64, i64),
+            ("transaction_count", self.transaction_count as i64, i64),
+            (
+                "number_of_accounts",
+                self.cost_by_writable_accounts.len() as i64,
+                i64
+            ),
+            ("costliest_account", max_account.to_string(), String),
+            ("costliest_account_cost", max_account_cost as i64, i64),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cost(accounts: &[Pubkey], execution_cost: u64) -> TransactionCost {
+        TransactionCost {
+            writable_accounts: accounts.to_vec(),
+            execution_cost,
+            ..TransactionCost::default()
+        }
+    }
+
+    #[test]
+    fn test_cost_tracker_block_limit() {
+        let mut tracker = CostTracker::new(Arc::new(CostModel::new(100, 150)));
+        let cost = test_cost(&[solana_sdk::pubkey::new_rand()], 80);
+        assert_eq!(tracker.would_fit(&cost), Ok(()));
+        tracker.add_transaction_cost(&cost);
+
+        let cost = test_cost(&[solana_sdk::pubkey::new_rand()], 80);
+        assert_eq!(
+            tracker.would_fit(&cost),
+            Err(CostTrackerError::WouldExceedBlockMaxLimit)
+        );
+        assert_eq!(tracker.block_cost(), 80);
+        assert_eq!(tracker.transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_cost_tracker_account_limit() {
+        let mut tracker = CostTracker::new(Arc::new(CostModel::new(100, 1_000)));
+        let hot_account = solana_sdk::pubkey::new_rand();
+        let cost = test_cost(&[hot_account], 60);
+        tracker.add_transaction_cost(&cost);
+        assert_eq!(
+            tracker.would_fit(&cost),
+            Err(CostTrackerError::WouldExceedAccountMaxLimit)
+        );
+        // Other accounts are not affected by the hot one
+        let cost = test_cost(&[solana_sdk::pubkey::new_rand()], 60);
+        assert_eq!(tracker.would_fit(&cost), Ok(()));
+        tracker.add_transaction_cost(&cost);
+        assert_eq!(tracker.max_account_cost(), Some((hot_account, 60)));
+        assert_eq!(tracker.block_cost(), 120);
+    }
+
+    #[test]
+    fn test_cost_tracker_remove_transaction_cost() {
+        let mut tracker = CostTracker::new(Arc::new(CostModel::new(100, 100)));
+        let account = solana_sdk::pubkey::new_rand();
+        let cost = test_cost(&[account], 100);
+        tracker.reset_if_new_bank(1);
+        tracker.add_transaction_cost(&cost);
+        assert!(tracker.would_fit(&cost).is_err());
+
+        // A refund for another block is ignored
+        tracker.remove_transaction_cost(2, &cost);
+        assert_eq!(tracker.block_cost(), 100);
+
+        tracker.remove_transaction_cost(1, &cost);
+        assert_eq!(tracker.block_cost(), 0);
+        assert_eq!(tracker.transaction_count(), 0);
+        assert_eq!(tracker.max_account_cost(), None);
+        assert_eq!(tracker.would_fit(&cost), Ok(()));
+    }
+
+    #[test]
+    fn test_cost_tracker_reset_if_new_bank() {
+        let mut tracker = CostTracker::new(Arc::new(CostModel::new(100, 100)));
+        let cost = test_cost(&[solana_sdk::pubkey::new_rand()], 100);
+        tracker.reset_if_new_bank(1);
+        tracker.add_transaction_cost(&cost);
+        assert!(tracker.would_fit(&cost).is_err());
+
+        tracker.reset_if_new_bank(1);
+        assert!(tracker.would_fit(&cost).is_err());
+        tracker.reset_if_new_bank(2);
+        assert_eq!(tracker.would_fit(&cost), Ok(()));
+        assert_eq!(tracker.block_cost(), 0);
+        assert_eq!(tracker.max_account_cost(), None);
+    }
+}
@@ -0,0 +1,33 @@
+use solana_sdk::pubkey::Pubkey;
+
+/// Execution statistics for a single top-level instruction
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstructionTrace {
+    pub program_id: Pubkey,
+    /// Compute units consumed by the instruction, including any cross-program invocations
+    pub compute_units: u64,
+    pub execution_us: u64,
+}
+
+impl InstructionTrace {
+    pub fn new(program_id: Pubkey) -> Self {
+        Self {
+            program_id,
+            ..Self::default()
+        }
+    }
+}
+
+/// Execution statistics for a transaction.  Only the instructions that were
+/// executed are recorded, so a failed transaction stops at the failing instruction.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransactionExecutionTrace {
+    pub instructions: Vec<InstructionTrace>,
+    pub execution_us: u64,
+}
+
+impl TransactionExecutionTrace {
+    pub fn compute_units(&self) -> u64 {
+        self.instructions.iter().map(|i| i.compute_units).sum()
+    }
+}
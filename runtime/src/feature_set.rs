@@ -73,6 +73,30 @@ pub mod pull_request_ping_pong_check {
     solana_sdk::declare_id!("5RzEHTnf6D7JPZCvwEzjM19kzBsyjSU3HoMfXaQmVgnZ");
 }
 
+pub mod keccak256_syscall_enabled {
+    solana_sdk::declare_id!("JDm8Bg35J4n4n7GrNYA1kBN7wEw3xGTvHXVe7M67fiKd");
+}
+
+pub mod secp256k1_recover_syscall_enabled {
+    solana_sdk::declare_id!("4GYeCdbReWhQxuDw7HhjpgTtbeQjrE1y2UqQRxVEEf7C");
+}
+
+pub mod ed25519_program_enabled {
+    solana_sdk::declare_id!("HGBf6r8bRHjogFNzBC4YczAHnhLuarXj6f98nH8ySFYA");
+}
+
+pub mod log_data_syscall_enabled {
+    solana_sdk::declare_id!("79jzngJMnYQmHybUFgeCLjz3DXvf8tvCPDZYZizZJqTZ");
+}
+
+pub mod merkle_shreds {
+    solana_sdk::declare_id!("sjuZX9CJm4XMWWcRVPYaD9cNgZkmCsGy9mEDHR5HVSe");
+}
+
+pub mod sign_repair_requests {
+    solana_sdk::declare_id!("89M3BYWNjsTroYSYs8miUiwD1JhbwrCbUjHRRbni7ne1");
+}
+
 lazy_static! {
     /// Map of feature identifiers to user-visible description
     pub static ref FEATURE_NAMES: HashMap<Pubkey, &'static str> = [
@@ -93,6 +117,12 @@ lazy_static! {
         (cumulative_rent_related_fixes::id(), "rent fixes (#10206, #10468, #11342)"),
         (pubkey_log_syscall_enabled::id(), "pubkey log syscall"),
         (pull_request_ping_pong_check::id(), "ping-pong packet check #12794"),
+        (keccak256_syscall_enabled::id(), "keccak256 syscall"),
+        (secp256k1_recover_syscall_enabled::id(), "secp256k1_recover syscall"),
+        (ed25519_program_enabled::id(), "ed25519 program"),
+        (log_data_syscall_enabled::id(), "log data syscall"),
+        (merkle_shreds::id(), "merkle-rooted shred signatures per erasure set"),
+        (sign_repair_requests::id(), "sign repair requests"),
         /*************** ADD NEW FEATURES HERE ***************/
     ]
     .iter()
@@ -140,6 +140,7 @@ pub fn unpack_snapshot<A: Read, P: AsRef<Path>>(
 
 fn is_valid_snapshot_archive_entry(parts: &[&str], kind: tar::EntryType) -> bool {
     let like_storage = Regex::new(r"^\d+\.\d+$").unwrap();
+    let like_storage_index = Regex::new(r"^\d+\.\d+\.index$").unwrap();
     let like_slot = Regex::new(r"^\d+$").unwrap();
 
     trace!("validating: {:?} {:?}", parts, kind);
@@ -148,6 +149,7 @@ fn is_valid_snapshot_archive_entry(parts: &[&str], kind: tar::EntryType) -> bool
         (["accounts"], Directory) => true,
         (["accounts", file], GNUSparse) if like_storage.is_match(file) => true,
         (["accounts", file], Regular) if like_storage.is_match(file) => true,
+        (["accounts", file], Regular) if like_storage_index.is_match(file) => true,
         (["snapshots"], Directory) => true,
         (["snapshots", "status_cache"], Regular) => true,
         (["snapshots", dir, file], Regular)
@@ -246,6 +248,10 @@ mod tests {
             &["accounts", "0.0"],
             tar::EntryType::Regular
         ));
+        assert!(is_valid_snapshot_archive_entry(
+            &["accounts", "0.0.index"],
+            tar::EntryType::Regular
+        ));
         assert!(is_valid_snapshot_archive_entry(
             &["snapshots"],
             tar::EntryType::Directory
@@ -271,6 +277,10 @@ mod tests {
             &["accounts", "0x0"],
             tar::EntryType::Regular
         ));
+        assert!(!is_valid_snapshot_archive_entry(
+            &["accounts", "0.0.idx"],
+            tar::EntryType::Regular
+        ));
         assert!(!is_valid_snapshot_archive_entry(
             &["snapshots"],
             tar::EntryType::Regular
@@ -2,17 +2,22 @@
 pub mod accounts;
 pub mod accounts_background_service;
 pub mod accounts_db;
+pub mod accounts_hash_tree;
 pub mod accounts_index;
 pub mod append_vec;
 pub mod bank;
 pub mod bank_client;
 pub mod bank_forks;
+pub mod bank_hash_details;
 pub mod bank_utils;
 mod blockhash_queue;
 pub mod bloom;
 pub mod builtins;
 pub mod commitment;
+pub mod cost_model;
+pub mod cost_tracker;
 pub mod epoch_stakes;
+pub mod execution_trace;
 pub mod feature;
 pub mod feature_set;
 pub mod genesis_utils;
@@ -29,6 +34,7 @@ pub mod snapshot_package;
 pub mod snapshot_utils;
 pub mod stakes;
 pub mod status_cache;
+pub mod storage_index;
 mod system_instruction_processor;
 pub mod transaction_batch;
 pub mod transaction_utils;
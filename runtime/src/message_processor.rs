@@ -1,4 +1,5 @@
 use crate::{
+    execution_trace::InstructionTrace,
     feature_set::{instructions_sysvar_enabled, FeatureSet},
     instruction_recorder::InstructionRecorder,
     log_collector::LogCollector,
@@ -11,6 +12,7 @@ use crate::{
 };
 use log::*;
 use serde::{Deserialize, Serialize};
+use solana_measure::measure::Measure;
 use solana_sdk::{
     account::{create_keyed_readonly_accounts, Account, KeyedAccount},
     clock::Epoch,
@@ -666,6 +668,7 @@ impl MessageProcessor {
         instruction_recorder: Option<InstructionRecorder>,
         instruction_index: usize,
         feature_set: Arc<FeatureSet>,
+        instruction_trace: Option<&mut InstructionTrace>,
     ) -> Result<(), InstructionError> {
         // Fixup the special instructions key if present
         // before the account pre-values are taken care of
@@ -696,7 +699,16 @@ impl MessageProcessor {
         );
         let keyed_accounts =
             Self::create_keyed_accounts(message, instruction, executable_accounts, accounts)?;
-        self.process_instruction(&keyed_accounts, &instruction.data, &mut invoke_context)?;
+        let result =
+            self.process_instruction(&keyed_accounts, &instruction.data, &mut invoke_context);
+        if let Some(instruction_trace) = instruction_trace {
+            let remaining = invoke_context.compute_meter.borrow().get_remaining();
+            instruction_trace.compute_units = invoke_context
+                .compute_budget
+                .max_units
+                .saturating_sub(remaining);
+        }
+        result?;
         Self::verify(
             message,
             instruction,
@@ -711,6 +723,7 @@ impl MessageProcessor {
     /// Process a message.
     /// This method calls each instruction in the message over the set of loaded Accounts
     /// The accounts are committed back to the bank only if every instruction succeeds
+    /// If `instruction_traces` is provided, a trace is appended for every executed instruction
     #[allow(clippy::too_many_arguments)]
     pub fn process_message(
         &self,
@@ -722,12 +735,17 @@ impl MessageProcessor {
         executors: Rc<RefCell<Executors>>,
         instruction_recorders: Option<&[InstructionRecorder]>,
         feature_set: Arc<FeatureSet>,
+        mut instruction_traces: Option<&mut Vec<InstructionTrace>>,
     ) -> Result<(), TransactionError> {
         for (instruction_index, instruction) in message.instructions.iter().enumerate() {
             let instruction_recorder = instruction_recorders
                 .as_ref()
                 .map(|recorders| recorders[instruction_index].clone());
-            self.execute_instruction(
+            let mut instruction_trace = instruction_traces
+                .as_ref()
+                .map(|_| InstructionTrace::new(*instruction.program_id(&message.account_keys)));
+            let mut execute_time = Measure::start("execute_instruction");
+            let result = self.execute_instruction(
                 message,
                 instruction,
                 &loaders[instruction_index],
@@ -738,8 +756,17 @@ impl MessageProcessor {
                 instruction_recorder,
                 instruction_index,
                 feature_set.clone(),
-            )
-            .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))?;
+                instruction_trace.as_mut(),
+            );
+            execute_time.stop();
+            if let (Some(instruction_traces), Some(mut instruction_trace)) =
+                (instruction_traces.as_mut(), instruction_trace)
+            {
+                instruction_trace.execution_us = execute_time.as_us();
+                instruction_traces.push(instruction_trace);
+            }
+            result
+                .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))?;
         }
         Ok(())
     }
@@ -1326,6 +1353,7 @@ mod tests {
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            None,
         );
         assert_eq!(result, Ok(()));
         assert_eq!(accounts[0].borrow().lamports, 100);
@@ -1340,6 +1368,7 @@ mod tests {
             Some(&from_pubkey),
         );
 
+        let mut instruction_traces = vec![];
         let result = message_processor.process_message(
             &message,
             &loaders,
@@ -1349,6 +1378,7 @@ mod tests {
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            Some(&mut instruction_traces),
         );
         assert_eq!(
             result,
@@ -1357,6 +1387,9 @@ mod tests {
                 InstructionError::ReadonlyLamportChange
             ))
         );
+        assert_eq!(instruction_traces.len(), 1);
+        assert_eq!(instruction_traces[0].program_id, mock_system_program_id);
+        assert_eq!(instruction_traces[0].compute_units, 0);
 
         let message = Message::new(
             &[Instruction::new(
@@ -1376,6 +1409,7 @@ mod tests {
             executors,
             None,
             Arc::new(FeatureSet::all_enabled()),
+            None,
         );
         assert_eq!(
             result,
@@ -1486,6 +1520,7 @@ mod tests {
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            None,
         );
         assert_eq!(
             result,
@@ -1513,6 +1548,7 @@ mod tests {
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            None,
         );
         assert_eq!(result, Ok(()));
 
@@ -1537,6 +1573,7 @@ mod tests {
             executors,
             None,
             Arc::new(FeatureSet::all_enabled()),
+            None,
         );
         assert_eq!(result, Ok(()));
         assert_eq!(accounts[0].borrow().lamports, 80);
@@ -101,6 +101,16 @@ pub struct ComputeBudget {
     pub stack_frame_size: usize,
     /// Number of compute units consumed by logging a `Pubkey`
     pub log_pubkey_units: u64,
+    /// Base number of compute units consumed to call Keccak256
+    pub keccak256_base_cost: u64,
+    /// Incremental number of units consumed by Keccak256 (based on bytes)
+    pub keccak256_byte_cost: u64,
+    /// Number of compute units consumed to call secp256k1_recover
+    pub secp256k1_recover_cost: u64,
+    /// Base number of compute units consumed by a log_data call
+    pub log_data_base_cost: u64,
+    /// Incremental number of units consumed by log_data (based on bytes)
+    pub log_data_byte_cost: u64,
 }
 impl Default for ComputeBudget {
     fn default() -> Self {
@@ -123,6 +133,11 @@ impl ComputeBudget {
             max_call_depth: 20,
             stack_frame_size: 4_096,
             log_pubkey_units: 0,
+            keccak256_base_cost: 85,
+            keccak256_byte_cost: 1,
+            secp256k1_recover_cost: 25_000,
+            log_data_base_cost: 100,
+            log_data_byte_cost: 1,
         };
 
         if feature_set.is_active(&compute_budget_balancing::id()) {
@@ -10,6 +10,7 @@ use {
         message_processor::MessageProcessor,
         rent_collector::RentCollector,
         stakes::Stakes,
+        storage_index,
     },
     bincode,
     bincode::{config::Options, Error},
@@ -17,6 +18,7 @@ use {
     log::{info, warn},
     rand::{thread_rng, Rng},
     serde::{de::DeserializeOwned, Deserialize, Serialize},
+    solana_measure::measure::Measure,
     solana_sdk::{
         clock::{Epoch, Slot, UnixTimestamp},
         epoch_schedule::EpochSchedule,
@@ -359,6 +361,39 @@ where
     accounts_db
         .write_version
         .fetch_add(version, Ordering::Relaxed);
-    accounts_db.generate_index();
+
+    // Use the storage indexes from the snapshot if they all match their storages
+    let mut generate_index_time = Measure::start("generate_index");
+    let storage_indexes = {
+        let slot_stores: Vec<_> = accounts_db
+            .storage
+            .0
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        let slot_stores: Vec<_> = slot_stores
+            .iter()
+            .map(|slot_stores| slot_stores.read().unwrap())
+            .collect();
+        storage_index::read_storage_indexes(
+            stream_append_vecs_path.as_ref(),
+            slot_stores
+                .iter()
+                .flat_map(|slot_stores| slot_stores.values())
+                .map(|store| store.as_ref()),
+        )
+    };
+    let from_storage_indexes = storage_indexes.is_some();
+    accounts_db.generate_index_from_storage_indexes(storage_indexes);
+    generate_index_time.stop();
+    info!(
+        "Generated the accounts index {} in {}ms",
+        if from_storage_indexes {
+            "from the storage indexes"
+        } else {
+            "by scanning the account storages"
+        },
+        generate_index_time.as_ms()
+    );
     Ok(accounts_db)
 }
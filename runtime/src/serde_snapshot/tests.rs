@@ -5,6 +5,7 @@ use {
         accounts::{create_test_accounts, Accounts},
         accounts_db::get_temp_accounts_paths,
         bank::{Bank, StatusCacheRc},
+        storage_index::StorageIndex,
     },
     bincode::serialize_into,
     rand::{thread_rng, Rng},
@@ -246,6 +247,72 @@ pub(crate) fn reconstruct_accounts_db_via_serialization(
     accountsdb_from_stream(SerdeStyle::NEWER, &mut reader, &[], copied_accounts.path()).unwrap()
 }
 
+#[test]
+fn test_accounts_serialize_with_storage_indexes() {
+    solana_logger::setup();
+    let (_accounts_dir, paths) = get_temp_accounts_paths(4).unwrap();
+    let accounts = Accounts::new(paths, &ClusterType::Development);
+
+    let mut pubkeys: Vec<Pubkey> = vec![];
+    create_test_accounts(&accounts, &mut pubkeys, 100, 0);
+    accounts.add_root(0);
+
+    let mut writer = Cursor::new(vec![]);
+    let snapshot_storages = accounts.accounts_db.get_snapshot_storages(0);
+    accountsdb_to_stream(
+        SerdeStyle::NEWER,
+        &mut writer,
+        &*accounts.accounts_db,
+        0,
+        &snapshot_storages,
+    )
+    .unwrap();
+    let buf = writer.into_inner();
+
+    let scanned_accounts = TempDir::new().unwrap();
+    copy_append_vecs(&accounts.accounts_db, scanned_accounts.path()).unwrap();
+    let indexed_accounts = TempDir::new().unwrap();
+    copy_append_vecs(&accounts.accounts_db, indexed_accounts.path()).unwrap();
+    for storage in snapshot_storages.iter().flatten() {
+        StorageIndex::new(storage)
+            .write(indexed_accounts.path())
+            .unwrap();
+    }
+
+    let (_accounts_dir, scanned_paths) = get_temp_accounts_paths(2).unwrap();
+    let scanned_accounts_db = accountsdb_from_stream(
+        SerdeStyle::NEWER,
+        &mut BufReader::new(&buf[..]),
+        &scanned_paths,
+        scanned_accounts.path(),
+    )
+    .unwrap();
+    let (_accounts_dir, indexed_paths) = get_temp_accounts_paths(2).unwrap();
+    let indexed_accounts_db = accountsdb_from_stream(
+        SerdeStyle::NEWER,
+        &mut BufReader::new(&buf[..]),
+        &indexed_paths,
+        indexed_accounts.path(),
+    )
+    .unwrap();
+
+    for pubkey in &pubkeys {
+        let (scanned_entry, scanned_index) = scanned_accounts_db
+            .accounts_index
+            .get(pubkey, None, None)
+            .unwrap();
+        let (indexed_entry, indexed_index) = indexed_accounts_db
+            .accounts_index
+            .get(pubkey, None, None)
+            .unwrap();
+        assert_eq!(
+            scanned_entry.slot_list()[scanned_index],
+            indexed_entry.slot_list()[indexed_index]
+        );
+    }
+    check_accounts(&Accounts::new_empty(indexed_accounts_db), &pubkeys, 100);
+}
+
 #[test]
 fn test_accounts_serialize_newer() {
     test_accounts_serialize_style(SerdeStyle::NEWER)
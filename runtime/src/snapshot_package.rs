@@ -24,6 +24,7 @@ pub struct AccountsPackage {
     pub hash: Hash,
     pub compression: CompressionType,
     pub snapshot_version: SnapshotVersion,
+    pub include_storage_indexes: bool,
 }
 
 impl AccountsPackage {
@@ -37,6 +38,7 @@ impl AccountsPackage {
         hash: Hash,
         compression: CompressionType,
         snapshot_version: SnapshotVersion,
+        include_storage_indexes: bool,
     ) -> Self {
         Self {
             root,
@@ -48,6 +50,7 @@ impl AccountsPackage {
             hash,
             compression,
             snapshot_version,
+            include_storage_indexes,
         }
     }
 }
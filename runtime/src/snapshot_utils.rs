@@ -7,6 +7,7 @@ use crate::{
     },
     snapshot_package::{AccountsPackage, AccountsPackageSendError, AccountsPackageSender},
     status_cache::MAX_CACHE_ENTRIES,
+    storage_index::StorageIndex,
 };
 use bincode::{config::Options, serialize_into};
 use bzip2::bufread::BzDecoder;
@@ -25,6 +26,7 @@ use std::collections::HashSet;
 use std::sync::Arc;
 use std::{
     cmp::Ordering,
+    ffi::OsStr,
     fmt,
     fs::{self, File},
     io::{self, BufReader, BufWriter, Error as IOError, ErrorKind, Read, Seek, SeekFrom, Write},
@@ -168,6 +170,7 @@ pub fn package_snapshot<P: AsRef<Path>, Q: AsRef<Path>>(
     snapshot_storages: SnapshotStorages,
     compression: CompressionType,
     snapshot_version: SnapshotVersion,
+    include_storage_indexes: bool,
 ) -> Result<AccountsPackage> {
     // Hard link all the snapshots we need for this package
     let snapshot_hard_links_dir = tempfile::tempdir_in(snapshot_path)?;
@@ -199,6 +202,7 @@ pub fn package_snapshot<P: AsRef<Path>, Q: AsRef<Path>>(
         bank.get_accounts_hash(),
         compression,
         snapshot_version,
+        include_storage_indexes,
     );
 
     Ok(package)
@@ -264,6 +268,12 @@ pub fn archive_snapshot_package(snapshot_package: &AccountsPackage) -> Result<()
         if !output_path.is_file() {
             return Err(SnapshotError::StoragePathSymlinkInvalid);
         }
+
+        // Include the index of the AppendVec so the accounts index can be rebuilt without
+        // scanning it when the snapshot is loaded
+        if snapshot_package.include_storage_indexes {
+            StorageIndex::new(storage).write(&staging_accounts_dir)?;
+        }
     }
 
     // Write version file
@@ -660,7 +670,7 @@ fn compression_type_from_str(compress: &str) -> Option<CompressionType> {
     }
 }
 
-fn snapshot_hash_of(archive_filename: &str) -> Option<(Slot, Hash, CompressionType)> {
+pub fn snapshot_hash_of(archive_filename: &str) -> Option<(Slot, Hash, CompressionType)> {
     let snapshot_filename_regex =
         Regex::new(r"snapshot-(\d+)-([[:alnum:]]+)\.tar\.(bz2|zst|gz)$").unwrap();
 
@@ -846,8 +856,15 @@ pub fn verify_snapshot_archive<P, Q, R>(
     let unpacked_snapshots = unpack_dir.join(&TAR_SNAPSHOTS_DIR);
     assert!(!dir_diff::is_different(&snapshots_to_verify, unpacked_snapshots).unwrap());
 
-    // Check the account entries are the same
+    // Check the account entries are the same, ignoring the storage indexes that only exist in
+    // the archive
     let unpacked_accounts = unpack_dir.join(&TAR_ACCOUNTS_DIR);
+    for entry in fs::read_dir(&unpacked_accounts).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() == Some(OsStr::new("index")) {
+            fs::remove_file(path).unwrap();
+        }
+    }
     assert!(!dir_diff::is_different(&storages_to_verify, unpacked_accounts).unwrap());
 }
 
@@ -872,6 +889,7 @@ pub fn snapshot_bank(
     snapshot_package_output_path: &Path,
     snapshot_version: SnapshotVersion,
     compression: &CompressionType,
+    include_storage_indexes: bool,
 ) -> Result<()> {
     let storages: Vec<_> = root_bank.get_snapshot_storages();
     let mut add_snapshot_time = Measure::start("add-snapshot-ms");
@@ -895,6 +913,7 @@ pub fn snapshot_bank(
         storages,
         compression.clone(),
         snapshot_version,
+        include_storage_indexes,
     )?;
 
     accounts_package_sender.send(package)?;
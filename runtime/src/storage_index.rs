@@ -0,0 +1,194 @@
+//! Storage index files record the accounts stored in an `AppendVec`, so that the accounts index
+//! can be rebuilt when a snapshot is loaded without scanning every account storage.
+use crate::accounts_db::{AccountStorageEntry, AppendVecId};
+use bincode::{deserialize_from, serialize_into};
+use log::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{clock::Slot, hash::Hash, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+const STORAGE_INDEX_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct StorageIndexEntry {
+    pub pubkey: Pubkey,
+    pub write_version: u64,
+    pub offset: usize,
+    pub lamports: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct StorageIndex {
+    version: u32,
+    pub slot: Slot,
+    pub id: AppendVecId,
+    /// Length of the `AppendVec` when it was indexed
+    pub accounts_len: usize,
+    /// Hash of the `AppendVec` when it was indexed
+    pub accounts_hash: Hash,
+    pub entries: Vec<StorageIndexEntry>,
+}
+
+impl StorageIndex {
+    pub fn new(storage: &AccountStorageEntry) -> Self {
+        let entries = storage
+            .accounts
+            .accounts(0)
+            .into_iter()
+            .map(|stored_account| StorageIndexEntry {
+                pubkey: stored_account.meta.pubkey,
+                write_version: stored_account.meta.write_version,
+                offset: stored_account.offset,
+                lamports: stored_account.account_meta.lamports,
+            })
+            .collect();
+        Self {
+            version: STORAGE_INDEX_VERSION,
+            slot: storage.slot,
+            id: storage.id,
+            accounts_len: storage.accounts.len(),
+            accounts_hash: storage.accounts.hash(),
+            entries,
+        }
+    }
+
+    pub fn file_name(slot: Slot, id: AppendVecId) -> String {
+        format!("{}.{}.index", slot, id)
+    }
+
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        let mut writer =
+            BufWriter::new(File::create(dir.join(Self::file_name(self.slot, self.id)))?);
+        serialize_into(&mut writer, self)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        writer.flush()
+    }
+
+    pub fn read(dir: &Path, slot: Slot, id: AppendVecId) -> io::Result<Self> {
+        let file = File::open(dir.join(Self::file_name(slot, id)))?;
+        deserialize_from(BufReader::new(file))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    // The index is only usable if it was made from exactly the accounts in `storage`, which
+    // the hash of the storage vouches for without reading the accounts one by one.  The
+    // offsets are still checked to be ones an account could start at
+    fn is_consistent_with(&self, storage: &AccountStorageEntry) -> bool {
+        let accounts_len = storage.accounts.len();
+        self.version == STORAGE_INDEX_VERSION
+            && self.slot == storage.slot
+            && self.id == storage.id
+            && self.accounts_len == accounts_len
+            && self.entries.iter().enumerate().all(|(i, entry)| {
+                entry.offset % std::mem::size_of::<u64>() == 0
+                    && entry.offset < accounts_len
+                    && (i == 0 || self.entries[i - 1].offset < entry.offset)
+            })
+            && self.accounts_hash == storage.accounts.hash()
+    }
+}
+
+/// Reads the index of every storage from `dir`, checking the storages in parallel.  Returns
+/// None unless every storage has an index consistent with it, in which case the accounts
+/// index must be generated by scanning the storages instead
+pub fn read_storage_indexes<'a>(
+    dir: &Path,
+    storages: impl Iterator<Item = &'a AccountStorageEntry>,
+) -> Option<HashMap<AppendVecId, StorageIndex>> {
+    let storages: Vec<_> = storages.collect();
+    storages
+        .into_par_iter()
+        .map(|storage| {
+            let storage_index = match StorageIndex::read(dir, storage.slot, storage.id) {
+                Ok(storage_index) => storage_index,
+                Err(err) => {
+                    info!(
+                        "Unable to read the index of storage {}.{}: {}",
+                        storage.slot, storage.id, err
+                    );
+                    return None;
+                }
+            };
+            if !storage_index.is_consistent_with(storage) {
+                warn!(
+                    "Index of storage {}.{} does not match the storage",
+                    storage.slot, storage.id
+                );
+                return None;
+            }
+            Some((storage.id, storage_index))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts_db::AccountsDB;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn test_storage_index_round_trip() {
+        let db = AccountsDB::new_single();
+        let pubkeys: Vec<_> = (0..10).map(|_| Pubkey::new_unique()).collect();
+        for (i, pubkey) in pubkeys.iter().enumerate() {
+            let account = Account::new(i as u64 + 1, i, &Pubkey::default());
+            db.store(0, &[(pubkey, &account)]);
+        }
+        db.add_root(0);
+        let storages = db.get_snapshot_storages(1);
+        let storages: Vec<_> = storages.iter().flatten().map(|s| s.as_ref()).collect();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        for storage in &storages {
+            StorageIndex::new(storage).write(dir.path()).unwrap();
+        }
+        let storage_indexes = read_storage_indexes(dir.path(), storages.iter().cloned()).unwrap();
+        assert_eq!(storage_indexes.len(), storages.len());
+        let lamports: HashMap<_, _> = storage_indexes
+            .values()
+            .flat_map(|storage_index| &storage_index.entries)
+            .map(|entry| (entry.pubkey, entry.lamports))
+            .collect();
+        for (i, pubkey) in pubkeys.iter().enumerate() {
+            assert_eq!(lamports[pubkey], i as u64 + 1);
+        }
+
+        // An index of a storage that has since been appended to is inconsistent
+        let mut storage_index = StorageIndex::new(storages[0]);
+        storage_index.accounts_len -= 1;
+        storage_index.write(dir.path()).unwrap();
+        assert!(read_storage_indexes(dir.path(), storages.iter().cloned()).is_none());
+
+        // So is an index made from other contents, or with offsets no account starts at
+        let storage_index = StorageIndex::new(storages[0]);
+        assert!(storage_index.is_consistent_with(storages[0]));
+        let mut bad_storage_index = StorageIndex::new(storages[0]);
+        bad_storage_index.accounts_hash = Hash::new_unique();
+        assert!(!bad_storage_index.is_consistent_with(storages[0]));
+        let mut bad_storage_index = StorageIndex::new(storages[0]);
+        bad_storage_index.entries[0].offset += 1;
+        assert!(!bad_storage_index.is_consistent_with(storages[0]));
+        let mut bad_storage_index = StorageIndex::new(storages[0]);
+        bad_storage_index.entries.last_mut().unwrap().offset = storages[0].accounts.len();
+        assert!(!bad_storage_index.is_consistent_with(storages[0]));
+        let mut bad_storage_index = StorageIndex::new(storages[0]);
+        bad_storage_index.entries.swap(0, 1);
+        assert!(!bad_storage_index.is_consistent_with(storages[0]));
+        bad_storage_index.write(dir.path()).unwrap();
+        assert!(read_storage_indexes(dir.path(), storages.iter().cloned()).is_none());
+
+        std::fs::remove_file(
+            dir.path()
+                .join(StorageIndex::file_name(storages[0].slot, storages[0].id)),
+        )
+        .unwrap();
+        assert!(read_storage_indexes(dir.path(), storages.iter().cloned()).is_none());
+    }
+}
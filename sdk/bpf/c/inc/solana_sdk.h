@@ -417,6 +417,50 @@ static uint64_t sol_sha256(
     const uint8_t *result
 );
 
+/**
+ * Length of a Keccak256 hash result
+ */
+#define KECCAK_RESULT_LENGTH 32
+
+/**
+ * Keccak256
+ *
+ * @param bytes Array of byte arrays
+ * @param bytes_len Number of byte arrays
+ * @param result 32 byte array to hold the result
+ */
+static uint64_t sol_keccak256(
+    const SolBytes *bytes,
+    int bytes_len,
+    const uint8_t *result
+);
+
+/**
+ * Length of a secp256k1 signature
+ */
+#define SECP256K1_SIGNATURE_LENGTH 64
+
+/**
+ * Length of a recovered secp256k1 public key
+ */
+#define SECP256K1_PUBLIC_KEY_LENGTH 64
+
+/**
+ * Recover the secp256k1 public key that signed a message hash
+ *
+ * @param hash 32 byte message hash
+ * @param recovery_id Recovery id of the signature, between 0 and 3
+ * @param signature 64 byte signature
+ * @param result 64 byte array to hold the recovered public key
+ * @return 0 on success, 1 on invalid hash, 2 on invalid recovery id, 3 on invalid signature
+ */
+static uint64_t sol_secp256k1_recover(
+    const uint8_t *hash,
+    uint64_t recovery_id,
+    const uint8_t *signature,
+    uint8_t *result
+);
+
 /**
  * Account Meta
  */
@@ -544,6 +588,17 @@ void sol_log_pubkey(
     const SolPubkey *pubkey
 );
 
+/**
+ * Logs each byte array base64 encoded on a single "Program data:" line
+ *
+ * @param bytes Array of byte arrays to log
+ * @param bytes_len Number of byte arrays
+ */
+void sol_log_data(
+    const SolBytes *bytes,
+    uint64_t bytes_len
+);
+
 /**
  * Prints the hexadecimal representation of an array
  *
@@ -63,6 +63,24 @@ extern "C" {
     fn sol_log_64_(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64);
 }
 
+/// Logs each slice base64 encoded on a single "Program data:" line, for
+/// events that are decoded by off-chain consumers
+///
+/// @param data - The slices to log
+#[inline]
+pub fn sol_log_data(data: &[&[u8]]) {
+    #[cfg(target_arch = "bpf")]
+    {
+        extern "C" {
+            fn sol_log_data(data: *const u8, data_len: u64);
+        };
+        unsafe { sol_log_data(data as *const _ as *const u8, data.len() as u64) };
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    crate::program_stubs::sol_log_data(data);
+}
+
 /// Prints the hexadecimal representation of a slice
 ///
 /// @param slice - The array to print
@@ -19,6 +19,15 @@ pub trait SyscallStubs: Sync + Send {
     fn sol_log(&self, message: &str) {
         println!("{}", message);
     }
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        println!(
+            "Program data: {}",
+            data.iter()
+                .map(base64::encode)
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+    }
     fn sol_invoke_signed(
         &self,
         _instruction: &Instruction,
@@ -37,6 +46,10 @@ pub(crate) fn sol_log(message: &str) {
     SYSCALL_STUBS.read().unwrap().sol_log(message);
 }
 
+pub(crate) fn sol_log_data(data: &[&[u8]]) {
+    SYSCALL_STUBS.read().unwrap().sol_log_data(data);
+}
+
 pub(crate) fn sol_log_64(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) {
     sol_log(&format!("{} {} {} {} {}", arg1, arg2, arg3, arg4, arg5));
 }
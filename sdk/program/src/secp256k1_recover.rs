@@ -0,0 +1,162 @@
+//! The `secp256k1_recover` module provides recovery of secp256k1 public keys
+//! from signed message hashes, as used by Ethereum.
+
+use crate::sanitize::Sanitize;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Secp256k1RecoverError {
+    #[error("The hash provided to a secp256k1_recover is invalid")]
+    InvalidHash,
+    #[error("The recovery_id provided to a secp256k1_recover is invalid")]
+    InvalidRecoveryId,
+    #[error("The signature provided to a secp256k1_recover is invalid")]
+    InvalidSignature,
+}
+
+impl From<u64> for Secp256k1RecoverError {
+    fn from(v: u64) -> Secp256k1RecoverError {
+        match v {
+            1 => Secp256k1RecoverError::InvalidHash,
+            2 => Secp256k1RecoverError::InvalidRecoveryId,
+            3 => Secp256k1RecoverError::InvalidSignature,
+            _ => panic!("Unsupported Secp256k1RecoverError"),
+        }
+    }
+}
+
+impl From<Secp256k1RecoverError> for u64 {
+    fn from(v: Secp256k1RecoverError) -> u64 {
+        match v {
+            Secp256k1RecoverError::InvalidHash => 1,
+            Secp256k1RecoverError::InvalidRecoveryId => 2,
+            Secp256k1RecoverError::InvalidSignature => 3,
+        }
+    }
+}
+
+pub const SECP256K1_HASH_LENGTH: usize = 32;
+pub const SECP256K1_SIGNATURE_LENGTH: usize = 64;
+pub const SECP256K1_PUBLIC_KEY_LENGTH: usize = 64;
+
+/// An uncompressed secp256k1 public key without the leading `0x04` tag byte
+#[repr(transparent)]
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Secp256k1Pubkey(pub [u8; SECP256K1_PUBLIC_KEY_LENGTH]);
+
+impl Secp256k1Pubkey {
+    pub fn new(pubkey_vec: &[u8]) -> Self {
+        let mut pubkey = [0u8; SECP256K1_PUBLIC_KEY_LENGTH];
+        pubkey.copy_from_slice(pubkey_vec);
+        Self(pubkey)
+    }
+
+    pub fn to_bytes(self) -> [u8; SECP256K1_PUBLIC_KEY_LENGTH] {
+        self.0
+    }
+}
+
+impl Default for Secp256k1Pubkey {
+    fn default() -> Self {
+        Self([0u8; SECP256K1_PUBLIC_KEY_LENGTH])
+    }
+}
+
+impl Sanitize for Secp256k1Pubkey {}
+
+impl std::fmt::Debug for Secp256k1Pubkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", bs58::encode(&self.0[..]).into_string())
+    }
+}
+
+/// Recover the public key that produced `signature` over the 32 byte message
+/// `hash`.  `recovery_id` is the Ethereum `v` value minus 27.
+pub fn secp256k1_recover(
+    hash: &[u8],
+    recovery_id: u8,
+    signature: &[u8],
+) -> Result<Secp256k1Pubkey, Secp256k1RecoverError> {
+    if hash.len() != SECP256K1_HASH_LENGTH {
+        return Err(Secp256k1RecoverError::InvalidHash);
+    }
+    if signature.len() != SECP256K1_SIGNATURE_LENGTH {
+        return Err(Secp256k1RecoverError::InvalidSignature);
+    }
+
+    #[cfg(target_arch = "bpf")]
+    {
+        extern "C" {
+            fn sol_secp256k1_recover(
+                hash: *const u8,
+                recovery_id: u64,
+                signature: *const u8,
+                result: *mut u8,
+            ) -> u64;
+        }
+
+        let mut pubkey_buffer = [0u8; SECP256K1_PUBLIC_KEY_LENGTH];
+        let result = unsafe {
+            sol_secp256k1_recover(
+                hash.as_ptr(),
+                recovery_id as u64,
+                signature.as_ptr(),
+                pubkey_buffer.as_mut_ptr(),
+            )
+        };
+
+        match result {
+            0 => Ok(Secp256k1Pubkey::new(&pubkey_buffer)),
+            error => Err(Secp256k1RecoverError::from(error)),
+        }
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    {
+        let message = secp256k1::Message::parse_slice(hash)
+            .map_err(|_| Secp256k1RecoverError::InvalidHash)?;
+        let recovery_id = secp256k1::RecoveryId::parse(recovery_id)
+            .map_err(|_| Secp256k1RecoverError::InvalidRecoveryId)?;
+        let signature = secp256k1::Signature::parse_slice(signature)
+            .map_err(|_| Secp256k1RecoverError::InvalidSignature)?;
+        let secp256k1_key = secp256k1::recover(&message, &signature, &recovery_id)
+            .map_err(|_| Secp256k1RecoverError::InvalidSignature)?;
+        Ok(Secp256k1Pubkey::new(&secp256k1_key.serialize()[1..65]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keccak;
+
+    #[test]
+    fn test_secp256k1_recover() {
+        let secret_key = secp256k1::SecretKey::parse(&[7u8; 32]).unwrap();
+        let public_key = secp256k1::PublicKey::from_secret_key(&secret_key);
+        let message_hash = keccak::hash(b"hello world");
+        let message = secp256k1::Message::parse(&message_hash.to_bytes());
+        let (signature, recovery_id) = secp256k1::sign(&message, &secret_key);
+
+        let recovered = secp256k1_recover(
+            message_hash.as_ref(),
+            recovery_id.serialize(),
+            &signature.serialize(),
+        )
+        .unwrap();
+        assert_eq!(recovered.to_bytes()[..], public_key.serialize()[1..65]);
+
+        assert_eq!(
+            secp256k1_recover(&[0u8; 31], recovery_id.serialize(), &signature.serialize()),
+            Err(Secp256k1RecoverError::InvalidHash)
+        );
+        assert_eq!(
+            secp256k1_recover(message_hash.as_ref(), 4, &signature.serialize()),
+            Err(Secp256k1RecoverError::InvalidRecoveryId)
+        );
+        assert_eq!(
+            secp256k1_recover(message_hash.as_ref(), recovery_id.serialize(), &[0u8; 63]),
+            Err(Secp256k1RecoverError::InvalidSignature)
+        );
+    }
+}
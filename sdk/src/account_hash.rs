@@ -0,0 +1,148 @@
+//! The hash of an account, which the accounts hash and the bank hash are built from
+#![cfg(feature = "full")]
+
+use crate::{
+    account::Account,
+    clock::{Epoch, Slot},
+    genesis_config::ClusterType,
+    hash::{Hash, Hasher, HASH_BYTES},
+    pubkey::Pubkey,
+};
+use blake3::traits::digest::Digest;
+use std::convert::TryFrom;
+
+/// Whether the owner of an account written in `slot` is included in its hash
+pub fn include_owner(cluster_type: &ClusterType, slot: Slot) -> bool {
+    // When devnet was moved to stable release channel, it was done without
+    // hashing account.owner. That's because devnet's slot was lower than
+    // 5_800_000 and the release channel's gating lacked ClusterType at the time...
+    match cluster_type {
+        ClusterType::Devnet => slot >= 5_800_000,
+        _ => true,
+    }
+}
+
+/// Accounts written after this slot are hashed with blake3 rather than sha256
+pub fn blake3_slot(cluster_type: &ClusterType) -> Slot {
+    match cluster_type {
+        ClusterType::Development => 0,
+        // Epoch 400
+        ClusterType::Devnet => 3_276_800,
+        // Epoch 78
+        ClusterType::MainnetBeta => 33_696_000,
+        // Epoch 95
+        ClusterType::Testnet => 35_516_256,
+    }
+}
+
+/// Hashes an account as it was written in `slot` on a cluster of `cluster_type`
+pub fn hash_account(
+    slot: Slot,
+    account: &Account,
+    pubkey: &Pubkey,
+    cluster_type: &ClusterType,
+) -> Hash {
+    let include_owner = include_owner(cluster_type, slot);
+
+    if slot > blake3_slot(cluster_type) {
+        blake3_hash_account_data(
+            slot,
+            account.lamports,
+            &account.owner,
+            account.executable,
+            account.rent_epoch,
+            &account.data,
+            pubkey,
+            include_owner,
+        )
+    } else {
+        hash_account_data(
+            slot,
+            account.lamports,
+            &account.owner,
+            account.executable,
+            account.rent_epoch,
+            &account.data,
+            pubkey,
+            include_owner,
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn hash_account_data(
+    slot: Slot,
+    lamports: u64,
+    owner: &Pubkey,
+    executable: bool,
+    rent_epoch: Epoch,
+    data: &[u8],
+    pubkey: &Pubkey,
+    include_owner: bool,
+) -> Hash {
+    if lamports == 0 {
+        return Hash::default();
+    }
+
+    let mut hasher = Hasher::default();
+
+    hasher.hash(&lamports.to_le_bytes());
+
+    hasher.hash(&slot.to_le_bytes());
+
+    hasher.hash(&rent_epoch.to_le_bytes());
+
+    hasher.hash(&data);
+
+    if executable {
+        hasher.hash(&[1u8; 1]);
+    } else {
+        hasher.hash(&[0u8; 1]);
+    }
+
+    if include_owner {
+        hasher.hash(&owner.as_ref());
+    }
+    hasher.hash(&pubkey.as_ref());
+
+    hasher.result()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn blake3_hash_account_data(
+    slot: Slot,
+    lamports: u64,
+    owner: &Pubkey,
+    executable: bool,
+    rent_epoch: Epoch,
+    data: &[u8],
+    pubkey: &Pubkey,
+    include_owner: bool,
+) -> Hash {
+    if lamports == 0 {
+        return Hash::default();
+    }
+
+    let mut hasher = blake3::Hasher::new();
+
+    hasher.update(&lamports.to_le_bytes());
+
+    hasher.update(&slot.to_le_bytes());
+
+    hasher.update(&rent_epoch.to_le_bytes());
+
+    hasher.update(&data);
+
+    if executable {
+        hasher.update(&[1u8; 1]);
+    } else {
+        hasher.update(&[0u8; 1]);
+    }
+
+    if include_owner {
+        hasher.update(&owner.as_ref());
+    }
+    hasher.update(&pubkey.as_ref());
+
+    Hash(<[u8; HASH_BYTES]>::try_from(hasher.finalize().as_slice()).unwrap())
+}
@@ -0,0 +1,184 @@
+//! Proofs that an account hash is included in an accounts hash.
+//!
+//! The accounts hash is the root of a Merkle tree whose leaves are the hashes of all accounts,
+//! sorted by pubkey.  Each level groups the hashes of the level below into chunks of
+//! `MERKLE_FANOUT` and hashes every chunk, until a single chunk remains whose hash is the root.
+use solana_sdk::hash::{Hash, Hasher};
+#[cfg(feature = "full")]
+use solana_sdk::{
+    account::Account, account_hash::hash_account, clock::Slot, genesis_config::ClusterType,
+    pubkey::Pubkey,
+};
+
+pub const MERKLE_FANOUT: usize = 16;
+
+/// The hashes that are combined with a node of the tree to compute its parent
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MerkleProofLevel {
+    /// Position of the node within its chunk
+    pub position: usize,
+    /// The other hashes of the chunk, in order
+    pub siblings: Vec<Hash>,
+}
+
+/// The path from an account hash to the accounts hash, starting at the leaf
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AccountsMerkleProof {
+    pub levels: Vec<MerkleProofLevel>,
+}
+
+impl AccountsMerkleProof {
+    /// Returns the root of the tree the proof leads to from `account_hash`, or None if the
+    /// proof is malformed
+    pub fn root(&self, account_hash: &Hash) -> Option<Hash> {
+        if self.levels.is_empty() {
+            return None;
+        }
+        self.levels.iter().try_fold(*account_hash, |hash, level| {
+            if level.position > level.siblings.len() || level.siblings.len() >= MERKLE_FANOUT {
+                return None;
+            }
+            let (left, right) = level.siblings.split_at(level.position);
+            let mut hasher = Hasher::default();
+            for sibling in left {
+                hasher.hash(sibling.as_ref());
+            }
+            hasher.hash(hash.as_ref());
+            for sibling in right {
+                hasher.hash(sibling.as_ref());
+            }
+            Some(hasher.result())
+        })
+    }
+
+    /// Checks that `account_hash` is included in `accounts_hash`
+    pub fn verify(&self, account_hash: &Hash, accounts_hash: &Hash) -> bool {
+        self.root(account_hash).as_ref() == Some(accounts_hash)
+    }
+}
+
+/// Checks that `account`, as last written in `account_slot` on a cluster of `cluster_type`, is
+/// included in `accounts_hash`
+#[cfg(feature = "full")]
+pub fn verify_account(
+    pubkey: &Pubkey,
+    account: &Account,
+    account_slot: Slot,
+    cluster_type: &ClusterType,
+    proof: &AccountsMerkleProof,
+    accounts_hash: &Hash,
+) -> bool {
+    // Accounts without lamports are left out of the accounts hash
+    let account_hash = hash_account(account_slot, account, pubkey, cluster_type);
+    account_hash != Hash::default() && proof.verify(&account_hash, accounts_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::hash;
+
+    fn hash_chunk(hashes: &[Hash]) -> Hash {
+        let mut hasher = Hasher::default();
+        hashes.iter().for_each(|hash| hasher.hash(hash.as_ref()));
+        hasher.result()
+    }
+
+    #[test]
+    fn test_verify() {
+        let leaves: Vec<_> = (0..MERKLE_FANOUT + 2)
+            .map(|i| hash(&i.to_le_bytes()))
+            .collect();
+        let chunk0 = hash_chunk(&leaves[..MERKLE_FANOUT]);
+        let chunk1 = hash_chunk(&leaves[MERKLE_FANOUT..]);
+        let accounts_hash = hash_chunk(&[chunk0, chunk1]);
+
+        let proof = AccountsMerkleProof {
+            levels: vec![
+                MerkleProofLevel {
+                    position: 1,
+                    siblings: vec![leaves[MERKLE_FANOUT]],
+                },
+                MerkleProofLevel {
+                    position: 1,
+                    siblings: vec![chunk0],
+                },
+            ],
+        };
+        assert!(proof.verify(&leaves[MERKLE_FANOUT + 1], &accounts_hash));
+        assert!(!proof.verify(&leaves[MERKLE_FANOUT], &accounts_hash));
+
+        let mut bad_proof = proof.clone();
+        bad_proof.levels[0].position = 0;
+        assert!(!bad_proof.verify(&leaves[MERKLE_FANOUT + 1], &accounts_hash));
+        bad_proof.levels[0].position = 2;
+        assert_eq!(bad_proof.root(&leaves[MERKLE_FANOUT + 1]), None);
+
+        assert_eq!(AccountsMerkleProof::default().root(&accounts_hash), None);
+    }
+
+    #[test]
+    fn test_verify_account() {
+        let pubkey = solana_sdk::pubkey::new_rand();
+        let mut account = Account::new(42, 0, &Pubkey::default());
+        let cluster_type = ClusterType::MainnetBeta;
+        let account_slot = 40_000_000;
+        let account_hash = hash_account(account_slot, &account, &pubkey, &cluster_type);
+        let sibling = hash(&[1]);
+        let accounts_hash = hash_chunk(&[sibling, account_hash]);
+        let proof = AccountsMerkleProof {
+            levels: vec![MerkleProofLevel {
+                position: 1,
+                siblings: vec![sibling],
+            }],
+        };
+        assert!(verify_account(
+            &pubkey,
+            &account,
+            account_slot,
+            &cluster_type,
+            &proof,
+            &accounts_hash
+        ));
+        assert!(!verify_account(
+            &pubkey,
+            &account,
+            account_slot + 1,
+            &cluster_type,
+            &proof,
+            &accounts_hash
+        ));
+
+        // Mainnet-beta hashed accounts with sha256 before its blake3 slot
+        let sha256_slot = 1;
+        let account_hash = hash_account(sha256_slot, &account, &pubkey, &cluster_type);
+        let accounts_hash = hash_chunk(&[sibling, account_hash]);
+        assert!(verify_account(
+            &pubkey,
+            &account,
+            sha256_slot,
+            &cluster_type,
+            &proof,
+            &accounts_hash
+        ));
+        assert!(!verify_account(
+            &pubkey,
+            &account,
+            sha256_slot,
+            &ClusterType::Development,
+            &proof,
+            &accounts_hash
+        ));
+
+        account.lamports = 0;
+        let accounts_hash = hash_chunk(&[sibling, Hash::default()]);
+        assert!(!verify_account(
+            &pubkey,
+            &account,
+            account_slot,
+            &cluster_type,
+            &proof,
+            &accounts_hash
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+#![cfg(feature = "full")]
+
+use crate::{
+    instruction::Instruction,
+    signature::{Keypair, Signature, Signer},
+};
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq)]
+pub enum Ed25519Error {
+    InvalidSignature,
+    InvalidDataOffsets,
+    InvalidInstructionDataSize,
+}
+
+pub const PUBKEY_SERIALIZED_SIZE: usize = 32;
+pub const SIGNATURE_SERIALIZED_SIZE: usize = 64;
+pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 11;
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Ed25519SignatureOffsets {
+    pub signature_offset: u16, // offset to ed25519 signature of 64 bytes
+    pub signature_instruction_index: u8,
+    pub public_key_offset: u16, // offset to public key of 32 bytes
+    pub public_key_instruction_index: u8,
+    pub message_data_offset: u16, // offset to start of message data
+    pub message_data_size: u16,   // size of message data
+    pub message_instruction_index: u8,
+}
+
+/// Build an instruction that has the ed25519 program verify `keypair`'s
+/// signature over `message`.  The signature, public key and message are all
+/// carried in the instruction's own data, so `instruction_index` must be the
+/// position the returned instruction will take in the transaction.
+pub fn new_ed25519_instruction(
+    keypair: &Keypair,
+    message: &[u8],
+    instruction_index: u8,
+) -> Instruction {
+    let signature = keypair.sign_message(message);
+    let pubkey = keypair.pubkey();
+
+    let data_start = 1 + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    let public_key_offset = data_start;
+    let signature_offset = public_key_offset + PUBKEY_SERIALIZED_SIZE;
+    let message_data_offset = signature_offset + SIGNATURE_SERIALIZED_SIZE;
+
+    let mut instruction_data = vec![0u8; message_data_offset + message.len()];
+    instruction_data[public_key_offset..signature_offset].copy_from_slice(pubkey.as_ref());
+    instruction_data[signature_offset..message_data_offset].copy_from_slice(signature.as_ref());
+    instruction_data[message_data_offset..].copy_from_slice(message);
+
+    let num_signatures = 1;
+    instruction_data[0] = num_signatures;
+    let offsets = Ed25519SignatureOffsets {
+        signature_offset: signature_offset as u16,
+        signature_instruction_index: instruction_index,
+        public_key_offset: public_key_offset as u16,
+        public_key_instruction_index: instruction_index,
+        message_data_offset: message_data_offset as u16,
+        message_data_size: message.len() as u16,
+        message_instruction_index: instruction_index,
+    };
+    let writer = std::io::Cursor::new(&mut instruction_data[1..data_start]);
+    bincode::serialize_into(writer, &offsets).unwrap();
+
+    Instruction {
+        program_id: crate::ed25519_program::id(),
+        accounts: vec![],
+        data: instruction_data,
+    }
+}
+
+fn get_data_slice<'a>(
+    instruction_datas: &'a [&[u8]],
+    instruction_index: u8,
+    offset_start: u16,
+    size: usize,
+) -> Result<&'a [u8], Ed25519Error> {
+    let instruction_index = instruction_index as usize;
+    if instruction_index >= instruction_datas.len() {
+        return Err(Ed25519Error::InvalidDataOffsets);
+    }
+    let instruction = &instruction_datas[instruction_index];
+    let start = offset_start as usize;
+    let end = start + size;
+    if end > instruction.len() {
+        return Err(Ed25519Error::InvalidDataOffsets);
+    }
+
+    Ok(&instruction[start..end])
+}
+
+/// Parse the signature offsets out of an ed25519 program instruction
+pub fn parse_signature_offsets(data: &[u8]) -> Result<Vec<Ed25519SignatureOffsets>, Ed25519Error> {
+    if data.is_empty() {
+        return Err(Ed25519Error::InvalidInstructionDataSize);
+    }
+    let count = data[0] as usize;
+    let expected_data_size = 1 + count * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    if data.len() < expected_data_size {
+        return Err(Ed25519Error::InvalidInstructionDataSize);
+    }
+    (0..count)
+        .map(|i| {
+            let start = 1 + i * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+            let end = start + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+            bincode::deserialize(&data[start..end]).map_err(|_| Ed25519Error::InvalidDataOffsets)
+        })
+        .collect()
+}
+
+/// Verify every signature referenced by an ed25519 program instruction.
+/// `instruction_datas` holds the data of all instructions in the transaction.
+pub fn verify_signatures(data: &[u8], instruction_datas: &[&[u8]]) -> Result<(), Ed25519Error> {
+    for offsets in parse_signature_offsets(data)? {
+        let signature = get_data_slice(
+            instruction_datas,
+            offsets.signature_instruction_index,
+            offsets.signature_offset,
+            SIGNATURE_SERIALIZED_SIZE,
+        )?;
+        let pubkey = get_data_slice(
+            instruction_datas,
+            offsets.public_key_instruction_index,
+            offsets.public_key_offset,
+            PUBKEY_SERIALIZED_SIZE,
+        )?;
+        let message = get_data_slice(
+            instruction_datas,
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            offsets.message_data_size as usize,
+        )?;
+
+        if !Signature::new(signature).verify(pubkey, message) {
+            return Err(Ed25519Error::InvalidSignature);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn test_case(
+        num_signatures: u8,
+        offsets: &Ed25519SignatureOffsets,
+    ) -> Result<(), Ed25519Error> {
+        let mut instruction_data = vec![0u8; 1 + SIGNATURE_OFFSETS_SERIALIZED_SIZE];
+        instruction_data[0] = num_signatures;
+        let writer = std::io::Cursor::new(&mut instruction_data[1..]);
+        bincode::serialize_into(writer, &offsets).unwrap();
+
+        verify_signatures(&instruction_data, &[&[0u8; 100]])
+    }
+
+    #[test]
+    fn test_offsets_serialized_size() {
+        let offsets = Ed25519SignatureOffsets::default();
+        assert_eq!(
+            bincode::serialized_size(&offsets).unwrap() as usize,
+            SIGNATURE_OFFSETS_SERIALIZED_SIZE
+        );
+    }
+
+    #[test]
+    fn test_invalid_offsets() {
+        let mut instruction_data = vec![0u8; 1 + SIGNATURE_OFFSETS_SERIALIZED_SIZE];
+        instruction_data[0] = 1;
+        instruction_data.truncate(instruction_data.len() - 1);
+        assert_eq!(
+            verify_signatures(&instruction_data, &[&[0u8; 100]]),
+            Err(Ed25519Error::InvalidInstructionDataSize)
+        );
+        assert_eq!(
+            verify_signatures(&[], &[&[0u8; 100]]),
+            Err(Ed25519Error::InvalidInstructionDataSize)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            signature_instruction_index: 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            public_key_instruction_index: 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            message_data_offset: 99,
+            message_data_size: 2,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            signature_offset: 100 - SIGNATURE_SERIALIZED_SIZE as u16 + 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        // In bounds, but all zeroes is not a valid signature
+        assert_eq!(
+            test_case(1, &Ed25519SignatureOffsets::default()),
+            Err(Ed25519Error::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_ed25519_instruction() {
+        let keypair = Keypair::new();
+        let message = b"hello";
+        let instruction = new_ed25519_instruction(&keypair, message, 0);
+        assert_eq!(
+            verify_signatures(&instruction.data, &[&instruction.data]),
+            Ok(())
+        );
+
+        // The signature data is looked up at the instruction's own index
+        let instruction = new_ed25519_instruction(&keypair, message, 1);
+        assert_eq!(
+            verify_signatures(&instruction.data, &[&[], &instruction.data]),
+            Ok(())
+        );
+        assert_eq!(
+            verify_signatures(&instruction.data, &[&instruction.data]),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let mut instruction = new_ed25519_instruction(&keypair, message, 0);
+        let message_start = instruction.data.len() - message.len();
+        instruction.data[message_start] = instruction.data[message_start].wrapping_add(1);
+        assert_eq!(
+            verify_signatures(&instruction.data, &[&instruction.data]),
+            Err(Ed25519Error::InvalidSignature)
+        );
+    }
+}
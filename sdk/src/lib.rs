@@ -6,10 +6,13 @@ extern crate self as solana_sdk;
 
 pub use solana_program::*;
 
+pub mod account_hash;
+pub mod accounts_merkle_proof;
 pub mod builtins;
 pub mod client;
 pub mod commitment_config;
 pub mod deserialize_utils;
+pub mod ed25519_instruction;
 pub mod entrypoint;
 pub mod entrypoint_deprecated;
 pub mod entrypoint_native;
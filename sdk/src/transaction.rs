@@ -2,6 +2,7 @@
 
 #![cfg(feature = "full")]
 
+use crate::ed25519_instruction::verify_signatures;
 use crate::sanitize::{Sanitize, SanitizeError};
 use crate::secp256k1::verify_eth_addresses;
 use crate::{
@@ -92,6 +93,11 @@ pub enum TransactionError {
 
     #[error("Transactions are currently disabled due to cluster maintenance")]
     ClusterMaintenance,
+
+    /// A precompiled program instruction carries a signature that did not verify, or
+    /// references signature data that is out of bounds
+    #[error("Transaction failed precompiled program signature verification")]
+    PrecompileVerificationFailure,
 }
 
 pub type Result<T> = result::Result<T, TransactionError>;
@@ -344,12 +350,7 @@ impl Transaction {
             }
             let program_id = &self.message().account_keys[instruction.program_id_index as usize];
             if crate::secp256k1_program::check_id(program_id) {
-                let instruction_datas: Vec<_> = self
-                    .message()
-                    .instructions
-                    .iter()
-                    .map(|instruction| instruction.data.as_ref())
-                    .collect();
+                let instruction_datas = self.instruction_datas();
                 let data = &instruction.data;
                 let e = verify_eth_addresses(data, &instruction_datas);
                 e.map_err(|_| TransactionError::InvalidAccountIndex)?;
@@ -358,6 +359,33 @@ impl Transaction {
         Ok(())
     }
 
+    /// Verify the signatures carried by ed25519 program instructions.  Packets arriving
+    /// through the TPU have these checked in the sigverify batch alongside the transaction
+    /// signatures, so this is only needed where no sigverify stage ran, e.g. replay and RPC
+    pub fn verify_ed25519_instructions(&self) -> Result<()> {
+        for instruction in &self.message().instructions {
+            // The Transaction may not be sanitized at this point
+            if instruction.program_id_index as usize >= self.message().account_keys.len() {
+                return Err(TransactionError::AccountNotFound);
+            }
+            let program_id = &self.message().account_keys[instruction.program_id_index as usize];
+            if crate::ed25519_program::check_id(program_id) {
+                let instruction_datas = self.instruction_datas();
+                verify_signatures(&instruction.data, &instruction_datas)
+                    .map_err(|_| TransactionError::PrecompileVerificationFailure)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn instruction_datas(&self) -> Vec<&[u8]> {
+        self.message()
+            .instructions
+            .iter()
+            .map(|instruction| instruction.data.as_ref())
+            .collect()
+    }
+
     /// Get the positions of the pubkeys in `account_keys` associated with signing keypairs
     pub fn get_signing_keypair_positions(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<usize>>> {
         if self.message.account_keys.len() < self.message.header.num_required_signatures as usize {
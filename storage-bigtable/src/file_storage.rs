@@ -0,0 +1,541 @@
+// Long-term ledger storage in a directory on the local filesystem
+//
+// Every BigTable row is stored as a file named after its row key, holding the same compressed
+// cell data as the BigTable cell:
+//
+//   blocks/<slot key prefix>/<slot key>                                 protobuf `ConfirmedBlock`
+//   tx/<signature prefix>/<signature>                                   bincode `TransactionInfo`
+//   tx-by-addr/<address>/<inverted slot key prefix>/<inverted slot key> bincode `Vec<TransactionByAddrInfo>`
+//
+// Rows are spread over subdirectories by key prefix, so that no single directory grows too large
+// to list, and range queries only list the subdirectories covering their range.
+//
+// The filesystem is only accessed from tokio's blocking thread pool, so that the async executor
+// serving other requests is never blocked on disk I/O.
+
+use crate::{
+    compression::{compress_best, decompress},
+    key_to_slot, slot_to_key, transaction_rows, Error, LedgerStorageAdapter, Result,
+    TransactionByAddrInfo, TransactionInfo,
+};
+use async_trait::async_trait;
+use log::*;
+use prost::Message;
+use serde::{de::DeserializeOwned, Serialize};
+use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
+use solana_storage_proto::convert::generated;
+use solana_transaction_status::{
+    ConfirmedBlock, ConfirmedTransaction, ConfirmedTransactionStatusWithSignature,
+    TransactionStatus,
+};
+use std::{
+    convert::TryInto,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Leading hex digits of a slot key naming its `blocks` or `tx-by-addr` subdirectory, which leaves
+// 16^4 slots in each subdirectory
+const SLOT_DIR_KEY_LEN: usize = 12;
+
+// Leading characters of a signature naming its `tx` subdirectory
+const TX_DIR_KEY_LEN: usize = 2;
+
+const TMP_FILE_EXTENSION: &str = "tmp";
+
+fn object_corrupt(path: &Path) -> Error {
+    Error::ObjectCorrupt(path.display().to_string())
+}
+
+#[derive(Clone)]
+pub struct FileLedgerStorage {
+    path: PathBuf,
+    read_only: bool,
+}
+
+impl FileLedgerStorage {
+    /// Open the ledger storage in the directory at `path`, which is created unless `read_only`
+    pub fn new(path: &Path, read_only: bool) -> Result<Self> {
+        if read_only {
+            if !path.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not a directory", path.display()),
+                )
+                .into());
+            }
+        } else {
+            for table in &["blocks", "tx", "tx-by-addr"] {
+                fs::create_dir_all(path.join(table))?;
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            read_only,
+        })
+    }
+
+    // Runs `f` on tokio's blocking thread pool
+    async fn run_blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> Result<T> + Send + 'static,
+    {
+        let storage = self.clone();
+        tokio::task::spawn_blocking(move || f(&storage))
+            .await
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?
+    }
+
+    fn block_path(&self, slot: Slot) -> PathBuf {
+        let key = slot_to_key(slot);
+        self.path
+            .join("blocks")
+            .join(&key[..SLOT_DIR_KEY_LEN])
+            .join(key)
+    }
+
+    fn tx_path(&self, key: &str) -> PathBuf {
+        self.path.join("tx").join(&key[..TX_DIR_KEY_LEN]).join(key)
+    }
+
+    fn address_path(&self, address: &str) -> PathBuf {
+        self.path.join("tx-by-addr").join(address)
+    }
+
+    // Row keys of the `tx-by-addr` table are `<address>/<inverted slot key>`, so each address
+    // gets a directory of its own, sharded by slot range
+    fn tx_by_addr_path(&self, key: &str) -> PathBuf {
+        let mut parts = key.splitn(2, '/');
+        let address = parts.next().unwrap_or_default();
+        let slot_key = parts.next().unwrap_or_default();
+        self.address_path(address)
+            .join(&slot_key[..SLOT_DIR_KEY_LEN])
+            .join(slot_key)
+    }
+
+    // Returns the decompressed contents of the file at `path`, or None if there is no such file
+    fn read_file(path: &Path) -> Result<Option<Vec<u8>>> {
+        match fs::read(path) {
+            Ok(data) => Ok(Some(decompress(&data)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn read_bincode_file<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+        match Self::read_file(path)? {
+            Some(data) => bincode::deserialize(&data)
+                .map(Some)
+                .map_err(|_| object_corrupt(path)),
+            None => Ok(None),
+        }
+    }
+
+    // Writes to a temporary file which is then renamed, so that readers never see a partially
+    // written file. Returns the number of bytes written
+    fn write_file(&self, path: &Path, data: &[u8]) -> Result<usize> {
+        if self.read_only {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "ledger storage is read-only",
+            )
+            .into());
+        }
+        let data = compress_best(data)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension(TMP_FILE_EXTENSION);
+        fs::write(&tmp_path, &data)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(data.len())
+    }
+
+    fn write_bincode_file<T: Serialize>(&self, path: &Path, value: &T) -> Result<usize> {
+        self.write_file(path, &bincode::serialize(value).unwrap())
+    }
+
+    fn get_transaction_info(&self, signature: &Signature) -> Result<TransactionInfo> {
+        Self::read_bincode_file(&self.tx_path(&signature.to_string()))?
+            .ok_or(Error::SignatureNotFound)
+    }
+
+    // Returns the names of the files in `dir` in ascending order, leaving out unfinished writes
+    fn sorted_file_names(dir: &Path) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+        let mut names = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .map_or(false, |ext| ext == TMP_FILE_EXTENSION)
+            {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+        let start_key = slot_to_key(start_slot);
+        let blocks_path = self.path.join("blocks");
+        let mut slots = vec![];
+        for dir in Self::sorted_file_names(&blocks_path)? {
+            if dir.as_str() < &start_key[..SLOT_DIR_KEY_LEN] {
+                continue;
+            }
+            for key in Self::sorted_file_names(&blocks_path.join(&dir))? {
+                if slots.len() >= limit {
+                    return Ok(slots);
+                }
+                if key >= start_key {
+                    slots.extend(key_to_slot(&key));
+                }
+            }
+        }
+        slots.truncate(limit);
+        Ok(slots)
+    }
+
+    fn confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+        let path = self.block_path(slot);
+        let data = Self::read_file(&path)?.ok_or(Error::BlockNotFound(slot))?;
+        let block =
+            generated::ConfirmedBlock::decode(&data[..]).map_err(|_| object_corrupt(&path))?;
+        block.try_into().map_err(|_| object_corrupt(&path))
+    }
+
+    fn confirmed_transaction(&self, signature: &Signature) -> Result<Option<ConfirmedTransaction>> {
+        let TransactionInfo { slot, index, .. } = self.get_transaction_info(signature)?;
+        let block = self.confirmed_block(slot)?;
+        match block.transactions.into_iter().nth(index as usize) {
+            Some(transaction) if transaction.transaction.signatures[0] == *signature => {
+                Ok(Some(ConfirmedTransaction { slot, transaction }))
+            }
+            _ => {
+                warn!(
+                    "Transaction info or confirmed block for {} is corrupt",
+                    signature
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    fn confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<(ConfirmedTransactionStatusWithSignature, u32)>> {
+        let (first_slot, before_transaction_index) = match before_signature {
+            None => (Slot::MAX, 0),
+            Some(before_signature) => {
+                let TransactionInfo { slot, index, .. } =
+                    self.get_transaction_info(before_signature)?;
+                (slot, index)
+            }
+        };
+        let (last_slot, until_transaction_index) = match until_signature {
+            None => (0, u32::MAX),
+            Some(until_signature) => {
+                let TransactionInfo { slot, index, .. } =
+                    self.get_transaction_info(until_signature)?;
+                (slot, index)
+            }
+        };
+
+        // Inverted slot keys list the newest slots first
+        let first_key = slot_to_key(!first_slot);
+        let last_key = slot_to_key(!last_slot);
+        let address_path = self.address_path(&address.to_string());
+        let mut infos = vec![];
+        for dir in Self::sorted_file_names(&address_path)? {
+            if dir.as_str() < &first_key[..SLOT_DIR_KEY_LEN] {
+                continue;
+            }
+            if dir.as_str() > &last_key[..SLOT_DIR_KEY_LEN] {
+                break;
+            }
+            let dir_path = address_path.join(&dir);
+            for key in Self::sorted_file_names(&dir_path)? {
+                if key < first_key {
+                    continue;
+                }
+                if key > last_key {
+                    return Ok(infos);
+                }
+                let path = dir_path.join(&key);
+                let slot = !key_to_slot(&key).ok_or_else(|| object_corrupt(&path))?;
+                let mut cell_data: Vec<TransactionByAddrInfo> =
+                    Self::read_bincode_file(&path)?.unwrap_or_default();
+                cell_data.reverse();
+                for tx_by_addr_info in cell_data {
+                    // Filter out records before `before_transaction_index`
+                    if slot == first_slot && tx_by_addr_info.index >= before_transaction_index {
+                        continue;
+                    }
+                    // Filter out records after `until_transaction_index`
+                    if slot == last_slot && tx_by_addr_info.index <= until_transaction_index {
+                        continue;
+                    }
+                    infos.push((
+                        ConfirmedTransactionStatusWithSignature {
+                            signature: tx_by_addr_info.signature,
+                            slot,
+                            err: tx_by_addr_info.err,
+                            memo: tx_by_addr_info.memo,
+                        },
+                        tx_by_addr_info.index,
+                    ));
+                    if infos.len() >= limit {
+                        return Ok(infos);
+                    }
+                }
+            }
+        }
+        Ok(infos)
+    }
+
+    fn store_confirmed_block(&self, slot: Slot, confirmed_block: ConfirmedBlock) -> Result<()> {
+        let mut bytes_written = 0;
+
+        let (tx_cells, tx_by_addr_cells) = transaction_rows(slot, &confirmed_block);
+        for (key, transaction_info) in &tx_cells {
+            bytes_written += self.write_bincode_file(&self.tx_path(key), transaction_info)?;
+        }
+        for (key, transaction_info_by_addr) in &tx_by_addr_cells {
+            bytes_written +=
+                self.write_bincode_file(&self.tx_by_addr_path(key), transaction_info_by_addr)?;
+        }
+
+        // Store the block itself last, so that a partially uploaded block is never visible to
+        // `get_confirmed_block()` and `get_confirmed_blocks()`
+        let num_transactions = confirmed_block.transactions.len();
+        let block = generated::ConfirmedBlock::from(confirmed_block);
+        let mut buf = Vec::with_capacity(block.encoded_len());
+        block.encode(&mut buf).unwrap();
+        bytes_written += self.write_file(&self.block_path(slot), &buf)?;
+        info!(
+            "stored block for slot {}: {} transactions, {} bytes",
+            slot, num_transactions, bytes_written
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LedgerStorageAdapter for FileLedgerStorage {
+    async fn get_first_available_block(&self) -> Result<Option<Slot>> {
+        Ok(self.get_confirmed_blocks(0, 1).await?.into_iter().next())
+    }
+
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+        self.run_blocking(move |storage| storage.confirmed_blocks(start_slot, limit))
+            .await
+    }
+
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+        self.run_blocking(move |storage| storage.confirmed_block(slot))
+            .await
+    }
+
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
+        let signature = *signature;
+        self.run_blocking(move |storage| Ok(storage.get_transaction_info(&signature)?.into()))
+            .await
+    }
+
+    async fn get_confirmed_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<ConfirmedTransaction>> {
+        let signature = *signature;
+        self.run_blocking(move |storage| storage.confirmed_transaction(&signature))
+            .await
+    }
+
+    async fn get_confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        limit: usize,
+    ) -> Result<
+        Vec<(
+            ConfirmedTransactionStatusWithSignature,
+            u32, /*slot index*/
+        )>,
+    > {
+        let address = *address;
+        let before_signature = before_signature.copied();
+        let until_signature = until_signature.copied();
+        self.run_blocking(move |storage| {
+            storage.confirmed_signatures_for_address(
+                &address,
+                before_signature.as_ref(),
+                until_signature.as_ref(),
+                limit,
+            )
+        })
+        .await
+    }
+
+    async fn upload_confirmed_block(
+        &self,
+        slot: Slot,
+        confirmed_block: ConfirmedBlock,
+    ) -> Result<()> {
+        self.run_blocking(move |storage| storage.store_confirmed_block(slot, confirmed_block))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        system_transaction,
+    };
+    use solana_transaction_status::{TransactionStatusMeta, TransactionWithStatusMeta};
+
+    fn new_block(parent_slot: Slot, payer: &Keypair, num_transactions: u64) -> ConfirmedBlock {
+        let transactions = (0..num_transactions)
+            .map(|lamports| TransactionWithStatusMeta {
+                transaction: system_transaction::transfer(
+                    payer,
+                    &solana_sdk::pubkey::new_rand(),
+                    lamports,
+                    Hash::new_unique(),
+                ),
+                meta: Some(TransactionStatusMeta {
+                    status: Ok(()),
+                    fee: 1,
+                    pre_balances: vec![43, 0, 1],
+                    post_balances: vec![0, 42, 1],
+                    inner_instructions: Some(vec![]),
+                    log_messages: Some(vec![]),
+                }),
+            })
+            .collect();
+        ConfirmedBlock {
+            transactions,
+            parent_slot,
+            blockhash: Hash::new_unique().to_string(),
+            previous_blockhash: Hash::new_unique().to_string(),
+            rewards: vec![],
+            block_time: Some(1_234_567_890),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_ledger_storage() {
+        let storage_dir = tempfile::tempdir().unwrap();
+        let storage = FileLedgerStorage::new(storage_dir.path(), false).unwrap();
+        let payer = Keypair::new();
+        assert_eq!(storage.get_first_available_block().await.unwrap(), None);
+
+        let slots = vec![3, 5, 0x1_0000_0007];
+        let blocks: Vec<_> = slots
+            .iter()
+            .map(|slot| new_block(slot - 1, &payer, 2))
+            .collect();
+        for (slot, block) in slots.iter().zip(&blocks) {
+            storage
+                .upload_confirmed_block(*slot, block.clone())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(storage.get_first_available_block().await.unwrap(), Some(3));
+        assert_eq!(
+            storage.get_confirmed_blocks(4, 10).await.unwrap(),
+            vec![5, 0x1_0000_0007]
+        );
+        assert_eq!(
+            storage.get_confirmed_blocks(0, 2).await.unwrap(),
+            vec![3, 5]
+        );
+        assert_eq!(storage.get_confirmed_block(5).await.unwrap(), blocks[1]);
+        assert!(matches!(
+            storage.get_confirmed_block(4).await,
+            Err(Error::BlockNotFound(4))
+        ));
+
+        let transaction = &blocks[1].transactions[1];
+        let signature = transaction.transaction.signatures[0];
+        let confirmed_transaction = storage
+            .get_confirmed_transaction(&signature)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(confirmed_transaction.slot, 5);
+        assert_eq!(confirmed_transaction.transaction, *transaction);
+        assert_eq!(
+            storage.get_signature_status(&signature).await.unwrap().slot,
+            5
+        );
+
+        // The payer signed every transaction, newest first
+        let all_signatures: Vec<_> = slots
+            .iter()
+            .zip(&blocks)
+            .rev()
+            .flat_map(|(slot, block)| {
+                block
+                    .transactions
+                    .iter()
+                    .rev()
+                    .map(move |transaction| (*slot, transaction.transaction.signatures[0]))
+            })
+            .collect();
+        let results = storage
+            .get_confirmed_signatures_for_address(&payer.pubkey(), None, None, usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|(result, _)| (result.slot, result.signature))
+                .collect::<Vec<_>>(),
+            all_signatures
+        );
+
+        let results = storage
+            .get_confirmed_signatures_for_address(
+                &payer.pubkey(),
+                Some(&all_signatures[1].1),
+                Some(&all_signatures[4].1),
+                usize::MAX,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|(result, _)| (result.slot, result.signature))
+                .collect::<Vec<_>>(),
+            all_signatures[2..4].to_vec()
+        );
+
+        let read_only = FileLedgerStorage::new(storage_dir.path(), true).unwrap();
+        assert_eq!(read_only.get_confirmed_block(3).await.unwrap(), blocks[0]);
+        assert!(read_only
+            .upload_confirmed_block(9, blocks[0].clone())
+            .await
+            .is_err());
+    }
+}
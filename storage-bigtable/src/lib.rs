@@ -1,3 +1,4 @@
+use async_trait::async_trait;
 use log::*;
 use serde::{Deserialize, Serialize};
 use solana_sdk::{
@@ -12,7 +13,7 @@ use solana_transaction_status::{
     ConfirmedBlock, ConfirmedTransaction, ConfirmedTransactionStatusWithSignature, Reward,
     TransactionStatus, TransactionStatusMeta, TransactionWithStatusMeta,
 };
-use std::{collections::HashMap, convert::TryInto};
+use std::{collections::HashMap, convert::TryInto, path::PathBuf, sync::Arc};
 use thiserror::Error;
 
 #[macro_use]
@@ -21,8 +22,11 @@ extern crate serde_derive;
 mod access_token;
 mod bigtable;
 mod compression;
+mod file_storage;
 mod root_ca_certificate;
 
+pub use file_storage::FileLedgerStorage;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("BigTable: {0}")]
@@ -39,6 +43,9 @@ pub enum Error {
 
     #[error("Signature not found")]
     SignatureNotFound,
+
+    #[error("Object is corrupt: {0}")]
+    ObjectCorrupt(String),
 }
 
 impl std::convert::From<bigtable::Error> for Error {
@@ -269,6 +276,137 @@ struct TransactionByAddrInfo {
     memo: Option<String>,          // Transaction memo
 }
 
+// Returns the `tx` and `tx-by-addr` rows to store for the transactions of `confirmed_block`
+fn transaction_rows(
+    slot: Slot,
+    confirmed_block: &ConfirmedBlock,
+) -> (
+    Vec<(String, TransactionInfo)>,
+    Vec<(String, Vec<TransactionByAddrInfo>)>,
+) {
+    let mut by_addr: HashMap<&Pubkey, Vec<TransactionByAddrInfo>> = HashMap::new();
+
+    let mut tx_cells = vec![];
+    for (index, transaction_with_meta) in confirmed_block.transactions.iter().enumerate() {
+        let TransactionWithStatusMeta { meta, transaction } = transaction_with_meta;
+        let err = meta.as_ref().and_then(|meta| meta.status.clone().err());
+        let index = index as u32;
+        let signature = transaction.signatures[0];
+
+        for address in &transaction.message.account_keys {
+            if !is_sysvar_id(&address) {
+                by_addr
+                    .entry(address)
+                    .or_default()
+                    .push(TransactionByAddrInfo {
+                        signature,
+                        err: err.clone(),
+                        index,
+                        memo: None, // TODO
+                    });
+            }
+        }
+
+        tx_cells.push((
+            signature.to_string(),
+            TransactionInfo {
+                slot,
+                index,
+                err,
+                memo: None, // TODO
+            },
+        ));
+    }
+
+    let tx_by_addr_cells: Vec<_> = by_addr
+        .into_iter()
+        .map(|(address, transaction_info_by_addr)| {
+            (
+                format!("{}/{}", address, slot_to_key(!slot)),
+                transaction_info_by_addr,
+            )
+        })
+        .collect();
+
+    (tx_cells, tx_by_addr_cells)
+}
+
+/// Long-term storage of confirmed blocks, which serves the history that has
+/// already been purged from the local ledger
+#[async_trait]
+pub trait LedgerStorageAdapter: Send + Sync {
+    /// Return the available slot that contains a block
+    async fn get_first_available_block(&self) -> Result<Option<Slot>>;
+
+    /// Fetch the next slots after the provided slot that contains a block
+    ///
+    /// start_slot: slot to start the search from (inclusive)
+    /// limit: stop after this many slots have been found.
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>>;
+
+    /// Fetch the confirmed block from the desired slot
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock>;
+
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus>;
+
+    /// Fetch a confirmed transaction
+    async fn get_confirmed_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<ConfirmedTransaction>>;
+
+    /// Get confirmed signatures for the provided address, in descending ledger order
+    ///
+    /// address: address to search for
+    /// before_signature: start with the first signature older than this one
+    /// limit: stop after this many signatures.
+    async fn get_confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        limit: usize,
+    ) -> Result<
+        Vec<(
+            ConfirmedTransactionStatusWithSignature,
+            u32, /*slot index*/
+        )>,
+    >;
+
+    // Upload a new confirmed block and associated meta data.
+    async fn upload_confirmed_block(
+        &self,
+        slot: Slot,
+        confirmed_block: ConfirmedBlock,
+    ) -> Result<()>;
+}
+
+/// Selects the backend of the long-term ledger storage
+#[derive(Clone, Debug, PartialEq)]
+pub enum LedgerStorageConfig {
+    /// The Google BigTable instance named by the environment, see `LedgerStorage`
+    BigTable,
+    /// A directory on the local filesystem, see `FileLedgerStorage`
+    FileSystem(PathBuf),
+}
+
+impl Default for LedgerStorageConfig {
+    fn default() -> Self {
+        Self::BigTable
+    }
+}
+
+/// Connect to the long-term ledger storage selected by `config`
+pub async fn new_ledger_storage(
+    config: &LedgerStorageConfig,
+    read_only: bool,
+) -> Result<Arc<dyn LedgerStorageAdapter>> {
+    Ok(match config {
+        LedgerStorageConfig::BigTable => Arc::new(LedgerStorage::new(read_only).await?),
+        LedgerStorageConfig::FileSystem(path) => Arc::new(FileLedgerStorage::new(path, read_only)?),
+    })
+}
+
 #[derive(Clone)]
 pub struct LedgerStorage {
     connection: bigtable::BigTableConnection,
@@ -279,9 +417,11 @@ impl LedgerStorage {
         let connection = bigtable::BigTableConnection::new("solana-ledger", read_only).await?;
         Ok(Self { connection })
     }
+}
 
-    /// Return the available slot that contains a block
-    pub async fn get_first_available_block(&self) -> Result<Option<Slot>> {
+#[async_trait]
+impl LedgerStorageAdapter for LedgerStorage {
+    async fn get_first_available_block(&self) -> Result<Option<Slot>> {
         let mut bigtable = self.connection.client();
         let blocks = bigtable.get_row_keys("blocks", None, None, 1).await?;
         if blocks.is_empty() {
@@ -290,11 +430,7 @@ impl LedgerStorage {
         Ok(key_to_slot(&blocks[0]))
     }
 
-    /// Fetch the next slots after the provided slot that contains a block
-    ///
-    /// start_slot: slot to start the search from (inclusive)
-    /// limit: stop after this many slots have been found.
-    pub async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
         let mut bigtable = self.connection.client();
         let blocks = bigtable
             .get_row_keys("blocks", Some(slot_to_key(start_slot)), None, limit as i64)
@@ -302,8 +438,7 @@ impl LedgerStorage {
         Ok(blocks.into_iter().filter_map(|s| key_to_slot(&s)).collect())
     }
 
-    /// Fetch the confirmed block from the desired slot
-    pub async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
         let mut bigtable = self.connection.client();
         let block_cell_data = bigtable
             .get_protobuf_or_bincode_cell::<StoredConfirmedBlock, generated::ConfirmedBlock>(
@@ -319,7 +454,7 @@ impl LedgerStorage {
         })
     }
 
-    pub async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
         let mut bigtable = self.connection.client();
         let transaction_info = bigtable
             .get_bincode_cell::<TransactionInfo>("tx", signature.to_string())
@@ -327,8 +462,7 @@ impl LedgerStorage {
         Ok(transaction_info.into())
     }
 
-    /// Fetch a confirmed transaction
-    pub async fn get_confirmed_transaction(
+    async fn get_confirmed_transaction(
         &self,
         signature: &Signature,
     ) -> Result<Option<ConfirmedTransaction>> {
@@ -363,12 +497,7 @@ impl LedgerStorage {
         }
     }
 
-    /// Get confirmed signatures for the provided address, in descending ledger order
-    ///
-    /// address: address to search for
-    /// before_signature: start with the first signature older than this one
-    /// limit: stop after this many signatures.
-    pub async fn get_confirmed_signatures_for_address(
+    async fn get_confirmed_signatures_for_address(
         &self,
         address: &Pubkey,
         before_signature: Option<&Signature>,
@@ -466,57 +595,14 @@ impl LedgerStorage {
         Ok(infos)
     }
 
-    // Upload a new confirmed block and associated meta data.
-    pub async fn upload_confirmed_block(
+    async fn upload_confirmed_block(
         &self,
         slot: Slot,
         confirmed_block: ConfirmedBlock,
     ) -> Result<()> {
         let mut bytes_written = 0;
 
-        let mut by_addr: HashMap<&Pubkey, Vec<TransactionByAddrInfo>> = HashMap::new();
-
-        let mut tx_cells = vec![];
-        for (index, transaction_with_meta) in confirmed_block.transactions.iter().enumerate() {
-            let TransactionWithStatusMeta { meta, transaction } = transaction_with_meta;
-            let err = meta.as_ref().and_then(|meta| meta.status.clone().err());
-            let index = index as u32;
-            let signature = transaction.signatures[0];
-
-            for address in &transaction.message.account_keys {
-                if !is_sysvar_id(&address) {
-                    by_addr
-                        .entry(address)
-                        .or_default()
-                        .push(TransactionByAddrInfo {
-                            signature,
-                            err: err.clone(),
-                            index,
-                            memo: None, // TODO
-                        });
-                }
-            }
-
-            tx_cells.push((
-                signature.to_string(),
-                TransactionInfo {
-                    slot,
-                    index,
-                    err,
-                    memo: None, // TODO
-                },
-            ));
-        }
-
-        let tx_by_addr_cells: Vec<_> = by_addr
-            .into_iter()
-            .map(|(address, transaction_info_by_addr)| {
-                (
-                    format!("{}/{}", address, slot_to_key(!slot)),
-                    transaction_info_by_addr,
-                )
-            })
-            .collect();
+        let (tx_cells, tx_by_addr_cells) = transaction_rows(slot, &confirmed_block);
 
         if !tx_cells.is_empty() {
             bytes_written += self
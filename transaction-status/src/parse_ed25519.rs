@@ -0,0 +1,78 @@
+use crate::parse_instruction::{ParsableProgram, ParseInstructionError, ParsedInstructionEnum};
+use serde_json::{json, Value};
+use solana_sdk::{ed25519_instruction::parse_signature_offsets, instruction::CompiledInstruction};
+
+pub fn parse_ed25519(
+    instruction: &CompiledInstruction,
+) -> Result<ParsedInstructionEnum, ParseInstructionError> {
+    let signature_offsets = parse_signature_offsets(&instruction.data)
+        .map_err(|_| ParseInstructionError::InstructionNotParsable(ParsableProgram::Ed25519))?;
+    let signatures: Vec<Value> = signature_offsets
+        .iter()
+        .map(|offsets| {
+            json!({
+                "signatureOffset": offsets.signature_offset,
+                "signatureInstructionIndex": offsets.signature_instruction_index,
+                "publicKeyOffset": offsets.public_key_offset,
+                "publicKeyInstructionIndex": offsets.public_key_instruction_index,
+                "messageDataOffset": offsets.message_data_offset,
+                "messageDataSize": offsets.message_data_size,
+                "messageInstructionIndex": offsets.message_instruction_index,
+            })
+        })
+        .collect();
+    Ok(ParsedInstructionEnum {
+        instruction_type: "verify".to_string(),
+        info: json!({ "signatures": signatures }),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_sdk::{
+        ed25519_instruction::{
+            new_ed25519_instruction, PUBKEY_SERIALIZED_SIZE, SIGNATURE_OFFSETS_SERIALIZED_SIZE,
+            SIGNATURE_SERIALIZED_SIZE,
+        },
+        message::Message,
+        pubkey,
+        signature::Keypair,
+    };
+
+    #[test]
+    fn test_parse_ed25519_instruction() {
+        let keypair = Keypair::new();
+        let message = b"hello";
+        let instruction = new_ed25519_instruction(&keypair, message, 0);
+        let fee_payer = pubkey::new_rand();
+        let public_key_offset = 1 + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+        let signature_offset = public_key_offset + PUBKEY_SERIALIZED_SIZE;
+        let message_data_offset = signature_offset + SIGNATURE_SERIALIZED_SIZE;
+        let message = Message::new(&[instruction], Some(&fee_payer));
+        assert_eq!(
+            parse_ed25519(&message.instructions[0]).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "verify".to_string(),
+                info: json!({
+                    "signatures": [{
+                        "signatureOffset": signature_offset,
+                        "signatureInstructionIndex": 0,
+                        "publicKeyOffset": public_key_offset,
+                        "publicKeyInstructionIndex": 0,
+                        "messageDataOffset": message_data_offset,
+                        "messageDataSize": 5,
+                        "messageInstructionIndex": 0,
+                    }],
+                }),
+            }
+        );
+
+        let bad_compiled_instruction = CompiledInstruction {
+            program_id_index: 0,
+            accounts: vec![],
+            data: vec![2, 0, 0],
+        };
+        assert!(parse_ed25519(&bad_compiled_instruction).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    parse_bpf_loader::parse_bpf_loader, parse_stake::parse_stake, parse_system::parse_system,
-    parse_token::parse_token,
+    parse_bpf_loader::parse_bpf_loader, parse_ed25519::parse_ed25519, parse_stake::parse_stake,
+    parse_system::parse_system, parse_token::parse_token,
 };
 use inflector::Inflector;
 use serde_json::Value;
@@ -14,6 +14,7 @@ use thiserror::Error;
 
 lazy_static! {
     static ref BPF_LOADER_PROGRAM_ID: Pubkey = solana_sdk::bpf_loader::id();
+    static ref ED25519_PROGRAM_ID: Pubkey = solana_sdk::ed25519_program::id();
     static ref MEMO_PROGRAM_ID: Pubkey =
         Pubkey::from_str(&spl_memo_v1_0::id().to_string()).unwrap();
     static ref STAKE_PROGRAM_ID: Pubkey = solana_stake_program::id();
@@ -24,6 +25,7 @@ lazy_static! {
         m.insert(*MEMO_PROGRAM_ID, ParsableProgram::SplMemo);
         m.insert(*TOKEN_PROGRAM_ID, ParsableProgram::SplToken);
         m.insert(*BPF_LOADER_PROGRAM_ID, ParsableProgram::BpfLoader);
+        m.insert(*ED25519_PROGRAM_ID, ParsableProgram::Ed25519);
         m.insert(*STAKE_PROGRAM_ID, ParsableProgram::Stake);
         m.insert(*SYSTEM_PROGRAM_ID, ParsableProgram::System);
         m
@@ -68,6 +70,7 @@ pub enum ParsableProgram {
     SplMemo,
     SplToken,
     BpfLoader,
+    Ed25519,
     Stake,
     System,
 }
@@ -86,6 +89,7 @@ pub fn parse(
         ParsableProgram::BpfLoader => {
             serde_json::to_value(parse_bpf_loader(instruction, account_keys)?)?
         }
+        ParsableProgram::Ed25519 => serde_json::to_value(parse_ed25519(instruction)?)?,
         ParsableProgram::Stake => serde_json::to_value(parse_stake(instruction, account_keys)?)?,
         ParsableProgram::System => serde_json::to_value(parse_system(instruction, account_keys)?)?,
     };
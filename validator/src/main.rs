@@ -13,7 +13,7 @@ use solana_clap_utils::{
 };
 use solana_client::rpc_client::RpcClient;
 use solana_core::ledger_cleanup_service::{
-    DEFAULT_MAX_LEDGER_SHREDS, DEFAULT_MIN_MAX_LEDGER_SHREDS,
+    LedgerCleanupConfig, DEFAULT_MAX_LEDGER_SHREDS, DEFAULT_MIN_MAX_LEDGER_SHREDS,
 };
 use solana_core::{
     cluster_info::{ClusterInfo, Node, MINIMUM_VALIDATOR_PORT_RANGE_WIDTH, VALIDATOR_PORT_RANGE},
@@ -21,6 +21,7 @@ use solana_core::{
     gossip_service::GossipService,
     rpc::JsonRpcConfig,
     rpc_pubsub_service::PubSubConfig,
+    sigverify_stage::DEFAULT_MAX_SIGVERIFY_BACKLOG_PACKETS,
     validator::{Validator, ValidatorConfig},
 };
 use solana_download_utils::{download_genesis_if_missing, download_snapshot};
@@ -39,6 +40,7 @@ use solana_sdk::{
     pubkey::Pubkey,
     signature::{Keypair, Signer},
 };
+use solana_storage_bigtable::LedgerStorageConfig;
 use std::{
     collections::HashSet,
     env,
@@ -117,8 +119,9 @@ fn start_gossip_node(
     gossip_socket: UdpSocket,
     expected_shred_version: Option<u16>,
     gossip_validators: Option<HashSet<Pubkey>>,
+    ledger_path: &Path,
 ) -> (Arc<ClusterInfo>, Arc<AtomicBool>, GossipService) {
-    let cluster_info = ClusterInfo::new(
+    let mut cluster_info = ClusterInfo::new(
         ClusterInfo::gossip_contact_info(
             &identity_keypair.pubkey(),
             *gossip_addr,
@@ -127,6 +130,7 @@ fn start_gossip_node(
         identity_keypair.clone(),
     );
     cluster_info.set_entrypoint(ContactInfo::new_gossip_entry_point(entrypoint_gossip));
+    cluster_info.restore_crds_backup(ledger_path);
     let cluster_info = Arc::new(cluster_info);
 
     let gossip_exit_flag = Arc::new(AtomicBool::new(false));
@@ -550,7 +554,11 @@ fn verify_reachable_ports(
     cluster_entrypoint: &ContactInfo,
     validator_config: &ValidatorConfig,
 ) {
-    let mut udp_sockets = vec![&node.sockets.gossip, &node.sockets.repair];
+    let mut udp_sockets = vec![
+        &node.sockets.gossip,
+        &node.sockets.repair,
+        &node.sockets.ancestor_hashes_requests,
+    ];
 
     if ContactInfo::is_valid_address(&node.info.serve_repair) {
         udp_sockets.push(&node.sockets.serve_repair);
@@ -561,6 +569,9 @@ fn verify_reachable_ports(
     if ContactInfo::is_valid_address(&node.info.tpu_forwards) {
         udp_sockets.extend(node.sockets.tpu_forwards.iter());
     }
+    if ContactInfo::is_valid_address(&node.tpu_vote) {
+        udp_sockets.extend(node.sockets.tpu_vote.iter());
+    }
     if ContactInfo::is_valid_address(&node.info.tvu) {
         udp_sockets.extend(node.sockets.tvu.iter());
         udp_sockets.extend(node.sockets.broadcast.iter());
@@ -656,6 +667,7 @@ fn rpc_bootstrap(
                 node.sockets.gossip.try_clone().unwrap(),
                 validator_config.expected_shred_version,
                 validator_config.gossip_validators.clone(),
+                ledger_path,
             ));
         }
 
@@ -828,6 +840,7 @@ pub fn main() {
     let default_dynamic_port_range =
         &format!("{}-{}", VALIDATOR_PORT_RANGE.0, VALIDATOR_PORT_RANGE.1);
     let default_genesis_archive_unpacked_size = &MAX_GENESIS_ARCHIVE_UNPACKED_SIZE.to_string();
+    let default_max_sigverify_backlog_packets = &DEFAULT_MAX_SIGVERIFY_BACKLOG_PACKETS.to_string();
     let default_rpc_pubsub_max_connections = PubSubConfig::default().max_connections.to_string();
     let default_rpc_pubsub_max_fragment_size =
         PubSubConfig::default().max_fragment_size.to_string();
@@ -990,6 +1003,14 @@ pub fn main() {
                        including the 'getConfirmedBlock' API.  \
                        This will cause an increase in disk usage and IOPS"),
         )
+        .arg(
+            Arg::with_name("enable_rpc_account_proofs")
+                .long("enable-rpc-account-proofs")
+                .takes_value(false)
+                .help("Enable the JSON RPC 'getAccountProof' API.  \
+                       Keeps the Merkle tree of the accounts hash computed for each \
+                       snapshot in memory, which requires snapshots to be enabled"),
+        )
         .arg(
             Arg::with_name("enable_rpc_bigtable_ledger_storage")
                 .long("enable-rpc-bigtable-ledger-storage")
@@ -1005,6 +1026,15 @@ pub fn main() {
                 .takes_value(false)
                 .help("Upload new confirmed blocks into a BigTable instance"),
         )
+        .arg(
+            Arg::with_name("ledger_storage_path")
+                .long("ledger-storage-path")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("Keep long-term ledger storage in this directory instead of a \
+                       BigTable instance, for use with --enable-rpc-bigtable-ledger-storage \
+                       and --enable-bigtable-ledger-upload"),
+        )
         .arg(
             Arg::with_name("health_check_slot_distance")
                 .long("health-check-slot-distance")
@@ -1104,6 +1134,14 @@ pub fn main() {
                 .default_value(SnapshotVersion::default().into())
                 .help("Output snapshot version"),
         )
+        .arg(
+            Arg::with_name("snapshot_storage_indexes")
+                .long("snapshot-storage-indexes")
+                .takes_value(false)
+                .help("Include the account index of every account storage in snapshot archives, \
+                       so that loading them skips scanning the storages. \
+                       Validators without support for these indexes reject such archives"),
+        )
         .arg(
             Arg::with_name("limit_ledger_size")
                 .long("limit-ledger-size")
@@ -1114,6 +1152,30 @@ pub fn main() {
                 /* .default_value() intentionally not used here! */
                 .help("Keep this amount of shreds in root slots."),
         )
+        .arg(
+            Arg::with_name("limit_ledger_bytes")
+                .long("limit-ledger-bytes")
+                .value_name("BYTES")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Purge the oldest slots once the ledger takes up more than this many bytes on disk"),
+        )
+        .arg(
+            Arg::with_name("limit_ledger_age_slots")
+                .long("limit-ledger-age-slots")
+                .value_name("SLOTS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Purge slots more than this many slots older than the latest root"),
+        )
+        .arg(
+            Arg::with_name("limit_ledger_age")
+                .long("limit-ledger-age")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Purge slots received more than this many seconds ago"),
+        )
         .arg(
             Arg::with_name("skip_poh_verify")
                 .long("skip-poh-verify")
@@ -1202,6 +1264,27 @@ pub fn main() {
                 .takes_value(true)
                 .help("Log when transactions are processed which reference a given key."),
         )
+        .arg(
+            Arg::with_name("bank_hash_details_dir")
+                .long("bank-hash-details-dir")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("Write the accounts and statistics that contribute to the hash of every \
+                       frozen bank to DIR, for debugging bank hash mismatches. \
+                       Only the most recent slots are kept. \
+                       Compare two such files with `solana-ledger-tool compare-bank-hash-details`"),
+        )
+        .arg(
+            Arg::with_name("max_sigverify_backlog_packets")
+                .long("max-sigverify-backlog-packets")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value(&default_max_sigverify_backlog_packets)
+                .help("The maximum number of transaction packets a signature verification \
+                       thread keeps out of those queued for it. Packets beyond this many are \
+                       discarded, preferring to keep those sent from the IPs of staked nodes"),
+        )
         .arg(
             Arg::with_name("no_untrusted_rpc")
                 .long("no-untrusted-rpc")
@@ -1434,9 +1517,14 @@ pub fn main() {
             enable_validator_exit: matches.is_present("enable_rpc_exit"),
             enable_set_log_filter: matches.is_present("enable_rpc_set_log_filter"),
             enable_rpc_transaction_history: matches.is_present("enable_rpc_transaction_history"),
+            enable_rpc_account_proofs: matches.is_present("enable_rpc_account_proofs"),
             enable_bigtable_ledger_storage: matches
                 .is_present("enable_rpc_bigtable_ledger_storage"),
             enable_bigtable_ledger_upload: matches.is_present("enable_bigtable_ledger_upload"),
+            ledger_storage_config: matches
+                .value_of("ledger_storage_path")
+                .map(|path| LedgerStorageConfig::FileSystem(PathBuf::from(path)))
+                .unwrap_or_default(),
             identity_pubkey: identity_keypair.pubkey(),
             faucet_addr: matches.value_of("rpc_faucet_addr").map(|address| {
                 solana_net_utils::parse_host_port(address).expect("failed to parse faucet address")
@@ -1480,6 +1568,12 @@ pub fn main() {
         wal_recovery_mode,
         poh_verify: !matches.is_present("skip_poh_verify"),
         debug_keys,
+        bank_hash_details_dir: value_t!(matches, "bank_hash_details_dir", PathBuf).ok(),
+        max_sigverify_backlog_packets: value_t_or_exit!(
+            matches,
+            "max_sigverify_backlog_packets",
+            usize
+        ),
         ..ValidatorConfig::default()
     };
 
@@ -1558,6 +1652,7 @@ pub fn main() {
         snapshot_package_output_path: ledger_path.clone(),
         compression: snapshot_compression,
         snapshot_version,
+        include_storage_indexes: matches.is_present("snapshot_storage_indexes"),
     });
 
     validator_config.accounts_hash_interval_slots =
@@ -1577,6 +1672,7 @@ pub fn main() {
         exit(1);
     }
 
+    let mut ledger_cleanup_config = LedgerCleanupConfig::default();
     if matches.is_present("limit_ledger_size") {
         let limit_ledger_size = match matches.value_of("limit_ledger_size") {
             Some(_) => value_t_or_exit!(matches, "limit_ledger_size", u64),
@@ -1589,7 +1685,16 @@ pub fn main() {
             );
             exit(1);
         }
-        validator_config.max_ledger_shreds = Some(limit_ledger_size);
+        ledger_cleanup_config.max_ledger_shreds = Some(limit_ledger_size);
+    }
+    ledger_cleanup_config.max_ledger_bytes = value_t!(matches, "limit_ledger_bytes", u64).ok();
+    ledger_cleanup_config.max_ledger_age_slots =
+        value_t!(matches, "limit_ledger_age_slots", u64).ok();
+    ledger_cleanup_config.max_ledger_age = value_t!(matches, "limit_ledger_age", u64)
+        .ok()
+        .map(Duration::from_secs);
+    if ledger_cleanup_config != LedgerCleanupConfig::default() {
+        validator_config.ledger_cleanup_config = Some(ledger_cleanup_config);
     }
 
     if matches.is_present("halt_on_trusted_validators_accounts_hash_mismatch") {
@@ -1680,6 +1785,7 @@ pub fn main() {
         // requests initiated by the node.  All other ports are unused.
         node.info.tpu = any;
         node.info.tpu_forwards = any;
+        node.tpu_vote = any;
         node.info.tvu = any;
         node.info.tvu_forwards = any;
         node.info.serve_repair = any;